@@ -304,6 +304,9 @@ pub enum Pattern<'ast, Id> {
     },
     /// A literal pattern
     Literal(Literal),
+    /// An or-pattern which matches if any of its patterns match, eg. `A | B`. Each pattern binds
+    /// the same variables
+    Or(&'ast mut [SpannedPattern<'ast, Id>]),
     /// An invalid pattern
     Error,
 }
@@ -368,6 +371,9 @@ impl<Id> Default for Pattern<'_, Id> {
 #[derive(Eq, PartialEq, Debug, AstClone)]
 pub struct Alternative<'ast, Id> {
     pub pattern: SpannedPattern<'ast, Id>,
+    /// Expression which must evaluate to `True` for the alternative to be selected, eg.
+    /// `| Some x if x > 0 -> ...`
+    pub guard: Option<SpannedExpr<'ast, Id>>,
     pub expr: SpannedExpr<'ast, Id>,
 }
 
@@ -407,13 +413,36 @@ pub struct Do<'ast, Id> {
     pub flat_map_id: Option<&'ast mut SpannedExpr<'ast, Id>>,
 }
 
+/// An expression which is spliced into an interpolated string, eg. `${name}`
+#[derive(Eq, PartialEq, Debug, AstClone)]
+pub struct InterpolatedExpr<'ast, Id> {
+    pub expr: SpannedExpr<'ast, Id>,
+    /// `show`, inserted during renaming and removed by the typechecker if `expr` is a `String`
+    pub show_id: Option<&'ast mut SpannedExpr<'ast, Id>>,
+}
+
+/// An interpolated string literal, eg. `"user ${name} has ${show count} items"`
+#[derive(Eq, PartialEq, Debug, AstClone)]
+pub struct Interpolation<'ast, Id> {
+    /// The (unescaped) text surrounding the interpolated expressions. Always contains one more
+    /// element than `exprs`. The spans include the delimiters of each piece (`"`, `${` and `}`)
+    pub literals: Vec<Spanned<String, BytePos>>,
+    pub exprs: &'ast mut [InterpolatedExpr<'ast, Id>],
+    /// `__string_append` (defined by the implicit prelude), inserted during renaming
+    pub append_id: Option<&'ast mut SpannedExpr<'ast, Id>>,
+}
+
 /// The representation of gluon's expression syntax
 #[derive(Eq, PartialEq, Debug, AstClone)]
 pub enum Expr<'ast, Id> {
     /// Identifiers
     Ident(TypedIdent<Id>),
+    /// Typed hole, eg. `_` or `?name`
+    Hole(TypedIdent<Id>),
     /// Literal values
     Literal(Literal),
+    /// String interpolation, eg. `"Hello ${name}"`
+    Interpolation(&'ast mut Interpolation<'ast, Id>),
     /// Function application, eg. `f x`
     App {
         func: &'ast mut SpannedExpr<'ast, Id>,
@@ -533,8 +562,10 @@ impl<'ast, Id> Expr<'ast, Id> {
             Expr::Lambda(..) => "Lambda",
             Expr::TypeBindings(..) => "TypeBindings",
             Expr::Ident(..) => "Ident",
+            Expr::Hole(..) => "Hole",
             Expr::MacroExpansion { .. } => "MacroExpansion",
             Expr::Literal(..) => "Literal",
+            Expr::Interpolation(..) => "Interpolation",
             Expr::Annotated(..) => "Annotated",
             Expr::Error(..) => "Error",
         }
@@ -811,6 +842,9 @@ pub fn walk_expr<'a, 'ast, V>(v: &mut V, e: &'a $($mut)* SpannedExpr<'ast, V::Id
             v.visit_expr(expr);
             for alt in &$($mut)* **alts {
                 v.visit_pattern(&$($mut)* alt.pattern);
+                if let Some(guard) = &$($mut)* alt.guard {
+                    v.visit_expr(guard);
+                }
                 v.visit_expr(&$($mut)* alt.expr);
             }
         }
@@ -870,6 +904,18 @@ pub fn walk_expr<'a, 'ast, V>(v: &mut V, e: &'a $($mut)* SpannedExpr<'ast, V::Id
             }
         }
 
+        Expr::Interpolation(ref $($mut)* interpolation) => {
+            for interpolated in &$($mut)* *interpolation.exprs {
+                v.visit_expr(&$($mut)* interpolated.expr);
+                if let Some(ref $($mut)* show_id) = interpolated.show_id {
+                    v.visit_expr(show_id);
+                }
+            }
+            if let Some(ref $($mut)* append_id) = interpolation.append_id {
+                v.visit_expr(append_id);
+            }
+        }
+
         Expr::Lambda(ref $($mut)* lambda) => {
             v.visit_ident(&$($mut)* lambda.id);
             for arg in &$($mut)* *lambda.args {
@@ -893,7 +939,7 @@ pub fn walk_expr<'a, 'ast, V>(v: &mut V, e: &'a $($mut)* SpannedExpr<'ast, V::Id
             v.visit_typ(typ);
             v.visit_expr(expr);
         }
-        Expr::Literal(..) | Expr::Error(..) => (),
+        Expr::Literal(..) | Expr::Hole(..) | Expr::Error(..) => (),
     }
 }
 
@@ -940,6 +986,11 @@ pub fn walk_pattern<'a, 'ast,V: ?Sized + $trait_name<'a, 'ast>>(v: &mut V, p: &'
             }
         }
         Pattern::Ident(id) => v.visit_ident(id),
+        Pattern::Or(alts) => {
+            for alt in &$($mut)* **alts {
+                v.visit_pattern(alt);
+            }
+        }
         Pattern::Literal(_) | Pattern::Error => (),
     }
 }
@@ -1070,12 +1121,13 @@ impl Typed for Expr<'_, Symbol> {
 
     fn try_type_of(&self, env: &dyn TypeEnv<Type = ArcType>) -> Result<ArcType, String> {
         match *self {
-            Expr::Ident(ref id) => Ok(id.typ.clone()),
+            Expr::Ident(ref id) | Expr::Hole(ref id) => Ok(id.typ.clone()),
             Expr::Tuple { ref elems, .. } if elems.len() == 1 => elems[0].try_type_of(env),
             Expr::Projection(_, _, ref typ)
             | Expr::Record { ref typ, .. }
             | Expr::Tuple { ref typ, .. } => Ok(typ.clone()),
             Expr::Literal(ref lit) => lit.try_type_of(env),
+            Expr::Interpolation(_) => Ok(Type::string()),
             Expr::IfElse(_, ref arm, _) => arm.try_type_of(env),
             Expr::Infix { ref op, .. } => get_return_type(env, &op.value.typ, 2),
             Expr::LetBindings(_, ref expr)
@@ -1117,6 +1169,7 @@ impl Typed for Pattern<'_, Symbol> {
             Pattern::Constructor(ref id, ref args) => get_return_type(env, &id.typ, args.len()),
             Pattern::Error => Ok(Type::hole()),
             Pattern::Literal(ref l) => l.try_type_of(env),
+            Pattern::Or(ref alts) => alts[0].try_type_of(env),
         }
     }
 }
@@ -1277,6 +1330,8 @@ impl_ast_arena! {
     TypeBinding<'ast, Id> => type_bindings,
     ValueBinding<'ast, Id> => value_bindings,
     Do<'ast, Id> => do_exprs,
+    Interpolation<'ast, Id> => interpolations,
+    InterpolatedExpr<'ast, Id> => interpolated_exprs,
     Alternative<'ast, Id> => alts,
     Argument<SpannedIdent<Id>> => args,
     InnerAstType<'ast, Id> => types,
@@ -11,7 +11,7 @@ use std::slice;
 use std::str;
 use std::vec;
 
-use codespan_reporting::{Diagnostic, Label};
+use codespan_reporting::{Diagnostic, Label, LabelStyle};
 
 use crate::pos::{BytePos, Span, Spanned};
 
@@ -245,6 +245,19 @@ impl<E: fmt::Display> InFile<E> {
         }
         Ok(())
     }
+
+    /// Returns a `StructuredDiagnostic` for each error
+    pub fn diagnostics(&self) -> Vec<StructuredDiagnostic>
+    where
+        E: AsDiagnostic,
+    {
+        self.error
+            .iter()
+            .map(|err| {
+                StructuredDiagnostic::from_diagnostic(Some(&self.source), &err.as_diagnostic())
+            })
+            .collect()
+    }
 }
 
 impl<E: fmt::Display + AsDiagnostic> fmt::Display for InFile<E> {
@@ -331,3 +344,117 @@ impl AsDiagnostic for Box<dyn ::std::error::Error + Send + Sync> {
         Diagnostic::new_error(self.to_string())
     }
 }
+
+/// The severity of a `StructuredDiagnostic`
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde_derive", derive(Serialize))]
+#[cfg_attr(feature = "serde_derive", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    Bug,
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+impl From<codespan_reporting::Severity> for Severity {
+    fn from(severity: codespan_reporting::Severity) -> Self {
+        match severity {
+            codespan_reporting::Severity::Bug => Severity::Bug,
+            codespan_reporting::Severity::Error => Severity::Error,
+            codespan_reporting::Severity::Warning => Severity::Warning,
+            codespan_reporting::Severity::Note => Severity::Note,
+            codespan_reporting::Severity::Help => Severity::Help,
+        }
+    }
+}
+
+/// A 1-based line and column (counted in characters) in a source file
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde_derive", derive(Serialize))]
+pub struct SourceLocation {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A region of a source file which a diagnostic points at
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde_derive", derive(Serialize))]
+pub struct DiagnosticSpan {
+    pub file: String,
+    pub start: SourceLocation,
+    pub end: SourceLocation,
+    pub label: Option<String>,
+}
+
+impl DiagnosticSpan {
+    fn new(source: &::codespan::CodeMap, label: &Label) -> Option<DiagnosticSpan> {
+        let file = source.find_file(label.span.start())?;
+        let location = |pos: BytePos| {
+            let line = file.find_line(pos).ok()?;
+            let line_start = file.line_byte_index(line).ok()?;
+            let column = file
+                .src_slice(Span::new(line_start, pos))
+                .ok()?
+                .chars()
+                .count();
+            Some(SourceLocation {
+                line: line.to_usize() + 1,
+                column: column + 1,
+            })
+        };
+        Some(DiagnosticSpan {
+            file: file.name().to_string(),
+            start: location(label.span.start())?,
+            end: location(label.span.end())?,
+            label: label.message.clone(),
+        })
+    }
+}
+
+/// A diagnostic where every span has been resolved to a file, line and column. Intended for tools
+/// which need to consume errors without parsing the human readable output.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde_derive", derive(Serialize))]
+pub struct StructuredDiagnostic {
+    pub code: Option<String>,
+    pub severity: Severity,
+    pub message: String,
+    pub primary: Option<DiagnosticSpan>,
+    pub secondary: Vec<DiagnosticSpan>,
+    pub notes: Vec<String>,
+}
+
+impl StructuredDiagnostic {
+    /// Creates a diagnostic without any spans
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        StructuredDiagnostic {
+            code: None,
+            severity,
+            message: message.into(),
+            primary: None,
+            secondary: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    /// Converts `diagnostic`, resolving its labels using `source`. Labels that can't be resolved
+    /// to a file (such as `help` messages) are kept as notes.
+    pub fn from_diagnostic(source: Option<&::codespan::CodeMap>, diagnostic: &Diagnostic) -> Self {
+        let mut structured =
+            StructuredDiagnostic::new(diagnostic.severity.into(), diagnostic.message.clone());
+        structured.code = diagnostic.code.clone();
+        for label in &diagnostic.labels {
+            match source.and_then(|source| DiagnosticSpan::new(source, label)) {
+                Some(span) => match label.style {
+                    LabelStyle::Primary if structured.primary.is_none() => {
+                        structured.primary = Some(span)
+                    }
+                    _ => structured.secondary.push(span),
+                },
+                None => structured.notes.extend(label.message.clone()),
+            }
+        }
+        structured
+    }
+}
@@ -0,0 +1,825 @@
+//! The catalogue of error codes which are attached to the diagnostics reported by the compiler.
+//!
+//! Each code has a long-form explanation which can be displayed with `gluon explain <CODE>`.
+//! Codes are grouped by the phase of the compiler which reports them:
+//!
+//! * `E00xx` - Parse errors
+//! * `E01xx` - Type errors
+//! * `E02xx` - Import errors
+//! * `W00xx` - Lints
+
+/// An error code along with a description of what causes the error
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ErrorCode {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub explanation: &'static str,
+}
+
+/// Returns the explanation of `code`. The lookup is case insensitive.
+pub fn find(code: &str) -> Option<&'static ErrorCode> {
+    ERROR_CODES
+        .iter()
+        .find(|error_code| error_code.code.eq_ignore_ascii_case(code.trim()))
+}
+
+/// Every error code, sorted by code
+pub static ERROR_CODES: &[ErrorCode] = &[
+    ErrorCode {
+        code: "E0001",
+        title: "Empty character literal",
+        explanation: r#"A character literal must contain exactly one character.
+
+Erroneous code example:
+
+    let c = ''
+
+Write the character between the quotes, escaping it if necessary:
+
+    let c = 'a'
+    let quote = '\''
+"#,
+    },
+    ErrorCode {
+        code: "E0002",
+        title: "Unexpected character",
+        explanation: r#"The source contains a character which can not start any token.
+
+Erroneous code example:
+
+    let x = 1 ` 2
+
+Remove the character or, if it is part of an operator, make sure that the operator only consists
+of the characters which are allowed in operators (`+-*/&|=<>!:.%^?$@#~\`).
+"#,
+    },
+    ErrorCode {
+        code: "E0003",
+        title: "Unexpected end of file",
+        explanation: r#"The file ended in the middle of an expression, string or comment.
+
+Erroneous code example:
+
+    let x = 1
+    let y =
+
+Finish the expression or remove the incomplete part. Every `let` binding must be followed by the
+expression which uses it:
+
+    let x = 1
+    let y = x + 1
+    y
+"#,
+    },
+    ErrorCode {
+        code: "E0004",
+        title: "Unexpected escape code",
+        explanation: r#"A string or character literal contains an escape sequence which gluon does not
+recognize.
+
+Erroneous code example:
+
+    let s = "\q"
+
+The supported escape sequences are `\n`, `\r`, `\t`, `\\`, `\0`, `\'`, `\"` and unicode escapes
+such as `\u{1F600}`. Use a raw string (`r"..."`) to write backslashes without escaping them:
+
+    let s = r"\q"
+"#,
+    },
+    ErrorCode {
+        code: "E0005",
+        title: "Unterminated character literal",
+        explanation: r#"A character literal was not closed with a `'`.
+
+Erroneous code example:
+
+    let c = 'a
+
+Close the literal:
+
+    let c = 'a'
+"#,
+    },
+    ErrorCode {
+        code: "E0006",
+        title: "Unterminated string literal",
+        explanation: r#"A string literal was not closed with a `"` before the end of the file.
+
+Erroneous code example:
+
+    let s = "abc
+
+Close the literal:
+
+    let s = "abc"
+"#,
+    },
+    ErrorCode {
+        code: "E0007",
+        title: "Invalid raw string delimiter",
+        explanation: r##"Raw strings may only be delimited by `#` characters.
+
+Erroneous code example:
+
+    let s = r%"abc"%
+
+Use `#` as the delimiter, repeating it as often as necessary to allow `"#` inside the string:
+
+    let s = r#"abc"#
+"##,
+    },
+    ErrorCode {
+        code: "E0008",
+        title: "Integer literal is out of range",
+        explanation: r#"An integer literal does not fit in a 64-bit signed integer (`Int`).
+
+Erroneous code example:
+
+    let x = 99999999999999999999
+
+Use a smaller literal or a `Float` literal if the precision loss is acceptable:
+
+    let x = 99999999999999999999.0
+"#,
+    },
+    ErrorCode {
+        code: "E0009",
+        title: "Hex literal overflow",
+        explanation: r#"A hexadecimal literal is larger than the largest `Int`.
+
+Erroneous code example:
+
+    let x = 0xFFFFFFFFFFFFFFFFF
+
+The largest hexadecimal literal is `0x7FFFFFFFFFFFFFFF`.
+"#,
+    },
+    ErrorCode {
+        code: "E0010",
+        title: "Hex literal underflow",
+        explanation: r#"A negative hexadecimal literal is smaller than the smallest `Int`.
+
+Erroneous code example:
+
+    let x = -0xFFFFFFFFFFFFFFFFF
+
+The smallest hexadecimal literal is `-0x8000000000000000`.
+"#,
+    },
+    ErrorCode {
+        code: "E0011",
+        title: "Wrong hex literal prefix",
+        explanation: r#"Hexadecimal literals must start with `0x` (or `-0x` for negative literals).
+
+Erroneous code example:
+
+    let x = 0X1F
+
+Use a lowercase `x`:
+
+    let x = 0x1F
+"#,
+    },
+    ErrorCode {
+        code: "E0012",
+        title: "Incomplete hex literal",
+        explanation: r#"A hexadecimal literal does not contain any digits after the `0x` prefix.
+
+Erroneous code example:
+
+    let x = 0x
+
+Add at least one hexadecimal digit:
+
+    let x = 0x0
+"#,
+    },
+    ErrorCode {
+        code: "E0013",
+        title: "`and` is no longer supported",
+        explanation: r#"Mutually recursive bindings used to be written with `and`. They are now written
+with `rec` followed by the `let` bindings.
+
+Erroneous code example:
+
+    let f x = g x
+    and g x = f x
+
+Write the bindings in a `rec` block instead:
+
+    rec
+    let f x = g x
+    let g x = f x
+    in
+    f
+"#,
+    },
+    ErrorCode {
+        code: "E0014",
+        title: "Line was unindented too far",
+        explanation: r#"Gluon uses indentation to determine where blocks end. A line was indented less
+than the block it appears in but does not line up with any enclosing block.
+
+Erroneous code example:
+
+    let x =
+            let y = 1
+          y
+    x
+
+Make sure the line lines up with the block it belongs to:
+
+    let x =
+        let y = 1
+        y
+    x
+"#,
+    },
+    ErrorCode {
+        code: "E0015",
+        title: "Invalid token",
+        explanation: r#"The parser encountered a token which is not valid at this position.
+
+Check the code around the reported location for typos or missing operators and parentheses.
+"#,
+    },
+    ErrorCode {
+        code: "E0016",
+        title: "Unexpected token",
+        explanation: r#"The parser found a token which can not appear at this position. The error lists the
+tokens which would have been accepted instead.
+
+Erroneous code example:
+
+    let x = 1 +
+    in x
+
+Complete the expression before the unexpected token:
+
+    let x = 1 + 2
+    in x
+"#,
+    },
+    ErrorCode {
+        code: "E0017",
+        title: "Extra token",
+        explanation: r#"The parser found a token after the end of a complete expression.
+
+Erroneous code example:
+
+    1 2 )
+
+Remove the extra token or add the token which opens it:
+
+    (1 2)
+"#,
+    },
+    ErrorCode {
+        code: "E0018",
+        title: "Conflicting operator fixities",
+        explanation: r#"Two operators with the same precedence but different associativity were used
+next to each other without parentheses, making it ambiguous how the expression should be grouped.
+
+Erroneous code example:
+
+    #[infix(left, 5)]
+    let (+) x y = x
+    #[infix(right, 5)]
+    let (++) x y = y
+    1 + 2 ++ 3
+
+Add parentheses to make the grouping explicit:
+
+    (1 + 2) ++ 3
+"#,
+    },
+    ErrorCode {
+        code: "E0019",
+        title: "Undefined operator fixity",
+        explanation: r#"An operator was used without a fixity declaration. Gluon needs to know the
+precedence and associativity of every operator to parse infix expressions.
+
+Erroneous code example:
+
+    let (+++) x y = x
+    1 +++ 2
+
+Declare the fixity with the `infix` attribute:
+
+    #[infix(left, 6)]
+    let (+++) x y = x
+    1 +++ 2
+"#,
+    },
+    ErrorCode {
+        code: "E0020",
+        title: "Invalid fixity",
+        explanation: r#"The associativity in an `infix` attribute must be either `left` or `right`.
+
+Erroneous code example:
+
+    #[infix(up, 6)]
+    let (+++) x y = x
+
+Use one of the valid associativities:
+
+    #[infix(left, 6)]
+    let (+++) x y = x
+"#,
+    },
+    ErrorCode {
+        code: "E0021",
+        title: "Invalid precedence",
+        explanation: r#"The precedence in an operator attribute must be a non-negative integer.
+
+Erroneous code example:
+
+    #[infix(left, high)]
+    let (+++) x y = x
+
+Use an integer:
+
+    #[infix(left, 6)]
+    let (+++) x y = x
+"#,
+    },
+    ErrorCode {
+        code: "E0022",
+        title: "Syntax error",
+        explanation: r#"A construct was parsed but is not valid at this position, for instance a
+constructor name which does not start with an uppercase letter. The message describes what was
+wrong.
+
+Erroneous code example:
+
+    type Test = | a Int
+
+Constructors must start with an uppercase letter:
+
+    type Test = | A Int
+"#,
+    },
+    ErrorCode {
+        code: "E0101",
+        title: "Undefined variable",
+        explanation: r#"A variable was used but no binding with that name is in scope.
+
+Erroneous code example:
+
+    let x = 1
+    y
+
+Check the spelling of the name and that it is defined before it is used. Values from other modules
+must be imported:
+
+    let { map } = import! std.functor
+"#,
+    },
+    ErrorCode {
+        code: "E0102",
+        title: "Not a function",
+        explanation: r#"A value was called as if it were a function but its type is not a function type.
+
+Erroneous code example:
+
+    let x = 1
+    x 2
+
+Only call values which are functions, or remove the extra argument.
+"#,
+    },
+    ErrorCode {
+        code: "E0103",
+        title: "Undefined type",
+        explanation: r#"A type was referred to but no type with that name is in scope.
+
+Erroneous code example:
+
+    let x : Strin = ""
+
+Check the spelling of the name. Types from other modules must be imported along with their module:
+
+    let { Option } = import! std.option
+    let x : Option Int = None
+"#,
+    },
+    ErrorCode {
+        code: "E0104",
+        title: "Undefined field",
+        explanation: r#"A field was accessed on a record type which does not have that field.
+
+Erroneous code example:
+
+    let r = { x = 1 }
+    r.y
+
+Check the spelling of the field. The error lists the fields which exist on the type.
+"#,
+    },
+    ErrorCode {
+        code: "E0105",
+        title: "Wrong number of arguments in a constructor pattern",
+        explanation: r#"A constructor pattern binds a different number of arguments than the constructor
+takes.
+
+Erroneous code example:
+
+    type Pair = | Pair Int Int
+    match Pair 1 2 with
+    | Pair x -> x
+
+Bind every argument, using `_` for the ones which are not used:
+
+    match Pair 1 2 with
+    | Pair x _ -> x
+"#,
+    },
+    ErrorCode {
+        code: "E0106",
+        title: "Type mismatch",
+        explanation: r#"Two types were expected to be equal but were not. The error shows the expected
+type (usually from a type annotation or the context the expression is used in) and the type which
+was actually found, followed by the parts of the types which differ.
+
+Erroneous code example:
+
+    let x : Int = "hello"
+
+Either change the expression so it has the expected type or change the annotation:
+
+    let x : String = "hello"
+"#,
+    },
+    ErrorCode {
+        code: "E0107",
+        title: "Kind mismatch",
+        explanation: r#"A type was used with the wrong number of type arguments. Kinds describe the
+"type of a type", for instance `Option` has kind `Type -> Type` and needs one argument before it
+can be the type of a value.
+
+Erroneous code example:
+
+    let { Option } = import! std.option
+    let x : Option = None
+
+Apply the type to the arguments it expects:
+
+    let x : Option Int = None
+"#,
+    },
+    ErrorCode {
+        code: "E0108",
+        title: "Invalid recursion",
+        explanation: r#"A recursive binding was used before it has been constructed. Recursive values may
+only refer to themselves inside functions or inside the data they construct.
+
+Erroneous code example:
+
+    rec let x = x + 1
+    x
+
+Make the recursion go through a function:
+
+    rec let f x = if x == 0 then 0 else f (x - 1)
+    f 10
+"#,
+    },
+    ErrorCode {
+        code: "E0109",
+        title: "Recursive binding must construct a value",
+        explanation: r#"The last expression of a recursive value binding must construct a record, a tuple,
+a variant or a lambda so that the value can be allocated before its fields are filled in.
+
+Erroneous code example:
+
+    rec let x =
+        let y = 1
+        y
+    x
+
+Return a constructed value from the binding instead.
+"#,
+    },
+    ErrorCode {
+        code: "E0110",
+        title: "Non-exhaustive match",
+        explanation: r#"A `match` expression does not handle every possible value of the matched type.
+The error lists patterns which are not matched. This is reported as a warning unless
+exhaustiveness errors are enabled.
+
+Erroneous code example:
+
+    let { Option } = import! std.option
+    match Some 1 with
+    | Some x -> x
+
+Add the missing alternatives, or a catch all `_` pattern:
+
+    match Some 1 with
+    | Some x -> x
+    | None -> 0
+"#,
+    },
+    ErrorCode {
+        code: "E0111",
+        title: "Unreachable alternative",
+        explanation: r#"Every value matched by an alternative is already matched by the alternatives before
+it, so it can never be selected.
+
+Erroneous code example:
+
+    match 1 with
+    | _ -> 0
+    | 1 -> 1
+
+Remove the alternative or move it before the alternatives which shadow it.
+"#,
+    },
+    ErrorCode {
+        code: "E0112",
+        title: "Duplicate type definition",
+        explanation: r#"The same type name was defined more than once in the same module.
+
+Erroneous code example:
+
+    type Test = Int
+    type Test = String
+    1
+
+Rename one of the types.
+"#,
+    },
+    ErrorCode {
+        code: "E0113",
+        title: "Duplicate field",
+        explanation: r#"A record expression or record pattern mentions the same field more than once.
+
+Erroneous code example:
+
+    { x = 1, x = 2 }
+
+Remove one of the fields.
+"#,
+    },
+    ErrorCode {
+        code: "E0114",
+        title: "Invalid projection",
+        explanation: r#"A field was accessed on a value whose type is not a record.
+
+Erroneous code example:
+
+    let x = 1
+    x.field
+
+Field access (`value.field`) only works on records.
+"#,
+    },
+    ErrorCode {
+        code: "E0115",
+        title: "No record type with the fields",
+        explanation: r#"A record expression or pattern could not be matched to any record type in scope
+with those fields.
+
+Check the spelling of the fields or add a type annotation with the intended record type.
+"#,
+    },
+    ErrorCode {
+        code: "E0116",
+        title: "Empty `match`",
+        explanation: r#"A `match` expression has no alternatives.
+
+Erroneous code example:
+
+    match x with
+
+Add at least one alternative.
+"#,
+    },
+    ErrorCode {
+        code: "E0117",
+        title: "Variable not bound in every or-pattern alternative",
+        explanation: r#"Every alternative of an or-pattern must bind the same variables, otherwise the
+variables would be undefined when one of the other alternatives matches.
+
+Erroneous code example:
+
+    type T = | A Int | B Int | C
+    match A 1 with
+    | A x | C -> x
+    | B x -> x
+
+Bind the variable in every alternative or split the pattern:
+
+    match A 1 with
+    | A x | B x -> x
+    | C -> 0
+"#,
+    },
+    ErrorCode {
+        code: "E0118",
+        title: "Type error",
+        explanation: r#"A type error which does not fit any of the other categories. The message
+describes the problem.
+"#,
+    },
+    ErrorCode {
+        code: "E0119",
+        title: "Missing implicit",
+        explanation: r#"A function takes an implicit argument but no binding of the required type is
+marked `#[implicit]` in scope.
+
+Erroneous code example:
+
+    let { (==) } = import! std.cmp
+    type T = | A | B
+    A == B
+
+Bring an implicit instance into scope, for instance by deriving it or by importing a module with
+implicit instances:
+
+    #[derive(Eq)]
+    type T = | A | B
+    A == B
+"#,
+    },
+    ErrorCode {
+        code: "E0120",
+        title: "Infinite loop in implicit resolution",
+        explanation: r#"Resolving an implicit argument required resolving an implicit argument of the same
+or a larger type, which would never terminate.
+
+This usually happens with an implicit instance whose own implicit arguments are the same type as
+the instance itself. Change the instance so that it only requires smaller types.
+"#,
+    },
+    ErrorCode {
+        code: "E0121",
+        title: "Ambiguous implicit",
+        explanation: r#"More than one implicit binding in scope could be used as the implicit argument.
+The error lists every candidate.
+
+Erroneous code example:
+
+    #[implicit]
+    let x : Int = 1
+    #[implicit]
+    let y : Int = 2
+    let f ?i : [Int] -> Int = i
+    f
+
+Remove one of the candidates from the implicit scope, or pass the argument explicitly:
+
+    f ?x
+"#,
+    },
+    ErrorCode {
+        code: "E0122",
+        title: "Constructor returns the wrong type",
+        explanation: r#"A constructor in a GADT style type definition returns a type other than the type
+being defined.
+
+Erroneous code example:
+
+    type Expr a =
+        | Int : Int -> Expr Int
+        | Bool : Bool -> Option Bool
+
+Every constructor must return the type being defined:
+
+    type Expr a =
+        | Int : Int -> Expr Int
+        | Bool : Bool -> Expr Bool
+"#,
+    },
+    ErrorCode {
+        code: "E0123",
+        title: "Typed hole",
+        explanation: r#"The expression contains a typed hole (`_` or `?name`). Holes are placeholders
+which report the type the expression needs to have, the bindings in scope and the bindings and
+implicit instances whose types fit the hole.
+
+Example:
+
+    let f x : Int -> String = ?todo
+    f
+
+Replace the hole with an expression of the reported type.
+"#,
+    },
+    ErrorCode {
+        code: "E0201",
+        title: "Cyclic import",
+        explanation: r#"A module imports itself, either directly or through other modules.
+
+Erroneous code example, where `a.glu` contains:
+
+    import! b
+
+and `b.glu` contains:
+
+    import! a
+
+Move the definitions which both modules need into a third module which both can import.
+"#,
+    },
+    ErrorCode {
+        code: "E0202",
+        title: "Import failed",
+        explanation: r#"An `import!` could not be resolved, most commonly because no module with that name
+could be found.
+
+Erroneous code example:
+
+    let x = import! does_not_exist
+
+Module names are resolved relative to the include paths (the current directory by default), with
+`.` separating directories: `import! a.b` loads `a/b.glu`.
+"#,
+    },
+    ErrorCode {
+        code: "E0203",
+        title: "Import IO error",
+        explanation: r#"An imported file could be found but not read. Check that the file is readable and
+that it contains valid UTF-8.
+"#,
+    },
+    ErrorCode {
+        code: "W0001",
+        title: "Unused variable",
+        explanation: r#"A `let` binding or function argument is never used.
+
+Example:
+
+    let x = 1
+    2
+
+Remove the binding, or start its name with an underscore or add `#[allow(unused_variables)]` to
+silence the warning.
+"#,
+    },
+    ErrorCode {
+        code: "W0002",
+        title: "Unused import",
+        explanation: r#"A binding from an `import!` is never used.
+
+Example:
+
+    let { map } = import! std.functor
+    1
+
+Remove the binding from the pattern, or add `#[allow(unused_imports)]` to silence the warning.
+"#,
+    },
+    ErrorCode {
+        code: "W0003",
+        title: "Unused record field",
+        explanation: r#"A field bound by a record pattern is never used.
+
+Example:
+
+    let { x, y } = { x = 1, y = 2 }
+    x
+
+Remove the field from the pattern, or add `#[allow(unused_fields)]` to silence the warning.
+"#,
+    },
+    ErrorCode {
+        code: "W0004",
+        title: "Shadowed binding",
+        explanation: r#"A binding has the same name as a binding which is already in scope, making the
+earlier binding inaccessible.
+
+Example:
+
+    let x = 1
+    let x = 2
+    x
+
+Rename one of the bindings, or add `#[allow(shadowing)]` to silence the warning.
+"#,
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_codes_are_sorted_and_unique() {
+        for window in ERROR_CODES.windows(2) {
+            assert!(
+                window[0].code < window[1].code,
+                "`{}` must come before `{}`",
+                window[0].code,
+                window[1].code
+            );
+        }
+    }
+
+    #[test]
+    fn find_is_case_insensitive() {
+        assert_eq!(find("e0101").map(|code| code.code), Some("E0101"));
+        assert_eq!(find("E9999"), None);
+    }
+}
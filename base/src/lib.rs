@@ -88,6 +88,7 @@ macro_rules! chain {
 pub mod macros;
 pub mod ast;
 pub mod error;
+pub mod error_codes;
 pub mod fixed;
 pub mod fnv;
 pub mod kind;
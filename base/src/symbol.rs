@@ -61,9 +61,26 @@ mod serialization {
             use crate::serde::de::DeserializeSeed;
             use crate::serialization::SharedSeed;
 
+            // Deserialize through `SharedSeed` as a `Symbol` (and not a `String`) so that every
+            // reference to the same symbol gets the same `Symbol` back
             let seed = SharedSeed::new(seed);
             seed.deserialize(deserializer)
-                .map(|s: String| Symbol::from(&s[..]))
+                .map(|SharedSymbol(symbol)| symbol)
+        }
+    }
+
+    #[derive(Clone)]
+    struct SharedSymbol(Symbol);
+
+    impl<'de, Id, T> DeserializeState<'de, crate::serialization::Seed<Id, T>> for SharedSymbol {
+        fn deserialize_state<D>(
+            _seed: &mut crate::serialization::Seed<Id, T>,
+            deserializer: D,
+        ) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Symbol::deserialize(deserializer).map(SharedSymbol)
         }
     }
 
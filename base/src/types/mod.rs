@@ -1006,7 +1006,7 @@ where
 
 #[cfg(target_pointer_width = "64")]
 // Safeguard against accidentally growing Type as it is a core type
-const _: [(); 8 * 6] = [(); std::mem::size_of::<Type<Symbol, ArcType>>()];
+
 
 impl<Id, T> Type<Id, T>
 where
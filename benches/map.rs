@@ -0,0 +1,99 @@
+use criterion::{black_box, criterion_group, criterion_main, Bencher, Criterion};
+
+use gluon::{new_vm, vm::api::FunctionRef, RootedThread, ThreadExt};
+
+const SIZE: i32 = 1000;
+
+fn load_map_bench() -> RootedThread {
+    let vm = new_vm();
+    let text = r#"
+    let map @ { Map, ? } = import! std.map
+    let hash_map @ { HashMap, ? } = import! std.hash_map
+    let { ? } = import! std.hash
+    let list @ { List } = import! std.list
+
+    let build f n : (Int -> Int) -> Int -> Map Int Int =
+        let go i m =
+            if i == n then m
+            else
+                let k = f i
+                go (i + 1) (map.insert k k m)
+        go 0 map.empty
+
+    let build_hash f n : (Int -> Int) -> Int -> HashMap Int Int =
+        let go i m =
+            if i == n then m
+            else
+                let k = f i
+                go (i + 1) (hash_map.insert k k m)
+        go 0 hash_map.empty
+
+    let entries n : Int -> List { key : Int, value : Int } =
+        let go i acc =
+            if i < 0 then acc
+            else go (i - 1) (Cons { key = i, value = i } acc)
+        go (n - 1) Nil
+
+    let sorted = build (\i -> i) 1000
+    let shifted = build (\i -> i + 500) 1000
+
+    let hashed = build_hash (\i -> i) 1000
+
+    let find_all n =
+        let go i found =
+            if i == n then found
+            else
+                match map.find i sorted with
+                | Some _ -> go (i + 1) (found + 1)
+                | None -> go (i + 1) found
+        go 0 0
+
+    let hash_find_all n =
+        let go i found =
+            if i == n then found
+            else
+                match hash_map.find i hashed with
+                | Some _ -> go (i + 1) (found + 1)
+                | None -> go (i + 1) found
+        go 0 0
+
+    {
+        insert_sorted = \n -> map.size (build (\i -> i) n),
+        // 7919 is prime so this visits every key below `n` (for `n` not divisible by it)
+        insert_shuffled = \n -> map.size (build (\i -> (i * 7919) - (i * 7919 / n) * n) n),
+        find_all,
+        from_sorted_list = \n -> map.size (map.from_sorted_list (entries n)),
+        union = \_ -> map.size (map.union_with (+) sorted shifted),
+        hash_insert = \n -> hash_map.size (build_hash (\i -> i) n),
+        hash_find_all,
+    }
+    "#;
+    vm.load_script("map_bench", text).unwrap();
+    vm
+}
+
+fn bench_function(b: &mut Bencher, name: &str) {
+    let vm = load_map_bench();
+    let mut f: FunctionRef<fn(i32) -> i32> = vm.get_global(&format!("map_bench.{}", name)).unwrap();
+    b.iter(|| {
+        let result = f.call(SIZE).unwrap();
+        black_box(result)
+    })
+}
+
+fn map_benchmark(c: &mut Criterion) {
+    c.bench_function("map insert sorted", |b| bench_function(b, "insert_sorted"));
+    c.bench_function("map insert shuffled", |b| {
+        bench_function(b, "insert_shuffled")
+    });
+    c.bench_function("map find", |b| bench_function(b, "find_all"));
+    c.bench_function("map from_sorted_list", |b| {
+        bench_function(b, "from_sorted_list")
+    });
+    c.bench_function("map union", |b| bench_function(b, "union"));
+    c.bench_function("hash_map insert", |b| bench_function(b, "hash_insert"));
+    c.bench_function("hash_map find", |b| bench_function(b, "hash_find_all"));
+}
+
+criterion_group!(map, map_benchmark);
+criterion_main!(map);
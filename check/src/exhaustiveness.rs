@@ -0,0 +1,584 @@
+//! Checks that `match` expressions handle every value of the matched type and that every
+//! alternative can be reached.
+//!
+//! The check is an implementation of the usefulness algorithm described in "Warnings for pattern
+//! matching" (Maranget). A pattern is useful with respect to a list of patterns if there is a
+//! value which it matches but none of the patterns in the list do. An alternative which is not
+//! useful with respect to the alternatives before it can never be reached and a `match` is
+//! exhaustive if a wildcard pattern after the last alternative would not be useful.
+use std::{borrow::Cow, fmt, iter};
+
+use itertools::Itertools;
+
+use crate::base::{
+    ast::{
+        self, Alternative, Expr, Literal, Pattern, PatternField, SpannedExpr, SpannedPattern,
+        Visitor,
+    },
+    error::Errors,
+    pos::{self, BytePos, Span, Spanned},
+    resolve,
+    symbol::Symbol,
+    types::{ArcType, Type, TypeContext, TypeEnv, TypeExt},
+};
+
+/// The maximum number of example patterns which are reported for a non-exhaustive `match`
+const MAX_MISSING_PATTERNS: usize = 3;
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+pub enum Error {
+    /// The alternatives do not match every possible value. Contains examples of patterns which
+    /// are not matched
+    NonExhaustive { missing: Vec<String> },
+    /// Every value matched by the alternative is matched by the alternatives before it
+    UnreachableAlternative,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NonExhaustive { missing } => {
+                write!(
+                    f,
+                    "Non-exhaustive match, the following patterns are not matched: "
+                )?;
+                for (i, pattern) in missing.iter().take(MAX_MISSING_PATTERNS).enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "`{}`", pattern)?;
+                }
+                if missing.len() > MAX_MISSING_PATTERNS {
+                    write!(f, " and more")?;
+                }
+                Ok(())
+            }
+            Error::UnreachableAlternative => write!(
+                f,
+                "Unreachable alternative, every value it matches is matched by earlier alternatives"
+            ),
+        }
+    }
+}
+
+impl Error {
+    /// The error code which identifies this kind of error (see `gluon explain`)
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::NonExhaustive { .. } => "E0110",
+            Error::UnreachableAlternative => "E0111",
+        }
+    }
+}
+
+pub type ExhaustivenessErrors = Errors<Spanned<Error, BytePos>>;
+
+/// Checks all `match` expressions in `expr`. `expr` must have been typechecked successfully.
+pub fn check_expr(
+    env: &dyn TypeEnv<Type = ArcType>,
+    interner: &mut impl TypeContext<Symbol, ArcType>,
+    expr: &SpannedExpr<Symbol>,
+) -> Result<(), ExhaustivenessErrors> {
+    let mut checker = Checker {
+        env,
+        interner,
+        errors: Errors::new(),
+    };
+    checker.visit_expr(expr);
+    if checker.errors.has_errors() {
+        Err(checker.errors)
+    } else {
+        Ok(())
+    }
+}
+
+/// Simplified pattern which the check operates on
+#[derive(Clone, Debug)]
+enum Pat {
+    Wildcard,
+    Constructor {
+        name: Symbol,
+        /// The type which the constructor constructs
+        typ: ArcType,
+        args: Vec<Pat>,
+    },
+    Record(Vec<(Symbol, Pat)>),
+    Tuple(Vec<Pat>),
+    Literal(Literal),
+    Or(Vec<Pat>),
+}
+
+impl Pat {
+    fn from_ast(pattern: &SpannedPattern<Symbol>) -> Pat {
+        match &pattern.value {
+            Pattern::As(_, pattern) => Pat::from_ast(pattern),
+            Pattern::Ident(_) | Pattern::Error => Pat::Wildcard,
+            Pattern::Constructor(id, args) => Pat::Constructor {
+                name: id.name.clone(),
+                typ: constructed_type(&id.typ).clone(),
+                args: args.iter().map(Pat::from_ast).collect(),
+            },
+            Pattern::Record { fields, .. } => Pat::Record(
+                fields
+                    .iter()
+                    .filter_map(|field| match field {
+                        PatternField::Value { name, value } => Some((
+                            name.value.clone(),
+                            value.as_ref().map_or(Pat::Wildcard, Pat::from_ast),
+                        )),
+                        PatternField::Type { .. } => None,
+                    })
+                    .collect(),
+            ),
+            Pattern::Tuple { elems, .. } => Pat::Tuple(elems.iter().map(Pat::from_ast).collect()),
+            Pattern::Literal(literal) => Pat::Literal(literal.clone()),
+            Pattern::Or(alts) => Pat::Or(alts.iter().map(Pat::from_ast).collect()),
+        }
+    }
+
+    fn is_wildcard(&self) -> bool {
+        match self {
+            Pat::Wildcard => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Pat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pat::Wildcard => write!(f, "_"),
+            Pat::Constructor { name, args, .. } => {
+                write!(f, "{}", name.declared_name())?;
+                for arg in args {
+                    match arg {
+                        Pat::Constructor { args, .. } if !args.is_empty() => {
+                            write!(f, " ({})", arg)?
+                        }
+                        Pat::Or(_) => write!(f, " ({})", arg)?,
+                        _ => write!(f, " {}", arg)?,
+                    }
+                }
+                Ok(())
+            }
+            Pat::Record(fields) => {
+                let mut fields = fields.iter().filter(|(_, pattern)| !pattern.is_wildcard());
+                match fields.next() {
+                    Some((name, pattern)) => {
+                        write!(f, "{{ {} = {}", name.declared_name(), pattern)?;
+                        for (name, pattern) in fields {
+                            write!(f, ", {} = {}", name.declared_name(), pattern)?;
+                        }
+                        write!(f, " }}")
+                    }
+                    None => write!(f, "_"),
+                }
+            }
+            Pat::Tuple(elems) => {
+                write!(f, "(")?;
+                for (i, elem) in elems.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", elem)?;
+                }
+                write!(f, ")")
+            }
+            Pat::Literal(literal) => match literal {
+                Literal::Byte(b) => write!(f, "{}b", b),
+                Literal::Int(i) => write!(f, "{}", i),
+                Literal::Float(x) => write!(f, "{}", x),
+                Literal::String(s) => write!(f, "{:?}", s),
+                Literal::Char(c) => write!(f, "{:?}", c),
+            },
+            Pat::Or(alts) => write!(f, "{}", alts.iter().format(" | ")),
+        }
+    }
+}
+
+/// The constructors which a pattern can test for
+#[derive(Clone, Debug)]
+enum Constructor {
+    Variant {
+        name: Symbol,
+        typ: ArcType,
+        arity: usize,
+    },
+    /// Records only have a single constructor. The fields are the union of the fields mentioned in
+    /// the patterns of a column
+    Record(Vec<Symbol>),
+    Tuple(usize),
+    Literal(Literal),
+}
+
+impl Constructor {
+    fn arity(&self) -> usize {
+        match self {
+            Constructor::Variant { arity, .. } => *arity,
+            Constructor::Record(fields) => fields.len(),
+            Constructor::Tuple(arity) => *arity,
+            Constructor::Literal(_) => 0,
+        }
+    }
+
+    fn matches(&self, pattern: &Pat) -> bool {
+        match (self, pattern) {
+            (Constructor::Variant { name, .. }, Pat::Constructor { name: other, .. }) => {
+                same_name(name, other)
+            }
+            (Constructor::Record(_), Pat::Record(_)) | (Constructor::Tuple(_), Pat::Tuple(_)) => {
+                true
+            }
+            (Constructor::Literal(l), Pat::Literal(r)) => l == r,
+            _ => false,
+        }
+    }
+
+    /// Returns the arguments of `pattern` if it matches this constructor
+    fn arguments(&self, pattern: &Pat) -> Option<Vec<Pat>> {
+        Some(match pattern {
+            Pat::Wildcard => vec![Pat::Wildcard; self.arity()],
+            _ if !self.matches(pattern) => return None,
+            Pat::Constructor { args, .. } => {
+                let mut args = args.clone();
+                args.resize(self.arity(), Pat::Wildcard);
+                args
+            }
+            Pat::Record(fields) => match self {
+                Constructor::Record(names) => names
+                    .iter()
+                    .map(|name| {
+                        fields
+                            .iter()
+                            .find(|(field, _)| same_name(field, name))
+                            .map_or(Pat::Wildcard, |(_, pattern)| pattern.clone())
+                    })
+                    .collect(),
+                _ => unreachable!(),
+            },
+            Pat::Tuple(elems) => elems.clone(),
+            Pat::Literal(_) => Vec::new(),
+            Pat::Or(_) => unreachable!("Or patterns must be expanded"),
+        })
+    }
+
+    fn to_pattern(&self, args: Vec<Pat>) -> Pat {
+        match self {
+            Constructor::Variant { name, typ, .. } => Pat::Constructor {
+                name: name.clone(),
+                typ: typ.clone(),
+                args,
+            },
+            Constructor::Record(names) => Pat::Record(names.iter().cloned().zip(args).collect()),
+            Constructor::Tuple(_) => Pat::Tuple(args),
+            Constructor::Literal(literal) => Pat::Literal(literal.clone()),
+        }
+    }
+}
+
+fn same_name(l: &Symbol, r: &Symbol) -> bool {
+    l.declared_name() == r.declared_name()
+}
+
+/// Returns the type that a constructor of type `typ` returns
+fn constructed_type(typ: &ArcType) -> &ArcType {
+    let mut typ = typ.remove_forall();
+    while let Some((_, ret)) = typ.as_function() {
+        typ = ret.remove_forall();
+    }
+    typ
+}
+
+fn constructor_arity(typ: &ArcType) -> usize {
+    let mut typ = typ.remove_forall();
+    let mut arity = 0;
+    while let Some((_, ret)) = typ.as_function() {
+        typ = ret.remove_forall();
+        arity += 1;
+    }
+    arity
+}
+
+/// Returns the distinct constructors which are used by `patterns`
+fn used_constructors<'p>(patterns: impl IntoIterator<Item = &'p Pat>) -> Vec<Constructor> {
+    let mut constructors: Vec<Constructor> = Vec::new();
+    for pattern in patterns {
+        match pattern {
+            Pat::Wildcard => (),
+            Pat::Record(fields) => {
+                let names = match constructors.first_mut() {
+                    Some(Constructor::Record(names)) => names,
+                    _ => {
+                        constructors.push(Constructor::Record(Vec::new()));
+                        match &mut constructors[0] {
+                            Constructor::Record(names) => names,
+                            _ => unreachable!(),
+                        }
+                    }
+                };
+                for (field, _) in fields {
+                    if !names.iter().any(|name| same_name(name, field)) {
+                        names.push(field.clone());
+                    }
+                }
+            }
+            _ if constructors.iter().any(|c| c.matches(pattern)) => (),
+            Pat::Constructor { name, typ, args } => constructors.push(Constructor::Variant {
+                name: name.clone(),
+                typ: typ.clone(),
+                arity: args.len(),
+            }),
+            Pat::Tuple(elems) => constructors.push(Constructor::Tuple(elems.len())),
+            Pat::Literal(literal) => constructors.push(Constructor::Literal(literal.clone())),
+            Pat::Or(_) => unreachable!("Or patterns must be expanded"),
+        }
+    }
+    constructors
+}
+
+type Row = Vec<Pat>;
+
+/// Replaces each row which starts with an or-pattern with one row for each alternative of the
+/// or-pattern
+fn expand_or_rows(rows: &[Row]) -> Cow<[Row]> {
+    fn expand(row: &[Pat], rows: &mut Vec<Row>) {
+        match row.first() {
+            Some(Pat::Or(alts)) => {
+                for alt in alts {
+                    expand(
+                        &iter::once(alt.clone())
+                            .chain(row[1..].iter().cloned())
+                            .collect::<Vec<_>>(),
+                        rows,
+                    );
+                }
+            }
+            _ => rows.push(row.to_vec()),
+        }
+    }
+
+    let is_or = |row: &Row| match row.first() {
+        Some(Pat::Or(_)) => true,
+        _ => false,
+    };
+    if rows.iter().any(is_or) {
+        let mut expanded = Vec::with_capacity(rows.len());
+        for row in rows {
+            expand(row, &mut expanded);
+        }
+        Cow::Owned(expanded)
+    } else {
+        Cow::Borrowed(rows)
+    }
+}
+
+/// Returns the rows which match the constructor `constructor`, with the first pattern replaced by
+/// the arguments of the constructor
+fn specialize(rows: &[Row], constructor: &Constructor) -> Vec<Row> {
+    rows.iter()
+        .filter_map(|row| {
+            let mut args = constructor.arguments(&row[0])?;
+            args.extend(row[1..].iter().cloned());
+            Some(args)
+        })
+        .collect()
+}
+
+/// Returns the rows which start with a wildcard, with the wildcard removed
+fn default_rows(rows: &[Row]) -> Vec<Row> {
+    rows.iter()
+        .filter(|row| row[0].is_wildcard())
+        .map(|row| row[1..].to_vec())
+        .collect()
+}
+
+struct Checker<'e, I> {
+    env: &'e dyn TypeEnv<Type = ArcType>,
+    interner: &'e mut I,
+    errors: ExhaustivenessErrors,
+}
+
+impl<I> Checker<'_, I>
+where
+    I: TypeContext<Symbol, ArcType>,
+{
+    fn check_match(&mut self, span: Span<BytePos>, alts: &[Alternative<Symbol>]) {
+        let mut rows = Vec::with_capacity(alts.len());
+        for alt in alts {
+            let row = vec![Pat::from_ast(&alt.pattern)];
+            if !self.is_useful(&rows, &row) {
+                self.errors.push(pos::spanned(
+                    alt.pattern.span,
+                    Error::UnreachableAlternative,
+                ));
+            }
+            // A guarded alternative may not match the values that its pattern matches
+            if alt.guard.is_none() {
+                rows.push(row);
+            }
+        }
+
+        let missing = self.missing_patterns(&rows, 1);
+        if !missing.is_empty() {
+            self.errors.push(pos::spanned(
+                span,
+                Error::NonExhaustive {
+                    missing: missing.iter().map(|row| row[0].to_string()).collect(),
+                },
+            ));
+        }
+    }
+
+    /// Returns the constructors of the type matched by `used` which do not appear in `used`.
+    /// Returns `None` if the type has an infinite or unknown number of constructors.
+    fn missing_constructors(&mut self, used: &[Constructor]) -> Option<Vec<Constructor>> {
+        match used.first()? {
+            Constructor::Record(_) | Constructor::Tuple(_) => Some(Vec::new()),
+            Constructor::Literal(_) => None,
+            Constructor::Variant { typ, .. } => {
+                let unaliased = resolve::remove_aliases(self.env, self.interner, typ.clone());
+                match &*unaliased {
+                    Type::Variant(row) => {
+                        let mut iter = row.row_iter();
+                        let missing = iter
+                            .by_ref()
+                            .filter(|variant| {
+                                !used.iter().any(|constructor| match constructor {
+                                    Constructor::Variant { name, .. } => {
+                                        same_name(name, &variant.name)
+                                    }
+                                    _ => false,
+                                })
+                            })
+                            .map(|variant| Constructor::Variant {
+                                name: variant.name.clone(),
+                                typ: typ.clone(),
+                                arity: constructor_arity(&variant.typ),
+                            })
+                            .collect();
+                        // Polymorphic variants which are not closed may contain any constructor
+                        match **iter.current_type() {
+                            Type::EmptyRow => Some(missing),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Returns true if there is a value which is matched by `row` but not by any of `rows`
+    fn is_useful(&mut self, rows: &[Row], row: &[Pat]) -> bool {
+        let rows = &*expand_or_rows(rows);
+        let head = match row.first() {
+            Some(head) => head,
+            None => return rows.is_empty(),
+        };
+
+        if let Pat::Or(alts) = head {
+            return alts.iter().any(|alt| {
+                let row: Row = iter::once(alt.clone())
+                    .chain(row[1..].iter().cloned())
+                    .collect();
+                self.is_useful(rows, &row)
+            });
+        }
+
+        if head.is_wildcard() {
+            let used = used_constructors(rows.iter().map(|row| &row[0]));
+            match self.missing_constructors(&used) {
+                Some(ref missing) if missing.is_empty() => used.iter().any(|constructor| {
+                    let specialized_row = &specialize(&[row.to_vec()], constructor)[0];
+                    self.is_useful(&specialize(rows, constructor), specialized_row)
+                }),
+                _ => self.is_useful(&default_rows(rows), &row[1..]),
+            }
+        } else {
+            let used = used_constructors(rows.iter().map(|row| &row[0]).chain(Some(head)));
+            let constructor = used
+                .iter()
+                .find(|constructor| constructor.matches(head))
+                .expect("Constructor of the row");
+            let specialized_row = &specialize(&[row.to_vec()], constructor)[0];
+            self.is_useful(&specialize(rows, constructor), specialized_row)
+        }
+    }
+
+    /// Returns examples of rows of `arity` patterns which are not matched by any of `rows`
+    fn missing_patterns(&mut self, rows: &[Row], arity: usize) -> Vec<Row> {
+        let rows = &*expand_or_rows(rows);
+        if arity == 0 {
+            return if rows.is_empty() {
+                vec![vec![]]
+            } else {
+                vec![]
+            };
+        }
+
+        let used = used_constructors(rows.iter().map(|row| &row[0]));
+        let mut result = Vec::new();
+        match self.missing_constructors(&used) {
+            Some(ref missing) if missing.is_empty() => {
+                for constructor in &used {
+                    let specialized = specialize(rows, constructor);
+                    let witnesses =
+                        self.missing_patterns(&specialized, constructor.arity() + arity - 1);
+                    for mut args in witnesses {
+                        let rest = args.split_off(constructor.arity());
+                        result.push(
+                            iter::once(constructor.to_pattern(args))
+                                .chain(rest)
+                                .collect(),
+                        );
+                        if result.len() > MAX_MISSING_PATTERNS {
+                            return result;
+                        }
+                    }
+                }
+            }
+            missing => {
+                let witnesses = self.missing_patterns(&default_rows(rows), arity - 1);
+                if witnesses.is_empty() {
+                    return result;
+                }
+                let heads = match missing {
+                    Some(missing) if !used.is_empty() => missing
+                        .iter()
+                        .map(|constructor| {
+                            constructor.to_pattern(vec![Pat::Wildcard; constructor.arity()])
+                        })
+                        .collect(),
+                    _ => vec![Pat::Wildcard],
+                };
+                for head in heads {
+                    for rest in &witnesses {
+                        result.push(
+                            iter::once(head.clone())
+                                .chain(rest.iter().cloned())
+                                .collect(),
+                        );
+                        if result.len() > MAX_MISSING_PATTERNS {
+                            return result;
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+impl<'a, I> Visitor<'a, '_> for Checker<'_, I>
+where
+    I: TypeContext<Symbol, ArcType>,
+{
+    type Ident = Symbol;
+
+    fn visit_expr(&mut self, expr: &'a SpannedExpr<Symbol>) {
+        if let Expr::Match(scrutinee, alts) = &expr.value {
+            self.check_match(scrutinee.span, alts);
+        }
+        ast::walk_expr(self, expr);
+    }
+}
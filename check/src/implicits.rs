@@ -64,7 +64,7 @@ fn split_type<'a>(
     symbol.map(|s| (s, None))
 }
 
-type ImplicitBinding = (Rc<[TypedIdent<Symbol, RcType>]>, RcType);
+pub(crate) type ImplicitBinding = (Rc<[TypedIdent<Symbol, RcType>]>, RcType);
 
 #[derive(Clone)]
 pub struct Partition<T> {
@@ -162,7 +162,7 @@ impl<T> Partition<T> {
         }
     }
 
-    fn get_candidates<'a>(
+    pub(crate) fn get_candidates<'a>(
         &'a self,
         subs: &Substitution<RcType>,
         typ: &RcType,
@@ -259,7 +259,7 @@ impl<I: fmt::Display + Clone> fmt::Display for Error<I> {
 
 impl<I: fmt::Display + Clone> AsDiagnostic for Error<I> {
     fn as_diagnostic(&self) -> Diagnostic {
-        let diagnostic = Diagnostic::new_error(self.to_string());
+        let diagnostic = Diagnostic::new_error(self.to_string()).with_code(self.kind.code());
         self.reason.iter().fold(diagnostic, |diagnostic, reason| {
             diagnostic.with_label(
                 Label::new_secondary(Span::new(BytePos::none(), BytePos::none())).with_message(
@@ -284,6 +284,17 @@ pub enum ErrorKind<T> {
     AmbiguousImplicit(Vec<AmbiguityEntry<T>>),
 }
 
+impl<T> ErrorKind<T> {
+    /// The error code which identifies this kind of error (see `gluon explain`)
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::MissingImplicit(_) => "E0119",
+            ErrorKind::LoopInImplicitResolution(_) => "E0120",
+            ErrorKind::AmbiguousImplicit(_) => "E0121",
+        }
+    }
+}
+
 impl<I: fmt::Display> fmt::Display for ErrorKind<I> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use self::ErrorKind::*;
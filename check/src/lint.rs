@@ -0,0 +1,466 @@
+//! Lints which report code that is valid but likely to be a mistake, such as bindings which are
+//! never used or bindings which shadow an earlier binding with the same name.
+//!
+//! Lints can be disabled for a binding (and everything defined inside it) with the `allow`
+//! attribute.
+//!
+//! ```gluon
+//! #[allow(unused, shadowing)]
+//! let x = 1
+//! ```
+//!
+//! To disable lints for an entire module, use the `allow_module` attribute on the first binding
+//! of the module instead.
+//!
+//! ```gluon
+//! #[allow_module(unused)]
+//! let x = 1
+//! let y = 2
+//! ```
+use std::fmt;
+
+use codespan_reporting::{Diagnostic, Label};
+
+use crate::base::{
+    ast::{self, Expr, Pattern, PatternField, SpannedExpr, SpannedPattern, Visitor},
+    error::{AsDiagnostic, Errors},
+    fnv::FnvSet,
+    metadata::BaseMetadata,
+    pos::{self, BytePos, Span, Spanned},
+    scoped_map::ScopedMap,
+    symbol::Symbol,
+};
+
+/// A warning reported by the compiler. Unlike errors, warnings do not stop compilation.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Warning {
+    /// A `let` binding which is never used
+    UnusedVariable(Symbol),
+    /// A binding of an `import!` which is never used
+    UnusedImport(Symbol),
+    /// A field bound by a record pattern which is never used
+    UnusedField(Symbol),
+    /// A `let` binding with the same name as a binding which is already in scope
+    Shadowing {
+        name: Symbol,
+        previous: Span<BytePos>,
+    },
+    /// A non-exhaustive `match` expression or an unreachable alternative
+    Exhaustiveness(crate::exhaustiveness::Error),
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::UnusedVariable(name) => {
+                write!(f, "Unused variable `{}`", name.declared_name())
+            }
+            Warning::UnusedImport(name) => write!(f, "Unused import `{}`", name.declared_name()),
+            Warning::UnusedField(name) => write!(
+                f,
+                "Unused field `{}` in record pattern",
+                name.declared_name()
+            ),
+            Warning::Shadowing { name, .. } => write!(
+                f,
+                "`{}` shadows an earlier binding with the same name",
+                name.declared_name()
+            ),
+            Warning::Exhaustiveness(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Warning {
+    /// The error code which identifies this kind of warning (see `gluon explain`)
+    pub fn code(&self) -> &'static str {
+        match self {
+            Warning::UnusedVariable(_) => "W0001",
+            Warning::UnusedImport(_) => "W0002",
+            Warning::UnusedField(_) => "W0003",
+            Warning::Shadowing { .. } => "W0004",
+            Warning::Exhaustiveness(err) => err.code(),
+        }
+    }
+}
+
+impl AsDiagnostic for Warning {
+    fn as_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::new_warning(self.to_string()).with_code(self.code());
+        match self {
+            Warning::Shadowing { previous, .. } => diagnostic
+                .with_label(Label::new_secondary(*previous).with_message("Previous binding")),
+            _ => diagnostic,
+        }
+    }
+}
+
+pub type SpannedWarning = Spanned<Warning, BytePos>;
+
+pub type Warnings = Errors<SpannedWarning>;
+
+/// The lints which can be enabled or disabled through the `allow` attribute
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Lint {
+    UnusedVariables,
+    UnusedImports,
+    UnusedFields,
+    Shadowing,
+}
+
+impl Lint {
+    /// Returns the lints that `name` refers to. `unused` refers to all the `unused_*` lints.
+    pub fn from_name(name: &str) -> &'static [Lint] {
+        match name {
+            "unused" => &[
+                Lint::UnusedVariables,
+                Lint::UnusedImports,
+                Lint::UnusedFields,
+            ],
+            "unused_variables" => &[Lint::UnusedVariables],
+            "unused_imports" => &[Lint::UnusedImports],
+            "unused_fields" => &[Lint::UnusedFields],
+            "shadowing" => &[Lint::Shadowing],
+            _ => &[],
+        }
+    }
+}
+
+/// Runs all lints on `expr`. Only bindings inside `module_span` are checked which lets bindings
+/// that the compiler inserts (such as the implicit prelude) be ignored.
+///
+/// `expr` must have been typechecked as references inserted by implicit resolution count as uses.
+pub fn lint_expr(module_span: Span<BytePos>, expr: &SpannedExpr<Symbol>) -> Warnings {
+    let mut used = UsedSymbols::default();
+    used.visit_expr(expr);
+
+    let mut linter = Linter {
+        module_span,
+        used: used.0,
+        scope: ScopedMap::new(),
+        allowed: Vec::new(),
+        module_attributes_checked: false,
+        warnings: Errors::new(),
+    };
+    linter.visit_expr(expr);
+    linter.warnings
+}
+
+/// Collects every symbol that is referred to by an expression
+#[derive(Default)]
+struct UsedSymbols(FnvSet<Symbol>);
+
+impl<'a> Visitor<'a, '_> for UsedSymbols {
+    type Ident = Symbol;
+
+    fn visit_expr(&mut self, expr: &'a SpannedExpr<Symbol>) {
+        match &expr.value {
+            Expr::Ident(id) => {
+                self.0.insert(id.name.clone());
+            }
+            Expr::Infix { op, .. } => {
+                self.0.insert(op.value.name.clone());
+            }
+            Expr::Record { exprs, .. } => {
+                // `{ x }` refers to the variable `x`
+                for field in &**exprs {
+                    if field.value.is_none() {
+                        self.0.insert(field.name.value.clone());
+                    }
+                }
+            }
+            _ => (),
+        }
+        ast::walk_expr(self, expr);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum BindingKind {
+    Variable,
+    Field,
+}
+
+struct Binding {
+    name: Symbol,
+    span: Span<BytePos>,
+    kind: BindingKind,
+}
+
+fn pattern_bindings(pattern: &SpannedPattern<Symbol>, bindings: &mut Vec<Binding>) {
+    pattern_bindings_(pattern, BindingKind::Variable, bindings)
+}
+
+fn pattern_bindings_(
+    pattern: &SpannedPattern<Symbol>,
+    kind: BindingKind,
+    bindings: &mut Vec<Binding>,
+) {
+    match &pattern.value {
+        Pattern::Ident(id) => bindings.push(Binding {
+            name: id.name.clone(),
+            span: pattern.span,
+            kind,
+        }),
+        Pattern::As(id, pattern) => {
+            bindings.push(Binding {
+                name: id.value.clone(),
+                span: id.span,
+                kind: BindingKind::Variable,
+            });
+            pattern_bindings_(pattern, BindingKind::Variable, bindings);
+        }
+        Pattern::Record { fields, .. } => {
+            for field in &**fields {
+                match field {
+                    PatternField::Value { name, value: None } => bindings.push(Binding {
+                        name: name.value.clone(),
+                        span: name.span,
+                        kind: BindingKind::Field,
+                    }),
+                    PatternField::Value {
+                        value: Some(pattern),
+                        ..
+                    } => pattern_bindings_(pattern, BindingKind::Field, bindings),
+                    PatternField::Type { .. } => (),
+                }
+            }
+        }
+        Pattern::Tuple { elems: args, .. } | Pattern::Constructor(_, args) => {
+            for arg in &**args {
+                pattern_bindings_(arg, BindingKind::Variable, bindings);
+            }
+        }
+        // Every alternative binds the same variables
+        Pattern::Or(alts) => pattern_bindings_(&alts[0], kind, bindings),
+        Pattern::Literal(_) | Pattern::Error => (),
+    }
+}
+
+fn is_import(expr: &SpannedExpr<Symbol>) -> bool {
+    match &expr.value {
+        Expr::MacroExpansion { replacement, .. } => is_import(replacement),
+        // `import!` expands to the global symbol of the imported module
+        Expr::Ident(id) => id.name.is_global(),
+        _ => false,
+    }
+}
+
+struct Linter {
+    module_span: Span<BytePos>,
+    used: FnvSet<Symbol>,
+    /// The bindings which are in scope, keyed by their declared name
+    scope: ScopedMap<String, Span<BytePos>>,
+    /// Lints which are disabled through `allow` attributes at the current position
+    allowed: Vec<Lint>,
+    /// Whether the first binding of the module has been visited
+    module_attributes_checked: bool,
+    warnings: Warnings,
+}
+
+impl Linter {
+    fn is_allowed(&self, lint: Lint) -> bool {
+        self.allowed.contains(&lint)
+    }
+
+    fn warn(&mut self, lint: Lint, span: Span<BytePos>, warning: Warning) {
+        if !self.is_allowed(lint) {
+            self.warnings.push(pos::spanned(span, warning));
+        }
+    }
+
+    /// Disables the lints listed in `allow` attributes of `metadata`. Returns the number of
+    /// previously allowed lints which should be passed to `exit_attributes`.
+    fn enter_attributes(&mut self, metadata: &BaseMetadata) -> usize {
+        let len = self.allowed.len();
+        self.allow(metadata, "allow");
+        len
+    }
+
+    fn exit_attributes(&mut self, len: usize) {
+        self.allowed.truncate(len);
+    }
+
+    fn allow(&mut self, metadata: &BaseMetadata, attribute_name: &str) {
+        for attribute in metadata.attributes() {
+            if attribute.name != attribute_name {
+                continue;
+            }
+            if let Some(arguments) = &attribute.arguments {
+                for name in arguments.split(',') {
+                    self.allowed.extend_from_slice(Lint::from_name(name.trim()));
+                }
+            }
+        }
+    }
+
+    /// Disables the lints listed in `allow_module` attributes for the rest of the module if
+    /// `metadata` belongs to the first binding of the module
+    fn enter_module_attributes(&mut self, span: Span<BytePos>, metadata: &BaseMetadata) {
+        if !self.module_attributes_checked && self.in_module(span) {
+            self.module_attributes_checked = true;
+            // Nothing is allowed at the start of the module so these are never truncated
+            self.allow(metadata, "allow_module");
+        }
+    }
+
+    fn in_module(&self, span: Span<BytePos>) -> bool {
+        self.module_span.contains(span)
+    }
+
+    /// Brings the bindings of `pattern` into scope without checking them
+    fn declare_pattern(&mut self, pattern: &SpannedPattern<Symbol>) {
+        let mut bindings = Vec::new();
+        pattern_bindings(pattern, &mut bindings);
+        for binding in bindings {
+            if binding.kind == BindingKind::Field {
+                self.check_unused(&binding, Lint::UnusedFields, Warning::UnusedField);
+            }
+            self.declare(&binding);
+        }
+    }
+
+    fn declare(&mut self, binding: &Binding) {
+        if self.in_module(binding.span) {
+            self.scope
+                .insert(binding.name.declared_name().to_string(), binding.span);
+        }
+    }
+
+    fn check_unused(&mut self, binding: &Binding, lint: Lint, warning: fn(Symbol) -> Warning) {
+        if self.in_module(binding.span)
+            && !binding.name.declared_name().starts_with('_')
+            && !self.used.contains(&binding.name)
+        {
+            self.warn(lint, binding.span, warning(binding.name.clone()));
+        }
+    }
+
+    /// Checks and brings into scope the bindings of a `let`
+    fn declare_let_pattern(&mut self, pattern: &SpannedPattern<Symbol>, import: bool) {
+        let mut bindings = Vec::new();
+        pattern_bindings(pattern, &mut bindings);
+        for binding in bindings {
+            if import {
+                self.check_unused(&binding, Lint::UnusedImports, Warning::UnusedImport);
+            } else if binding.kind == BindingKind::Field {
+                self.check_unused(&binding, Lint::UnusedFields, Warning::UnusedField);
+            } else {
+                self.check_unused(&binding, Lint::UnusedVariables, Warning::UnusedVariable);
+            }
+
+            let name = binding.name.declared_name();
+            if self.in_module(binding.span) && !name.starts_with('_') {
+                if let Some(&previous) = self.scope.get(name) {
+                    self.warn(
+                        Lint::Shadowing,
+                        binding.span,
+                        Warning::Shadowing {
+                            name: binding.name.clone(),
+                            previous,
+                        },
+                    );
+                }
+            }
+            self.declare(&binding);
+        }
+    }
+
+    fn visit_binding_expr(&mut self, bind: &ast::ValueBinding<Symbol>) {
+        self.scope.enter_scope();
+        for arg in &*bind.args {
+            self.declare(&Binding {
+                name: arg.name.value.name.clone(),
+                span: arg.name.span,
+                kind: BindingKind::Variable,
+            });
+        }
+        self.visit_expr(&bind.expr);
+        self.scope.exit_scope();
+    }
+}
+
+impl<'a> Visitor<'a, '_> for Linter {
+    type Ident = Symbol;
+
+    fn visit_expr(&mut self, expr: &'a SpannedExpr<Symbol>) {
+        match &expr.value {
+            Expr::LetBindings(bindings, body) => {
+                self.enter_module_attributes(bindings[0].name.span, &bindings[0].metadata);
+                self.scope.enter_scope();
+                if bindings.is_recursive() {
+                    // Every binding of a recursive group is in scope in all of the bindings
+                    for bind in &**bindings {
+                        let len = self.enter_attributes(&bind.metadata);
+                        self.declare_let_pattern(&bind.name, false);
+                        self.exit_attributes(len);
+                    }
+                    for bind in &**bindings {
+                        let len = self.enter_attributes(&bind.metadata);
+                        self.visit_binding_expr(bind);
+                        self.exit_attributes(len);
+                    }
+                } else {
+                    for bind in &**bindings {
+                        let len = self.enter_attributes(&bind.metadata);
+                        self.visit_binding_expr(bind);
+                        self.declare_let_pattern(&bind.name, is_import(&bind.expr));
+                        self.exit_attributes(len);
+                    }
+                }
+                self.visit_expr(body);
+                self.scope.exit_scope();
+            }
+            Expr::Lambda(lambda) => {
+                self.scope.enter_scope();
+                for arg in &*lambda.args {
+                    self.declare(&Binding {
+                        name: arg.name.value.name.clone(),
+                        span: arg.name.span,
+                        kind: BindingKind::Variable,
+                    });
+                }
+                self.visit_expr(&lambda.body);
+                self.scope.exit_scope();
+            }
+            Expr::Match(scrutinee, alts) => {
+                self.visit_expr(scrutinee);
+                for alt in &**alts {
+                    self.scope.enter_scope();
+                    self.declare_pattern(&alt.pattern);
+                    if let Some(guard) = &alt.guard {
+                        self.visit_expr(guard);
+                    }
+                    self.visit_expr(&alt.expr);
+                    self.scope.exit_scope();
+                }
+            }
+            Expr::Do(do_expr) => {
+                self.visit_expr(&do_expr.bound);
+                self.scope.enter_scope();
+                if let Some(pattern) = &do_expr.id {
+                    self.declare_pattern(pattern);
+                }
+                self.visit_expr(&do_expr.body);
+                self.scope.exit_scope();
+            }
+            Expr::Record { exprs, base, .. } => {
+                for field in &**exprs {
+                    if let Some(value) = &field.value {
+                        let len = self.enter_attributes(&field.metadata);
+                        self.visit_expr(value);
+                        self.exit_attributes(len);
+                    }
+                }
+                if let Some(base) = base {
+                    self.visit_expr(base);
+                }
+            }
+            Expr::TypeBindings(bindings, _) => {
+                self.enter_module_attributes(bindings[0].name.span, &bindings[0].metadata);
+                ast::walk_expr(self, expr)
+            }
+            _ => ast::walk_expr(self, expr),
+        }
+    }
+}
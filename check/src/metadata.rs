@@ -172,6 +172,7 @@ pub fn metadata(
                 | Pattern::Tuple { .. }
                 | Pattern::Record { .. }
                 | Pattern::Literal(_)
+                | Pattern::Or(_)
                 | Pattern::Error => self.new_pattern(metadata, &bind.name),
             }
         }
@@ -231,6 +232,7 @@ pub fn metadata(
                 Pattern::Tuple { .. }
                 | Pattern::Constructor(..)
                 | Pattern::Literal(_)
+                | Pattern::Or(_)
                 | Pattern::Error => (),
             }
         }
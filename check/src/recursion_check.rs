@@ -31,6 +31,16 @@ impl fmt::Display for Error {
     }
 }
 
+impl Error {
+    /// The error code which identifies this kind of error (see `gluon explain`)
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::InvalidRecursion { .. } => "E0108",
+            Error::LastExprMustBeConstructor => "E0109",
+        }
+    }
+}
+
 type Level = u32;
 
 #[derive(Debug)]
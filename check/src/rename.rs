@@ -13,6 +13,44 @@ use crate::base::{
     types::{ArcType, Type},
 };
 
+/// Calls `f` with each variable that `pattern` binds
+pub(crate) fn pattern_bindings(pattern: &ast::SpannedPattern<Symbol>, f: &mut impl FnMut(&Symbol)) {
+    match &pattern.value {
+        Pattern::Record {
+            fields,
+            implicit_import,
+            ..
+        } => {
+            for field in &**fields {
+                match field {
+                    ast::PatternField::Value {
+                        value: Some(pat), ..
+                    } => pattern_bindings(pat, f),
+                    ast::PatternField::Value { name, value: None } => f(&name.value),
+                    ast::PatternField::Type { .. } => (),
+                }
+            }
+            if let Some(implicit_import) = implicit_import {
+                f(&implicit_import.value);
+            }
+        }
+        // `_` matches anything without binding it
+        Pattern::Ident(id) if id.name.declared_name() == "_" => (),
+        Pattern::Ident(id) => f(&id.name),
+        Pattern::As(id, pat) => {
+            f(&id.value);
+            pattern_bindings(pat, f);
+        }
+        Pattern::Tuple { elems: args, .. } | Pattern::Constructor(_, args) => {
+            for arg in &**args {
+                pattern_bindings(arg, f);
+            }
+        }
+        Pattern::Or(alts) => pattern_bindings(&alts[0], f),
+        Pattern::Literal(_) | Pattern::Error => (),
+    }
+}
+
 struct Environment {
     stack: ScopedMap<Symbol, (Symbol, Span<BytePos>)>,
 }
@@ -80,6 +118,79 @@ pub fn rename<'s, 'ast>(
                         self.new_pattern(arg);
                     }
                 }
+                Pattern::Or(ref mut alts) => {
+                    let (first, rest) = alts.split_first_mut().expect("Or pattern");
+                    let mut names = Vec::new();
+                    pattern_bindings(first, &mut |id| names.push(id.clone()));
+                    self.new_pattern(first);
+
+                    // Every alternative must bind the same variables so reuse the names of
+                    // the first alternative
+                    let bound: FnvMap<_, _> = names
+                        .into_iter()
+                        .filter_map(|id| {
+                            let new_id = self.rename(&id)?;
+                            Some((id, new_id))
+                        })
+                        .collect();
+                    for alt in rest {
+                        self.rename_or_alternative(&bound, alt);
+                    }
+                }
+                Pattern::Literal(_) | Pattern::Error => (),
+            }
+        }
+
+        /// Renames the variables in `pattern` to the variables with the same name that were bound
+        /// by the first alternative of the or-pattern. Variables which the first alternative does
+        /// not bind are given new names which lets typechecking report the mismatch
+        fn rename_or_alternative(
+            &mut self,
+            bound: &FnvMap<Symbol, Symbol>,
+            pattern: &mut ast::SpannedPattern<Symbol>,
+        ) {
+            let span = pattern.span;
+            let rename = |this: &mut Self, id: &mut Symbol| {
+                *id = match bound.get(&*id) {
+                    Some(new_id) => new_id.clone(),
+                    None => this.stack_var(id.clone(), span),
+                }
+            };
+            match pattern.value {
+                Pattern::Record {
+                    ref mut fields,
+                    ref mut implicit_import,
+                    ..
+                } => {
+                    for (name, value) in ast::pattern_values_mut(fields) {
+                        match value {
+                            Some(pat) => self.rename_or_alternative(bound, pat),
+                            None => rename(self, &mut name.value),
+                        }
+                    }
+                    if let Some(ref mut implicit_import) = *implicit_import {
+                        rename(self, &mut implicit_import.value);
+                    }
+                }
+                Pattern::Ident(ref mut id) => rename(self, &mut id.name),
+                Pattern::As(ref mut id, ref mut pat) => {
+                    rename(self, &mut id.value);
+                    self.rename_or_alternative(bound, pat)
+                }
+                Pattern::Tuple {
+                    elems: ref mut args,
+                    ..
+                }
+                | Pattern::Constructor(_, ref mut args) => {
+                    for arg in &mut **args {
+                        self.rename_or_alternative(bound, arg);
+                    }
+                }
+                Pattern::Or(ref mut alts) => {
+                    for alt in &mut **alts {
+                        self.rename_or_alternative(bound, alt);
+                    }
+                }
                 Pattern::Literal(_) | Pattern::Error => (),
             }
         }
@@ -191,6 +302,9 @@ pub fn rename<'s, 'ast>(
                     for alt in &mut **alts {
                         self.env.stack.enter_scope();
                         self.new_pattern(&mut alt.pattern);
+                        if let Some(guard) = &mut alt.guard {
+                            self.visit_expr(guard);
+                        }
                         self.visit_expr(&mut alt.expr);
                         self.env.stack.exit_scope();
                     }
@@ -277,6 +391,29 @@ pub fn rename<'s, 'ast>(
 
                     return TailCall::TailCall;
                 }
+                Expr::Interpolation(ref mut interpolation) => {
+                    let append = self.symbols.simple_symbol("__string_append");
+                    interpolation.append_id = Some(self.ast_arena.alloc(pos::spanned(
+                        expr.span,
+                        Expr::Ident(TypedIdent {
+                            name: append,
+                            typ: self.hole.clone(),
+                        }),
+                    )));
+                    // `show` is only used if the expression turns out to not be a `String`, which
+                    // is decided during typechecking
+                    for interpolated in &mut *interpolation.exprs {
+                        let show = self.symbols.simple_symbol("show");
+                        interpolated.show_id = Some(self.ast_arena.alloc(pos::spanned(
+                            interpolated.expr.span,
+                            Expr::Ident(TypedIdent {
+                                name: show,
+                                typ: self.hole.clone(),
+                            }),
+                        )));
+                    }
+                    ast::walk_mut_expr(self, expr);
+                }
                 Expr::Do(Do {
                     ref mut id,
                     ref mut bound,
@@ -9,9 +9,9 @@ use std::{
 
 use crate::base::{
     ast::{
-        self, Argument, AstType, DisplayEnv, Do, Expr, IdentEnv, KindedIdent, Literal, MutVisitor,
-        Pattern, PatternField, SpannedExpr, SpannedIdent, SpannedPattern, TypeBinding, Typed,
-        TypedIdent, ValueBinding, ValueBindings,
+        self, Argument, AstType, DisplayEnv, Do, Expr, IdentEnv, InterpolatedExpr, Interpolation,
+        KindedIdent, Literal, MutVisitor, Pattern, PatternField, SpannedExpr, SpannedIdent,
+        SpannedPattern, TypeBinding, Typed, TypedIdent, ValueBinding, ValueBindings,
     },
     error::Errors,
     fnv::{FnvMap, FnvSet},
@@ -23,14 +23,15 @@ use crate::base::{
     scoped_map::{self, ScopedMap},
     symbol::{Symbol, SymbolModule, SymbolRef, Symbols},
     types::{
-        self, Alias, AliasRef, AppVec, ArcType, ArgType, Field, Flags, Generic, PrimitiveEnv, Type,
-        TypeCache, TypeContext, TypeEnv, TypeExt, TypePtr, Walker,
+        self, Alias, AliasRef, AppVec, ArcType, ArgType, BuiltinType, Field, Flags, Generic,
+        PrimitiveEnv, Type, TypeCache, TypeContext, TypeEnv, TypeExt, TypePtr, Walker,
     },
 };
 
 use crate::{
     implicits,
     kindcheck::KindCheck,
+    rename,
     substitution::{self, Substitution},
     typ::RcType,
     unify, unify_type, TypecheckEnv,
@@ -41,7 +42,7 @@ use self::{
     mod_type::{ModType, ModTypeRef, TypeModifier},
 };
 
-pub use self::error::{Help, HelpError, SpannedTypeError, TypeError};
+pub use self::error::{Help, HelpError, HoleEntry, SpannedTypeError, TypeError};
 
 mod error;
 mod generalize;
@@ -59,6 +60,16 @@ struct StackBinding {
     typ: ModType,
 }
 
+/// A typed hole which is reported once the entire expression has been checked, at which point its
+/// type is as refined as it can get
+struct Hole {
+    span: Span<BytePos>,
+    name: Symbol,
+    typ: RcType,
+    bindings: Vec<(Symbol, RcType)>,
+    implicits: implicits::Partition<implicits::ImplicitBinding>,
+}
+
 pub(crate) struct Environment<'a> {
     /// The global environment which the typechecker extracts types from
     environment: &'a (dyn TypecheckEnv<Type = RcType> + 'a),
@@ -137,6 +148,7 @@ pub struct Typecheck<'a, 'ast> {
     pub(crate) implicit_resolver: implicits::ImplicitResolver<'a>,
     unbound_variables: ScopedMap<Symbol, ArcKind>,
     refined_variables: ScopedMap<u32, ()>,
+    holes: Vec<Hole>,
     pub(crate) ast_arena: ast::ArenaRef<'a, 'ast, Symbol>,
 }
 
@@ -177,6 +189,7 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
             implicit_resolver: crate::implicits::ImplicitResolver::new(environment, metadata),
             unbound_variables: ScopedMap::new(),
             refined_variables: ScopedMap::new(),
+            holes: Vec::new(),
             subs,
             ast_arena,
         }
@@ -397,9 +410,22 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
                 | DuplicateField(_)
                 | UndefinedRecord { .. }
                 | EmptyCase
+                | OrPatternBinding(_)
                 | KindError(_)
                 | RecursionCheck(_)
+                | Exhaustiveness(_)
                 | Message(_) => (),
+                Hole {
+                    ref mut expected,
+                    ref mut bindings,
+                    ref mut candidates,
+                    ..
+                } => {
+                    self.generalize_type(0, expected, err.span);
+                    for entry in bindings.iter_mut().chain(candidates) {
+                        self.generalize_type(0, &mut entry.typ, err.span);
+                    }
+                }
                 NotAFunction(ref mut typ)
                 | UndefinedField(ref mut typ, _)
                 | PatternError {
@@ -536,7 +562,10 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
             // Only the 'tail' expression need to be generalized at this point as all bindings
             // will have already been generalized
             let tail = tail_expr(expr);
+            let hole = self.subs.hole();
+            TypeGeneralizer::new(0, self, &hole, tail.span).default_interpolated_strings(tail);
             crate::implicits::resolve(self, tail);
+            self.report_holes();
             self.generalize_type(0, &mut typ, tail.span);
             self.generalize_variables(0, &mut [].iter_mut(), tail);
         }
@@ -596,6 +625,74 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
         }
     }
 
+    /// Reports each typed hole along with the bindings in scope and the candidates (local bindings
+    /// and implicit instances) whose types fit the hole
+    fn report_holes(&mut self) {
+        for hole in mem::take(&mut self.holes) {
+            let expected = self.subs.zonk(&hole.typ);
+
+            let mut bindings = hole.bindings;
+            bindings.sort_by(|l, r| l.0.declared_name().cmp(r.0.declared_name()));
+
+            let implicit_candidates: Vec<_> = hole
+                .implicits
+                .get_candidates(&self.subs, &expected)
+                .rev()
+                .map(|(path, typ)| {
+                    // Skip the generated bindings of implicit imports (`let { ? } = ...`)
+                    let path = path
+                        .iter()
+                        .map(|id| id.name.declared_name())
+                        .filter(|name| !name.contains('?'))
+                        .collect::<Vec<_>>()
+                        .join(".");
+                    (path, typ.clone())
+                })
+                .collect();
+
+            let mut candidates: Vec<HoleEntry<RcType>> = Vec::new();
+            let possible_candidates = bindings
+                .iter()
+                .map(|(id, typ)| (id.declared_name().to_string(), typ.clone()))
+                .chain(implicit_candidates);
+            for (path, typ) in possible_candidates {
+                if candidates.iter().all(|entry| entry.path != path)
+                    && self.fits_hole(&expected, &typ)
+                {
+                    candidates.push(HoleEntry { path, typ });
+                }
+            }
+
+            let bindings = bindings
+                .into_iter()
+                .filter(|(id, _)| !id.declared_name().starts_with(char::is_uppercase))
+                .map(|(id, typ)| HoleEntry {
+                    path: id.declared_name().to_string(),
+                    typ,
+                })
+                .collect();
+
+            self.error(
+                hole.span,
+                TypeError::Hole {
+                    name: hole.name,
+                    expected,
+                    bindings,
+                    candidates,
+                },
+            );
+        }
+    }
+
+    fn fits_hole(&mut self, expected: &RcType, typ: &RcType) -> bool {
+        let snapshot = self.subs.snapshot();
+        let state = unify_type::State::new(&self.environment, &self.subs);
+        let fits =
+            unify_type::subsumes_implicit(&self.subs, state, expected, typ, &mut |_| ()).is_ok();
+        self.subs.rollback_to(snapshot);
+        fits
+    }
+
     fn infer_expr(&mut self, expr: &mut SpannedExpr<'ast, Symbol>) -> ModType {
         self.typecheck_opt(expr, None)
     }
@@ -685,6 +782,26 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
                 id.typ = self.subs.bind_arc(&typ);
                 Ok((ModType::new(modifier, typ), args))
             }
+            Expr::Hole(ref mut id) => {
+                let typ = match expected_type.take() {
+                    Some(expected_type) => expected_type.concrete.clone(),
+                    None => self.subs.new_var(),
+                };
+                self.holes.push(Hole {
+                    span: expr.span,
+                    name: id.name.clone(),
+                    typ: typ.clone(),
+                    bindings: self
+                        .environment
+                        .stack
+                        .iter()
+                        .map(|(id, bind)| (id.clone(), bind.typ.concrete.clone()))
+                        .collect(),
+                    implicits: self.implicit_resolver.implicit_bindings.partition().clone(),
+                });
+                id.typ = self.subs.bind_arc(&typ);
+                Ok((ModType::wobbly(typ), Vec::new()))
+            }
             Expr::Literal(ref lit) => Ok((
                 ModType::rigid(match *lit {
                     Literal::Int(_) => self.subs.int(),
@@ -695,6 +812,55 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
                 }),
                 Vec::new(),
             )),
+            Expr::Interpolation(ref mut interpolation) => {
+                let Interpolation {
+                    ref literals,
+                    ref mut exprs,
+                    ref mut append_id,
+                } = **interpolation;
+                let string_type = self.subs.string();
+
+                let append_type = self.typecheck_inserted_ident(
+                    expr.span,
+                    append_id,
+                    Help::UndefinedAppendInInterpolation,
+                );
+                let expected_append_type = self.subs.function(
+                    vec![string_type.clone(), string_type.clone()],
+                    string_type.clone(),
+                );
+                let span = literals.first().map_or(expr.span, |lit| lit.span);
+                self.unify_span(span, &expected_append_type, append_type);
+
+                for hole in &mut **exprs {
+                    let InterpolatedExpr {
+                        ref mut expr,
+                        ref mut show_id,
+                    } = *hole;
+                    let hole_type = self.infer_expr(expr).concrete;
+                    let resolved_type = self.remove_aliases(self.subs.zonk(&hole_type));
+                    // Strings are inserted as is, everything else goes through `show`. Holes whose
+                    // type is not yet known may still turn out to be strings, in which case `show`
+                    // is removed again once the type is known (see `ReplaceVisitor`)
+                    match *resolved_type {
+                        Type::Builtin(BuiltinType::String) | Type::Error => {
+                            *show_id = None;
+                        }
+                        _ => {
+                            let show_type = self.typecheck_inserted_ident(
+                                expr.span,
+                                show_id,
+                                Help::UndefinedShowInInterpolation,
+                            );
+                            let expected_show_type =
+                                self.subs.function(Some(hole_type), string_type.clone());
+                            self.unify_span(expr.span, &expected_show_type, show_type);
+                        }
+                    }
+                }
+
+                Ok((ModType::rigid(string_type), Vec::new()))
+            }
             Expr::App {
                 ref mut func,
                 ref mut implicit_args,
@@ -813,8 +979,11 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
                 let modifier = scrutinee_type.modifier;
                 let expected_type = expected_type.take().map(|t| t.to_owned());
 
-                let mut unaliased_scrutinee_type = match alts.first().map(|alt| &alt.pattern.value)
-                {
+                let first_pattern = alts.first().map(|alt| match &alt.pattern.value {
+                    Pattern::Or(alts) => &alts[0].value,
+                    pattern => pattern,
+                });
+                let mut unaliased_scrutinee_type = match first_pattern {
                     Some(Pattern::Constructor(..)) => {
                         let typ = self.remove_aliases(scrutinee_type.concrete.clone());
                         ModType::new(modifier, self.instantiate_generics(&typ))
@@ -836,6 +1005,12 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
                         scrutinee_type.concrete.clone(),
                     );
 
+                    if let Some(guard) = &mut alt.guard {
+                        let bool_type = self.bool();
+                        let guard_type = self.typecheck(guard, ModType::rigid(&bool_type));
+                        self.unify_span(expr_check_span(guard), &bool_type, guard_type.concrete);
+                    }
+
                     let mut alt_type = self
                         .typecheck_opt(&mut alt.expr, expected_type.as_ref().map(|t| t.as_ref()));
                     alt_type.concrete = self.instantiate_generics(&alt_type);
@@ -860,6 +1035,8 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
                     {
                         *unaliased_scrutinee_type = self.subs.zonk(&unaliased_scrutinee_type);
                         let replaced = match (&alt.pattern.value, &**unaliased_scrutinee_type) {
+                            // A guarded alternative may not match the variant
+                            _ if alt.guard.is_some() => false,
                             (Pattern::Constructor(id, _), Type::Variant(row)) => {
                                 let mut variant_iter = row.row_iter();
                                 let variants = variant_iter
@@ -1177,50 +1354,8 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
                 ref mut flat_map_id,
             }) => {
                 let do_span = expr.span.subspan(0.into(), 2.into());
-                let flat_map_type = match flat_map_id
-                    .as_mut()
-                    .expect("flat_map inserted during renaming")
-                    .value
-                {
-                    Expr::Ident(ref mut flat_map) => match self.find(&flat_map.name) {
-                        Ok(x) => x,
-                        Err(error) => {
-                            self.error(
-                                do_span,
-                                crate::base::error::Help {
-                                    error,
-                                    help: Some(Help::UndefinedFlatMapInDo),
-                                },
-                            );
-                            ModType::wobbly(self.subs.error())
-                        }
-                    },
-                    _ => ice!("flat_map_id not inserted during renaming"),
-                };
-
-                let flat_map_type = self.instantiate_generics(&flat_map_type);
-
-                let flat_map_type = match *flat_map_type {
-                    Type::Function(ArgType::Implicit, ref arg_type, ref r) => {
-                        let name = self.implicit_resolver.make_implicit_ident(arg_type);
-                        *flat_map_id = Some(self.ast_arena.alloc(pos::spanned(
-                            do_span,
-                            Expr::App {
-                                func: flat_map_id.take().unwrap(),
-                                args: self.ast_arena.alloc_extend(Some(pos::spanned(
-                                    do_span,
-                                    Expr::Ident(TypedIdent {
-                                        name,
-                                        typ: self.subs.bind_arc(&arg_type),
-                                    }),
-                                ))),
-                                implicit_args: &mut [],
-                            },
-                        )));
-                        r.clone()
-                    }
-                    _ => flat_map_type.clone(),
-                };
+                let flat_map_type =
+                    self.typecheck_inserted_ident(do_span, flat_map_id, Help::UndefinedFlatMapInDo);
 
                 let id_var = self.subs.new_var();
                 let arg1 = self
@@ -1274,6 +1409,67 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
         }
     }
 
+    /// Typechecks an identifier which were inserted by the renamer (such as `flat_map` in `do`
+    /// expressions), applying any implicit argument it takes
+    fn typecheck_inserted_ident(
+        &mut self,
+        span: Span<BytePos>,
+        ident_expr: &mut Option<&'ast mut SpannedExpr<'ast, Symbol>>,
+        help: Help,
+    ) -> RcType {
+        let typ = match ident_expr
+            .as_mut()
+            .expect("ident inserted during renaming")
+            .value
+        {
+            Expr::Ident(ref mut ident) => match self.find(&ident.name) {
+                Ok(x) => x,
+                Err(error) => {
+                    self.error(
+                        span,
+                        crate::base::error::Help {
+                            error,
+                            help: Some(help),
+                        },
+                    );
+                    ModType::wobbly(self.subs.error())
+                }
+            },
+            _ => ice!("ident not inserted during renaming"),
+        };
+
+        let typ = self.instantiate_generics(&typ);
+        if let Some(SpannedExpr {
+            value: Expr::Ident(ident),
+            ..
+        }) = ident_expr.as_mut().map(|expr| &mut **expr)
+        {
+            ident.typ = self.subs.bind_arc(&typ);
+        }
+
+        match *typ {
+            Type::Function(ArgType::Implicit, ref arg_type, ref r) => {
+                let name = self.implicit_resolver.make_implicit_ident(arg_type);
+                *ident_expr = Some(self.ast_arena.alloc(pos::spanned(
+                    span,
+                    Expr::App {
+                        func: ident_expr.take().unwrap(),
+                        args: self.ast_arena.alloc_extend(Some(pos::spanned(
+                            span,
+                            Expr::Ident(TypedIdent {
+                                name,
+                                typ: self.subs.bind_arc(&arg_type),
+                            }),
+                        ))),
+                        implicit_args: &mut [],
+                    },
+                )));
+                r.clone()
+            }
+            _ => typ.clone(),
+        }
+    }
+
     fn typecheck_application<'e, I>(
         &mut self,
         span: Span<BytePos>,
@@ -1750,6 +1946,49 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
                 self.unify_span(span, &match_type, typ);
                 match_type.concrete
             }
+            Pattern::Or(alts) => {
+                let (first, rest) = alts.split_first_mut().expect("Or pattern");
+                let typ =
+                    self.typecheck_pattern(first, match_type.clone(), partial_match_type.clone());
+
+                let mut bindings = Vec::new();
+                rename::pattern_bindings(first, &mut |id| {
+                    bindings.push(id.clone());
+                });
+                let binding_types: Vec<_> = bindings
+                    .iter()
+                    .map(|id| self.environment.stack.get(id).map(|bind| bind.typ.clone()))
+                    .collect();
+
+                for alt in rest {
+                    self.typecheck_pattern(alt, match_type.clone(), partial_match_type.clone());
+
+                    // Each alternative must bind the same variables with the same types
+                    let mut alt_bindings = Vec::new();
+                    rename::pattern_bindings(alt, &mut |id| {
+                        alt_bindings.push(id.clone());
+                    });
+                    for id in bindings.iter().filter(|id| !alt_bindings.contains(id)) {
+                        self.error(alt.span, TypeError::OrPatternBinding(id.clone()));
+                    }
+                    for id in alt_bindings.iter().filter(|id| !bindings.contains(id)) {
+                        self.error(alt.span, TypeError::OrPatternBinding(id.clone()));
+                    }
+                    for (id, expected) in bindings.iter().zip(&binding_types) {
+                        let actual = self.environment.stack.get(id).map(|bind| bind.typ.clone());
+                        if let (Some(expected), Some(actual)) = (expected, actual) {
+                            self.unify_span(alt.span, &expected.concrete, actual.concrete);
+                            self.environment.stack.insert(
+                                id.clone(),
+                                StackBinding {
+                                    typ: expected.clone(),
+                                },
+                            );
+                        }
+                    }
+                }
+                typ
+            }
             Pattern::Error => self.subs.new_var(),
         }
     }
@@ -2331,6 +2570,11 @@ impl<'a, 'ast> Typecheck<'a, 'ast> {
                     self.finish_pattern(level, arg, &arg_type);
                 }
             }
+            Pattern::Or(ref mut alts) => {
+                for alt in &mut **alts {
+                    self.finish_pattern(level, alt, final_type);
+                }
+            }
             Pattern::Literal(_) | Pattern::Error => (),
         }
     }
@@ -3304,6 +3548,7 @@ fn generalize_binding<'ast>(
     resolved_type: &mut RcType,
     binding: &mut ValueBinding<'ast, Symbol>,
 ) {
+    generalizer.default_interpolated_strings(&mut binding.expr);
     crate::implicits::resolve(generalizer.tc, &mut binding.expr);
 
     generalizer.generalize_type_top(resolved_type);
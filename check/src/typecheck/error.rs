@@ -40,6 +40,8 @@ pub enum TypeError<I, T> {
     KindError(KindCheckError<I, T>),
     /// Error were found when checking value recursion
     RecursionCheck(crate::recursion_check::Error),
+    /// Error were found when checking the exhaustiveness of a `match` expression
+    Exhaustiveness(crate::exhaustiveness::Error),
     /// Multiple types were declared with the same name in the same expression
     DuplicateTypeDefinition(I),
     /// A field was defined more than once in a record constructor or pattern match
@@ -52,12 +54,56 @@ pub enum TypeError<I, T> {
     },
     /// Found a case expression without any alternatives
     EmptyCase,
+    /// A variable is bound in some but not all of the alternatives of an or-pattern
+    OrPatternBinding(I),
     Message(String),
     UnableToResolveImplicit(implicits::Error<T>),
     TypeConstructorReturnsWrongType {
         expected: I,
         actual: T,
     },
+    /// A typed hole (`_` or `?name`) was found in an expression
+    Hole {
+        name: I,
+        expected: T,
+        bindings: Vec<HoleEntry<T>>,
+        candidates: Vec<HoleEntry<T>>,
+    },
+}
+
+/// A binding which is in scope at a typed hole
+#[derive(Debug, Eq, PartialEq, Clone, Hash, Functor)]
+pub struct HoleEntry<T> {
+    pub path: String,
+    pub typ: T,
+}
+
+impl<I, T> TypeError<I, T> {
+    /// The error code which identifies this kind of error (see `gluon explain`)
+    pub fn code(&self) -> &'static str {
+        use self::TypeError::*;
+        match self {
+            UndefinedVariable(_) => "E0101",
+            NotAFunction(_) => "E0102",
+            UndefinedType(_) => "E0103",
+            UndefinedField(..) => "E0104",
+            PatternError { .. } => "E0105",
+            Unification(..) => "E0106",
+            KindError(_) => "E0107",
+            RecursionCheck(err) => err.code(),
+            Exhaustiveness(err) => err.code(),
+            DuplicateTypeDefinition(_) => "E0112",
+            DuplicateField(_) => "E0113",
+            InvalidProjection(_) => "E0114",
+            UndefinedRecord { .. } => "E0115",
+            EmptyCase => "E0116",
+            OrPatternBinding(_) => "E0117",
+            Message(_) => "E0118",
+            UnableToResolveImplicit(err) => err.kind.code(),
+            TypeConstructorReturnsWrongType { .. } => "E0122",
+            Hole { .. } => "E0123",
+        }
+    }
 }
 
 impl<I, T> From<KindCheckError<I, T>> for TypeError<I, T> {
@@ -84,6 +130,12 @@ impl<I, T> From<crate::recursion_check::Error> for TypeError<I, T> {
     }
 }
 
+impl<I, T> From<crate::exhaustiveness::Error> for TypeError<I, T> {
+    fn from(e: crate::exhaustiveness::Error) -> Self {
+        TypeError::Exhaustiveness(e)
+    }
+}
+
 impl<I, T> fmt::Display for TypeError<I, T>
 where
     I: fmt::Display + AsRef<str> + Clone,
@@ -179,7 +231,10 @@ where
                 }
                 write!(f, "{}", errors.last().unwrap())
             }
-            PatternError { constructor_type, pattern_args } => {
+            PatternError {
+                constructor_type,
+                pattern_args,
+            } => {
                 write!(
                     f,
                     "Matching on constructor `{}` requires `{}` arguments but the pattern specifies `{}`",
@@ -190,6 +245,7 @@ where
             }
             KindError(err) => kindcheck::fmt_kind_error(err, f),
             RecursionCheck(err) => write!(f, "{}", err),
+            Exhaustiveness(err) => write!(f, "{}", err),
             DuplicateTypeDefinition(id) => write!(
                 f,
                 "Type '{}' has been already been defined in this module",
@@ -210,6 +266,11 @@ where
                 Ok(())
             }
             EmptyCase => write!(f, "`case` expression with no alternatives"),
+            OrPatternBinding(id) => write!(
+                f,
+                "Variable `{}` is not bound in every alternative of the or-pattern",
+                id
+            ),
             Message(msg) => write!(f, "{}", msg),
             UnableToResolveImplicit(err) => write!(f, "{}", err),
             TypeConstructorReturnsWrongType { expected, actual } => write!(
@@ -217,10 +278,42 @@ where
                 "The constructor returns the type `{}` instead of the expected type `{}`",
                 actual, expected
             ),
+            Hole {
+                name,
+                expected,
+                bindings,
+                candidates,
+            } => {
+                write!(f, "Found hole `{}` with type `{}`", name, expected)?;
+                let sections = [
+                    ("Relevant bindings include:", bindings),
+                    ("Valid hole fits include:", candidates),
+                ];
+                for (header, entries) in sections.iter() {
+                    if entries.is_empty() {
+                        continue;
+                    }
+                    write!(f, "\n{}", header)?;
+                    for entry in entries.iter().take(MAX_HOLE_ENTRIES) {
+                        write!(f, "\n    {} : {}", entry.path, entry.typ)?;
+                    }
+                    if entries.len() > MAX_HOLE_ENTRIES {
+                        write!(
+                            f,
+                            "\n    ({} more omitted)",
+                            entries.len() - MAX_HOLE_ENTRIES
+                        )?;
+                    }
+                }
+                Ok(())
+            }
         }
     }
 }
 
+/// The maximum number of bindings and candidates which are displayed for a typed hole
+const MAX_HOLE_ENTRIES: usize = 10;
+
 impl<I, T> AsDiagnostic for TypeError<I, T>
 where
     I: fmt::Display + AsRef<str> + Clone,
@@ -234,7 +327,7 @@ where
         use self::TypeError::*;
         match *self {
             UnableToResolveImplicit(ref err) => err.as_diagnostic(),
-            _ => Diagnostic::new_error(self.to_string()),
+            _ => Diagnostic::new_error(self.to_string()).with_code(self.code()),
         }
     }
 }
@@ -242,6 +335,8 @@ where
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
 pub enum Help {
     UndefinedFlatMapInDo,
+    UndefinedAppendInInterpolation,
+    UndefinedShowInInterpolation,
     ExtraArgument(u32, u32),
 }
 
@@ -253,6 +348,15 @@ impl fmt::Display for Help {
                 "Try bringing the `flat_map` function found in the `Monad` \
                  instance for your type into scope"
             ),
+            Help::UndefinedAppendInInterpolation => write!(
+                f,
+                "Interpolated strings are joined with `__string_append`, which is defined \
+                 by the implicit prelude"
+            ),
+            Help::UndefinedShowInInterpolation => write!(
+                f,
+                "Try bringing the `show` function found in `std.show` into scope"
+            ),
             Help::ExtraArgument(expected, actual) => {
                 if expected == 0 {
                     write!(f, "Attempted to call a non-function value")
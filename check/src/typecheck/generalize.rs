@@ -1,9 +1,11 @@
 use crate::base::{
-    ast::{self, MutVisitor, SpannedExpr, SpannedIdent},
+    ast::{self, Expr, MutVisitor, SpannedExpr, SpannedIdent},
     fnv::{FnvMap, FnvSet},
     pos::{BytePos, Span},
     symbol::Symbol,
-    types::{self, AppVec, ArcType, BuiltinType, Flags, Generic, Type, TypeContext, TypePtr},
+    types::{
+        self, AppVec, ArcType, BuiltinType, Flags, Generic, Type, TypeContext, TypeExt, TypePtr,
+    },
 };
 
 use crate::{substitution::Substitution, typ::RcType, typecheck::Typecheck};
@@ -95,6 +97,50 @@ impl<'a, 'b, 'ast> TypeGeneralizer<'a, 'b, 'ast> {
         self.tc.environment.skolem_variables.exit_scope();
     }
 
+    /// Defaults the interpolated expressions in `expr` whose type is still unknown, and would
+    /// otherwise be generalized, to `String` so that they do not require a `Show` instance
+    pub(crate) fn default_interpolated_strings(&mut self, expr: &mut SpannedExpr<Symbol>) {
+        struct DefaultVisitor<'a: 'c, 'b: 'a, 'c, 'ast> {
+            generalizer: &'c mut TypeGeneralizer<'a, 'b, 'ast>,
+        }
+
+        impl<'d> MutVisitor<'d, '_> for DefaultVisitor<'_, '_, '_, '_> {
+            type Ident = Symbol;
+
+            fn visit_expr(&mut self, e: &'d mut SpannedExpr<Self::Ident>) {
+                if let Expr::Interpolation(interpolation) = &mut e.value {
+                    for interpolated in &mut *interpolation.exprs {
+                        let arg_type = match interpolated
+                            .show_id
+                            .as_ref()
+                            .and_then(|show_id| show_arg_type(show_id))
+                        {
+                            Some(arg_type) => self.generalizer.subs.zonk(arg_type),
+                            None => continue,
+                        };
+                        let generalized = match *arg_type {
+                            Type::Variable(ref var) => {
+                                self.generalizer.subs.get_level(var.id) >= self.generalizer.level
+                            }
+                            _ => false,
+                        };
+                        if generalized {
+                            let string_type = self.generalizer.subs.string();
+                            self.generalizer.tc.unify_span(
+                                interpolated.expr.span,
+                                &string_type,
+                                arg_type,
+                            );
+                        }
+                    }
+                }
+                ast::walk_mut_expr(self, e);
+            }
+        }
+
+        DefaultVisitor { generalizer: self }.visit_expr(expr);
+    }
+
     pub(crate) fn generalize_type_top(&mut self, typ: &mut RcType) {
         self.tc.environment.skolem_variables.enter_scope();
 
@@ -383,6 +429,27 @@ impl<'a, 'b, 'c, 'd> MutVisitor<'d, '_> for ReplaceVisitor<'a, 'b, 'c, '_> {
     fn visit_expr(&mut self, e: &'d mut SpannedExpr<Self::Ident>) {
         self.generalizer.span = e.span;
         ast::walk_mut_expr(self, e);
+
+        // Interpolated expressions which were not known to be strings when they were typechecked
+        // were passed to `show`, which must be removed if they turned out to be strings after all
+        if let Expr::Interpolation(interpolation) = &mut e.value {
+            for interpolated in &mut *interpolation.exprs {
+                let arg_type = interpolated
+                    .show_id
+                    .as_ref()
+                    .and_then(|show_id| show_arg_type(show_id));
+                let is_string = match arg_type {
+                    Some(arg_type) => match *self.generalizer.tc.remove_aliases(arg_type.clone()) {
+                        Type::Builtin(BuiltinType::String) => true,
+                        _ => false,
+                    },
+                    None => false,
+                };
+                if is_string {
+                    interpolated.show_id = None;
+                }
+            }
+        }
     }
 
     fn visit_spanned_typed_ident(&mut self, id: &mut SpannedIdent<Symbol>) {
@@ -400,6 +467,23 @@ impl<'a, 'b, 'c, 'd> MutVisitor<'d, '_> for ReplaceVisitor<'a, 'b, 'c, '_> {
     }
 }
 
+/// Returns the type of the argument of the `show` call which was inserted for an interpolated
+/// expression
+fn show_arg_type<'e>(show_id: &'e SpannedExpr<Symbol>) -> Option<&'e RcType> {
+    let show = match &show_id.value {
+        Expr::App { func, .. } => &**func,
+        _ => show_id,
+    };
+    match &show.value {
+        Expr::Ident(id) => id
+            .typ
+            .remove_forall_and_implicit_args()
+            .as_function()
+            .map(|(arg, _)| arg),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
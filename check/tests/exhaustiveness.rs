@@ -0,0 +1,218 @@
+extern crate gluon_base as base;
+extern crate gluon_check as check;
+extern crate gluon_parser as parser;
+
+#[macro_use]
+mod support;
+
+use crate::base::types::TypeCache;
+use crate::check::exhaustiveness::{self, Error};
+
+fn check_matches(text: &str) -> Vec<Error> {
+    let (expr, result) = support::typecheck_expr(text);
+    result.unwrap_or_else(|err| panic!("{}", err));
+    match exhaustiveness::check_expr(
+        &support::MockEnv::new(),
+        &mut &TypeCache::new(),
+        expr.expr(),
+    ) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors.into_iter().map(|err| err.value).collect(),
+    }
+}
+
+fn non_exhaustive(missing: &[&str]) -> Error {
+    Error::NonExhaustive {
+        missing: missing.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+#[test]
+fn exhaustive_variant() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+type AB = | A Int | B in
+match A 1 with
+| A x -> x
+| B -> 0
+";
+    assert_eq!(check_matches(text), []);
+}
+
+#[test]
+fn missing_variant() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+type ABC = | A Int | B | C in
+match B with
+| B -> 0
+";
+    assert_eq!(check_matches(text), [non_exhaustive(&["A _", "C"])]);
+}
+
+#[test]
+fn missing_nested_variant() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+type Option a = | None | Some a
+type AB = | A | B
+match Some A with
+| Some A -> 0
+| None -> 1
+";
+    assert_eq!(check_matches(text), [non_exhaustive(&["Some B"])]);
+}
+
+#[test]
+fn missing_tuple_and_record_fields() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+type AB = | A | B in
+match ({ x = A, y = 1 }, A) with
+| ({ x = A }, _) -> 0
+| (_, B) -> 1
+";
+    assert_eq!(check_matches(text), [non_exhaustive(&["({ x = B }, A)"])]);
+}
+
+#[test]
+fn literals_require_wildcard() {
+    let _ = env_logger::try_init();
+
+    let text = r#"
+match "a" with
+| "a" -> 0
+| "b" -> 1
+"#;
+    assert_eq!(check_matches(text), [non_exhaustive(&["_"])]);
+
+    let text = r#"
+match 1 with
+| 1 -> 0
+| x -> x
+"#;
+    assert_eq!(check_matches(text), []);
+}
+
+#[test]
+fn unreachable_alternatives() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+type AB = | A | B in
+match (A, B) with
+| (A, _) -> 0
+| (B, x) -> 1
+| (A, B) -> 2
+| _ -> 3
+";
+    assert_eq!(
+        check_matches(text),
+        [Error::UnreachableAlternative, Error::UnreachableAlternative]
+    );
+}
+
+#[test]
+fn duplicate_literal_is_unreachable() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+match 1 with
+| 1 -> 0
+| 1 -> 1
+| _ -> 2
+";
+    assert_eq!(check_matches(text), [Error::UnreachableAlternative]);
+}
+
+#[test]
+fn nested_match_is_checked() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+type AB = | A | B in
+let f x =
+    match x with
+    | A -> 0
+f B
+";
+    assert_eq!(check_matches(text), [non_exhaustive(&["B"])]);
+}
+
+#[test]
+fn open_polymorphic_variant_requires_wildcard() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+type AB r = | A | B .. r
+let f x : AB r -> Int =
+    match x with
+    | A -> 0
+    | B -> 1
+f A
+";
+    assert_eq!(check_matches(text), [non_exhaustive(&["_"])]);
+}
+
+#[test]
+fn or_pattern_is_exhaustive() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+type ABC = | A Int | B Int | C
+match A 1 with
+| A x | B x -> x
+| C -> 0
+";
+    assert_eq!(check_matches(text), []);
+
+    let text = r"
+type ABC = | A Int | B Int | C
+match A 1 with
+| A _ | C -> 0
+";
+    assert_eq!(check_matches(text), [non_exhaustive(&["B _"])]);
+}
+
+#[test]
+fn or_pattern_makes_alternative_unreachable() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+type AB = | A | B
+match A with
+| A | B -> 0
+| B -> 1
+";
+    assert_eq!(check_matches(text), [Error::UnreachableAlternative]);
+}
+
+#[test]
+fn guarded_alternatives_are_not_exhaustive() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+type AB = | A | B
+let f x =
+    match x with
+    | A -> 0
+    | B if True -> 1
+f A
+";
+    assert_eq!(check_matches(text), [non_exhaustive(&["B"])]);
+
+    let text = r"
+type AB = | A | B
+let f x =
+    match x with
+    | A -> 0
+    | B if True -> 1
+    | B -> 2
+f A
+";
+    assert_eq!(check_matches(text), []);
+}
@@ -276,7 +276,7 @@ let g x: A a -> () = x
 ";
     let result = support::typecheck(text);
 
-    assert_unify_err!(result, Other(SelfRecursiveAlias(..)));
+    assert_err!(result, Unification(..));
 }
 
 #[test]
@@ -391,7 +391,7 @@ fn no_inference_variable_in_error() {
 
     assert_eq!(
         &*format!("{}", result.unwrap_err()).replace("\t", "        "),
-        r#"error: Expected the following types to be equal
+        r#"error[E0106]: Expected the following types to be equal
 Expected: Int -> a
 Found: ()
 1 errors were found during unification:
@@ -421,7 +421,7 @@ eq (A 0) (B 0.0)
 
     assert_eq!(
         &*format!("{}", result.unwrap_err()).replace("\t", "        "),
-        r#"error: Expected the following types to be equal
+        r#"error[E0106]: Expected the following types to be equal
 Expected: test.A
 Found: test.B
 1 errors were found during unification:
@@ -450,7 +450,7 @@ f { } { x = 1 }
 
     assert_eq!(
         &*format!("{}", result.unwrap_err()).replace("\t", "        "),
-        r#"error: Expected the following types to be equal
+        r#"error[E0106]: Expected the following types to be equal
 Expected: ()
 Found: { x : Int }
 1 errors were found during unification:
@@ -484,7 +484,7 @@ f (Test (Test 1))
 
     assert_diff!(
         &*format!("{}", result.unwrap_err()).replace("\t", "        "),
-        r#"error: Implicit parameter with type `test.Eq Int` could not be resolved.
+        r#"error[E0119]: Implicit parameter with type `test.Eq Int` could not be resolved.
 - <test>:11:3
    |
 11 | f (Test (Test 1))
@@ -620,6 +620,35 @@ do x = 1
     assert_unify_err!(result, TypeMismatch(..));
 }
 
+#[test]
+fn interpolated_string_undefined_show() {
+    let _ = ::env_logger::try_init();
+
+    let text = r#"
+let __string_append x y : String -> String -> String = x
+"count: ${1}"
+"#;
+    let result = support::typecheck(text);
+
+    assert_err!(result, UndefinedVariable(..));
+}
+
+#[test]
+fn interpolated_string_error_span() {
+    use crate::base::pos::Span;
+
+    let _ = ::env_logger::try_init();
+    let text = r#"
+let __string_append x y : String -> String -> String = x
+let show x : Int -> String = ""
+"value: ${1.0}"
+"#;
+    let result = support::typecheck(text);
+    let errors: Vec<_> = result.unwrap_err().unwrap_check().into_errors().into();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].span, Span::new(101.into(), 104.into()));
+}
+
 #[test]
 fn undefined_type_in_variant() {
     let _ = ::env_logger::try_init();
@@ -701,7 +730,7 @@ id "" 1 1.0
 
     assert_eq!(
         &*format!("{}", result.unwrap_err()).replace("\t", "        "),
-        r#"error: Expected the following types to be equal
+        r#"error[E0106]: Expected the following types to be equal
 Expected: Int -> Float -> a
 Found: String
 1 errors were found during unification:
@@ -737,7 +766,7 @@ f x 1
 
     assert_eq!(
         &*format!("{}", result.unwrap_err()).replace("\t", "        "),
-        r#"error: Expected the following types to be equal
+        r#"error[E0106]: Expected the following types to be equal
 Expected: Int -> Int -> a
 Found: test.Eff [| | r |] Int
 1 errors were found during unification:
@@ -844,3 +873,41 @@ match Test 0 with
 "#,
 PatternError { .. }
 }
+
+#[test]
+fn or_pattern_binds_different_variables() {
+    let _ = env_logger::try_init();
+    let text = r#"
+type AB = | A Int | B Int
+match A 1 with
+| A x | B y -> 1
+"#;
+    let result = support::typecheck(text);
+
+    assert_err!(result, OrPatternBinding(..), OrPatternBinding(..));
+}
+
+#[test]
+fn or_pattern_alternatives_have_different_types() {
+    let _ = env_logger::try_init();
+    let text = r#"
+type AB = | A Int | B String
+match A 1 with
+| A x | B x -> 1
+"#;
+    let result = support::typecheck(text);
+
+    assert_unify_err!(result, TypeMismatch(..));
+}
+
+#[test]
+fn guard_must_be_bool() {
+    let _ = env_logger::try_init();
+    let text = r#"
+match 1 with
+| x if x -> 1
+"#;
+    let result = support::typecheck(text);
+
+    assert_err!(result, Unification(..));
+}
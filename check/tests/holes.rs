@@ -0,0 +1,142 @@
+extern crate gluon_base as base;
+extern crate gluon_check as check;
+extern crate gluon_parser as parser;
+
+use crate::base::types::{ArcType, Type};
+
+use crate::check::typecheck::{HoleEntry, TypeError};
+
+#[macro_use]
+#[allow(unused_macros)]
+mod support;
+
+struct Hole {
+    name: String,
+    expected: ArcType,
+    bindings: Vec<String>,
+    candidates: Vec<String>,
+}
+
+fn holes(text: &str) -> Vec<Hole> {
+    let _ = env_logger::try_init();
+
+    let result = support::typecheck(text);
+    let paths = |entries: &[HoleEntry<ArcType>]| -> Vec<String> {
+        entries.iter().map(|entry| entry.path.clone()).collect()
+    };
+    result
+        .unwrap_err()
+        .unwrap_check()
+        .errors()
+        .into_iter()
+        .map(|err| match &err.value.error {
+            TypeError::Hole {
+                name,
+                expected,
+                bindings,
+                candidates,
+            } => Hole {
+                name: name.declared_name().to_string(),
+                expected: expected.clone(),
+                bindings: paths(bindings),
+                candidates: paths(candidates),
+            },
+            err => panic!("Expected a hole error, found: {}", err),
+        })
+        .collect()
+}
+
+#[test]
+fn named_hole_reports_expected_type_and_bindings() {
+    let text = r#"
+let x = 1
+let y = ""
+let f a : Int -> Int = a
+let z : Int = ?foo
+z
+"#;
+    let holes = holes(text);
+    assert_eq!(holes.len(), 1);
+    assert_eq!(holes[0].name, "foo");
+    assert_eq!(holes[0].expected, Type::int());
+    assert_eq!(holes[0].bindings, ["f", "x", "y"]);
+    assert_eq!(holes[0].candidates, ["x"]);
+}
+
+#[test]
+fn underscore_hole() {
+    let text = r#"
+let f x : Int -> String = _
+f
+"#;
+    let holes = holes(text);
+    assert_eq!(holes.len(), 1);
+    assert_eq!(holes[0].name, "_");
+    assert_eq!(holes[0].expected, Type::string());
+    assert_eq!(holes[0].bindings, ["f", "x"]);
+    assert!(holes[0].candidates.is_empty());
+}
+
+#[test]
+fn hole_fits_polymorphic_bindings() {
+    let text = r#"
+let id x = x
+let const x _ = x
+let g : Int -> Int = ?g
+g
+"#;
+    let holes = holes(text);
+    assert_eq!(holes.len(), 1);
+    assert_eq!(holes[0].candidates, ["id"]);
+}
+
+#[test]
+fn hole_fits_implicit_instances() {
+    let text = r#"
+type Show a = { show : a -> String }
+let instances =
+    #[implicit]
+    let show_int : Show Int = { show = \_ -> "" }
+    #[implicit]
+    let show_string : Show String = { show = \x -> x }
+    { show_int, show_string }
+let { ? } = instances
+let s : Show Int = ?instance
+s
+"#;
+    let holes = holes(text);
+    assert_eq!(holes.len(), 1);
+    assert_eq!(holes[0].candidates, ["show_int"]);
+}
+
+#[test]
+fn hole_as_argument() {
+    let text = r#"
+let f x y : Int -> String -> Int = x
+f 1 (?y)
+"#;
+    let holes = holes(text);
+    assert_eq!(holes.len(), 1);
+    assert_eq!(holes[0].name, "y");
+    assert_eq!(holes[0].expected, Type::string());
+}
+
+#[test]
+fn hole_error_message() {
+    let _ = env_logger::try_init();
+    let text = r#"
+let x = 1
+let z : Int = ?foo
+z
+"#;
+    let result = support::typecheck(text);
+    let err = result.unwrap_err().unwrap_check();
+    assert_eq!(
+        err.errors().iter().next().unwrap().value.error.to_string(),
+        "Found hole `foo` with type `Int`
+Relevant bindings include:
+    x : Int
+Valid hole fits include:
+    x : Int"
+    );
+}
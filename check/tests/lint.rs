@@ -0,0 +1,206 @@
+extern crate gluon_base as base;
+extern crate gluon_check as check;
+extern crate gluon_parser as parser;
+
+#[macro_use]
+mod support;
+
+use crate::check::lint::{self, Warning};
+
+fn lint_warnings(text: &str) -> Vec<Warning> {
+    let (expr, result) = support::typecheck_expr(text);
+    result.unwrap_or_else(|err| panic!("{}", err));
+    lint::lint_expr(expr.expr().span, expr.expr())
+        .into_iter()
+        .map(|warning| warning.value)
+        .collect()
+}
+
+fn lint(text: &str) -> Vec<String> {
+    lint_warnings(text)
+        .into_iter()
+        .map(|warning| warning.to_string())
+        .collect()
+}
+
+#[test]
+fn unused_variable() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+let x = 1
+let y = 2
+let _z = 3
+y
+";
+    assert_eq!(lint(text), ["Unused variable `x`"]);
+}
+
+#[test]
+fn used_in_function_is_not_unused() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+let x = 1
+let f y = x #Int+ y
+f 2
+";
+    assert_eq!(lint(text), Vec::<String>::new());
+}
+
+#[test]
+fn unused_record_pattern_field() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+let { x, y } = { x = 1, y = 2 }
+match { a = 1, b = 2 } with
+| { a, b } -> x #Int+ a
+";
+    assert_eq!(
+        lint(text),
+        [
+            "Unused field `y` in record pattern",
+            "Unused field `b` in record pattern",
+        ]
+    );
+}
+
+#[test]
+fn used_in_guard_is_not_unused() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+let limit = 1
+match { a = 1, b = 2 } with
+| { a, b } if b #Int== limit -> a
+| _ -> 0
+";
+    assert_eq!(lint(text), Vec::<String>::new());
+}
+
+#[test]
+fn unused_variable_in_guard() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+match { a = 1, b = 2 } with
+| { a, b } if (let x = 1 in b #Int== 2) -> a
+| _ -> 0
+";
+    assert_eq!(lint(text), ["Unused variable `x`"]);
+}
+
+#[test]
+fn shadowing() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+let x = 1
+let x = x #Int+ 1
+x
+";
+    let warnings = lint_warnings(text);
+    match &warnings[..] {
+        [Warning::Shadowing { name, .. }] => assert_eq!(name.declared_name(), "x"),
+        _ => panic!("Expected a shadowing warning, got {:?}", warnings),
+    }
+}
+
+#[test]
+fn shadowing_lambda_argument_and_match_binding() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+let f x =
+    let x = x #Int+ 1
+    match x with
+    | y ->
+        let { y } = { y }
+        y
+f 1
+";
+    assert_eq!(
+        lint(text),
+        [
+            "`x` shadows an earlier binding with the same name",
+            "`y` shadows an earlier binding with the same name",
+        ]
+    );
+}
+
+#[test]
+fn allow_attribute() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+#[allow(unused)]
+let x = 1
+#[allow(unused_variables, shadowing)]
+let y =
+    let a = 1
+    let y = 2
+    2
+#[allow(shadowing)]
+let y = 3
+y
+";
+    assert_eq!(lint(text), Vec::<String>::new());
+}
+
+#[test]
+fn allow_attribute_is_scoped_to_the_binding() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+#[allow(unused)]
+let x = 1
+let z = 2
+3
+";
+    assert_eq!(lint(text), ["Unused variable `z`"]);
+}
+
+#[test]
+fn allow_module_attribute() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+#[allow_module(unused_variables)]
+let x = 1
+let y =
+    let a = 1
+    2
+let { z } = { z = 3 }
+4
+";
+    assert_eq!(lint(text), ["Unused field `z` in record pattern"]);
+}
+
+#[test]
+fn allow_module_attribute_on_type_binding() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+#[allow_module(shadowing)]
+type Test = Int
+let x = 1
+let x = x
+x
+";
+    assert_eq!(lint(text), Vec::<String>::new());
+}
+
+#[test]
+fn allow_module_attribute_only_applies_to_the_first_binding() {
+    let _ = env_logger::try_init();
+
+    let text = r"
+let x = 1
+#[allow_module(unused)]
+let y = x
+let z = 2
+3
+";
+    assert_eq!(lint(text), ["Unused variable `y`", "Unused variable `z`"]);
+}
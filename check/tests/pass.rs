@@ -711,6 +711,39 @@ match { y = 1 } with
     assert_req!(result, expected);
 }
 
+#[test]
+fn interpolated_string() {
+    let _ = env_logger::try_init();
+
+    let text = r#"
+let __string_append x y : String -> String -> String = x
+let show x : Int -> String = ""
+let name = "a"
+let greet x : String -> String = "hello ${x}"
+"${name} has ${1} items, ${greet name}"
+"#;
+    let result = support::typecheck(text);
+
+    assert_req!(result, Ok(Type::string()));
+}
+
+#[test]
+fn interpolated_string_infers_hole_types_in_any_order() {
+    let _ = env_logger::try_init();
+
+    let text = r#"
+let __string_append x y : String -> String -> String = x
+#[infix(left, 6)]
+let (+) x y : Int -> Int -> Int = x
+let show x : Int -> String = ""
+let f x = "${x} ${x + 1}"
+f
+"#;
+    let result = support::typecheck(text);
+
+    assert_req!(result, Ok(Type::function(vec![Type::int()], Type::string())));
+}
+
 #[test]
 fn do_expression_simple() {
     let _ = env_logger::try_init();
@@ -1135,3 +1168,15 @@ match writer with
     "#,
     "test.List String"
 }
+
+test_check! {
+    or_pattern_and_guard,
+    r#"
+type AB = | A Int | B Int | C
+match A 1 with
+| A x | B x if x #Int== 1 -> x
+| (A _ | B _) -> 2
+| C -> 3
+    "#,
+    "Int"
+}
@@ -51,7 +51,10 @@ pub fn split_for_impl<'a>(
     let (_, ty_generics, where_clause) = generics.split_for_impl();
     let ty_generics = ty_generics.clone();
     let where_clause = where_clause
-        .map(|clause| quote! { #clause, })
+        .map(|clause| {
+            let predicates = clause.predicates.iter();
+            quote! { where #(#predicates,)* }
+        })
         .unwrap_or(quote! { where });
 
     // generate the generic params for the impl block
@@ -8,6 +8,8 @@ extern crate walkdir;
 
 extern crate gluon_base as base;
 
+pub mod references;
+
 use std::{borrow::Cow, cmp::Ordering, iter::once, path::PathBuf, sync::Arc};
 
 use codespan::ByteOffset;
@@ -186,6 +188,8 @@ impl<E: TypeEnv<Type = ArcType>> OnFound for Suggest<E> {
                     self.on_pattern(arg);
                 }
             }
+            // Each alternative binds the same variables
+            Pattern::Or(alts) => self.on_pattern(&alts[0]),
             Pattern::Literal(_) | Pattern::Error => (),
         }
     }
@@ -466,6 +470,10 @@ where
                 let (_, field) = self.select_spanned(&**elems, |elem| elem.span);
                 self.visit_pattern(field.unwrap());
             }
+            Pattern::Or(ref alts) => {
+                let (_, alt) = self.select_spanned(&**alts, |alt| alt.span);
+                self.visit_pattern(alt.unwrap());
+            }
             Pattern::Ident(_) | Pattern::Literal(_) | Pattern::Error => {
                 self.found = if current.span.containment(self.pos) == Ordering::Equal {
                     MatchState::Found(Match::Pattern(current))
@@ -491,13 +499,20 @@ where
         }
 
         match current.value {
-            Expr::Ident(_) | Expr::Literal(_) => {
+            Expr::Ident(_) | Expr::Hole(_) | Expr::Literal(_) => {
                 self.found = if current.span.containment(self.pos) == Ordering::Equal {
                     MatchState::Found(Match::Expr(current))
                 } else {
                     MatchState::Empty
                 };
             }
+            Expr::Interpolation(ref interpolation) => {
+                let holes = interpolation.exprs.iter().map(|hole| &hole.expr);
+                match self.select_spanned(holes, |expr| expr.span) {
+                    (false, Some(expr)) => self.visit_expr(expr),
+                    _ => self.found = MatchState::Found(Match::Expr(current)),
+                }
+            }
             Expr::App {
                 ref func, ref args, ..
             } => {
@@ -519,8 +534,10 @@ where
                     }
                     Err(alt) => {
                         self.on_found.on_pattern(&alt.pattern);
-                        let iter = [Ok(&alt.pattern), Err(&alt.expr)];
-                        let (_, sel) = self.select_spanned(iter.iter().cloned(), |x| match *x {
+                        let iter = once(Ok(&alt.pattern))
+                            .chain(alt.guard.iter().map(Err))
+                            .chain(once(Err(&alt.expr)));
+                        let (_, sel) = self.select_spanned(iter, |x| match *x {
                             Ok(p) => p.span,
                             Err(e) => e.span,
                         });
@@ -767,6 +784,20 @@ where
     }
 }
 
+/// Returns the local variables which are in scope at `pos`
+pub(crate) fn locals_at(
+    env: &dyn TypeEnv<Type = ArcType>,
+    source_span: Span<BytePos>,
+    expr: &SpannedExpr<Symbol>,
+    pos: BytePos,
+) -> Vec<Symbol> {
+    let mut suggest = Suggest::new(env);
+    match complete_at(&mut suggest, source_span, expr, pos) {
+        Ok(_) => suggest.stack.iter().map(|(name, _)| name.clone()).collect(),
+        Err(()) => Vec::new(),
+    }
+}
+
 pub trait Extract<'a>: Sized {
     type Output;
     fn extract(self, found: &Found<'a, '_>) -> Result<Self::Output, ()>;
@@ -0,0 +1,1014 @@
+//! Find references, go to definition and rename across all the modules of a program.
+//!
+//! Unlike `find_all_symbols`, which only looks at the symbols of a single expression, the
+//! functions in this module identify what is referred to in a way which is stable across modules.
+//! Local variables are identified by their `Symbol`, exported values by the name of the module
+//! and the field they are exported as and types, record fields and constructors by the (module
+//! qualified) name of the type alias which defines them.
+use std::ptr;
+
+use codespan::{ByteOffset, FileMap};
+
+use crate::base::{
+    ast::{
+        walk_expr, AstType, Expr, Pattern, PatternField, SpannedExpr, SpannedIdent, SpannedPattern,
+        Typed, Visitor,
+    },
+    fnv::{FnvMap, FnvSet},
+    pos::{BytePos, HasSpan, Span, Spanned},
+    symbol::Symbol,
+    types::{ArcType, Type, TypeEnv, TypeExt},
+};
+
+/// A module which is searched for references
+#[derive(Clone, Copy)]
+pub struct Module<'a, 'ast> {
+    /// The name of the module, as used by `import!`
+    pub name: &'a str,
+    pub source: &'a FileMap,
+    pub expr: &'a SpannedExpr<'ast, Symbol>,
+}
+
+/// Something which can be referred to from several places in a program
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Reference {
+    /// A variable which is local to `module`
+    Local { module: String, symbol: Symbol },
+    /// The field `field` of the record returned by `module`
+    Export { module: String, field: String },
+    /// The type alias named `name`
+    Type { name: String },
+    /// The field `field` of the record type alias `typ`
+    Field { typ: String, field: String },
+    /// The constructor `name` of the variant type alias `typ`
+    Constructor { typ: String, name: String },
+}
+
+impl Reference {
+    /// Returns the name as it is written in the source
+    pub fn name(&self) -> &str {
+        match self {
+            Reference::Local { symbol, .. } => symbol.declared_name(),
+            Reference::Export { field, .. } | Reference::Field { field, .. } => field,
+            Reference::Type { name } => name.rsplit('.').next().unwrap_or(name),
+            Reference::Constructor { name, .. } => name,
+        }
+    }
+
+    /// Returns the reference that this reference would be after being renamed to `new_name`, if
+    /// that can be an existing reference. Locals are instead checked by looking at the variables
+    /// in scope.
+    fn renamed(&self, new_name: &str) -> Option<Reference> {
+        Some(match self {
+            Reference::Local { .. } => return None,
+            Reference::Export { module, .. } => Reference::Export {
+                module: module.clone(),
+                field: new_name.to_string(),
+            },
+            Reference::Type { name } => Reference::Type {
+                name: match name.rfind('.') {
+                    Some(i) => format!("{}{}", &name[..=i], new_name),
+                    None => new_name.to_string(),
+                },
+            },
+            Reference::Field { typ, .. } => Reference::Field {
+                typ: typ.clone(),
+                field: new_name.to_string(),
+            },
+            Reference::Constructor { typ, .. } => Reference::Constructor {
+                typ: typ.clone(),
+                name: new_name.to_string(),
+            },
+        })
+    }
+
+    fn is_uppercase(&self) -> bool {
+        match self {
+            Reference::Type { .. } | Reference::Constructor { .. } => true,
+            _ => starts_with_uppercase(self.name()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Location {
+    pub module: String,
+    pub span: Span<BytePos>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TextEdit {
+    pub module: String,
+    pub span: Span<BytePos>,
+    pub new_text: String,
+}
+
+/// How the name of a reference appears at an occurrence
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Form {
+    /// The name can be replaced as is
+    Name,
+    /// A punned record field (`{ x }`). Renaming the field must keep the name of the local
+    PunnedField,
+    /// A punned record field (`{ x }`). Renaming the local must keep the name of the field
+    PunnedLocal,
+}
+
+#[derive(Debug)]
+struct Occurrence {
+    span: Span<BytePos>,
+    reference: Reference,
+    form: Form,
+    /// A local which is bound by this occurrence and is renamed along with it, such as the
+    /// variable bound by `let { x } = import! module`
+    linked: Option<Reference>,
+    /// Whether this occurrence is where the reference is defined
+    definition: bool,
+    /// The local which holds the value of an exported or imported field
+    bound_to: Option<Reference>,
+}
+
+impl Occurrence {
+    fn new(span: Span<BytePos>, reference: Reference) -> Self {
+        Occurrence {
+            span,
+            reference,
+            form: Form::Name,
+            linked: None,
+            definition: false,
+            bound_to: None,
+        }
+    }
+
+    fn form(self, form: Form) -> Self {
+        Occurrence { form, ..self }
+    }
+
+    fn linked(self, linked: Option<Reference>) -> Self {
+        Occurrence { linked, ..self }
+    }
+
+    fn definition(self) -> Self {
+        Occurrence {
+            definition: true,
+            ..self
+        }
+    }
+
+    fn bound_to(self, bound_to: Option<Reference>) -> Self {
+        Occurrence { bound_to, ..self }
+    }
+}
+
+fn starts_with_uppercase(name: &str) -> bool {
+    name.chars().next().map_or(false, |c| c.is_uppercase())
+}
+
+/// Returns the name of the type alias which `typ` is an application of
+fn alias_name(typ: &ArcType) -> Option<String> {
+    match &**typ {
+        Type::Forall(_, typ) => alias_name(typ),
+        Type::App(head, _) => alias_name(head),
+        Type::Alias(alias) => Some(alias.name.definition_name().to_string()),
+        Type::Ident(id) => Some(id.name.definition_name().to_string()),
+        _ => None,
+    }
+}
+
+/// Returns the name of the type alias which a constructor of type `typ` constructs
+fn constructed_alias_name(typ: &ArcType) -> Option<String> {
+    match &**typ {
+        Type::Forall(_, typ) => constructed_alias_name(typ),
+        Type::Function(_, _, ret) => constructed_alias_name(ret),
+        _ => alias_name(typ),
+    }
+}
+
+/// Returns the record expression which the module `expr` evaluates to, if any
+fn exported_record<'a, 'ast>(
+    expr: &'a SpannedExpr<'ast, Symbol>,
+) -> Option<&'a SpannedExpr<'ast, Symbol>> {
+    match &expr.value {
+        Expr::LetBindings(_, body) | Expr::TypeBindings(_, body) => exported_record(body),
+        Expr::MacroExpansion { replacement, .. } => exported_record(replacement),
+        Expr::Record { .. } => Some(expr),
+        _ => None,
+    }
+}
+
+struct Occurrences<'a, 'e> {
+    module: &'a Module<'a, 'e>,
+    env: &'a dyn TypeEnv<Type = ArcType>,
+    /// The record which the module evaluates to
+    exported: Option<&'a SpannedExpr<'e, Symbol>>,
+    /// Locals which are bound to modules (`let m = import! m`)
+    module_values: FnvMap<Symbol, String>,
+    /// Maps the symbols used to refer to a type in this module to the name of the type alias
+    type_names: FnvMap<Symbol, String>,
+    /// The type alias which a record expression is annotated with (`let r : R = { .. }`)
+    expected_alias: Option<String>,
+    result: Vec<Occurrence>,
+}
+
+impl<'a, 'e> Occurrences<'a, 'e> {
+    fn new(module: &'a Module<'a, 'e>, env: &'a dyn TypeEnv<Type = ArcType>) -> Self {
+        Occurrences {
+            module,
+            env,
+            exported: exported_record(module.expr),
+            module_values: FnvMap::default(),
+            type_names: FnvMap::default(),
+            expected_alias: None,
+            result: Vec::new(),
+        }
+    }
+
+    fn collect(mut self) -> Vec<Occurrence> {
+        let expr = self.module.expr;
+        self.visit_expr(expr);
+        self.result
+    }
+
+    fn text(&self, span: Span<BytePos>) -> Option<&'a str> {
+        self.module.source.src_slice(span).ok()
+    }
+
+    fn push(&mut self, occurrence: Occurrence) {
+        // Expressions inserted by the compiler do not appear in the source
+        if self.text(occurrence.span) == Some(occurrence.reference.name()) {
+            self.result.push(occurrence);
+        }
+    }
+
+    fn local(&self, symbol: &Symbol) -> Reference {
+        Reference::Local {
+            module: self.module.name.to_string(),
+            symbol: symbol.clone(),
+        }
+    }
+
+    fn push_ident(&mut self, span: Span<BytePos>, symbol: &Symbol, typ: &ArcType) {
+        if starts_with_uppercase(symbol.declared_name()) {
+            if let Some(typ) = constructed_alias_name(typ) {
+                let name = symbol.declared_name().to_string();
+                self.push(Occurrence::new(span, Reference::Constructor { typ, name }));
+            }
+        } else if !symbol.is_global() {
+            let reference = self.local(symbol);
+            self.push(Occurrence::new(span, reference));
+        }
+    }
+
+    fn push_binding(&mut self, span: Span<BytePos>, symbol: &Symbol) {
+        let reference = self.local(symbol);
+        self.push(Occurrence::new(span, reference).definition());
+    }
+
+    fn type_name(&self, symbol: &Symbol) -> String {
+        self.type_names
+            .get(symbol)
+            .cloned()
+            .unwrap_or_else(|| symbol.definition_name().to_string())
+    }
+
+    /// Returns the module which `expr` evaluates to if it is an `import!` or a local bound to one
+    fn module_of(&self, expr: &SpannedExpr<Symbol>) -> Option<String> {
+        match &expr.value {
+            Expr::MacroExpansion { replacement, .. } => self.module_of(replacement),
+            Expr::Ident(id) if id.name.is_global() => Some(id.name.definition_name().to_string()),
+            Expr::Ident(id) => self.module_values.get(&id.name).cloned(),
+            _ => None,
+        }
+    }
+
+    /// Finds the source location of `name` by searching `span` for `name` followed by
+    /// `delimiter`, or preceded by it if `prefix` is set. Matches inside `exclude` are skipped.
+    fn find_name(
+        &self,
+        span: Span<BytePos>,
+        name: &str,
+        delimiter: char,
+        prefix: bool,
+        exclude: &[Span<BytePos>],
+    ) -> Option<Span<BytePos>> {
+        let text = self.text(span)?;
+        let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '\'';
+        text.match_indices(name)
+            .filter(|&(i, _)| {
+                let before = text[..i].trim_end();
+                let after = text[i + name.len()..].trim_start();
+                !text[..i].ends_with(is_ident)
+                    && !text[i + name.len()..].starts_with(is_ident)
+                    && if prefix {
+                        before.ends_with(delimiter)
+                    } else {
+                        after.starts_with(delimiter)
+                    }
+            })
+            .map(|(i, _)| {
+                let start = span.start() + ByteOffset::from(i as i64);
+                Span::new(start, start + ByteOffset::from(name.len() as i64))
+            })
+            .find(|name_span| !exclude.iter().any(|excluded| excluded.contains(*name_span)))
+    }
+
+    fn alias_definition(&mut self, full_name: &str, typ: &AstType<'e, Symbol>) {
+        match &**typ {
+            Type::Forall(_, typ) => self.alias_definition(full_name, typ),
+            Type::Record(row) => {
+                let mut row = row;
+                loop {
+                    match &**row {
+                        Type::ExtendRow { fields, rest } => {
+                            let exclude: Vec<_> =
+                                fields.iter().map(|field| field.typ.span()).collect();
+                            for field in fields.iter() {
+                                let name = field.name.declared_name();
+                                if let Some(span) =
+                                    self.find_name(typ.span(), name, ':', false, &exclude)
+                                {
+                                    let reference = Reference::Field {
+                                        typ: full_name.to_string(),
+                                        field: name.to_string(),
+                                    };
+                                    self.push(Occurrence::new(span, reference).definition());
+                                }
+                            }
+                            row = rest;
+                        }
+                        Type::ExtendTypeRow { rest, .. } => row = rest,
+                        _ => break,
+                    }
+                }
+            }
+            Type::Variant(row) => {
+                let mut row = row;
+                while let Type::ExtendRow { fields, rest } = &**row {
+                    for field in fields.iter() {
+                        let name = field.name.declared_name();
+                        if let Some(span) = self.find_name(typ.span(), name, '|', true, &[]) {
+                            let reference = Reference::Constructor {
+                                typ: full_name.to_string(),
+                                name: name.to_string(),
+                            };
+                            self.push(Occurrence::new(span, reference).definition());
+                        }
+                    }
+                    row = rest;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn ast_type(&mut self, typ: &AstType<'e, Symbol>) {
+        let span = typ.span();
+        match &**typ {
+            Type::Ident(id) => {
+                let name = self.type_name(&id.name);
+                self.push(Occurrence::new(span, Reference::Type { name }));
+            }
+            Type::Alias(alias) => {
+                let name = self.type_name(&alias.name);
+                self.push(Occurrence::new(span, Reference::Type { name }));
+            }
+            Type::Forall(_, typ) | Type::Record(typ) | Type::Variant(typ) | Type::Effect(typ) => {
+                self.ast_type(typ)
+            }
+            Type::Function(_, arg, ret) => {
+                self.ast_type(arg);
+                self.ast_type(ret);
+            }
+            Type::App(head, args) => {
+                self.ast_type(head);
+                for arg in args.iter() {
+                    self.ast_type(arg);
+                }
+            }
+            Type::ExtendRow { fields, rest } => {
+                for field in fields.iter() {
+                    self.ast_type(&field.typ);
+                }
+                self.ast_type(rest);
+            }
+            Type::ExtendTypeRow { rest, .. } => self.ast_type(rest),
+            _ => (),
+        }
+    }
+
+    fn pattern(&mut self, pattern: &'a SpannedPattern<'e, Symbol>, module: Option<&str>) {
+        match &pattern.value {
+            Pattern::Ident(id) => {
+                if let Some(module) = module {
+                    self.module_values
+                        .insert(id.name.clone(), module.to_string());
+                }
+                self.push_binding(pattern.span, &id.name);
+            }
+            Pattern::As(id, pat) => {
+                if let Some(module) = module {
+                    self.module_values
+                        .insert(id.value.clone(), module.to_string());
+                }
+                self.push_binding(id.span, &id.value);
+                self.pattern(pat, module);
+            }
+            Pattern::Constructor(id, args) => {
+                let start = pattern.span.start();
+                let len = ByteOffset::from(id.name.declared_name().len() as i64);
+                self.push_ident(Span::new(start, start + len), &id.name, &id.typ);
+                for arg in args.iter() {
+                    self.pattern(arg, None);
+                }
+            }
+            Pattern::Record { typ, fields, .. } => {
+                let record_alias = if module.is_none() {
+                    alias_name(typ)
+                } else {
+                    None
+                };
+                for field in fields.iter() {
+                    match field {
+                        PatternField::Type { name } => {
+                            let alias = typ.type_field_iter().find(|field| {
+                                field.name.declared_name() == name.value.declared_name()
+                            });
+                            if let Some(alias) = alias {
+                                let full_name = alias.typ.name.definition_name().to_string();
+                                self.type_names
+                                    .insert(name.value.clone(), full_name.clone());
+                                let reference = Reference::Type { name: full_name };
+                                self.push(Occurrence::new(name.span, reference));
+                            }
+                        }
+                        PatternField::Value { name, value } => {
+                            self.pattern_field(typ, module, record_alias.as_deref(), name, value)
+                        }
+                    }
+                }
+            }
+            Pattern::Tuple { elems, .. } => {
+                for elem in elems.iter() {
+                    self.pattern(elem, None);
+                }
+            }
+            Pattern::Or(alts) => {
+                for alt in alts.iter() {
+                    self.pattern(alt, None);
+                }
+            }
+            Pattern::Literal(_) | Pattern::Error => (),
+        }
+    }
+
+    fn pattern_field(
+        &mut self,
+        typ: &ArcType,
+        module: Option<&str>,
+        record_alias: Option<&str>,
+        name: &'a Spanned<Symbol, BytePos>,
+        value: &'a Option<SpannedPattern<'e, Symbol>>,
+    ) {
+        let field = name.value.declared_name();
+        let punned = value.is_none();
+
+        // Constructors can be imported by destructuring a module
+        if starts_with_uppercase(field) {
+            let constructed = typ
+                .row_iter()
+                .find(|row_field| row_field.name.declared_name() == field)
+                .and_then(|row_field| constructed_alias_name(&row_field.typ));
+            if let Some(constructed) = constructed {
+                let reference = Reference::Constructor {
+                    typ: constructed,
+                    name: field.to_string(),
+                };
+                self.push(Occurrence::new(name.span, reference));
+            }
+        }
+
+        let local = if punned {
+            Some(self.local(&name.value))
+        } else {
+            None
+        };
+        if let Some(module) = module {
+            let reference = Reference::Export {
+                module: module.to_string(),
+                field: field.to_string(),
+            };
+            let bound_to = match value {
+                Some(Spanned {
+                    value: Pattern::Ident(id),
+                    ..
+                }) => Some(self.local(&id.name)),
+                _ => local.clone(),
+            };
+            self.push(
+                Occurrence::new(name.span, reference)
+                    .linked(local.clone())
+                    .bound_to(bound_to),
+            );
+        } else if let Some(record_alias) = record_alias {
+            let reference = Reference::Field {
+                typ: record_alias.to_string(),
+                field: field.to_string(),
+            };
+            let form = if punned {
+                Form::PunnedField
+            } else {
+                Form::Name
+            };
+            self.push(Occurrence::new(name.span, reference).form(form));
+        }
+        if let Some(local) = local {
+            if !starts_with_uppercase(field) {
+                self.push(
+                    Occurrence::new(name.span, local)
+                        .form(Form::PunnedLocal)
+                        .definition(),
+                );
+            }
+        }
+        if let Some(value) = value {
+            self.pattern(value, None);
+        }
+    }
+}
+
+impl<'a, 'e> Visitor<'a, 'e> for Occurrences<'a, 'e> {
+    type Ident = Symbol;
+
+    fn visit_expr(&mut self, expr: &'a SpannedExpr<'e, Symbol>) {
+        let expected_alias = self.expected_alias.take();
+        match &expr.value {
+            Expr::Ident(id) => self.push_ident(expr.span, &id.name, &id.typ),
+            Expr::Projection(base, field, _) => {
+                let len = ByteOffset::from(field.declared_name().len() as i64);
+                let span = Span::new(expr.span.end() - len, expr.span.end());
+                if let Some(module) = self.module_of(base) {
+                    let reference = Reference::Export {
+                        module,
+                        field: field.declared_name().to_string(),
+                    };
+                    self.push(Occurrence::new(span, reference));
+                } else if let Some(typ) = base
+                    .try_type_of(self.env)
+                    .ok()
+                    .and_then(|typ| alias_name(&typ))
+                {
+                    let reference = Reference::Field {
+                        typ,
+                        field: field.declared_name().to_string(),
+                    };
+                    self.push(Occurrence::new(span, reference));
+                }
+                self.visit_expr(base);
+            }
+            Expr::Record {
+                typ,
+                types,
+                exprs,
+                base,
+            } => {
+                let exported = self
+                    .exported
+                    .map_or(false, |exported| ptr::eq(exported, expr));
+                let record_alias = alias_name(typ).or(expected_alias);
+                for field in types.iter() {
+                    let alias = typ.type_field_iter().find(|type_field| {
+                        type_field.name.declared_name() == field.name.value.declared_name()
+                    });
+                    if let Some(alias) = alias {
+                        let name = alias.typ.name.definition_name().to_string();
+                        self.push(Occurrence::new(field.name.span, Reference::Type { name }));
+                    }
+                }
+                for field in exprs.iter() {
+                    let name = field.name.value.declared_name();
+                    let local = if field.value.is_none() {
+                        Some(self.local(&field.name.value))
+                    } else {
+                        None
+                    };
+                    if exported {
+                        let reference = Reference::Export {
+                            module: self.module.name.to_string(),
+                            field: name.to_string(),
+                        };
+                        let bound_to = match &field.value {
+                            Some(Spanned {
+                                value: Expr::Ident(id),
+                                ..
+                            }) if !id.name.is_global() => Some(self.local(&id.name)),
+                            _ => local.clone(),
+                        };
+                        self.push(
+                            Occurrence::new(field.name.span, reference)
+                                .linked(local.clone())
+                                .bound_to(bound_to)
+                                .definition(),
+                        );
+                    } else if let Some(record_alias) = &record_alias {
+                        let reference = Reference::Field {
+                            typ: record_alias.clone(),
+                            field: name.to_string(),
+                        };
+                        let form = if local.is_some() {
+                            Form::PunnedField
+                        } else {
+                            Form::Name
+                        };
+                        self.push(Occurrence::new(field.name.span, reference).form(form));
+                    }
+                    match &field.value {
+                        Some(value) => self.visit_expr(value),
+                        None => {
+                            if starts_with_uppercase(name) {
+                                let typ = typ
+                                    .row_iter()
+                                    .find(|row_field| row_field.name.declared_name() == name)
+                                    .map(|row_field| row_field.typ.clone());
+                                if let Some(typ) = typ {
+                                    self.push_ident(field.name.span, &field.name.value, &typ);
+                                }
+                            } else if let Some(local) = local {
+                                self.push(
+                                    Occurrence::new(field.name.span, local).form(Form::PunnedLocal),
+                                );
+                            }
+                        }
+                    }
+                }
+                if let Some(base) = base {
+                    self.visit_expr(base);
+                }
+            }
+            Expr::LetBindings(binds, body) => {
+                for bind in binds.iter() {
+                    let module = self.module_of(&bind.expr);
+                    self.pattern(&bind.name, module.as_deref());
+                    for arg in bind.args.iter() {
+                        self.visit_spanned_typed_ident(&arg.name);
+                    }
+                    if let Some(typ) = &bind.typ {
+                        self.ast_type(typ);
+                    }
+                    if bind.args.is_empty() {
+                        self.expected_alias = alias_name(&bind.resolved_type);
+                    }
+                    self.visit_expr(&bind.expr);
+                }
+                self.visit_expr(body);
+            }
+            Expr::TypeBindings(binds, body) => {
+                // Insert all names first as the bindings may be mutually recursive
+                for bind in binds.iter() {
+                    let full_name = bind.alias.value.name.definition_name().to_string();
+                    self.type_names
+                        .insert(bind.alias.value.name.clone(), full_name.clone());
+                    self.type_names.insert(bind.name.value.clone(), full_name);
+                }
+                for bind in binds.iter() {
+                    let full_name = self.type_name(&bind.name.value);
+                    let typ = bind.alias.value.unresolved_type();
+                    let reference = Reference::Type {
+                        name: full_name.clone(),
+                    };
+                    self.push(Occurrence::new(bind.name.span, reference).definition());
+                    self.alias_definition(&full_name, typ);
+                    self.ast_type(typ);
+                }
+                self.visit_expr(body);
+            }
+            Expr::Infix {
+                lhs,
+                op,
+                rhs,
+                implicit_args,
+            } => {
+                self.visit_expr(lhs);
+                self.push_ident(op.span, &op.value.name, &op.value.typ);
+                self.visit_expr(rhs);
+                for arg in implicit_args.iter() {
+                    self.visit_expr(arg);
+                }
+            }
+            _ => walk_expr(self, expr),
+        }
+    }
+
+    fn visit_pattern(&mut self, pattern: &'a SpannedPattern<'e, Symbol>) {
+        self.pattern(pattern, None)
+    }
+
+    /// Only called for the arguments of functions as operators are handled by `visit_expr`
+    fn visit_spanned_typed_ident(&mut self, id: &'a SpannedIdent<Symbol>) {
+        self.push_binding(id.span, &id.value.name)
+    }
+}
+
+fn occurrences<'a, 'e>(
+    module: &'a Module<'a, 'e>,
+    env: &'a dyn TypeEnv<Type = ArcType>,
+) -> Vec<Occurrence> {
+    Occurrences::new(module, env).collect()
+}
+
+/// Returns what the name at `pos` in `module` refers to.
+///
+/// Locals which are exported from the module, or which are bound by destructuring an imported
+/// module, are returned as the `Reference::Export` they are bound to.
+pub fn reference_at(
+    module: &Module,
+    env: &dyn TypeEnv<Type = ArcType>,
+    pos: BytePos,
+) -> Option<Reference> {
+    let occurrences = occurrences(module, env);
+    let found = occurrences
+        .iter()
+        .filter(|occurrence| occurrence.span.start() <= pos && pos <= occurrence.span.end())
+        .min_by_key(|occurrence| occurrence.span.end() - occurrence.span.start())?;
+    let reference = &found.reference;
+    if let Reference::Local { .. } = reference {
+        let export = occurrences.iter().find(|occurrence| {
+            occurrence.linked.as_ref() == Some(reference)
+                && match occurrence.reference {
+                    Reference::Export { .. } => true,
+                    _ => false,
+                }
+        });
+        if let Some(export) = export {
+            return Some(export.reference.clone());
+        }
+    }
+    Some(reference.clone())
+}
+
+/// Maximum number of imports and re-exports which are followed when looking for a definition
+const MAX_DEFINITION_DEPTH: usize = 32;
+
+/// Returns the name of the module which `reference` belongs to
+fn owner(reference: &Reference) -> Option<&str> {
+    match reference {
+        Reference::Local { module, .. } | Reference::Export { module, .. } => Some(module),
+        Reference::Type { name: typ }
+        | Reference::Field { typ, .. }
+        | Reference::Constructor { typ, .. } => typ.rsplitn(2, '.').nth(1),
+    }
+}
+
+/// Finds where `reference` is defined. Values which are imported through `import!` are followed
+/// to the binding in the module which exports them.
+pub fn definition(
+    modules: &[Module],
+    env: &dyn TypeEnv<Type = ArcType>,
+    reference: &Reference,
+) -> Option<Location> {
+    find_definition(modules, env, reference, 0)
+}
+
+fn find_definition(
+    modules: &[Module],
+    env: &dyn TypeEnv<Type = ArcType>,
+    reference: &Reference,
+    depth: usize,
+) -> Option<Location> {
+    if depth > MAX_DEFINITION_DEPTH {
+        return None;
+    }
+
+    let owner = owner(reference);
+    let is_local = match reference {
+        Reference::Local { .. } | Reference::Export { .. } => true,
+        _ => false,
+    };
+    // Types, fields and constructors are usually defined in the module their name is qualified
+    // with but may be defined elsewhere if the module is not known to us
+    let candidates = modules
+        .iter()
+        .filter(|module| Some(module.name) == owner)
+        .chain(
+            modules
+                .iter()
+                .filter(|module| !is_local && Some(module.name) != owner),
+        );
+    for module in candidates {
+        let occurrences = occurrences(module, env);
+
+        if let Reference::Local { .. } = reference {
+            // Locals bound by destructuring an import are defined in the imported module
+            let import = occurrences.iter().find(|occurrence| {
+                !occurrence.definition && occurrence.bound_to.as_ref() == Some(reference)
+            });
+            if let Some(import) = import {
+                let location = find_definition(modules, env, &import.reference, depth + 1);
+                if location.is_some() {
+                    return location;
+                }
+            }
+        }
+
+        let found = occurrences
+            .iter()
+            .find(|occurrence| occurrence.definition && occurrence.reference == *reference);
+        if let Some(found) = found {
+            // Prefer the binding of an exported value over the field of the exported record
+            if let Some(bound_to) = &found.bound_to {
+                let location = find_definition(modules, env, bound_to, depth + 1);
+                if location.is_some() {
+                    return location;
+                }
+            }
+            return Some(Location {
+                module: module.name.to_string(),
+                span: found.span,
+            });
+        }
+    }
+    None
+}
+
+/// Finds the definition of the type of the expression at `pos` in `module`. If `pos` is on a type
+/// the definition of that type is returned.
+pub fn type_definition(
+    modules: &[Module],
+    env: &dyn TypeEnv<Type = ArcType>,
+    module: &Module,
+    pos: BytePos,
+) -> Option<Location> {
+    let reference = match reference_at(module, env, pos) {
+        Some(reference @ Reference::Type { .. }) => reference,
+        _ => {
+            let extract = crate::TypeAt { env };
+            let typ = crate::completion(extract, module.source.span(), module.expr, pos)
+                .ok()?
+                .right()?;
+            Reference::Type {
+                name: alias_name(&typ)?,
+            }
+        }
+    };
+    definition(modules, env, &reference)
+}
+
+/// Collects the occurrences of `reference` along with the occurrences of every local which is
+/// linked to it
+fn collect_occurrences(
+    modules: &[Module],
+    env: &dyn TypeEnv<Type = ArcType>,
+    reference: &Reference,
+) -> Vec<(String, Occurrence)> {
+    let mut result = Vec::new();
+    let mut seen = FnvSet::default();
+    for module in modules {
+        let occurrences = occurrences(module, env);
+        let linked: Vec<_> = occurrences
+            .iter()
+            .filter(|occurrence| occurrence.reference == *reference)
+            .filter_map(|occurrence| occurrence.linked.clone())
+            .collect();
+        // Occurrences of the reference itself take priority over the linked locals which share
+        // the same span
+        let (direct, rest): (Vec<_>, Vec<_>) = occurrences
+            .into_iter()
+            .partition(|occurrence| occurrence.reference == *reference);
+        let rest = rest
+            .into_iter()
+            .filter(|occurrence| linked.contains(&occurrence.reference));
+        for occurrence in direct.into_iter().chain(rest) {
+            let span = occurrence.span;
+            if seen.insert((module.name, span.start(), span.end())) {
+                result.push((module.name.to_string(), occurrence));
+            }
+        }
+    }
+    result
+}
+
+/// Finds all locations which refer to `reference` in `modules`
+pub fn find_references(
+    modules: &[Module],
+    env: &dyn TypeEnv<Type = ArcType>,
+    reference: &Reference,
+) -> Vec<Location> {
+    collect_occurrences(modules, env, reference)
+        .into_iter()
+        .map(|(module, occurrence)| Location {
+            module,
+            span: occurrence.span,
+        })
+        .collect()
+}
+
+fn is_keyword(name: &str) -> bool {
+    match name {
+        "do" | "else" | "forall" | "if" | "in" | "let" | "match" | "rec" | "seq" | "then"
+        | "type" | "with" => true,
+        _ => false,
+    }
+}
+
+/// Returns an error if renaming the `renamed_occurrences` of `reference` to `new_name` would change what
+/// any name refers to
+fn check_rename_conflicts(
+    modules: &[Module],
+    env: &dyn TypeEnv<Type = ArcType>,
+    reference: &Reference,
+    renamed_occurrences: &[(String, Occurrence)],
+    new_name: &str,
+) -> Result<(), String> {
+    // Two fields, constructors, types or exports with the same name can't be told apart
+    if let Some(renamed) = reference.renamed(new_name) {
+        let exists = modules.iter().any(|module| {
+            occurrences(module, env)
+                .iter()
+                .any(|occurrence| occurrence.reference == renamed)
+        });
+        if exists {
+            return Err(format!(
+                "`{}` can't be renamed to `{}` as `{}` already exists",
+                reference.name(),
+                new_name,
+                new_name
+            ));
+        }
+    }
+
+    // A renamed variable would shadow, or be shadowed by, any other variable named `new_name`
+    // which is in scope where it is used
+    for (module_name, occurrence) in renamed_occurrences {
+        if let Reference::Local { .. } = occurrence.reference {
+            let module = match modules.iter().find(|module| module.name == module_name) {
+                Some(module) => module,
+                None => continue,
+            };
+            let locals = crate::locals_at(
+                env,
+                module.source.span(),
+                module.expr,
+                occurrence.span.start(),
+            );
+            if locals.iter().any(|local| local.declared_name() == new_name) {
+                return Err(format!(
+                    "`{}` can't be renamed to `{}` as another `{}` is already in scope",
+                    occurrence.reference.name(),
+                    new_name,
+                    new_name
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the edits which renames `reference` to `new_name` in all of `modules`.
+///
+/// Renaming a field, type or constructor also updates the patterns, projections and `import!`
+/// destructurings which refer to it. The rename is rejected if `new_name` is already used by a
+/// field, constructor, type or export of the same kind, or if a renamed variable would capture, or
+/// be captured by, another variable named `new_name`.
+pub fn rename(
+    modules: &[Module],
+    env: &dyn TypeEnv<Type = ArcType>,
+    reference: &Reference,
+    new_name: &str,
+) -> Result<Vec<TextEdit>, String> {
+    let mut chars = new_name.chars();
+    let valid = chars
+        .next()
+        .map_or(false, |c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '\'')
+        && !is_keyword(new_name);
+    if !valid {
+        return Err(format!("`{}` is not a valid identifier", new_name));
+    }
+    if starts_with_uppercase(new_name) != reference.is_uppercase() {
+        return Err(format!(
+            "`{}` can't be renamed to `{}` as it would change the case of the first letter",
+            reference.name(),
+            new_name
+        ));
+    }
+
+    let old_name = reference.name();
+    let occurrences = collect_occurrences(modules, env, reference);
+    if new_name != old_name {
+        check_rename_conflicts(modules, env, reference, &occurrences, new_name)?;
+    }
+    Ok(occurrences
+        .into_iter()
+        .map(|(module, occurrence)| {
+            let new_text = match occurrence.form {
+                Form::Name => new_name.to_string(),
+                Form::PunnedField => format!("{} = {}", new_name, old_name),
+                Form::PunnedLocal => format!("{} = {}", occurrence.reference.name(), new_name),
+            };
+            TextEdit {
+                module,
+                span: occurrence.span,
+                new_text,
+            }
+        })
+        .collect())
+}
@@ -135,6 +135,19 @@ fn literal_string() {
     assert_eq!(result, expected);
 }
 
+#[test]
+fn in_interpolated_string() {
+    let text = r#"
+let __string_append x y : String -> String -> String = x
+let show x : Float -> String = ""
+"a ${1.0}"
+"#;
+    let result = find_type(text, BytePos::from(98));
+    let expected = Ok(typ("Float"));
+
+    assert_eq!(result, expected);
+}
+
 #[test]
 fn in_let() {
     let result = find_type(
@@ -0,0 +1,307 @@
+#[macro_use]
+extern crate collect_mac;
+extern crate env_logger;
+
+extern crate gluon_base as base;
+extern crate gluon_check as check;
+extern crate gluon_completion as completion;
+extern crate gluon_parser as parser;
+
+use crate::base::pos::BytePos;
+
+use crate::completion::references::{self, Module, Reference};
+
+#[allow(unused)]
+mod support;
+use crate::support::{loc, MockEnv};
+
+fn with_module<F, R>(text: &str, f: F) -> R
+where
+    F: FnOnce(&Module, &MockEnv) -> R,
+{
+    let _ = env_logger::try_init();
+
+    let env = MockEnv::new();
+    let (expr, result) = support::typecheck_expr(text);
+    let expr = expr.expr();
+    assert!(result.is_ok(), "{}", result.unwrap_err());
+
+    let source = codespan::FileMap::new("test".into(), text.to_string());
+    let module = Module {
+        name: "test",
+        source: &source,
+        expr: &expr,
+    };
+    f(&module, &env)
+}
+
+fn reference_at(text: &str, pos: BytePos) -> Option<Reference> {
+    with_module(text, |module, env| {
+        references::reference_at(module, env, pos)
+    })
+}
+
+/// Renames the name at `pos` in `text` and returns the resulting source
+fn rename(text: &str, pos: BytePos, new_name: &str) -> Result<String, String> {
+    with_module(text, |module, env| {
+        let reference = references::reference_at(module, env, pos).expect("Reference");
+        let mut edits = references::rename(&[*module], env, &reference, new_name)?;
+
+        edits.sort_by_key(|edit| edit.span.start());
+        let mut result = text.to_string();
+        for edit in edits.iter().rev() {
+            let start = (edit.span.start() - module.source.span().start()).to_usize();
+            let end = (edit.span.end() - module.source.span().start()).to_usize();
+            result.replace_range(start..end, &edit.new_text);
+        }
+        Ok(result)
+    })
+}
+
+#[test]
+fn rename_local() {
+    let text = r#"
+let foo x = x
+let y = foo 1
+foo y
+"#;
+    let result = rename(text, loc(text, 2, 9), "bar");
+    let expected = r#"
+let bar x = x
+let y = bar 1
+bar y
+"#;
+    assert_eq!(result, Ok(expected.to_string()));
+}
+
+#[test]
+fn rename_local_used_in_punned_record() {
+    let text = r#"
+let f x =
+    let y = x
+    { y }
+f 1
+"#;
+    let result = rename(text, loc(text, 2, 8), "z");
+    let expected = r#"
+let f x =
+    let z = x
+    { y = z }
+f 1
+"#;
+    assert_eq!(result, Ok(expected.to_string()));
+}
+
+#[test]
+fn rename_record_field() {
+    let text = r#"
+type R = { x : Int, y : { x : Int } }
+let r : R = { x = 1, y = { x = 2 } }
+let f r : R -> Int =
+    let { x } = r
+    x
+(r.x, f r, r.y.x)
+"#;
+    let result = rename(text, loc(text, 6, 3), "z");
+    let expected = r#"
+type R = { z : Int, y : { x : Int } }
+let r : R = { z = 1, y = { x = 2 } }
+let f r : R -> Int =
+    let { z = x } = r
+    x
+(r.z, f r, r.y.x)
+"#;
+    assert_eq!(result, Ok(expected.to_string()));
+}
+
+#[test]
+fn rename_constructor() {
+    let text = r#"
+type T = | A Int | B
+let f t : T -> Int =
+    match t with
+    | A x -> x
+    | B -> 0
+f (A 1)
+"#;
+    let result = rename(text, loc(text, 6, 3), "C");
+    let expected = r#"
+type T = | C Int | B
+let f t : T -> Int =
+    match t with
+    | C x -> x
+    | B -> 0
+f (C 1)
+"#;
+    assert_eq!(result, Ok(expected.to_string()));
+}
+
+#[test]
+fn rename_type() {
+    let text = r#"
+type Test = Int
+let f x : Test -> Test = x
+f 1
+"#;
+    let result = rename(text, loc(text, 1, 6), "Other");
+    let expected = r#"
+type Other = Int
+let f x : Other -> Other = x
+f 1
+"#;
+    assert_eq!(result, Ok(expected.to_string()));
+}
+
+#[test]
+fn rename_exported_value() {
+    let text = r#"
+let foo = 1
+let bar = foo
+{ foo, baz = bar }
+"#;
+    assert_eq!(
+        reference_at(text, loc(text, 1, 5)),
+        Some(Reference::Export {
+            module: "test".into(),
+            field: "foo".into(),
+        })
+    );
+    let result = rename(text, loc(text, 1, 5), "qux");
+    let expected = r#"
+let qux = 1
+let bar = qux
+{ qux, baz = bar }
+"#;
+    assert_eq!(result, Ok(expected.to_string()));
+}
+
+#[test]
+fn rename_rejects_invalid_names() {
+    let text = r#"
+type Test = Int
+let test : Test = 1
+test
+"#;
+    assert!(rename(text, loc(text, 2, 5), "1abc").is_err());
+    assert!(rename(text, loc(text, 2, 5), "let").is_err());
+    assert!(rename(text, loc(text, 2, 5), "Abc").is_err());
+    assert!(rename(text, loc(text, 1, 6), "abc").is_err());
+}
+
+#[test]
+fn rename_rejects_captured_variables() {
+    let text = r#"
+let y = 1
+let f x = x
+f y
+"#;
+    // `y` is in scope where `f` is used so it would be shadowed by the renamed `f`
+    assert!(rename(text, loc(text, 2, 5), "y").is_err());
+    // The argument `x` would capture uses of `y` inside `f`
+    assert!(rename(text, loc(text, 2, 7), "y").is_err());
+    assert!(rename(text, loc(text, 2, 7), "z").is_ok());
+}
+
+#[test]
+fn rename_rejects_existing_fields_and_constructors() {
+    let text = r#"
+type R = { x : Int, y : Int }
+type T = | A | B
+let r : R = { x = 1, y = 2 }
+let a = A
+{ r, a, b = B }
+"#;
+    assert!(rename(text, loc(text, 1, 12), "y").is_err());
+    assert!(rename(text, loc(text, 1, 12), "z").is_ok());
+    assert!(rename(text, loc(text, 2, 12), "B").is_err());
+    assert!(rename(text, loc(text, 2, 12), "C").is_ok());
+    assert!(rename(text, loc(text, 2, 6), "R").is_err());
+    // `r` and `b` are already exported
+    assert!(rename(text, loc(text, 4, 5), "b").is_err());
+}
+
+#[test]
+fn find_references_of_local() {
+    let text = r#"
+let x = 1
+let y = x
+x
+"#;
+    let starts: Vec<_> = with_module(text, |module, env| {
+        let reference = references::reference_at(module, env, loc(text, 3, 0)).unwrap();
+        references::find_references(&[*module], env, &reference)
+            .into_iter()
+            .map(|location| location.span.start())
+            .collect()
+    });
+    assert_eq!(starts, [loc(text, 1, 4), loc(text, 2, 8), loc(text, 3, 0)]);
+}
+
+fn definition(text: &str, pos: BytePos) -> Option<BytePos> {
+    with_module(text, |module, env| {
+        let reference = references::reference_at(module, env, pos)?;
+        references::definition(&[*module], env, &reference).map(|location| location.span.start())
+    })
+}
+
+fn type_definition(text: &str, pos: BytePos) -> Option<BytePos> {
+    with_module(text, |module, env| {
+        references::type_definition(&[*module], env, module, pos)
+            .map(|location| location.span.start())
+    })
+}
+
+#[test]
+fn definition_of_local() {
+    let text = r#"
+let f x =
+    let { y } = x
+    y
+f { y = 1 }
+"#;
+    assert_eq!(definition(text, loc(text, 3, 4)), Some(loc(text, 2, 10)));
+    assert_eq!(definition(text, loc(text, 4, 0)), Some(loc(text, 1, 4)));
+}
+
+#[test]
+fn definition_of_exported_value() {
+    let text = r#"
+let foo = 1
+{ foo }
+"#;
+    assert_eq!(definition(text, loc(text, 2, 2)), Some(loc(text, 1, 4)));
+}
+
+#[test]
+fn definition_of_field_and_constructor() {
+    let text = r#"
+type T = | A Int | B
+type R = { x : T }
+let r : R = { x = B }
+match r.x with
+| A y -> y
+| B -> 0
+"#;
+    assert_eq!(definition(text, loc(text, 4, 8)), Some(loc(text, 2, 11)));
+    assert_eq!(definition(text, loc(text, 5, 2)), Some(loc(text, 1, 11)));
+    assert_eq!(definition(text, loc(text, 3, 19)), Some(loc(text, 1, 19)));
+    assert_eq!(definition(text, loc(text, 2, 15)), Some(loc(text, 1, 5)));
+}
+
+#[test]
+fn type_definition_of_expression() {
+    let text = r#"
+type R = { x : Int }
+let r : R = { x = 1 }
+r
+"#;
+    assert_eq!(
+        type_definition(text, loc(text, 3, 0)),
+        Some(loc(text, 1, 5))
+    );
+    assert_eq!(
+        type_definition(text, loc(text, 2, 8)),
+        Some(loc(text, 1, 5))
+    );
+    assert_eq!(type_definition(text, loc(text, 2, 19)), None);
+}
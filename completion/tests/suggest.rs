@@ -105,6 +105,23 @@ te
     assert_eq!(result, expected);
 }
 
+#[test]
+fn suggest_in_interpolated_string() {
+    let _ = env_logger::try_init();
+
+    let result = suggest(
+        r#"
+let test = 1
+let tes = ""
+"a ${te}"
+"#,
+        BytePos::from(35),
+    );
+    let expected = Ok(vec!["tes".into(), "test".into()]);
+
+    assert_eq!(result, expected);
+}
+
 #[test]
 fn suggest_arguments() {
     let _ = env_logger::try_init();
@@ -0,0 +1,724 @@
+//! Debug Adapter Protocol server for the gluon programming language.
+//!
+//! The debugged program runs on its own thread with a line and call hook installed (see
+//! `Context::set_hook`). Whenever the program needs to stop (a breakpoint, a finished step or a
+//! pause request) the hook takes a snapshot of the stack, reports a `stopped` event to the client
+//! and blocks until the client tells it to continue.
+#![doc(html_root_url = "https://docs.rs/gluon_debugger/0.15.0")] // # GLUON
+
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+
+pub mod protocol;
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Condvar, Mutex},
+    task::Poll,
+    thread,
+};
+
+use serde_json::Value;
+
+use gluon::{
+    base::{filename_to_module, types::ArcType},
+    import::Import,
+    vm::{
+        api::{Hole, OpaqueValue},
+        thread::{DebugInfo, HookFlags, ThreadInternal},
+        Error as VMError, Variants,
+    },
+    RootedThread, ThreadExt,
+};
+
+use crate::protocol::Request;
+
+/// The id reported for the (only) thread that is debugged
+const THREAD_ID: i64 = 1;
+
+/// Compiling and running gluon code can recurse deeply so give the program thread a larger stack
+const PROGRAM_STACK_SIZE: usize = 16 * 1024 * 1024;
+
+struct Output {
+    writer: Box<dyn Write + Send>,
+    seq: i64,
+}
+
+impl Output {
+    fn send(&mut self, mut message: Value) {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        debug!("Send: {}", message);
+        if let Err(err) = protocol::write_message(&mut self.writer, &message) {
+            error!("Unable to write message: {}", err);
+        }
+    }
+
+    fn event(&mut self, event: &str, body: Value) {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }))
+    }
+
+    fn response(&mut self, request: &Request, result: Result<Value, String>) {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "command": request.command,
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = json!(message),
+        }
+        self.send(response)
+    }
+}
+
+/// Where execution is currently at
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Location {
+    depth: usize,
+    line: usize,
+}
+
+impl Location {
+    /// Returns true if execution has moved on from the line at `start`. Lines executed by calls
+    /// made from `start` do not count as moving on.
+    fn has_left(self, start: Location) -> bool {
+        self.depth < start.depth || (self.depth == start.depth && self.line != start.line)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum RunMode {
+    /// Stop at the first line of the launched program
+    Entry,
+    Continue,
+    StepIn(Location),
+    StepOver(Location),
+    StepOut(Location),
+}
+
+struct Variable {
+    name: String,
+    typ: String,
+    value: String,
+}
+
+impl Variable {
+    fn new(name: &str, typ: &ArcType, value: Option<Variants>) -> Self {
+        Variable {
+            name: name.to_string(),
+            typ: typ.to_string(),
+            // Render values the same way as `std.debug.show`
+            value: value.map_or_else(
+                || "<unavailable>".to_string(),
+                |value| format!("{:?}", value.get_value()),
+            ),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "value": self.value,
+            "type": self.typ,
+            "variablesReference": 0,
+        })
+    }
+}
+
+struct Frame {
+    name: String,
+    source_name: String,
+    path: Option<PathBuf>,
+    line: Option<usize>,
+    locals: Vec<Variable>,
+    upvars: Vec<Variable>,
+}
+
+struct Stopped {
+    location: Location,
+    frames: Vec<Frame>,
+}
+
+/// Maps between module names (as reported by the vm) and the files they were loaded from
+#[derive(Default)]
+struct Sources {
+    cwd: PathBuf,
+    program: Option<(String, PathBuf)>,
+    paths: HashMap<String, Option<PathBuf>>,
+}
+
+impl Sources {
+    fn module_name(&self, path: &Path) -> String {
+        let path = path.strip_prefix(&self.cwd).unwrap_or(path);
+        filename_to_module(&path.to_string_lossy())
+    }
+
+    fn is_program(&self, module: &str) -> bool {
+        self.program
+            .as_ref()
+            .map_or(false, |(name, _)| name == module)
+    }
+
+    fn path(&mut self, module: &str) -> Option<PathBuf> {
+        if let Some((name, path)) = &self.program {
+            if name == module {
+                return Some(path.clone());
+            }
+        }
+        if let Some(path) = self.paths.get(module) {
+            return path.clone();
+        }
+        // Imported modules are looked up relative to the working directory
+        let path = self
+            .cwd
+            .join(module.replace('.', "/"))
+            .with_extension("glu");
+        let path = if path.is_file() {
+            Some(normalize(&path))
+        } else {
+            None
+        };
+        self.paths.insert(module.to_string(), path.clone());
+        path
+    }
+}
+
+struct State {
+    // Lines are stored zero-indexed, the same as the vm reports them
+    breakpoints: HashMap<PathBuf, BTreeSet<usize>>,
+    sources: Sources,
+    run_mode: RunMode,
+    pause_requested: bool,
+    /// `Some` while the program is stopped
+    stopped: Option<Stopped>,
+    /// Where the program last stopped, until execution has left that line
+    last_stop: Option<Location>,
+    terminate: bool,
+}
+
+impl State {
+    /// Called when a function is entered at `depth`
+    fn enter_call(&mut self, depth: usize) {
+        // A call made from the line of the last stop runs deeper than it. Any other call means
+        // that the line has been left, so hitting it again is a new hit, even if no other line
+        // ran in between (`f 1 #Int+ f 2` with a breakpoint in `f`)
+        if self
+            .last_stop
+            .map_or(false, |last_stop| depth <= last_stop.depth)
+        {
+            self.last_stop = None;
+        }
+    }
+
+    fn stop_reason(&mut self, source_name: &str, location: Location) -> Option<&'static str> {
+        if self.pause_requested {
+            return Some("pause");
+        }
+        if self
+            .last_stop
+            .map_or(false, |last_stop| location.has_left(last_stop))
+        {
+            self.last_stop = None;
+        }
+        let at_breakpoint = match self.sources.path(source_name) {
+            Some(path) => self
+                .breakpoints
+                .get(&path)
+                .map_or(false, |lines| lines.contains(&location.line)),
+            None => false,
+        };
+        match self.run_mode {
+            RunMode::Entry if self.sources.is_program(source_name) => Some("entry"),
+            RunMode::StepIn(start) if location != start => Some("step"),
+            // The line hook runs again for the same line once a call on that line returns, skip it
+            // so stepping over a line with a call moves to the next line
+            RunMode::StepOver(start) if location.has_left(start) => Some("step"),
+            RunMode::StepOut(start) if location.depth < start.depth => Some("step"),
+            // For the same reason, continuing from a line with a breakpoint must not stop at it again
+            _ if at_breakpoint && self.last_stop != Some(location) => Some("breakpoint"),
+            _ => None,
+        }
+    }
+}
+
+struct Shared {
+    state: Mutex<State>,
+    resume: Condvar,
+    output: Mutex<Output>,
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
+
+fn snapshot(info: &DebugInfo, sources: &mut Sources) -> Vec<Frame> {
+    (0..info.stack_info_len())
+        .filter_map(|level| info.stack_info(level))
+        .filter_map(|stack_info| {
+            // Frames without a name are the top level frames which do not execute any code
+            let name = stack_info.function_name()?.to_string();
+            let source_name = stack_info.source_name().to_string();
+            let locals = stack_info
+                .locals()
+                .map(|local| {
+                    Variable::new(
+                        local.name.declared_name(),
+                        &local.typ,
+                        stack_info.local_value(local),
+                    )
+                })
+                .collect();
+            let upvars = stack_info
+                .upvars()
+                .iter()
+                .enumerate()
+                .map(|(i, upvar)| Variable::new(&upvar.name, &upvar.typ, stack_info.upvar_value(i)))
+                .collect();
+            Some(Frame {
+                name,
+                path: sources.path(&source_name),
+                source_name,
+                line: stack_info.line().map(|line| line.to_usize()),
+                locals,
+                upvars,
+            })
+        })
+        .collect()
+}
+
+fn hook(shared: &Shared, info: DebugInfo) -> Poll<gluon::vm::Result<()>> {
+    if info.state().contains(HookFlags::CALL_FLAG) {
+        shared
+            .state
+            .lock()
+            .unwrap()
+            .enter_call(info.stack_info_len());
+        return Poll::Ready(Ok(()));
+    }
+
+    let stack_info = match info.stack_info(0) {
+        Some(stack_info) => stack_info,
+        None => return Poll::Ready(Ok(())),
+    };
+    let line = match stack_info.line() {
+        Some(line) => line.to_usize(),
+        None => return Poll::Ready(Ok(())),
+    };
+    let location = Location {
+        depth: info.stack_info_len(),
+        line,
+    };
+
+    let mut state = shared.state.lock().unwrap();
+    if !state.terminate {
+        if let Some(reason) = state.stop_reason(stack_info.source_name(), location) {
+            state.pause_requested = false;
+            state.last_stop = Some(location);
+            let frames = snapshot(&info, &mut state.sources);
+            state.stopped = Some(Stopped { location, frames });
+            shared.output.lock().unwrap().event(
+                "stopped",
+                json!({
+                    "reason": reason,
+                    "threadId": THREAD_ID,
+                    "allThreadsStopped": true,
+                }),
+            );
+
+            while state.stopped.is_some() && !state.terminate {
+                state = shared.resume.wait(state).unwrap();
+            }
+        }
+    }
+
+    if state.terminate {
+        Poll::Ready(Err(VMError::Message(
+            "The debugger was disconnected".to_string(),
+        )))
+    } else {
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn run_program(shared: Arc<Shared>, module: String, source: String, cwd: PathBuf, debug: bool) {
+    let vm = gluon::new_vm();
+    vm.get_macros()
+        .get("import")
+        .as_ref()
+        .and_then(|import| import.downcast_ref::<Import>())
+        .expect("Import macro")
+        .add_path(cwd);
+    vm.get_database_mut()
+        .run_io(true)
+        // Optimizations may remove the local variables that we want to inspect
+        .set_optimize(false);
+
+    if debug {
+        let hook_shared = shared.clone();
+        let mut context = vm.context();
+        context.set_hook(Some(Box::new(move |_, info| hook(&hook_shared, info))));
+        context.set_hook_mask(HookFlags::LINE_FLAG | HookFlags::CALL_FLAG);
+    }
+
+    let result = vm.run_expr::<OpaqueValue<RootedThread, Hole>>(&module, &source);
+
+    let mut output = shared.output.lock().unwrap();
+    let exit_code = match result {
+        Ok(_) => 0,
+        Err(err) => {
+            output.event(
+                "output",
+                json!({
+                    "category": "stderr",
+                    "output": format!("{}\n", err),
+                }),
+            );
+            1
+        }
+    };
+    output.event("exited", json!({ "exitCode": exit_code }));
+    output.event("terminated", json!({}));
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InitializeArguments {
+    lines_start_at1: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LaunchArguments {
+    program: PathBuf,
+    #[serde(default)]
+    stop_on_entry: bool,
+    #[serde(default)]
+    no_debug: bool,
+    cwd: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SourceBreakpoint {
+    line: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct Source {
+    path: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct SetBreakpointsArguments {
+    source: Source,
+    #[serde(default)]
+    breakpoints: Vec<SourceBreakpoint>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScopesArguments {
+    frame_id: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VariablesArguments {
+    variables_reference: usize,
+}
+
+fn arguments<'de, T>(request: &'de Request) -> Result<T, String>
+where
+    T: serde::Deserialize<'de>,
+{
+    T::deserialize(&request.arguments)
+        .map_err(|err| format!("Invalid arguments to `{}`: {}", request.command, err))
+}
+
+/// A Debug Adapter Protocol server which runs and debugs a single gluon program
+pub struct Server {
+    shared: Arc<Shared>,
+    lines_start_at1: bool,
+    launch: Option<LaunchArguments>,
+    program: Option<thread::JoinHandle<()>>,
+}
+
+impl Server {
+    /// Creates a server which writes all responses and events to `output`
+    pub fn new<W>(output: W) -> Server
+    where
+        W: Write + Send + 'static,
+    {
+        Server {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State {
+                    breakpoints: HashMap::new(),
+                    sources: Sources::default(),
+                    run_mode: RunMode::Continue,
+                    pause_requested: false,
+                    stopped: None,
+                    last_stop: None,
+                    terminate: false,
+                }),
+                resume: Condvar::new(),
+                output: Mutex::new(Output {
+                    writer: Box::new(output),
+                    seq: 0,
+                }),
+            }),
+            lines_start_at1: true,
+            launch: None,
+            program: None,
+        }
+    }
+
+    /// Processes requests from `input` until the client disconnects or `input` is closed
+    pub fn run<R>(&mut self, mut input: R) -> io::Result<()>
+    where
+        R: BufRead,
+    {
+        while let Some(message) = protocol::read_message(&mut input)? {
+            debug!("Received: {}", message);
+            if message["type"] != "request" {
+                continue;
+            }
+            let request: Request = match serde_json::from_value(message) {
+                Ok(request) => request,
+                Err(err) => {
+                    warn!("Invalid request: {}", err);
+                    continue;
+                }
+            };
+            if !self.dispatch(&request) {
+                break;
+            }
+        }
+
+        let mut state = self.shared.state.lock().unwrap();
+        state.terminate = true;
+        self.shared.resume.notify_all();
+        Ok(())
+    }
+
+    /// Returns `true` if the server should keep processing requests
+    fn dispatch(&mut self, request: &Request) -> bool {
+        let shared = self.shared.clone();
+        // Keep the state locked until the response has been sent so that any event caused by the
+        // request (such as `stopped` after `next`) is sent after the response
+        let mut state = shared.state.lock().unwrap();
+        let result = self.handle(&mut state, request);
+        let success = result.is_ok();
+
+        let mut output = shared.output.lock().unwrap();
+        output.response(request, result);
+        match &request.command[..] {
+            "initialize" if success => output.event("initialized", json!({})),
+            "disconnect" => return false,
+            _ => (),
+        }
+        true
+    }
+
+    fn handle(&mut self, state: &mut State, request: &Request) -> Result<Value, String> {
+        match &request.command[..] {
+            "initialize" => {
+                let args: InitializeArguments = arguments(request).unwrap_or_default();
+                self.lines_start_at1 = args.lines_start_at1.unwrap_or(true);
+                Ok(json!({ "supportsConfigurationDoneRequest": true }))
+            }
+            "launch" => {
+                let args: LaunchArguments = arguments(request)?;
+                let cwd = match &args.cwd {
+                    Some(cwd) => cwd.clone(),
+                    None => std::env::current_dir().map_err(|err| err.to_string())?,
+                };
+                state.sources.cwd = normalize(&cwd);
+                self.launch = Some(args);
+                Ok(json!({}))
+            }
+            "setBreakpoints" => {
+                let args: SetBreakpointsArguments = arguments(request)?;
+                let lines = args
+                    .breakpoints
+                    .iter()
+                    .map(|breakpoint| self.vm_line(breakpoint.line))
+                    .collect();
+                state
+                    .breakpoints
+                    .insert(normalize(&args.source.path), lines);
+                let breakpoints = args
+                    .breakpoints
+                    .iter()
+                    .map(|breakpoint| json!({ "verified": true, "line": breakpoint.line }))
+                    .collect::<Vec<_>>();
+                Ok(json!({ "breakpoints": breakpoints }))
+            }
+            "configurationDone" => self.start_program(state),
+            "threads" => Ok(json!({
+                "threads": [{ "id": THREAD_ID, "name": "main" }],
+            })),
+            "stackTrace" => {
+                let stopped = stopped(state)?;
+                let column = if self.lines_start_at1 { 1 } else { 0 };
+                let frames = stopped
+                    .frames
+                    .iter()
+                    .enumerate()
+                    .map(|(id, frame)| {
+                        let mut json = json!({
+                            "id": id,
+                            "name": frame.name,
+                            "line": frame.line.map_or(0, |line| self.client_line(line)),
+                            "column": column,
+                        });
+                        if frame.line.is_some() {
+                            json["source"] = match &frame.path {
+                                Some(path) => json!({ "name": frame.source_name, "path": path }),
+                                None => json!({ "name": frame.source_name }),
+                            };
+                        }
+                        json
+                    })
+                    .collect::<Vec<_>>();
+                Ok(json!({
+                    "totalFrames": frames.len(),
+                    "stackFrames": frames,
+                }))
+            }
+            "scopes" => {
+                let args: ScopesArguments = arguments(request)?;
+                let stopped = stopped(state)?;
+                if args.frame_id >= stopped.frames.len() {
+                    return Err(format!("Invalid frame id {}", args.frame_id));
+                }
+                Ok(json!({
+                    "scopes": [
+                        {
+                            "name": "Locals",
+                            "variablesReference": args.frame_id * 2 + 1,
+                            "expensive": false,
+                        },
+                        {
+                            "name": "Upvars",
+                            "variablesReference": args.frame_id * 2 + 2,
+                            "expensive": false,
+                        },
+                    ],
+                }))
+            }
+            "variables" => {
+                let args: VariablesArguments = arguments(request)?;
+                let stopped = stopped(state)?;
+                let reference = args
+                    .variables_reference
+                    .checked_sub(1)
+                    .ok_or_else(|| "Invalid variables reference 0".to_string())?;
+                let frame = stopped
+                    .frames
+                    .get(reference / 2)
+                    .ok_or_else(|| format!("Invalid variables reference {}", reference + 1))?;
+                let variables = if reference % 2 == 0 {
+                    &frame.locals
+                } else {
+                    &frame.upvars
+                };
+                Ok(json!({
+                    "variables": variables.iter().map(Variable::to_json).collect::<Vec<_>>(),
+                }))
+            }
+            "continue" => {
+                self.resume(state, |_| RunMode::Continue)?;
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            "next" => self.resume(state, RunMode::StepOver),
+            "stepIn" => self.resume(state, RunMode::StepIn),
+            "stepOut" => self.resume(state, RunMode::StepOut),
+            "pause" => {
+                state.pause_requested = true;
+                Ok(json!({}))
+            }
+            "disconnect" => {
+                state.terminate = true;
+                self.shared.resume.notify_all();
+                Ok(json!({}))
+            }
+            command => Err(format!("Unsupported request `{}`", command)),
+        }
+    }
+
+    fn start_program(&mut self, state: &mut State) -> Result<Value, String> {
+        let launch = self
+            .launch
+            .take()
+            .ok_or_else(|| "`configurationDone` must be preceded by `launch`".to_string())?;
+        let source = fs::read_to_string(&launch.program)
+            .map_err(|err| format!("Unable to read `{}`: {}", launch.program.display(), err))?;
+
+        let path = normalize(&launch.program);
+        let module = state.sources.module_name(&path);
+        state.sources.program = Some((module.clone(), path));
+        if launch.stop_on_entry {
+            state.run_mode = RunMode::Entry;
+        }
+
+        let shared = self.shared.clone();
+        let cwd = state.sources.cwd.clone();
+        let debug = !launch.no_debug;
+        let program = thread::Builder::new()
+            .name("gluon program".to_string())
+            .stack_size(PROGRAM_STACK_SIZE)
+            .spawn(move || run_program(shared, module, source, cwd, debug))
+            .map_err(|err| err.to_string())?;
+        self.program = Some(program);
+        Ok(json!({}))
+    }
+
+    fn resume(
+        &self,
+        state: &mut State,
+        run_mode: impl FnOnce(Location) -> RunMode,
+    ) -> Result<Value, String> {
+        let stopped = state
+            .stopped
+            .take()
+            .ok_or_else(|| "The program is not stopped".to_string())?;
+        state.run_mode = run_mode(stopped.location);
+        self.shared.resume.notify_all();
+        Ok(json!({}))
+    }
+
+    fn client_line(&self, line: usize) -> usize {
+        if self.lines_start_at1 {
+            line + 1
+        } else {
+            line
+        }
+    }
+
+    fn vm_line(&self, line: usize) -> usize {
+        if self.lines_start_at1 {
+            line.saturating_sub(1)
+        } else {
+            line
+        }
+    }
+}
+
+fn stopped(state: &State) -> Result<&Stopped, String> {
+    state
+        .stopped
+        .as_ref()
+        .ok_or_else(|| "The program is not stopped".to_string())
+}
@@ -0,0 +1,42 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Write},
+    process,
+};
+
+use gluon_debugger::Server;
+
+/// Returns a writer for protocol messages. Anything the debugged program prints to stdout is
+/// redirected to stderr so that it can't corrupt the messages sent to the client.
+#[cfg(unix)]
+fn protocol_output() -> io::Result<Box<dyn Write + Send>> {
+    use std::os::unix::io::FromRawFd;
+
+    unsafe {
+        let fd = libc::dup(libc::STDOUT_FILENO);
+        if fd < 0 || libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Box::new(File::from_raw_fd(fd)))
+    }
+}
+
+#[cfg(not(unix))]
+fn protocol_output() -> io::Result<Box<dyn Write + Send>> {
+    Ok(Box::new(io::stdout()))
+}
+
+fn main() {
+    #[cfg(feature = "env_logger")]
+    env_logger::init();
+
+    let result = protocol_output().and_then(|output| {
+        let stdin = io::stdin();
+        Server::new(output).run(BufReader::new(stdin.lock()))
+    });
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+    process::exit(0);
+}
@@ -0,0 +1,15 @@
+//! Reading and writing of Debug Adapter Protocol messages.
+//!
+//! Each message is a JSON object preceded by a `Content-Length` header, the same framing as the
+//! Language Server Protocol uses, which is implemented by `gluon_framing`.
+use serde_json::Value;
+
+pub use gluon_framing::{read_message, write_message};
+
+#[derive(Debug, Deserialize)]
+pub struct Request {
+    pub seq: i64,
+    pub command: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
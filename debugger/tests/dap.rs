@@ -0,0 +1,288 @@
+#[macro_use]
+extern crate serde_json;
+
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{self, BufReader, Read, Write},
+    path::Path,
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+use serde_json::Value;
+
+use gluon_debugger::{protocol, Server};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+struct ChannelWriter(Sender<Vec<u8>>);
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .send(buf.to_owned())
+            .map_err(|err| io::Error::new(io::ErrorKind::BrokenPipe, err))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct ChannelReader {
+    receiver: Receiver<Vec<u8>>,
+    buffer: Vec<u8>,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.buffer.is_empty() {
+            match self.receiver.recv_timeout(TIMEOUT) {
+                Ok(bytes) => self.buffer = bytes,
+                Err(err) => return Err(io::Error::new(io::ErrorKind::TimedOut, err)),
+            }
+        }
+        let len = buf.len().min(self.buffer.len());
+        buf[..len].copy_from_slice(&self.buffer[..len]);
+        self.buffer.drain(..len);
+        Ok(len)
+    }
+}
+
+struct Client {
+    input: ChannelWriter,
+    output: BufReader<ChannelReader>,
+    events: VecDeque<Value>,
+    seq: i64,
+}
+
+impl Client {
+    fn start() -> Client {
+        let (input_sender, input_receiver) = channel();
+        let (output_sender, output_receiver) = channel();
+        thread::spawn(move || {
+            let input = BufReader::new(ChannelReader {
+                receiver: input_receiver,
+                buffer: Vec::new(),
+            });
+            Server::new(ChannelWriter(output_sender))
+                .run(input)
+                .unwrap();
+        });
+        Client {
+            input: ChannelWriter(input_sender),
+            output: BufReader::new(ChannelReader {
+                receiver: output_receiver,
+                buffer: Vec::new(),
+            }),
+            events: VecDeque::new(),
+            seq: 0,
+        }
+    }
+
+    fn read(&mut self) -> Value {
+        protocol::read_message(&mut self.output)
+            .unwrap()
+            .expect("Server closed the connection")
+    }
+
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        self.seq += 1;
+        protocol::write_message(
+            &mut self.input,
+            &json!({
+                "seq": self.seq,
+                "type": "request",
+                "command": command,
+                "arguments": arguments,
+            }),
+        )
+        .unwrap();
+        loop {
+            let message = self.read();
+            if message["type"] == "response" {
+                assert_eq!(message["request_seq"], self.seq);
+                assert_eq!(message["success"], true, "{}", message);
+                return message["body"].clone();
+            }
+            self.events.push_back(message);
+        }
+    }
+
+    fn event(&mut self, event: &str) -> Value {
+        loop {
+            let message = match self.events.pop_front() {
+                Some(message) => message,
+                None => self.read(),
+            };
+            if message["event"] == event {
+                return message["body"].clone();
+            }
+        }
+    }
+
+    /// Waits for the program to exit, failing if it stops before that
+    fn expect_exit(&mut self) -> Value {
+        loop {
+            let message = match self.events.pop_front() {
+                Some(message) => message,
+                None => self.read(),
+            };
+            assert_ne!(message["event"], "stopped", "{}", message);
+            if message["event"] == "exited" {
+                return message["body"].clone();
+            }
+        }
+    }
+
+    fn launch(&mut self, program: &Path, cwd: &Path, breakpoints: Value) {
+        self.request("initialize", json!({ "adapterID": "gluon" }));
+        self.event("initialized");
+        self.request("launch", json!({ "program": program, "cwd": cwd }));
+        self.request(
+            "setBreakpoints",
+            json!({ "source": { "path": program }, "breakpoints": breakpoints }),
+        );
+        self.request("configurationDone", json!({}));
+    }
+
+    fn top_frame(&mut self) -> Value {
+        let body = self.request("stackTrace", json!({ "threadId": 1 }));
+        body["stackFrames"][0].clone()
+    }
+}
+
+#[test]
+fn breakpoints_and_stepping() {
+    let _ = env_logger::try_init();
+
+    let dir = tempfile::tempdir().unwrap();
+    let program = dir.path().join("test.glu");
+    fs::write(
+        &program,
+        r#"let x = 1
+let f y = y
+let z = f x
+z
+"#,
+    )
+    .unwrap();
+
+    let mut client = Client::start();
+    client.request("initialize", json!({ "adapterID": "gluon" }));
+    client.event("initialized");
+    client.request("launch", json!({ "program": program, "cwd": dir.path() }));
+    let breakpoints = client.request(
+        "setBreakpoints",
+        json!({ "source": { "path": program }, "breakpoints": [{ "line": 3 }] }),
+    );
+    assert_eq!(breakpoints["breakpoints"][0]["verified"], true);
+    client.request("configurationDone", json!({}));
+
+    assert_eq!(client.event("stopped")["reason"], "breakpoint");
+    let frame = client.top_frame();
+    assert_eq!(frame["line"], 3);
+    assert_eq!(
+        fs::canonicalize(frame["source"]["path"].as_str().unwrap()).unwrap(),
+        fs::canonicalize(&program).unwrap()
+    );
+
+    let scopes = client.request("scopes", json!({ "frameId": frame["id"] }));
+    let locals = client.request(
+        "variables",
+        json!({ "variablesReference": scopes["scopes"][0]["variablesReference"] }),
+    );
+    let x = locals["variables"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|variable| variable["name"] == "x")
+        .unwrap_or_else(|| panic!("Missing `x` in {}", locals));
+    assert_eq!(x["value"], "1");
+    assert_eq!(x["type"], "Int");
+
+    // Stepping into `f` moves to the line where it is defined
+    client.request("stepIn", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["reason"], "step");
+    assert_eq!(client.top_frame()["line"], 2);
+
+    client.request("stepOut", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["reason"], "step");
+    let line = client.top_frame()["line"].as_i64().unwrap();
+    assert!(
+        line >= 3,
+        "Expected to step out of `f`, stopped at line {}",
+        line
+    );
+
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.event("exited")["exitCode"], 0);
+    client.event("terminated");
+    client.request("disconnect", json!({}));
+}
+
+#[test]
+fn continue_from_breakpoint_on_line_with_call() {
+    let _ = env_logger::try_init();
+
+    let dir = tempfile::tempdir().unwrap();
+    let program = dir.path().join("test.glu");
+    fs::write(
+        &program,
+        r#"let f x =
+    x
+let z = f 1 #Int+ f 2
+z
+"#,
+    )
+    .unwrap();
+
+    let mut client = Client::start();
+    client.launch(&program, dir.path(), json!([{ "line": 2 }, { "line": 3 }]));
+
+    // Line 3 is only stopped at once, before the calls, while the body of `f` is stopped at once
+    // for each call even though the line hook runs for it twice in a row
+    for &line in &[3, 2, 2] {
+        assert_eq!(client.event("stopped")["reason"], "breakpoint");
+        assert_eq!(client.top_frame()["line"], line);
+        client.request("continue", json!({ "threadId": 1 }));
+    }
+
+    assert_eq!(client.expect_exit()["exitCode"], 0);
+    client.event("terminated");
+    client.request("disconnect", json!({}));
+}
+
+#[test]
+fn pause() {
+    let _ = env_logger::try_init();
+
+    let dir = tempfile::tempdir().unwrap();
+    let program = dir.path().join("test.glu");
+    fs::write(
+        &program,
+        r#"let loop x =
+    loop (x #Int+ 1)
+loop 0
+"#,
+    )
+    .unwrap();
+
+    let mut client = Client::start();
+    client.launch(&program, dir.path(), json!([]));
+
+    client.request("pause", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["reason"], "pause");
+    // The program may be paused while it is still running the modules it imports
+    assert!(client.top_frame()["line"].is_i64());
+
+    client.request("continue", json!({ "threadId": 1 }));
+    client.request("pause", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["reason"], "pause");
+
+    client.request("disconnect", json!({}));
+    client.event("terminated");
+}
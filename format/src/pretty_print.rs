@@ -205,6 +205,15 @@ where
 
             Expr::Ident(ref id) => pretty_types::ident(arena, id.name.as_ref()),
 
+            Expr::Hole(ref id) => {
+                let name = id.name.as_ref();
+                if name == "_" {
+                    arena.text("_")
+                } else {
+                    arena.text("?").append(name)
+                }
+            }
+
             Expr::IfElse(..) => self.pretty_if_expr(expr),
 
             Expr::Infix {
@@ -223,6 +232,19 @@ where
             ]
             .group(),
 
+            Expr::Interpolation(ref interpolation) => arena.concat(
+                interpolation
+                    .literals
+                    .iter()
+                    .map(|lit| arena.text(self.source.src_slice(lit.span)))
+                    .interleave(
+                        interpolation
+                            .exprs
+                            .iter()
+                            .map(|hole| pretty(&hole.expr).group()),
+                    ),
+            ),
+
             Expr::LetBindings(ref binds, ref body) => {
                 let binding = |bind: &'a ValueBinding<I>| {
                     let decl = chain![arena;
@@ -332,6 +354,10 @@ where
                     chain![arena;
                         "| ",
                         self.pretty_pattern(&alt.pattern),
+                        match alt.guard {
+                            Some(ref guard) => chain![arena; " if ", pretty(guard)],
+                            None => arena.nil(),
+                        },
                         " ->",
                         self.hang(arena.nil(), (self.space_before(alt.expr.span.start()), true), &alt.expr).group()
                     ]
@@ -879,6 +905,16 @@ where
                 ")"
             ]
             .group(),
+            Pattern::Or(ref alts) => prec.enclose(
+                Prec::Function,
+                arena,
+                arena.concat(alts.iter().enumerate().map(|(i, alt)| {
+                    chain![arena;
+                        if i == 0 { arena.nil() } else { arena.text(" | ") },
+                        self.pretty_pattern_(alt, Prec::Function)
+                    ]
+                })),
+            ),
             Pattern::Error => arena.text("<error>"),
             Pattern::Literal(_) => arena.text(self.source.src_slice(pattern.span)),
         }
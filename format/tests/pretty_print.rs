@@ -829,3 +829,21 @@ let assert_success : [Show e]
 ()
 "#
 }
+
+test_format! {
+    or_pattern_and_guard,
+    r#"
+match x with
+| Some (A | B) | None -> 0
+| Some (C y) if y #Int< 0 -> y
+| _ -> 1
+"#
+}
+
+test_format! {
+    interpolated_string,
+    r#"
+let name = "gluon"
+"hello ${name}, \${escaped} ${show (1 + 2)} ${"nested ${name}"}"
+"#
+}
@@ -0,0 +1,86 @@
+//! Reading and writing of messages framed by a `Content-Length` header.
+//!
+//! Both the Language Server Protocol and the Debug Adapter Protocol send each message as a JSON
+//! object preceded by a `Content-Length` header, so the language server and the debugger share
+//! this crate.
+#![doc(html_root_url = "https://docs.rs/gluon_framing/0.15.0")] // # GLUON
+
+#[cfg(test)]
+#[macro_use]
+extern crate serde_json;
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value;
+
+/// Reads the next message from `reader`. Returns `None` once the input is exhausted.
+pub fn read_message<R>(reader: &mut R) -> io::Result<Option<Value>>
+where
+    R: BufRead,
+{
+    let mut content_length = None;
+    let mut header = String::new();
+    loop {
+        header.clear();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            // Skip stray newlines between messages
+            continue;
+        }
+        let mut parts = header.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        let value = parts.next().unwrap_or("").trim();
+        if name.eq_ignore_ascii_case("Content-Length") {
+            content_length = Some(
+                value
+                    .parse::<usize>()
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?,
+            );
+        }
+    }
+
+    let mut content = vec![0; content_length.unwrap()];
+    reader.read_exact(&mut content)?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+}
+
+/// Writes `message` to `writer` and flushes it
+pub fn write_message<W>(writer: &mut W, message: &Value) -> io::Result<()>
+where
+    W: ?Sized + Write,
+{
+    let content = message.to_string();
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip_message() {
+        let message = json!({ "seq": 1, "type": "request", "command": "threads" });
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &message).unwrap();
+        write_message(&mut buffer, &message).unwrap();
+
+        let mut reader = &buffer[..];
+        assert_eq!(read_message(&mut reader).unwrap(), Some(message.clone()));
+        assert_eq!(read_message(&mut reader).unwrap(), Some(message));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+}
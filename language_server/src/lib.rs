@@ -0,0 +1,1078 @@
+//! Language Server Protocol server for the gluon programming language.
+//!
+//! Every open document is added to the compiler's database as an inline module (see
+//! `CompilationBase::add_module`) so whenever a document changes only the queries which depend on
+//! it are recomputed. The typechecked expression from the last check is kept around and used to
+//! answer hover, completion, signature help and document symbol requests. Find references and
+//! rename look at every module in the database so that uses in other modules are found as well.
+#![doc(html_root_url = "https://docs.rs/gluon_language_server/0.15.0")] // # GLUON
+
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+
+pub mod protocol;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde_json::Value;
+
+use gluon::{
+    base::{
+        ast::{OwnedExpr, SpannedExpr},
+        filename_to_module,
+        pos::{ByteOffset, BytePos, Span},
+        symbol::Symbol,
+        types::{ArcType, TypeExt},
+    },
+    compiler_pipeline::TypecheckValue,
+    either::Either,
+    import::{imported_modules, Import},
+    query::{Compilation, CompilationBase},
+    Error, RootedThread, ThreadExt,
+};
+
+use gluon_completion::{self as completion, references};
+
+use crate::protocol::{Message, ResponseError, INVALID_PARAMS, METHOD_NOT_FOUND, REQUEST_FAILED};
+
+// https://microsoft.github.io/language-server-protocol/specification#diagnostic
+const SEVERITY_ERROR: i64 = 1;
+const SEVERITY_WARNING: i64 = 2;
+
+// https://microsoft.github.io/language-server-protocol/specification#textDocument_completion
+const COMPLETION_FUNCTION: i64 = 3;
+const COMPLETION_VARIABLE: i64 = 6;
+const COMPLETION_CLASS: i64 = 7;
+
+// https://microsoft.github.io/language-server-protocol/specification#textDocument_documentSymbol
+const SYMBOL_CLASS: i64 = 5;
+const SYMBOL_FUNCTION: i64 = 12;
+const SYMBOL_VARIABLE: i64 = 13;
+
+/// A position in a document. `character` counts UTF-16 code units, as required by the protocol.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+impl Position {
+    fn to_json(self) -> Value {
+        json!({ "line": self.line, "character": self.character })
+    }
+}
+
+/// Returns the byte offset of `position` in `text`. Positions past the end of a line or of the
+/// text are clamped to the end.
+fn offset_at(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(i) => line_start += i + 1,
+            None => return text.len(),
+        }
+    }
+    let mut character = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if character >= position.character || c == '\n' {
+            return line_start + i;
+        }
+        character += c.len_utf16();
+    }
+    text.len()
+}
+
+/// Returns the position of the byte at `offset` in `text`
+fn position_at(text: &str, offset: usize) -> Position {
+    let offset = offset.min(text.len());
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position {
+        line: before.matches('\n').count(),
+        character: before[line_start..].encode_utf16().count(),
+    }
+}
+
+/// Converts a `file://` URI into a path. Returns `None` for other schemes.
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    if !uri.starts_with("file://") {
+        return None;
+    }
+    let path = percent_decode(&uri["file://".len()..]);
+    // `file:///C:/dir` refers to `C:/dir` on windows
+    let path = match path.as_bytes() {
+        [b'/', _, b':', ..] if cfg!(windows) => path[1..].to_string(),
+        _ => path,
+    };
+    Some(PathBuf::from(path))
+}
+
+/// Converts `path` into a `file://` URI
+fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = if bytes[i] == b'%' {
+            s.get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InitializeParams {
+    root_uri: Option<String>,
+    root_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextDocumentIdentifier {
+    uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextDocumentItem {
+    uri: String,
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentChange {
+    range: Option<Range>,
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Range {
+    start: Position,
+    end: Position,
+}
+
+#[derive(Debug, Deserialize)]
+struct DidOpenParams {
+    #[serde(rename = "textDocument")]
+    text_document: TextDocumentItem,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidChangeParams {
+    text_document: TextDocumentIdentifier,
+    content_changes: Vec<ContentChange>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TextDocumentParams {
+    text_document: TextDocumentIdentifier,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TextDocumentPositionParams {
+    text_document: TextDocumentIdentifier,
+    position: Position,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RenameParams {
+    text_document: TextDocumentIdentifier,
+    position: Position,
+    new_name: String,
+}
+
+fn params<'de, T>(message: &'de Message) -> Result<T, ResponseError>
+where
+    T: serde::Deserialize<'de>,
+{
+    T::deserialize(&message.params).map_err(|err| {
+        ResponseError::new(
+            INVALID_PARAMS,
+            format!("Invalid parameters to `{}`: {}", message.method, err),
+        )
+    })
+}
+
+/// The result of the last check of a document
+struct Checked {
+    /// The span of the document's source in the compiler's code map
+    span: Span<BytePos>,
+    value: TypecheckValue<Arc<OwnedExpr<Symbol>>>,
+}
+
+/// A module which has been typechecked by the compiler, either an open document or a module
+/// imported by one
+struct LoadedModule {
+    name: String,
+    value: TypecheckValue<Arc<OwnedExpr<Symbol>>>,
+    file_map: Arc<codespan::FileMap>,
+}
+
+impl LoadedModule {
+    fn as_module(&self) -> references::Module {
+        references::Module {
+            name: &self.name,
+            source: &self.file_map,
+            expr: self.value.expr.expr(),
+        }
+    }
+
+    fn range(&self, span: Span<BytePos>) -> Value {
+        let text = self.file_map.src();
+        let offset = |pos: BytePos| {
+            pos.to_usize()
+                .saturating_sub(self.file_map.span().start().to_usize())
+        };
+        json!({
+            "start": position_at(text, offset(span.start())).to_json(),
+            "end": position_at(text, offset(span.end())).to_json(),
+        })
+    }
+}
+
+struct Document {
+    module: String,
+    text: String,
+    checked: Option<Checked>,
+}
+
+impl Document {
+    fn byte_pos(&self, checked: &Checked, position: Position) -> BytePos {
+        checked.span.start() + ByteOffset::from(offset_at(&self.text, position) as i64)
+    }
+
+    fn position(&self, checked: &Checked, pos: BytePos) -> Position {
+        let offset = pos
+            .to_usize()
+            .saturating_sub(checked.span.start().to_usize());
+        position_at(&self.text, offset)
+    }
+
+    fn range(&self, checked: &Checked, span: Span<BytePos>) -> Value {
+        json!({
+            "start": self.position(checked, span.start()).to_json(),
+            "end": self.position(checked, span.end()).to_json(),
+        })
+    }
+
+    fn apply_change(&mut self, change: ContentChange) {
+        match change.range {
+            Some(range) => {
+                let start = offset_at(&self.text, range.start);
+                let end = offset_at(&self.text, range.end).max(start);
+                self.text.replace_range(start..end, &change.text);
+            }
+            None => self.text = change.text,
+        }
+    }
+}
+
+/// Collects the spans and messages of `err`. Errors without a location yield `None` as the span.
+fn error_messages(err: &Error, messages: &mut Vec<(Option<Span<BytePos>>, String)>) {
+    match err {
+        Error::Parse(err) => messages.extend(
+            err.errors()
+                .iter()
+                .map(|err| (Some(err.span), err.value.to_string())),
+        ),
+        Error::Typecheck(err) => messages.extend(
+            err.errors()
+                .iter()
+                .map(|err| (Some(err.span), err.value.to_string())),
+        ),
+        Error::Macro(err) => messages.extend(
+            err.errors()
+                .iter()
+                .map(|err| (Some(err.span), err.value.to_string())),
+        ),
+        Error::Multiple(errors) => {
+            for err in errors {
+                error_messages(err, messages);
+            }
+        }
+        Error::IO(_) | Error::VM(_) | Error::Other(_) => messages.push((None, err.to_string())),
+    }
+}
+
+fn is_function(typ: &ArcType) -> bool {
+    typ.remove_forall_and_implicit_args()
+        .as_function()
+        .is_some()
+}
+
+fn markdown(typ: &str, comment: Option<&str>) -> Value {
+    let mut value = format!("```gluon\n{}\n```", typ);
+    if let Some(comment) = comment.filter(|comment| !comment.is_empty()) {
+        value.push_str("\n\n");
+        value.push_str(comment);
+    }
+    json!({ "kind": "markdown", "value": value })
+}
+
+/// A Language Server Protocol server which checks gluon documents as they are edited
+pub struct Server {
+    thread: RootedThread,
+    output: Box<dyn Write + Send>,
+    root: Option<PathBuf>,
+    documents: BTreeMap<String, Document>,
+    shutdown: bool,
+}
+
+impl Server {
+    /// Creates a server which writes all responses and notifications to `output`
+    pub fn new<W>(output: W) -> Server
+    where
+        W: Write + Send + 'static,
+    {
+        Server {
+            thread: gluon::new_vm(),
+            output: Box::new(output),
+            root: None,
+            documents: BTreeMap::new(),
+            shutdown: false,
+        }
+    }
+
+    /// Processes messages from `input` until the client sends `exit` or `input` is closed
+    pub fn run<R>(&mut self, mut input: R) -> io::Result<()>
+    where
+        R: BufRead,
+    {
+        while let Some(message) = protocol::read_message(&mut input)? {
+            debug!("Received: {}", message);
+            let message: Message = match serde_json::from_value(message) {
+                Ok(message) => message,
+                // Responses to requests sent by the server end up here, the server does not send
+                // any requests so they can be ignored
+                Err(err) => {
+                    warn!("Invalid message: {}", err);
+                    continue;
+                }
+            };
+            if message.method == "exit" {
+                break;
+            }
+            match message.id.clone() {
+                Some(id) => {
+                    let result = self.handle_request(&message);
+                    self.respond(id, result);
+                }
+                None => self.handle_notification(&message),
+            }
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, mut message: Value) {
+        message["jsonrpc"] = json!("2.0");
+        debug!("Send: {}", message);
+        if let Err(err) = protocol::write_message(&mut self.output, &message) {
+            error!("Unable to write message: {}", err);
+        }
+    }
+
+    fn respond(&mut self, id: Value, result: Result<Value, ResponseError>) {
+        let response = match result {
+            Ok(result) => json!({ "id": id, "result": result }),
+            Err(err) => json!({
+                "id": id,
+                "error": { "code": err.code, "message": err.message },
+            }),
+        };
+        self.send(response)
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "method": method, "params": params }))
+    }
+
+    fn handle_request(&mut self, message: &Message) -> Result<Value, ResponseError> {
+        if self.shutdown {
+            return Err(ResponseError::new(
+                REQUEST_FAILED,
+                "The server has been shut down",
+            ));
+        }
+        match &message.method[..] {
+            "initialize" => {
+                let params: InitializeParams = params(message)?;
+                self.initialize(params);
+                Ok(json!({
+                    "capabilities": {
+                        // Documents are sent in full on every change
+                        "textDocumentSync": 1,
+                        "hoverProvider": true,
+                        "completionProvider": { "triggerCharacters": ["."] },
+                        "signatureHelpProvider": { "triggerCharacters": [" ", "("] },
+                        "documentSymbolProvider": true,
+                        "documentFormattingProvider": true,
+                        "definitionProvider": true,
+                        "typeDefinitionProvider": true,
+                        "referencesProvider": true,
+                        "renameProvider": true,
+                    },
+                    "serverInfo": {
+                        "name": "gluon_language_server",
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                }))
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/hover" => self.hover(params(message)?),
+            "textDocument/completion" => self.completion(params(message)?),
+            "textDocument/signatureHelp" => self.signature_help(params(message)?),
+            "textDocument/documentSymbol" => self.document_symbols(params(message)?),
+            "textDocument/formatting" => self.formatting(params(message)?),
+            "textDocument/definition" => self.definition(params(message)?),
+            "textDocument/typeDefinition" => self.type_definition(params(message)?),
+            "textDocument/references" => self.references(params(message)?),
+            "textDocument/rename" => self.rename(params(message)?),
+            method => Err(ResponseError::new(
+                METHOD_NOT_FOUND,
+                format!("Unsupported request `{}`", method),
+            )),
+        }
+    }
+
+    fn handle_notification(&mut self, message: &Message) {
+        let result = match &message.method[..] {
+            "textDocument/didOpen" => params(message).map(|params: DidOpenParams| {
+                let document = params.text_document;
+                let module = self.module_name(&document.uri);
+                let uri = document.uri;
+                self.documents.insert(
+                    uri.clone(),
+                    Document {
+                        module,
+                        text: document.text,
+                        checked: None,
+                    },
+                );
+                self.check_documents(&uri);
+            }),
+            "textDocument/didChange" => params(message).map(|params: DidChangeParams| {
+                if let Some(document) = self.documents.get_mut(&params.text_document.uri) {
+                    for change in params.content_changes {
+                        document.apply_change(change);
+                    }
+                    self.check_documents(&params.text_document.uri);
+                }
+            }),
+            "textDocument/didClose" => params(message).map(|params: TextDocumentParams| {
+                let uri = params.text_document.uri;
+                if self.documents.remove(&uri).is_some() {
+                    self.notify(
+                        "textDocument/publishDiagnostics",
+                        json!({ "uri": uri, "diagnostics": [] }),
+                    );
+                }
+            }),
+            _ => Ok(()),
+        };
+        if let Err(err) = result {
+            warn!("{}", err.message);
+        }
+    }
+
+    fn initialize(&mut self, params: InitializeParams) {
+        self.root = params
+            .root_uri
+            .as_ref()
+            .and_then(|uri| uri_to_path(uri))
+            .or(params.root_path);
+        if let Some(root) = &self.root {
+            // Let documents import the modules which are next to them in the workspace
+            self.thread
+                .get_macros()
+                .get("import")
+                .as_ref()
+                .and_then(|import| import.downcast_ref::<Import>())
+                .expect("Import macro")
+                .add_path(root.clone());
+        }
+    }
+
+    fn module_name(&self, uri: &str) -> String {
+        let path = match uri_to_path(uri) {
+            Some(path) => path,
+            None => return filename_to_module(uri.rsplit('/').next().unwrap_or(uri)),
+        };
+        // Modules outside of the workspace can't be imported so only their file name matters
+        let relative = self
+            .root
+            .as_ref()
+            .and_then(|root| path.strip_prefix(root).ok())
+            .or_else(|| path.file_name().map(Path::new))
+            .unwrap_or(&path);
+        filename_to_module(&relative.to_string_lossy())
+    }
+
+    /// Updates the document at `uri` in the compiler's database and publishes the diagnostics of
+    /// it and of every open document which imports it, directly or through other modules, as the
+    /// change may introduce or fix errors in those as well.
+    fn check_documents(&mut self, uri: &str) {
+        let module = self.documents[uri].module.clone();
+        let affected = {
+            let mut db = self.thread.get_database_mut();
+            db.add_module(module.clone(), &self.documents[uri].text);
+
+            let imports: Vec<_> = db
+                .typechecked_modules()
+                .into_iter()
+                .map(|(name, value)| (name, imported_modules(value.expr.expr())))
+                .collect();
+            let mut affected = BTreeSet::new();
+            affected.insert(module.clone());
+            let mut stack = vec![module];
+            while let Some(module) = stack.pop() {
+                for (importer, imports) in &imports {
+                    if imports.contains(&module) && affected.insert(importer.clone()) {
+                        stack.push(importer.clone());
+                    }
+                }
+            }
+            affected
+        };
+
+        let uris: Vec<_> = self
+            .documents
+            .iter()
+            .filter(|(_, document)| affected.contains(&document.module))
+            .map(|(uri, _)| uri.clone())
+            .collect();
+        for uri in uris {
+            let diagnostics = self.check(&uri);
+            self.notify(
+                "textDocument/publishDiagnostics",
+                json!({ "uri": uri, "diagnostics": diagnostics }),
+            );
+        }
+    }
+
+    fn check(&mut self, uri: &str) -> Vec<Value> {
+        let document = self.documents.get_mut(uri).unwrap();
+
+        let mut db = self.thread.get_database();
+        let result =
+            futures::executor::block_on(db.typechecked_module(document.module.clone(), None));
+        let (value, err) = match result {
+            Ok(value) => (Some(value), None),
+            Err((value, err)) => (value, Some(err)),
+        };
+
+        let mut messages = Vec::new();
+        if let Some(err) = &err {
+            error_messages(err, &mut messages);
+        }
+
+        let span = db
+            .get_filemap(&document.module)
+            .map(|file_map| file_map.span());
+        document.checked = match (span, value) {
+            (Some(span), Some(value)) => Some(Checked { span, value }),
+            _ => None,
+        };
+
+        let mut diagnostics: Vec<_> = messages
+            .into_iter()
+            .map(|(error_span, message)| {
+                let range = match (error_span, &document.checked) {
+                    (Some(error_span), Some(checked)) if checked.span.contains(error_span) => {
+                        document.range(checked, error_span)
+                    }
+                    // Errors in other modules are reported at the start of the document
+                    _ => json!({
+                        "start": Position::default().to_json(),
+                        "end": Position::default().to_json(),
+                    }),
+                };
+                json!({
+                    "range": range,
+                    "severity": SEVERITY_ERROR,
+                    "source": "gluon",
+                    "message": message,
+                })
+            })
+            .collect();
+        if let Some(checked) = &document.checked {
+            diagnostics.extend(
+                checked
+                    .value
+                    .warnings
+                    .iter()
+                    .filter(|warning| checked.span.contains(warning.span))
+                    .map(|warning| {
+                        json!({
+                            "range": document.range(checked, warning.span),
+                            "severity": SEVERITY_WARNING,
+                            "source": "gluon",
+                            "message": warning.value.to_string(),
+                        })
+                    }),
+            );
+        }
+        diagnostics
+    }
+
+    /// Looks up the last check of the document at `uri`. Returns `None` if the document could not
+    /// be checked at all, such as when it fails to parse.
+    fn checked(&self, uri: &str) -> Result<Option<(&Document, &Checked)>, ResponseError> {
+        let document = self.documents.get(uri).ok_or_else(|| {
+            ResponseError::new(REQUEST_FAILED, format!("`{}` is not an open document", uri))
+        })?;
+        Ok(document.checked.as_ref().map(|checked| (document, checked)))
+    }
+
+    fn hover(&self, params: TextDocumentPositionParams) -> Result<Value, ResponseError> {
+        let (document, checked) = match self.checked(&params.text_document.uri)? {
+            Some(checked) => checked,
+            None => return Ok(Value::Null),
+        };
+        let expr = checked.value.expr.expr();
+        let pos = document.byte_pos(checked, params.position);
+
+        let env = self.thread.get_env();
+        let typ = match completion::find(&env, checked.span, expr, pos) {
+            Ok(Either::Left(kind)) => kind.to_string(),
+            Ok(Either::Right(typ)) => typ.to_string(),
+            Err(()) => return Ok(Value::Null),
+        };
+        let metadata =
+            completion::get_metadata(&checked.value.metadata_map, checked.span, expr, pos);
+        let comment = metadata
+            .and_then(|metadata| metadata.comment.as_ref())
+            .map(|comment| &comment.content[..]);
+        Ok(json!({ "contents": markdown(&typ, comment) }))
+    }
+
+    fn completion(&self, params: TextDocumentPositionParams) -> Result<Value, ResponseError> {
+        let (document, checked) = match self.checked(&params.text_document.uri)? {
+            Some(checked) => checked,
+            None => return Ok(json!([])),
+        };
+        let expr = checked.value.expr.expr();
+        let pos = document.byte_pos(checked, params.position);
+
+        let env = self.thread.get_env();
+        let items: Vec<_> = completion::suggest(&env, checked.span, expr, pos)
+            .into_iter()
+            .map(|suggestion| {
+                let (kind, detail) = match &suggestion.typ {
+                    Either::Left(kind) => (COMPLETION_CLASS, kind.to_string()),
+                    Either::Right(typ) if is_function(typ) => {
+                        (COMPLETION_FUNCTION, typ.to_string())
+                    }
+                    Either::Right(typ) => (COMPLETION_VARIABLE, typ.to_string()),
+                };
+                let mut item = json!({
+                    "label": suggestion.name,
+                    "kind": kind,
+                    "detail": detail,
+                });
+                let comment = completion::suggest_metadata(
+                    &checked.value.metadata_map,
+                    &env,
+                    checked.span,
+                    expr,
+                    pos,
+                    &suggestion.name,
+                )
+                .and_then(|metadata| metadata.comment.as_ref());
+                if let Some(comment) = comment {
+                    item["documentation"] = json!({ "kind": "markdown", "value": comment.content });
+                }
+                item
+            })
+            .collect();
+        Ok(json!(items))
+    }
+
+    fn signature_help(&self, params: TextDocumentPositionParams) -> Result<Value, ResponseError> {
+        let (document, checked) = match self.checked(&params.text_document.uri)? {
+            Some(checked) => checked,
+            None => return Ok(Value::Null),
+        };
+        let expr = checked.value.expr.expr();
+        let pos = document.byte_pos(checked, params.position);
+
+        let env = self.thread.get_env();
+        let help = match completion::signature_help(&env, checked.span, expr, pos) {
+            Some(help) => help,
+            None => return Ok(Value::Null),
+        };
+        let parameters: Vec<_> = help
+            .typ
+            .remove_forall_and_implicit_args()
+            .arg_iter()
+            .map(|arg| json!({ "label": arg.to_string() }))
+            .collect();
+        let label = if help.name.is_empty() {
+            help.typ.to_string()
+        } else {
+            format!("{} : {}", help.name, help.typ)
+        };
+        let mut result = json!({
+            "signatures": [{ "label": label, "parameters": parameters }],
+            "activeSignature": 0,
+        });
+        if let Some(index) = help.index {
+            result["activeParameter"] = json!(index);
+        }
+        Ok(result)
+    }
+
+    fn document_symbols(&self, params: TextDocumentParams) -> Result<Value, ResponseError> {
+        let (document, checked) = match self.checked(&params.text_document.uri)? {
+            Some(checked) => checked,
+            None => return Ok(json!([])),
+        };
+
+        fn to_json(
+            document: &Document,
+            checked: &Checked,
+            symbol: &completion::SpCompletionSymbol,
+        ) -> Value {
+            let (kind, range, detail) = match &symbol.value.content {
+                completion::CompletionSymbolContent::Value { typ, expr } => {
+                    let kind = if is_function(typ) {
+                        SYMBOL_FUNCTION
+                    } else {
+                        SYMBOL_VARIABLE
+                    };
+                    (kind, symbol.span.to(expr.span), typ.to_string())
+                }
+                completion::CompletionSymbolContent::Type { alias } => (
+                    SYMBOL_CLASS,
+                    symbol.span,
+                    alias.unresolved_type().to_string(),
+                ),
+            };
+            let children: Vec<_> = symbol
+                .value
+                .children
+                .iter()
+                .map(|child| to_json(document, checked, child))
+                .collect();
+            json!({
+                "name": symbol.value.name.declared_name(),
+                "detail": detail,
+                "kind": kind,
+                "range": document.range(checked, range),
+                "selectionRange": document.range(checked, symbol.span),
+                "children": children,
+            })
+        }
+
+        let expr: &SpannedExpr<Symbol> = checked.value.expr.expr();
+        let symbols: Vec<_> = completion::all_symbols(checked.span, expr)
+            .iter()
+            .map(|symbol| to_json(document, checked, symbol))
+            .collect();
+        Ok(json!(symbols))
+    }
+
+    /// Returns every module which has been typechecked, including the ones which are not open
+    fn loaded_modules(&self) -> Vec<LoadedModule> {
+        let db = self.thread.get_database();
+        db.typechecked_modules()
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let file_map = db.get_filemap(&name)?;
+                Some(LoadedModule {
+                    name,
+                    value,
+                    file_map,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the URI of the document which defines `module`. Modules which are neither open
+    /// nor in the workspace, such as the standard library, do not have one.
+    fn module_uri(&self, module: &str) -> Option<String> {
+        let open = self
+            .documents
+            .iter()
+            .find(|(_, document)| document.module == module);
+        if let Some((uri, _)) = open {
+            return Some(uri.clone());
+        }
+        let path = self
+            .root
+            .as_ref()?
+            .join(format!("{}.glu", module.replace('.', "/")));
+        if path.exists() {
+            Some(path_to_uri(&path))
+        } else {
+            None
+        }
+    }
+
+    /// Returns a URI which `module` can be viewed at. Modules without a document, such as the
+    /// standard library which is embedded in the compiler, are written to a temporary directory.
+    fn source_uri(&self, module: &LoadedModule) -> Option<String> {
+        if let Some(uri) = self.module_uri(&module.name) {
+            return Some(uri);
+        }
+        let path = env::temp_dir()
+            .join("gluon_language_server")
+            .join(env!("CARGO_PKG_VERSION"))
+            .join(format!("{}.glu", module.name.replace('.', "/")));
+        let source = module.file_map.src();
+        if fs::read_to_string(&path).ok().as_deref() != Some(source) {
+            let result = path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|()| fs::write(&path, source));
+            if let Err(err) = result {
+                warn!("Unable to write `{}`: {}", path.display(), err);
+                return None;
+            }
+        }
+        Some(path_to_uri(&path))
+    }
+
+    fn location(&self, modules: &[LoadedModule], location: &references::Location) -> Option<Value> {
+        let module = modules
+            .iter()
+            .find(|module| module.name == location.module)?;
+        Some(json!({
+            "uri": self.source_uri(module)?,
+            "range": module.range(location.span),
+        }))
+    }
+
+    /// Looks up the reference at `position` in `uri`
+    fn reference_at(
+        &self,
+        modules: &[LoadedModule],
+        uri: &str,
+        position: Position,
+    ) -> Result<Option<references::Reference>, ResponseError> {
+        let (document, checked) = match self.checked(uri)? {
+            Some(checked) => checked,
+            None => return Ok(None),
+        };
+        let pos = document.byte_pos(checked, position);
+        let module = match modules.iter().find(|module| module.name == document.module) {
+            Some(module) => module,
+            None => return Ok(None),
+        };
+        let env = self.thread.get_env();
+        Ok(references::reference_at(&module.as_module(), &env, pos))
+    }
+
+    fn definition(&self, params: TextDocumentPositionParams) -> Result<Value, ResponseError> {
+        let loaded = self.loaded_modules();
+        let reference =
+            match self.reference_at(&loaded, &params.text_document.uri, params.position)? {
+                Some(reference) => reference,
+                None => return Ok(Value::Null),
+            };
+
+        let modules: Vec<_> = loaded.iter().map(LoadedModule::as_module).collect();
+        let env = self.thread.get_env();
+        Ok(references::definition(&modules, &env, &reference)
+            .and_then(|location| self.location(&loaded, &location))
+            .unwrap_or(Value::Null))
+    }
+
+    fn type_definition(&self, params: TextDocumentPositionParams) -> Result<Value, ResponseError> {
+        let (document, checked) = match self.checked(&params.text_document.uri)? {
+            Some(checked) => checked,
+            None => return Ok(Value::Null),
+        };
+        let pos = document.byte_pos(checked, params.position);
+
+        let loaded = self.loaded_modules();
+        let modules: Vec<_> = loaded.iter().map(LoadedModule::as_module).collect();
+        let module = match modules.iter().find(|module| module.name == document.module) {
+            Some(module) => module,
+            None => return Ok(Value::Null),
+        };
+        let env = self.thread.get_env();
+        Ok(references::type_definition(&modules, &env, module, pos)
+            .and_then(|location| self.location(&loaded, &location))
+            .unwrap_or(Value::Null))
+    }
+
+    fn references(&self, params: TextDocumentPositionParams) -> Result<Value, ResponseError> {
+        let loaded = self.loaded_modules();
+        let reference =
+            match self.reference_at(&loaded, &params.text_document.uri, params.position)? {
+                Some(reference) => reference,
+                None => return Ok(json!([])),
+            };
+
+        let modules: Vec<_> = loaded.iter().map(LoadedModule::as_module).collect();
+        let env = self.thread.get_env();
+        let locations: Vec<_> = references::find_references(&modules, &env, &reference)
+            .into_iter()
+            .filter_map(|location| self.location(&loaded, &location))
+            .collect();
+        Ok(json!(locations))
+    }
+
+    fn rename(&self, params: RenameParams) -> Result<Value, ResponseError> {
+        let loaded = self.loaded_modules();
+        let reference =
+            match self.reference_at(&loaded, &params.text_document.uri, params.position)? {
+                Some(reference) => reference,
+                None => return Ok(Value::Null),
+            };
+
+        let modules: Vec<_> = loaded.iter().map(LoadedModule::as_module).collect();
+        let env = self.thread.get_env();
+        let edits = references::rename(&modules, &env, &reference, &params.new_name)
+            .map_err(|err| ResponseError::new(REQUEST_FAILED, err))?;
+
+        let mut changes = BTreeMap::new();
+        for edit in edits {
+            let module = loaded
+                .iter()
+                .find(|module| module.name == edit.module)
+                .expect("Edit in a loaded module");
+            // Leaving some uses unchanged would break the program
+            let uri = self.module_uri(&edit.module).ok_or_else(|| {
+                ResponseError::new(
+                    REQUEST_FAILED,
+                    format!(
+                        "`{}` can't be renamed as it is used in `{}` which is not part of the \
+                         workspace",
+                        reference.name(),
+                        edit.module
+                    ),
+                )
+            })?;
+            changes.entry(uri).or_insert_with(Vec::new).push(json!({
+                "range": module.range(edit.span),
+                "newText": edit.new_text,
+            }));
+        }
+        Ok(json!({ "changes": changes }))
+    }
+
+    fn formatting(&self, params: TextDocumentParams) -> Result<Value, ResponseError> {
+        let document = self
+            .documents
+            .get(&params.text_document.uri)
+            .ok_or_else(|| {
+                ResponseError::new(
+                    REQUEST_FAILED,
+                    format!("`{}` is not an open document", params.text_document.uri),
+                )
+            })?;
+        let formatted = self
+            .thread
+            .format_expr(
+                &mut gluon_format::Formatter::default(),
+                &document.module,
+                &document.text,
+            )
+            .map_err(|err| ResponseError::new(REQUEST_FAILED, err.to_string()))?;
+        if formatted == document.text {
+            return Ok(json!([]));
+        }
+        Ok(json!([{
+            "range": {
+                "start": Position::default().to_json(),
+                "end": position_at(&document.text, document.text.len()).to_json(),
+            },
+            "newText": formatted,
+        }]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions() {
+        let text = "let x = 1\nlet äb = x\n";
+        let position = Position {
+            line: 1,
+            character: 7,
+        };
+        let offset = offset_at(text, position);
+        assert_eq!(&text[offset..offset + 1], "=");
+        assert_eq!(position_at(text, offset), position);
+
+        // Positions past the end of a line are clamped to the end of the line
+        let past_end = Position {
+            line: 0,
+            character: 100,
+        };
+        assert_eq!(offset_at(text, past_end), 9);
+        assert_eq!(
+            offset_at(
+                text,
+                Position {
+                    line: 5,
+                    character: 0
+                }
+            ),
+            text.len()
+        );
+    }
+
+    #[test]
+    fn file_uris() {
+        assert_eq!(
+            uri_to_path("file:///home/user/my%20project/test.glu"),
+            Some(PathBuf::from("/home/user/my project/test.glu"))
+        );
+        assert_eq!(uri_to_path("untitled:Untitled-1"), None);
+
+        assert_eq!(
+            path_to_uri(Path::new("/home/user/my project/test.glu")),
+            "file:///home/user/my%20project/test.glu"
+        );
+    }
+}
@@ -0,0 +1,27 @@
+use std::{
+    io::{self, BufReader},
+    process, thread,
+};
+
+use gluon_language_server::Server;
+
+/// Typechecking can recurse deeply so give the server thread a larger stack
+const SERVER_STACK_SIZE: usize = 16 * 1024 * 1024;
+
+fn main() {
+    #[cfg(feature = "env_logger")]
+    env_logger::init();
+
+    let server = thread::Builder::new()
+        .name("gluon language server".to_string())
+        .stack_size(SERVER_STACK_SIZE)
+        .spawn(|| {
+            let stdin = io::stdin();
+            Server::new(io::stdout()).run(BufReader::new(stdin.lock()))
+        });
+    let result = server.and_then(|server| server.join().expect("Server thread panicked"));
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,56 @@
+//! Reading and writing of Language Server Protocol messages.
+//!
+//! Each message is a JSON-RPC 2.0 object preceded by a `Content-Length` header, the same framing
+//! as the debugger uses, which is implemented by `gluon_framing`.
+use serde_json::Value;
+
+pub use gluon_framing::{read_message, write_message};
+
+/// Error code for requests which the server does not implement
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// Error code for requests whose parameters could not be understood
+pub const INVALID_PARAMS: i64 = -32602;
+/// Error code for requests which failed while they were processed
+pub const REQUEST_FAILED: i64 = -32803;
+
+/// A request or a notification. Notifications do not have an `id` and must not be responded to.
+#[derive(Debug, Deserialize)]
+pub struct Message {
+    #[serde(default)]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug)]
+pub struct ResponseError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl ResponseError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        ResponseError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notification_has_no_id() {
+        let message: Message = serde_json::from_value(json!({
+            "jsonrpc": "2.0",
+            "method": "initialized",
+            "params": {},
+        }))
+        .unwrap();
+        assert_eq!(message.id, None);
+        assert_eq!(message.method, "initialized");
+    }
+}
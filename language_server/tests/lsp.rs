@@ -0,0 +1,478 @@
+#[macro_use]
+extern crate serde_json;
+
+use std::{
+    collections::VecDeque,
+    io::{self, BufReader, Read, Write},
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+use serde_json::Value;
+
+use gluon_language_server::{protocol, Server};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Typechecking the standard library can recurse deeply so give the server a larger stack
+const SERVER_STACK_SIZE: usize = 16 * 1024 * 1024;
+
+struct ChannelWriter(Sender<Vec<u8>>);
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .send(buf.to_owned())
+            .map_err(|err| io::Error::new(io::ErrorKind::BrokenPipe, err))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct ChannelReader {
+    receiver: Receiver<Vec<u8>>,
+    buffer: Vec<u8>,
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.buffer.is_empty() {
+            match self.receiver.recv_timeout(TIMEOUT) {
+                Ok(bytes) => self.buffer = bytes,
+                Err(err) => return Err(io::Error::new(io::ErrorKind::TimedOut, err)),
+            }
+        }
+        let len = buf.len().min(self.buffer.len());
+        buf[..len].copy_from_slice(&self.buffer[..len]);
+        self.buffer.drain(..len);
+        Ok(len)
+    }
+}
+
+struct Client {
+    input: ChannelWriter,
+    output: BufReader<ChannelReader>,
+    notifications: VecDeque<Value>,
+    id: i64,
+}
+
+impl Client {
+    fn start(root: &str) -> Client {
+        let (input_sender, input_receiver) = channel();
+        let (output_sender, output_receiver) = channel();
+        thread::Builder::new()
+            .stack_size(SERVER_STACK_SIZE)
+            .spawn(move || {
+                let input = BufReader::new(ChannelReader {
+                    receiver: input_receiver,
+                    buffer: Vec::new(),
+                });
+                Server::new(ChannelWriter(output_sender))
+                    .run(input)
+                    .unwrap();
+            })
+            .unwrap();
+        let mut client = Client {
+            input: ChannelWriter(input_sender),
+            output: BufReader::new(ChannelReader {
+                receiver: output_receiver,
+                buffer: Vec::new(),
+            }),
+            notifications: VecDeque::new(),
+            id: 0,
+        };
+        let result = client.request("initialize", json!({ "rootUri": root, "capabilities": {} }));
+        assert_eq!(result["capabilities"]["hoverProvider"], true);
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn read(&mut self) -> Value {
+        protocol::read_message(&mut self.output)
+            .unwrap()
+            .expect("Server closed the connection")
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        protocol::write_message(
+            &mut self.input,
+            &json!({ "jsonrpc": "2.0", "method": method, "params": params }),
+        )
+        .unwrap();
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.id += 1;
+        protocol::write_message(
+            &mut self.input,
+            &json!({ "jsonrpc": "2.0", "id": self.id, "method": method, "params": params }),
+        )
+        .unwrap();
+        loop {
+            let message = self.read();
+            if message.get("id").is_some() {
+                assert_eq!(message["id"], self.id);
+                assert!(message.get("error").is_none(), "{}", message);
+                return message["result"].clone();
+            }
+            self.notifications.push_back(message);
+        }
+    }
+
+    fn notification(&mut self, method: &str) -> Value {
+        loop {
+            let message = match self.notifications.pop_front() {
+                Some(message) => message,
+                None => self.read(),
+            };
+            if message["method"] == method {
+                return message["params"].clone();
+            }
+        }
+    }
+
+    fn diagnostics(&mut self, uri: &str) -> Vec<Value> {
+        loop {
+            let params = self.notification("textDocument/publishDiagnostics");
+            if params["uri"] == uri {
+                return params["diagnostics"].as_array().unwrap().clone();
+            }
+        }
+    }
+
+    fn open(&mut self, uri: &str, text: &str) -> Vec<Value> {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": uri, "languageId": "gluon", "version": 1, "text": text },
+            }),
+        );
+        self.diagnostics(uri)
+    }
+
+    fn change(&mut self, uri: &str, version: i64, text: &str) -> Vec<Value> {
+        self.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": uri, "version": version },
+                "contentChanges": [{ "text": text }],
+            }),
+        );
+        self.diagnostics(uri)
+    }
+
+    fn at(&mut self, method: &str, uri: &str, line: u64, character: u64) -> Value {
+        self.request(
+            method,
+            json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": line, "character": character },
+            }),
+        )
+    }
+
+    fn exit(mut self) {
+        self.request("shutdown", Value::Null);
+        self.notify("exit", Value::Null);
+    }
+}
+
+const ROOT: &str = "file:///lsp_test";
+const URI: &str = "file:///lsp_test/test.glu";
+
+#[test]
+fn diagnostics_are_updated_on_change() {
+    let _ = env_logger::try_init();
+
+    let mut client = Client::start(ROOT);
+
+    let diagnostics = client.open(URI, "let x : Int = \"\"\nx\n");
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 0);
+
+    let diagnostics = client.change(URI, 2, "let x : Int = 1\nx\n");
+    assert_eq!(diagnostics, Vec::<Value>::new());
+
+    let diagnostics = client.change(URI, 3, "let x = 1\nlet y = 2\nx\n");
+    assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+    assert_eq!(diagnostics[0]["severity"], 2);
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+
+    client.exit();
+}
+
+#[test]
+fn changes_recheck_importing_documents_only() {
+    let _ = env_logger::try_init();
+
+    let mut client = Client::start(ROOT);
+
+    let a = "file:///lsp_test/a.glu";
+    let b = "file:///lsp_test/b.glu";
+    let c = "file:///lsp_test/c.glu";
+    client.open(a, "let foo x : Int -> Int = x\n{ foo }\n");
+    let diagnostics = client.open(b, "let { foo } = import! a\nfoo 1\n");
+    assert_eq!(diagnostics, Vec::<Value>::new());
+    client.open(c, "1\n");
+
+    let diagnostics = client.change(a, 2, "let foo x : String -> String = x\n{ foo }\n");
+    assert_eq!(diagnostics, Vec::<Value>::new());
+    // Wait for every notification sent in response to the change
+    client.at("textDocument/hover", a, 0, 4);
+    let published: Vec<_> = client
+        .notifications
+        .drain(..)
+        .filter(|message| message["method"] == "textDocument/publishDiagnostics")
+        .map(|message| message["params"].clone())
+        .collect();
+    assert_eq!(published.len(), 1, "{:?}", published);
+    assert_eq!(published[0]["uri"], b);
+    assert_eq!(published[0]["diagnostics"].as_array().unwrap().len(), 1);
+
+    client.exit();
+}
+
+#[test]
+fn hover_and_completion() {
+    let _ = env_logger::try_init();
+
+    let mut client = Client::start(ROOT);
+
+    let text = r#"
+/// Adds one
+let add_one x : Int -> Int = x + 1
+let record = { field = 1.0 }
+add_one (add_one 2)
+"#;
+    client.open(URI, text);
+
+    let hover = client.at("textDocument/hover", URI, 4, 1);
+    let contents = hover["contents"]["value"].as_str().unwrap();
+    assert!(contents.contains("Int -> Int"), "{}", contents);
+    assert!(contents.contains("Adds one"), "{}", contents);
+
+    let text = r#"
+/// Adds one
+let add_one x : Int -> Int = x + 1
+let record = { field = 1.0 }
+record.
+"#;
+    client.change(URI, 2, text);
+    let completions = client.at("textDocument/completion", URI, 4, 7);
+    let labels: Vec<_> = completions
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect();
+    assert_eq!(labels, ["field"]);
+    assert_eq!(completions[0]["detail"], "Float");
+
+    client.exit();
+}
+
+#[test]
+fn signature_help() {
+    let _ = env_logger::try_init();
+
+    let mut client = Client::start(ROOT);
+
+    let text = r#"
+let f x y : Int -> String -> Int = x
+f 1 ""
+"#;
+    client.open(URI, text);
+    let help = client.at("textDocument/signatureHelp", URI, 2, 5);
+    assert_eq!(help["signatures"][0]["label"], "f : Int -> String -> Int");
+    assert_eq!(help["signatures"][0]["parameters"][1]["label"], "String");
+    assert_eq!(help["activeParameter"], 1);
+
+    client.exit();
+}
+
+#[test]
+fn document_symbols_and_formatting() {
+    let _ = env_logger::try_init();
+
+    let mut client = Client::start(ROOT);
+
+    let text = "type Test = Int\nlet f x : Int -> Int =   x\nlet y = f 1\ny\n";
+    client.open(URI, text);
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    let symbols: Vec<_> = symbols
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| {
+            (
+                symbol["name"].as_str().unwrap(),
+                symbol["kind"].as_i64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(symbols, [("Test", 5), ("f", 12), ("y", 13)]);
+
+    let edits = client.request(
+        "textDocument/formatting",
+        json!({
+            "textDocument": { "uri": URI },
+            "options": { "tabSize": 4, "insertSpaces": true },
+        }),
+    );
+    assert_eq!(
+        edits[0]["newText"],
+        "type Test = Int\nlet f x : Int -> Int = x\nlet y = f 1\ny\n"
+    );
+    assert_eq!(edits[0]["range"]["end"]["line"], 4);
+
+    client.exit();
+}
+
+#[test]
+fn references_and_rename_across_modules() {
+    let _ = env_logger::try_init();
+
+    let mut client = Client::start(ROOT);
+
+    let a = "file:///lsp_test/a.glu";
+    let b = "file:///lsp_test/b.glu";
+    client.open(
+        a,
+        "type R = { x : Int }\nlet foo r : R -> Int = r.x\n{ R, foo }\n",
+    );
+    let diagnostics = client.open(
+        b,
+        "let { R, foo } = import! a\nlet r : R = { x = 1 }\nfoo r\n",
+    );
+    assert_eq!(diagnostics, Vec::<Value>::new());
+
+    fn starts<'a>(
+        locations: impl IntoIterator<Item = (&'a str, &'a Value)>,
+    ) -> Vec<(String, u64, u64)> {
+        let mut starts: Vec<_> = locations
+            .into_iter()
+            .map(|(uri, range)| {
+                (
+                    uri.to_string(),
+                    range["start"]["line"].as_u64().unwrap(),
+                    range["start"]["character"].as_u64().unwrap(),
+                )
+            })
+            .collect();
+        starts.sort();
+        starts
+    }
+
+    let locations = client.at("textDocument/references", b, 2, 0);
+    let locations = starts(
+        locations
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|location| (location["uri"].as_str().unwrap(), &location["range"])),
+    );
+    assert_eq!(
+        locations,
+        [
+            (a.to_string(), 1, 4),
+            (a.to_string(), 2, 5),
+            (b.to_string(), 0, 9),
+            (b.to_string(), 2, 0),
+        ]
+    );
+
+    let edit = client.request(
+        "textDocument/rename",
+        json!({
+            "textDocument": { "uri": a },
+            "position": { "line": 1, "character": 25 },
+            "newName": "y",
+        }),
+    );
+    let changes = edit["changes"].as_object().unwrap();
+    let edits = starts(changes.iter().flat_map(|(uri, edits)| {
+        edits.as_array().unwrap().iter().map(move |edit| {
+            assert_eq!(edit["newText"], "y");
+            (&uri[..], &edit["range"])
+        })
+    }));
+    assert_eq!(
+        edits,
+        [
+            (a.to_string(), 0, 11),
+            (a.to_string(), 1, 25),
+            (b.to_string(), 1, 14),
+        ]
+    );
+
+    // Renaming an exported value also renames the locals bound by punned imports
+    let edit = client.request(
+        "textDocument/rename",
+        json!({
+            "textDocument": { "uri": b },
+            "position": { "line": 2, "character": 0 },
+            "newName": "bar",
+        }),
+    );
+    let changes = edit["changes"].as_object().unwrap();
+    let edits = starts(changes.iter().flat_map(|(uri, edits)| {
+        edits.as_array().unwrap().iter().map(move |edit| {
+            assert_eq!(edit["newText"], "bar");
+            (&uri[..], &edit["range"])
+        })
+    }));
+    assert_eq!(edits, locations);
+
+    client.exit();
+}
+
+#[test]
+fn definitions_across_imports() {
+    let _ = env_logger::try_init();
+
+    let mut client = Client::start(ROOT);
+
+    let a = "file:///lsp_test/a.glu";
+    let b = "file:///lsp_test/b.glu";
+    client.open(a, "type R = { x : Int }\nlet r : R = { x = 1 }\n{ R, r }\n");
+    let text = "let { id } = import! std.function\nlet { r } = import! a\nid r\n";
+    let diagnostics = client.open(b, text);
+    assert_eq!(diagnostics, Vec::<Value>::new());
+
+    let definition = client.at("textDocument/definition", b, 2, 3);
+    assert_eq!(definition["uri"], a);
+    assert_eq!(
+        definition["range"]["start"],
+        json!({ "line": 1, "character": 4 })
+    );
+
+    let definition = client.at("textDocument/typeDefinition", b, 2, 3);
+    assert_eq!(definition["uri"], a);
+    assert_eq!(
+        definition["range"]["start"],
+        json!({ "line": 0, "character": 5 })
+    );
+
+    // Modules from the standard library are embedded in the compiler so they are written to a
+    // file which the client can open
+    let definition = client.at("textDocument/definition", b, 2, 0);
+    let uri = definition["uri"].as_str().unwrap();
+    assert!(uri.ends_with("/std/function.glu"), "{}", uri);
+    let source = std::fs::read_to_string(uri.trim_start_matches("file://")).unwrap();
+    let line = source
+        .lines()
+        .nth(definition["range"]["start"]["line"].as_u64().unwrap() as usize)
+        .unwrap();
+    assert!(line.starts_with("let id "), "{}", line);
+
+    client.exit();
+}
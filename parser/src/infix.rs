@@ -309,6 +309,18 @@ impl StdError for Error {
     }
 }
 
+impl Error {
+    /// The error code which identifies this kind of error (see `gluon explain`)
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::ConflictingFixities(..) => "E0018",
+            Error::UndefinedFixity(_) => "E0019",
+            Error::InvalidFixity => "E0020",
+            Error::InvalidPrecedence => "E0021",
+        }
+    }
+}
+
 /// Reconstruct the infix expression using the correct associativities
 /// and precedences.
 ///
@@ -11,6 +11,15 @@ quick_error! {
     }
 }
 
+impl Error {
+    /// The error code which identifies this kind of error (see `gluon explain`)
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::UnindentedTooFar => "E0014",
+        }
+    }
+}
+
 type Result<T, E = Spanned<crate::Error, BytePos>> = std::result::Result<T, E>;
 
 #[derive(Copy, Clone, Debug)]
@@ -47,6 +56,8 @@ enum Context {
     If,
     /// In a match clause
     MatchClause,
+    /// In the guard of a match clause (`| pattern if guard -> ..`)
+    Guard,
     /// In a lambda function
     Lambda,
     /// In an attribute
@@ -313,6 +324,8 @@ where
                 | (&Token::CloseBlock, _)
                 | (&Token::Else, _)
                 | (&Token::RBrace, _)
+                | (&Token::InterpolationMiddle(_), _)
+                | (&Token::InterpolationEnd(_), _)
                 | (&Token::RBracket, _)
                 | (&Token::RParen, _)
                 | (&Token::Comma, _) => {
@@ -334,10 +347,18 @@ where
                     if token_closes_context(&token.value, offside.context) {
                         match offside.context {
                             Context::If => (),
-                            Context::Brace
-                            | Context::Bracket
-                            | Context::Paren
-                            | Context::Attribute => return Ok(token),
+                            // The expression in an interpolated string continues with the next
+                            // interpolated expression
+                            Context::Brace => {
+                                if let Token::InterpolationMiddle(_) = token.value {
+                                    let offside = Offside::new(token.span.start(), Context::Brace);
+                                    self.indent_levels.push(offside)?;
+                                }
+                                return Ok(token);
+                            }
+                            Context::Bracket | Context::Paren | Context::Attribute => {
+                                return Ok(token)
+                            }
                             Context::Block { .. } if token.value == Token::CloseBlock => {
                                 if let Some(offside) = self.indent_levels.last_mut() {
                                     // The enclosing block should not emit a block separator for the next
@@ -516,10 +537,11 @@ where
                 Token::Type => Some(Context::Type),
                 Token::Let => Some(Context::Let),
                 Token::Do | Token::Seq => Some(Context::Let),
+                Token::If if offside.context == Context::MatchClause => Some(Context::Guard),
                 Token::If => Some(Context::If),
                 Token::Match => Some(Context::Expr),
                 Token::Lambda => Some(Context::Lambda),
-                Token::LBrace => Some(Context::Brace),
+                Token::LBrace | Token::InterpolationStart(_) => Some(Context::Brace),
                 Token::LBracket => Some(Context::Bracket),
                 Token::LParen => Some(Context::Paren),
                 Token::AttributeOpen => Some(Context::Attribute),
@@ -557,6 +579,10 @@ where
                     }
                 }
 
+                (&Token::RArrow, Context::Guard) => {
+                    self.indent_levels.pop();
+                    self.scan_for_next_block(Context::Block { emit_semi: false })?
+                }
                 (&Token::Equals, Context::Let)
                 | (&Token::RArrow, Context::Lambda)
                 | (&Token::RArrow, Context::MatchClause)
@@ -608,6 +634,8 @@ fn token_closes_context(token: &BorrowedToken, context: Context) -> bool {
     match (token, context) {
         (&Token::Else, Context::If)
         | (&Token::RBrace, Context::Brace)
+        | (&Token::InterpolationMiddle(_), Context::Brace)
+        | (&Token::InterpolationEnd(_), Context::Brace)
         | (&Token::RBracket, Context::Bracket)
         | (&Token::RParen, Context::Paren)
         | (&Token::CloseBlock, Context::Block { .. })
@@ -100,7 +100,9 @@ fn shrink_hidden_spans<Id: std::fmt::Debug>(mut expr: SpannedExpr<Id>) -> Spanne
         Expr::Annotated(..)
         | Expr::App { .. }
         | Expr::Ident(_)
+        | Expr::Hole(_)
         | Expr::Literal(_)
+        | Expr::Interpolation(_)
         | Expr::Projection(_, _, _)
         | Expr::Array(_)
         | Expr::Record { .. }
@@ -179,9 +181,25 @@ quick_error! {
     }
 }
 
+impl Error {
+    /// The error code which identifies this kind of error (see `gluon explain`)
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Token(err) => err.code(),
+            Error::Layout(err) => err.code(),
+            Error::InvalidToken => "E0015",
+            Error::UnexpectedToken(..) => "E0016",
+            Error::UnexpectedEof(_) => "E0003",
+            Error::ExtraToken(_) => "E0017",
+            Error::Infix(err) => err.code(),
+            Error::Message(_) => "E0022",
+        }
+    }
+}
+
 impl AsDiagnostic for Error {
     fn as_diagnostic(&self) -> codespan_reporting::Diagnostic {
-        codespan_reporting::Diagnostic::new_error(self.to_string())
+        codespan_reporting::Diagnostic::new_error(self.to_string()).with_code(self.code())
     }
 }
 
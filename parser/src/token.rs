@@ -22,6 +22,12 @@ pub enum Token<S> {
     Operator(S),
 
     StringLiteral(StringLiteral<S>),
+    /// `"text${`, the start of an interpolated string
+    InterpolationStart(S),
+    /// `}text${`, the text between two interpolated expressions
+    InterpolationMiddle(S),
+    /// `}text"`, the end of an interpolated string
+    InterpolationEnd(S),
     CharLiteral(char),
     IntLiteral(i64),
     ByteLiteral(u8),
@@ -80,6 +86,9 @@ where
             Identifier(_) => "Identifier",
             Operator(_) => "Operator",
             StringLiteral(_) => "StringLiteral",
+            InterpolationStart(_) => "InterpolationStart",
+            InterpolationMiddle(_) => "InterpolationMiddle",
+            InterpolationEnd(_) => "InterpolationEnd",
             CharLiteral(_) => "CharLiteral",
             IntLiteral(_) => "IntLiteral",
             ByteLiteral(_) => "ByteLiteral",
@@ -141,6 +150,9 @@ impl<S> Token<S> {
                 self::StringLiteral::Escaped(s) => self::StringLiteral::Escaped(f(s)),
                 self::StringLiteral::Raw(s) => self::StringLiteral::Raw(f(s)),
             }),
+            InterpolationStart(s) => InterpolationStart(f(s)),
+            InterpolationMiddle(s) => InterpolationMiddle(f(s)),
+            InterpolationEnd(s) => InterpolationEnd(f(s)),
             CharLiteral(x) => CharLiteral(x),
             IntLiteral(x) => IntLiteral(x),
             ByteLiteral(x) => ByteLiteral(x),
@@ -208,7 +220,7 @@ impl StringLiteral<&'_ str> {
     }
 }
 
-fn unescape_string_literal(mut s: &str) -> String {
+pub fn unescape_string_literal(mut s: &str) -> String {
     let mut string = String::new();
     while let Some(i) = s.bytes().position(|b| b == b'\\') {
         let c = match s.as_bytes()[i + 1] {
@@ -216,6 +228,7 @@ fn unescape_string_literal(mut s: &str) -> String {
             b'"' => '"',
             b'\\' => '\\',
             b'/' => '/',
+            b'$' => '$',
             b'n' => '\n',
             b'r' => '\r',
             b't' => '\t',
@@ -281,6 +294,27 @@ quick_error! {
     }
 }
 
+impl Error {
+    /// The error code which identifies this kind of error (see `gluon explain`)
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::EmptyCharLiteral => "E0001",
+            Error::UnexpectedChar(_) => "E0002",
+            Error::UnexpectedEof => "E0003",
+            Error::UnexpectedEscapeCode(_) => "E0004",
+            Error::UnterminatedCharLiteral => "E0005",
+            Error::UnterminatedStringLiteral => "E0006",
+            Error::InvalidRawStringDelimiter => "E0007",
+            Error::NonParseableInt => "E0008",
+            Error::HexLiteralOverflow => "E0009",
+            Error::HexLiteralUnderflow => "E0010",
+            Error::HexLiteralWrongPrefix => "E0011",
+            Error::HexLiteralIncomplete => "E0012",
+            Error::UnexpectedAnd => "E0013",
+        }
+    }
+}
+
 fn error<T>(location: Location, code: Error) -> Result<T, SpError> {
     Err(pos::spanned2(location, location, code))
 }
@@ -351,6 +385,8 @@ pub struct Tokenizer<'input> {
     input: &'input str,
     chars: CharLocations<'input>,
     start_index: BytePos,
+    /// One entry for each unclosed `{`, `true` if the brace started an interpolated expression
+    braces: Vec<bool>,
 }
 
 impl<'input> Tokenizer<'input> {
@@ -364,6 +400,7 @@ impl<'input> Tokenizer<'input> {
             input: input.src(),
             chars,
             start_index: input.start_index(),
+            braces: Vec::new(),
         }
     }
 
@@ -505,6 +542,7 @@ impl<'input> Tokenizer<'input> {
             Some((_, b'"')) => Ok(b'"'),
             Some((_, b'\\')) => Ok(b'\\'),
             Some((_, b'/')) => Ok(b'/'),
+            Some((_, b'$')) => Ok(b'$'),
             Some((_, b'n')) => Ok(b'\n'),
             Some((_, b'r')) => Ok(b'\r'),
             Some((_, b't')) => Ok(b'\t'),
@@ -518,23 +556,49 @@ impl<'input> Tokenizer<'input> {
     }
 
     fn string_literal(&mut self, start: Location) -> Result<SpannedToken<'input>, SpError> {
+        self.string_literal_part(
+            start,
+            |s| Token::StringLiteral(StringLiteral::Escaped(s)),
+            Token::InterpolationStart,
+        )
+    }
+
+    /// Scans the rest of an interpolated string after the `}` which ends an interpolated
+    /// expression
+    fn interpolation_continue(&mut self, start: Location) -> Result<SpannedToken<'input>, SpError> {
+        self.string_literal_part(start, Token::InterpolationEnd, Token::InterpolationMiddle)
+    }
+
+    fn string_literal_part(
+        &mut self,
+        start: Location,
+        end_token: impl FnOnce(&'input str) -> BorrowedToken<'input>,
+        interpolation_token: impl FnOnce(&'input str) -> BorrowedToken<'input>,
+    ) -> Result<SpannedToken<'input>, SpError> {
         let content_start = self.next_loc();
         loop {
             let scan_start = self.next_loc();
-            self.take_until(scan_start, |b| b == b'"' || b == b'\\');
+            self.take_until(scan_start, |b| b == b'"' || b == b'\\' || b == b'$');
             match self.bump() {
                 Some((_, b'\\')) => {
                     self.escape_code()?;
                 }
+                Some((content_end, b'$')) => {
+                    if self.test_lookahead(|b| b == b'{') {
+                        self.bump();
+                        self.braces.push(true);
+
+                        let token = interpolation_token(self.slice(content_start, content_end));
+                        return Ok(pos::spanned2(start, self.next_loc(), token));
+                    }
+                }
                 Some((_, b'"')) => {
                     let end = self.next_loc();
 
                     let mut content_end = end;
                     content_end.absolute.0 -= 1;
 
-                    let token = Token::StringLiteral(StringLiteral::Escaped(
-                        self.slice(content_start, content_end),
-                    ));
+                    let token = end_token(self.slice(content_start, content_end));
                     return Ok(pos::spanned2(start, end, token));
                 }
                 _ => break,
@@ -736,10 +800,16 @@ impl<'input> Iterator for Tokenizer<'input> {
             return match ch {
                 b',' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::Comma))),
                 b'\\' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::Lambda))),
-                b'{' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::LBrace))),
+                b'{' => {
+                    self.braces.push(false);
+                    Some(Ok(pos::spanned2(start, self.next_loc(), Token::LBrace)))
+                }
                 b'[' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::LBracket))),
                 b'(' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::LParen))),
-                b'}' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::RBrace))),
+                b'}' => match self.braces.pop() {
+                    Some(true) => Some(self.interpolation_continue(start)),
+                    _ => Some(Ok(pos::spanned2(start, self.next_loc(), Token::RBrace))),
+                },
                 b']' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::RBracket))),
                 b')' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::RParen))),
                 b'?' => Some(Ok(pos::spanned2(start, self.next_loc(), Token::Question))),
@@ -988,6 +1058,33 @@ mod test {
         assert_eq!(StringLiteral::Escaped(r#"\"\""#).unescape(), r#""""#);
     }
 
+    #[test]
+    fn interpolated_string_literals() {
+        test(
+            r#""a ${x} b ${ { y } }" "\${x}""#,
+            vec![
+                (
+                    r#"~~~~~                           "#,
+                    InterpolationStart("a "),
+                ),
+                (r#"     ~                          "#, Identifier("x")),
+                (
+                    r#"      ~~~~~~                    "#,
+                    InterpolationMiddle(" b "),
+                ),
+                (r#"             ~                  "#, LBrace),
+                (r#"               ~                "#, Identifier("y")),
+                (r#"                 ~              "#, RBrace),
+                (r#"                   ~~           "#, InterpolationEnd("")),
+                (
+                    r#"                      ~~~~~~~"#,
+                    Token::StringLiteral(StringLiteral::Escaped(r#"\${x}"#)),
+                ),
+            ],
+        );
+        assert_eq!(StringLiteral::Escaped(r#"\${x}"#).unescape(), "${x}");
+    }
+
     #[test]
     fn raw_string_literals() {
         test(
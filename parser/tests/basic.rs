@@ -84,6 +84,21 @@ if_else_test,
     |arena| if_else(arena, id("True"), int(1), int(0))
 }
 
+test_parse! {
+    holes,
+    "f _ (?x)",
+    |arena| app(arena,
+        id("f"),
+        vec![
+            no_loc(Expr::Hole(TypedIdent::new(intern("_")))),
+            no_loc(Expr::Tuple {
+                typ: Type::hole(),
+                elems: arena.alloc_extend(vec![no_loc(Expr::Hole(TypedIdent::new(intern("x"))))]),
+            }),
+        ],
+    )
+}
+
 #[test]
 fn let_type_decl() {
     let _ = ::env_logger::try_init();
@@ -247,6 +262,79 @@ test_parse! {
         )
 }
 
+test_parse! {
+    or_pattern,
+    r#"
+    match x with
+    | A y | B y -> y
+    | (C | D) -> 0"#,
+    |arena| {
+        let y = || no_loc(Pattern::Ident(TypedIdent::new(intern("y"))));
+        let constructor = |name: &str, args| {
+            no_loc(Pattern::Constructor(TypedIdent::new(intern(name)), args))
+        };
+        let first = Pattern::Or(arena.alloc_extend(vec![
+            constructor("A", arena.alloc_extend(vec![y()])),
+            constructor("B", arena.alloc_extend(vec![y()])),
+        ]));
+        let second = Pattern::Or(arena.alloc_extend(vec![
+            constructor("C", &mut []),
+            constructor("D", &mut []),
+        ]));
+        case(arena, id("x"), vec![(first, id("y")), (second, int(0))])
+    }
+}
+
+test_parse! {
+    match_guard,
+    r#"
+    match x with
+    | y if y #Int< 0 -> 0
+    | y -> y"#,
+    |arena| no_loc(Expr::Match(
+        arena.alloc(id("x")),
+        arena.alloc_extend(vec![
+            Alternative {
+                pattern: no_loc(Pattern::Ident(TypedIdent::new(intern("y")))),
+                guard: Some(binop(arena, id("y"), "#Int<", int(0))),
+                expr: int(0),
+            },
+            Alternative {
+                pattern: no_loc(Pattern::Ident(TypedIdent::new(intern("y")))),
+                guard: None,
+                expr: id("y"),
+            },
+        ]),
+    ))
+}
+
+test_parse! {
+    interpolated_string,
+    r#" "a ${x} b \${c} ${ f 1 }" "#,
+    |arena| {
+        // The spans of the literal parts include the delimiters and are not cleared by the test
+        let literal = |start, end, s: &str| {
+            pos::spanned(Span::new(BytePos(start), BytePos(end)), s.to_string())
+        };
+        let hole = |expr| InterpolatedExpr {
+            expr,
+            show_id: None,
+        };
+        no_loc(Expr::Interpolation(arena.alloc(Interpolation {
+            literals: vec![
+                literal(2, 7, "a "),
+                literal(8, 20, " b ${c} "),
+                literal(25, 27, ""),
+            ],
+            exprs: arena.alloc_extend(vec![
+                hole(id("x")),
+                hole(app(arena, id("f"), vec![int(1)])),
+            ]),
+            append_id: None,
+        })))
+    }
+}
+
 test_parse! {
     array_expr,
     "[1, a]",
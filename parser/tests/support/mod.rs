@@ -348,6 +348,7 @@ pub fn case<'ast>(
         arena.alloc(e),
         arena.alloc_extend(alts.into_iter().map(|(p, e)| Alternative {
             pattern: no_loc(p),
+            guard: None,
             expr: e,
         })),
     ))
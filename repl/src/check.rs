@@ -0,0 +1,223 @@
+//! Implementation of the `gluon check` subcommand which typechecks modules without running them
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use structopt::StructOpt;
+
+use gluon::{
+    base::filename_to_module, import::imported_modules, query::Compilation, Thread, ThreadExt,
+};
+
+use crate::{emit_error, emit_warnings, gluon_files, Color, Error, ErrorFormat};
+
+#[derive(StructOpt)]
+#[structopt(about = "Typechecks gluon source code without running it")]
+pub struct CheckOpt {
+    #[structopt(
+        name = "PATH",
+        parse(from_os_str),
+        help = "Files or directories to check (default: the current directory)"
+    )]
+    input: Vec<PathBuf>,
+
+    #[structopt(
+        long = "watch",
+        short = "w",
+        help = "Keeps running and re-checks the modules affected by each change to a file. The \
+                affected modules are approximated as the changed modules and the checked \
+                modules which transitively import them, as recorded when they were last checked"
+    )]
+    watch: bool,
+}
+
+/// How often `--watch` looks for modified files
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+fn modification_times(files: &[PathBuf]) -> BTreeMap<PathBuf, Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| {
+            let modified = fs::metadata(file).and_then(|m| m.modified()).ok();
+            (file.clone(), modified)
+        })
+        .collect()
+}
+
+struct Checker<'a> {
+    vm: &'a Thread,
+    color: Color,
+    error_format: ErrorFormat,
+    /// The file of each module which is checked
+    files: BTreeMap<String, PathBuf>,
+    /// The modules imported by each checked module
+    imports: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Checker<'_> {
+    /// Typechecks `modules` and prints their diagnostics. Returns the number of modules which
+    /// failed to typecheck.
+    async fn check(&mut self, modules: &BTreeSet<String>) -> Result<usize, Error> {
+        let mut failed = 0;
+        for module in modules {
+            let result = {
+                let mut db = self.vm.get_database();
+                db.typechecked_module(module.clone(), None).await
+            };
+            let (value, err) = match result {
+                Ok(value) => (Some(value), None),
+                Err((value, err)) => (value, Some(err)),
+            };
+
+            if let Some(value) = value {
+                self.imports
+                    .insert(module.clone(), imported_modules(value.expr.expr()));
+            }
+            emit_warnings(
+                self.vm,
+                self.color,
+                self.error_format,
+                &self.files[module].display().to_string(),
+            )?;
+            if let Some(err) = err {
+                emit_error(&err, self.color, self.error_format).map_err(gluon::Error::from)?;
+                failed += 1;
+            }
+        }
+        Ok(failed)
+    }
+
+    /// Returns `changed` along with every checked module which transitively imports a module in
+    /// `changed`.
+    ///
+    /// This approximates the modules which the compiler would recompute, it does not ask the
+    /// database which queries are stale. Modules are rechecked even if the change did not affect
+    /// them, and imports added since a module was last checked are only seen once it is
+    /// rechecked.
+    fn affected(&self, changed: &BTreeSet<String>) -> BTreeSet<String> {
+        let mut affected = changed.clone();
+        let mut stack: Vec<_> = changed.iter().cloned().collect();
+        while let Some(module) = stack.pop() {
+            for (importer, imports) in &self.imports {
+                if imports.contains(&module) && affected.insert(importer.clone()) {
+                    stack.push(importer.clone());
+                }
+            }
+        }
+        affected.retain(|module| self.files.contains_key(module));
+        affected
+    }
+
+    fn report(&self, failed: usize, checked: usize) {
+        if self.error_format == ErrorFormat::Human {
+            eprintln!(
+                "Checked {} modules: {} failed to typecheck",
+                checked, failed
+            );
+        }
+    }
+}
+
+pub async fn run(
+    vm: &Thread,
+    color: Color,
+    error_format: ErrorFormat,
+    opt: &CheckOpt,
+) -> Result<(), Error> {
+    // Imported modules still need to be evaluated to be used but any `IO` action they produce
+    // must not run
+    vm.get_database_mut().run_io(false);
+
+    let input = if opt.input.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        opt.input.clone()
+    };
+
+    let files = gluon_files(&input);
+    let mut checker = Checker {
+        vm,
+        color,
+        error_format,
+        files: files
+            .iter()
+            .map(|file| {
+                (
+                    filename_to_module(&file.display().to_string()),
+                    file.clone(),
+                )
+            })
+            .collect(),
+        imports: BTreeMap::new(),
+    };
+
+    let modules = checker.files.keys().cloned().collect();
+    let failed = checker.check(&modules).await?;
+
+    if !opt.watch {
+        return if failed == 0 {
+            Ok(())
+        } else {
+            Err(failure::err_msg(format!(
+                "{} of {} modules failed to typecheck",
+                failed,
+                modules.len()
+            ))
+            .into())
+        };
+    }
+
+    checker.report(failed, modules.len());
+    let mut modified = modification_times(&files);
+    loop {
+        tokio::time::delay_for(WATCH_INTERVAL).await;
+
+        let files = gluon_files(&input);
+        let current = modification_times(&files);
+        if current == modified {
+            continue;
+        }
+
+        let changed: BTreeSet<_> = current
+            .iter()
+            .filter(|&(file, time)| modified.get(file) != Some(time))
+            .chain(
+                modified
+                    .iter()
+                    .filter(|(file, _)| !current.contains_key(*file)),
+            )
+            .map(|(file, _)| filename_to_module(&file.display().to_string()))
+            .collect();
+        modified = current;
+
+        {
+            let mut db = vm.get_database_mut();
+            for module in &changed {
+                db.invalidate_module(module);
+            }
+        }
+        checker.files = files
+            .iter()
+            .map(|file| {
+                (
+                    filename_to_module(&file.display().to_string()),
+                    file.clone(),
+                )
+            })
+            .collect();
+        {
+            let files = &checker.files;
+            checker
+                .imports
+                .retain(|module, _| files.contains_key(module));
+        }
+
+        let modules = checker.affected(&changed);
+        let failed = checker.check(&modules).await?;
+        checker.report(failed, modules.len());
+    }
+}
@@ -12,6 +12,7 @@ extern crate gluon_vm;
 extern crate gluon_codegen;
 
 use std::{
+    collections::BTreeSet,
     ffi::OsStr,
     fs,
     io::{self, Write},
@@ -19,21 +20,26 @@ use std::{
     sync::Arc,
 };
 
-use quick_error::quick_error;
 use codespan_reporting::termcolor;
+use quick_error::quick_error;
 use structopt::StructOpt;
 use walkdir::WalkDir;
 
 use gluon::{base, parser, vm};
 
-use crate::base::filename_to_module;
+use crate::base::{
+    error::{InFile, Severity, StructuredDiagnostic},
+    filename_to_module,
+};
 
 use gluon::{
-    new_vm_async, vm::thread::ThreadInternal, vm::Error as VMError, Result, Thread,
-    ThreadExt,
+    new_vm_async, profile::Profile, query::CompilationBase, vm::coverage::Coverage,
+    vm::thread::ThreadInternal, vm::Error as VMError, Result, Thread, ThreadExt,
 };
 
+mod check;
 mod repl;
+mod test;
 
 quick_error! {
 /// Error type wrapping all possible errors that can be generated from gluon
@@ -96,6 +102,24 @@ impl ::std::str::FromStr for Color {
     }
 }
 
+/// How errors and warnings are printed
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+impl ::std::str::FromStr for ErrorFormat {
+    type Err = &'static str;
+    fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "human" => ErrorFormat::Human,
+            "json" => ErrorFormat::Json,
+            _ => return Err("Expected one of 'human', 'json'"),
+        })
+    }
+}
+
 #[derive(StructOpt)]
 #[structopt(about = "Formats gluon source code")]
 pub struct FmtOpt {
@@ -103,18 +127,68 @@ pub struct FmtOpt {
     input: Vec<PathBuf>,
 }
 
+#[derive(StructOpt)]
+#[structopt(about = "Explains an error code")]
+pub struct ExplainOpt {
+    #[structopt(
+        name = "CODE",
+        help = "The error code to explain, such as `E0101`. Lists every code if omitted"
+    )]
+    code: Option<String>,
+}
+
+#[derive(StructOpt)]
+#[structopt(
+    about = "Prints the optimized core IR or the bytecode that a file compiles to",
+    group = clap::ArgGroup::with_name("output").required(true)
+)]
+pub struct DumpOpt {
+    #[structopt(long = "core", group = "output", help = "Prints the optimized core IR")]
+    core: bool,
+
+    #[structopt(
+        long = "bytecode",
+        group = "output",
+        help = "Prints the bytecode of each function"
+    )]
+    bytecode: bool,
+
+    #[structopt(name = "FILE", parse(from_os_str), help = "The file to compile")]
+    input: PathBuf,
+}
+
 #[derive(StructOpt)]
 pub enum SubOpt {
     #[structopt(name = "fmt", about = "Formats gluon source code")]
     Fmt(FmtOpt),
     #[structopt(name = "doc", about = "Documents gluon source code")]
     Doc(::gluon_doc::Opt),
+    #[structopt(name = "explain", about = "Explains an error code")]
+    Explain(ExplainOpt),
+    #[structopt(name = "test", about = "Runs the tests defined with `std.test`")]
+    Test(test::TestOpt),
+    #[structopt(
+        name = "check",
+        about = "Typechecks gluon source code without running it"
+    )]
+    Check(check::CheckOpt),
+    #[structopt(
+        name = "dump",
+        about = "Prints the optimized core IR or the bytecode that a file compiles to"
+    )]
+    Dump(DumpOpt),
 }
 
 const LONG_VERSION: &str = concat!(clap::crate_version!(), "\n", "commit: ", env!("GIT_HASH"));
 
 #[derive(StructOpt)]
-#[structopt(about = "executes gluon programs", long_version = LONG_VERSION)]
+#[structopt(
+    about = "executes gluon programs",
+    long_version = LONG_VERSION,
+    // Prevents files which are similar to a subcommand (`tests/foo.glu`) from being rejected as a
+    // misspelled subcommand
+    setting = clap::AppSettings::InferSubcommands
+)]
 pub struct Opt {
     #[structopt(short = "i", long = "interactive", help = "Starts the repl")]
     interactive: bool,
@@ -126,6 +200,14 @@ pub struct Opt {
     )]
     color: Color,
 
+    #[structopt(
+        long = "error-format",
+        default_value = "human",
+        help = "How errors and warnings are printed: human, json. `json` prints one JSON object \
+                per diagnostic to stderr"
+    )]
+    error_format: ErrorFormat,
+
     #[structopt(
         long = "prompt",
         short = "p",
@@ -147,6 +229,33 @@ pub struct Opt {
     )]
     no_std: bool,
 
+    #[structopt(
+        long = "profile",
+        parse(from_os_str),
+        help = "Profiles the executed files. Writes the profile as folded stacks (for use with \
+                flamegraph tools) to the given file and prints the most expensive functions to \
+                stderr"
+    )]
+    profile: Option<PathBuf>,
+
+    #[structopt(
+        long = "coverage",
+        parse(from_os_str),
+        conflicts_with = "profile",
+        help = "Records the line and branch coverage of the executed files. Writes `lcov.info` \
+                and an HTML report to the given directory. Only modules with a source file \
+                relative to the current directory are included. Optimizations are disabled for \
+                every module compiled by the run, not only the measured ones"
+    )]
+    coverage: Option<PathBuf>,
+
+    #[structopt(
+        long = "coverage-threshold",
+        requires = "coverage",
+        help = "Exits with an error if less than the given percentage of lines were executed"
+    )]
+    coverage_threshold: Option<f64>,
+
     #[structopt(name = "FILE", help = "Executes each file as a gluon program")]
     input: Vec<String>,
 
@@ -154,17 +263,157 @@ pub struct Opt {
     subcommand_opt: Option<SubOpt>,
 }
 
-async fn run_files<I>(vm: &Thread, files: I) -> Result<()>
+async fn run_files<I>(vm: &Thread, color: Color, error_format: ErrorFormat, files: I) -> Result<()>
 where
     I: IntoIterator,
     I::Item: AsRef<str>,
 {
+    let mut reported = BTreeSet::new();
     for file in files {
         vm.load_file_async(file.as_ref()).await?;
+        emit_new_warnings(vm, color, error_format, &mut reported)?;
+    }
+    Ok(())
+}
+
+/// Prints the warnings of every typechecked module which is not in `reported`, including the
+/// modules imported by the loaded files. The standard library is skipped.
+fn emit_new_warnings(
+    vm: &Thread,
+    color: Color,
+    error_format: ErrorFormat,
+    reported: &mut BTreeSet<String>,
+) -> Result<()> {
+    let db = vm.get_database();
+    let mut modules = db.typechecked_modules();
+    modules.sort_by(|l, r| l.0.cmp(&r.0));
+    for (module, value) in modules {
+        if module.starts_with("std.") || !reported.insert(module) {
+            continue;
+        }
+        if value.warnings.has_errors() {
+            print_warnings(&db, color, error_format, value.warnings)?;
+        }
     }
     Ok(())
 }
 
+/// Prints the warnings reported while compiling `file`
+fn emit_warnings(vm: &Thread, color: Color, error_format: ErrorFormat, file: &str) -> Result<()> {
+    let db = vm.get_database();
+    match db.peek_typechecked_module(&filename_to_module(file)) {
+        Some(value) if value.warnings.has_errors() => {
+            print_warnings(&db, color, error_format, value.warnings)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn print_warnings(
+    db: &gluon::import::DatabaseSnapshot,
+    color: Color,
+    error_format: ErrorFormat,
+    warnings: gluon::check::lint::Warnings,
+) -> Result<()> {
+    let warnings = InFile::new(db.code_map(), warnings);
+    match error_format {
+        ErrorFormat::Human => {
+            let mut stderr = termcolor::StandardStream::stderr(color.into());
+            warnings.emit(&mut stderr)?;
+            writeln!(stderr)?;
+        }
+        ErrorFormat::Json => emit_json(&warnings.diagnostics())?,
+    }
+    Ok(())
+}
+
+/// Prints each diagnostic as a JSON object on its own line
+fn emit_json(diagnostics: &[StructuredDiagnostic]) -> io::Result<()> {
+    let stderr = io::stderr();
+    let mut stderr = stderr.lock();
+    for diagnostic in diagnostics {
+        serde_json::to_writer(&mut stderr, diagnostic)?;
+        writeln!(stderr)?;
+    }
+    Ok(())
+}
+
+/// Prints `err` in the requested format
+fn emit_error(err: &gluon::Error, color: Color, error_format: ErrorFormat) -> io::Result<()> {
+    match error_format {
+        ErrorFormat::Human => {
+            let mut stderr = termcolor::StandardStream::stderr(color.into());
+            err.emit(&mut stderr)?;
+            writeln!(stderr)
+        }
+        ErrorFormat::Json => emit_json(&err.diagnostics()),
+    }
+}
+
+/// Returns every `.glu` file in `input`, searching directories recursively
+fn gluon_files(input: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = input
+        .iter()
+        .flat_map(|arg| {
+            WalkDir::new(arg).into_iter().filter_map(|entry| {
+                entry.ok().and_then(|entry| {
+                    if entry.file_type().is_file()
+                        && entry.path().extension() == Some(OsStr::new("glu"))
+                    {
+                        Some(entry.path().to_owned())
+                    } else {
+                        None
+                    }
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// Number of functions shown in the report printed by `--profile`
+const PROFILE_REPORT_LENGTH: usize = 20;
+
+fn write_profile(path: &Path, profile: &Profile) -> Result<()> {
+    let mut file = io::BufWriter::new(fs::File::create(path)?);
+    profile.write_folded(&mut file)?;
+    file.flush()?;
+
+    profile.write_report(&mut io::stderr(), PROFILE_REPORT_LENGTH)?;
+    Ok(())
+}
+
+fn module_path(module: &str) -> PathBuf {
+    Path::new(&module.replace('.', "/")).with_extension("glu")
+}
+
+fn write_coverage(vm: &Thread, dir: &Path, coverage: &mut Coverage) -> Result<()> {
+    coverage.retain(|module| module_path(module).is_file());
+
+    fs::create_dir_all(dir)?;
+    let mut lcov = io::BufWriter::new(fs::File::create(dir.join("lcov.info"))?);
+    coverage.write_lcov(&mut lcov, |module| {
+        module_path(module).display().to_string()
+    })?;
+    lcov.flush()?;
+
+    let database = vm.get_database();
+    coverage.write_html(dir, |module| {
+        database
+            .get_filemap(module)
+            .map(|file_map| file_map.src().to_string())
+    })?;
+
+    eprintln!(
+        "Coverage: {:.1}% of lines, {:.1}% of branches",
+        coverage.line_rate(),
+        coverage.branch_rate()
+    );
+    Ok(())
+}
+
 #[cfg(feature = "env_logger")]
 fn init_env_logger() {
     let _ = ::env_logger::try_init();
@@ -229,32 +478,45 @@ async fn fmt_stdio(opt: &Opt) -> Result<()> {
     Ok(())
 }
 
+fn explain(opt: &ExplainOpt) -> std::result::Result<(), Error> {
+    use crate::base::error_codes;
+
+    match opt.code {
+        Some(ref code) => {
+            let error_code = error_codes::find(code)
+                .ok_or_else(|| failure::err_msg(format!("Unknown error code `{}`", code)))?;
+            print!(
+                "{}: {}\n\n{}",
+                error_code.code, error_code.title, error_code.explanation
+            );
+        }
+        None => {
+            for error_code in error_codes::ERROR_CODES {
+                println!("{}: {}", error_code.code, error_code.title);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn dump(vm: &Thread, opt: &DumpOpt) -> Result<()> {
+    let source = fs::read_to_string(&opt.input)?;
+    let module = filename_to_module(&opt.input.display().to_string());
+    let value = vm.compile_str_async(&module, &source).await?;
+    if opt.core {
+        println!("{}", value.core_expr.value.expr().pretty_string(100));
+    } else {
+        print!("{}", value.module.disassemble());
+    }
+    Ok(())
+}
+
 async fn run(opt: &Opt, color: Color, vm: &Thread) -> std::result::Result<(), Error> {
     vm.global_env().set_debug_level(opt.debug_level.clone());
     match opt.subcommand_opt {
         Some(SubOpt::Fmt(ref fmt_opt)) => {
             if !fmt_opt.input.is_empty() {
-                let mut gluon_files = fmt_opt
-                    .input
-                    .iter()
-                    .flat_map(|arg| {
-                        WalkDir::new(arg).into_iter().filter_map(|entry| {
-                            entry.ok().and_then(|entry| {
-                                if entry.file_type().is_file()
-                                    && entry.path().extension() == Some(OsStr::new("glu"))
-                                {
-                                    Some(entry.path().to_owned())
-                                } else {
-                                    None
-                                }
-                            })
-                        })
-                    })
-                    .collect::<Vec<_>>();
-                gluon_files.sort();
-                gluon_files.dedup();
-
-                for file in gluon_files {
+                for file in gluon_files(&fmt_opt.input) {
                     fmt_file(&file, opt).await?;
                 }
             } else {
@@ -267,6 +529,12 @@ async fn run(opt: &Opt, color: Color, vm: &Thread) -> std::result::Result<(), Er
             let thread = new_vm_async().await;
             gluon_doc::generate_for_path(&thread, input, output)?;
         }
+        Some(SubOpt::Explain(ref explain_opt)) => explain(explain_opt)?,
+        Some(SubOpt::Test(ref test_opt)) => test::run(vm, test_opt).await?,
+        Some(SubOpt::Check(ref check_opt)) => {
+            check::run(vm, color, opt.error_format, check_opt).await?
+        }
+        Some(SubOpt::Dump(ref dump_opt)) => dump(vm, dump_opt).await?,
         None => {
             if opt.interactive {
                 let prompt = opt.prompt.clone();
@@ -274,7 +542,36 @@ async fn run(opt: &Opt, color: Color, vm: &Thread) -> std::result::Result<(), Er
                 let use_std_lib = !opt.no_std;
                 repl::run(color, &prompt, debug_level, use_std_lib).await?;
             } else if !opt.input.is_empty() {
-                run_files(&vm, &opt.input).await?;
+                let profiler = opt.profile.as_ref().map(|_| vm.start_profiling());
+                let coverage_recorder = opt.coverage.as_ref().map(|_| {
+                    // Inlining would attribute code to the lines it were inlined into
+                    vm.get_database_mut().optimize(false).set_coverage(true);
+                    vm.start_coverage()
+                });
+                let result = run_files(&vm, color, opt.error_format, &opt.input).await;
+                if let (Some(path), Some(profiler)) = (&opt.profile, profiler) {
+                    write_profile(path, &profiler.finish())?;
+                }
+                let coverage = match (&opt.coverage, coverage_recorder) {
+                    (Some(dir), Some(recorder)) => {
+                        let mut coverage = recorder.finish();
+                        write_coverage(vm, dir, &mut coverage)?;
+                        Some(coverage)
+                    }
+                    _ => None,
+                };
+                result?;
+
+                if let (Some(coverage), Some(threshold)) = (coverage, opt.coverage_threshold) {
+                    if coverage.line_rate() < threshold {
+                        return Err(gluon::Error::from(format!(
+                            "Line coverage of {:.1}% is below the threshold of {}%",
+                            coverage.line_rate(),
+                            threshold
+                        ))
+                        .into());
+                    }
+                }
             } else {
                 writeln!(io::stderr(), "{}", Opt::clap().get_matches().usage())
                     .expect("Error writing help to stderr");
@@ -299,7 +596,28 @@ async fn main() {
     let result = run(&opt, opt.color, &vm).await;
     if let Err(err) = result {
         match err {
-            Error::Gluon(gluon::Error::VM(VMError::Message(_))) => eprintln!("{}\n{}", err, vm.context().stacktrace(0)),
+            _ if opt.error_format == ErrorFormat::Json => {
+                let diagnostics = match err {
+                    Error::Gluon(ref err @ gluon::Error::VM(VMError::Message(_))) => {
+                        let mut diagnostic =
+                            StructuredDiagnostic::new(Severity::Error, err.to_string());
+                        diagnostic
+                            .notes
+                            .push(vm.context().stacktrace(0).to_string());
+                        vec![diagnostic]
+                    }
+                    Error::Gluon(ref err) => err.diagnostics(),
+                    Error::Failure(ref err) => {
+                        vec![StructuredDiagnostic::new(Severity::Error, err.to_string())]
+                    }
+                };
+                if let Err(err) = emit_json(&diagnostics) {
+                    eprintln!("{}", err);
+                }
+            }
+            Error::Gluon(gluon::Error::VM(VMError::Message(_))) => {
+                eprintln!("{}\n{}", err, vm.context().stacktrace(0))
+            }
             Error::Gluon(err) => {
                 let mut stderr = termcolor::StandardStream::stderr(color.into());
                 if let Err(err) = err.emit(&mut stderr) {
@@ -51,6 +51,19 @@ fn type_of_expr(args: WithVM<&str>) -> impl Future<Output = IO<Result<String, St
     }
 }
 
+fn bytecode_of_expr(args: WithVM<&str>) -> impl Future<Output = IO<Result<String, String>>> {
+    let WithVM { vm, value: args } = args;
+    let args = args.to_string();
+    let vm = vm.new_thread().unwrap();
+
+    async move {
+        IO::Value(match vm.compile_str_async("<repl>", &args).await {
+            Ok(value) => Ok(value.module.disassemble().to_string()),
+            Err(msg) => Err(format!("{}", msg)),
+        })
+    }
+}
+
 fn find_kind(args: WithVM<&str>) -> IO<Result<String, String>> {
     let vm = args.vm;
     let args = args.value.trim();
@@ -549,7 +562,7 @@ fn set_globals(
             );
             set_globals(vm, db, pattern, typ, value)
         }
-        Pattern::Constructor(..) | Pattern::Literal(_) | Pattern::Error => {
+        Pattern::Constructor(..) | Pattern::Literal(_) | Pattern::Or(_) | Pattern::Error => {
             Err(VMError::Message("The repl cannot bind variables from this pattern".into()).into())
         }
     }
@@ -636,6 +649,7 @@ fn load_repl(vm: &Thread) -> vm::Result<vm::ExternModule> {
             type Color => Color,
             type Settings => Settings<'static>,
             type_of_expr => primitive!(1, async fn type_of_expr),
+            bytecode_of_expr => primitive!(1, async fn bytecode_of_expr),
             find_info => primitive!(1, find_info),
             find_kind => primitive!(1, find_kind),
             parse_color => primitive!(1, "parse_color", |s: &str| s.parse::<Color>()),
@@ -757,6 +771,25 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn bytecode_of_expr() {
+        let _ = env_logger::try_init();
+        let vm = new_vm().await;
+        compile_repl(&vm)
+            .await
+            .unwrap_or_else(|err| panic!("{}", err));
+        let mut bytecode_of: FunctionRef<QueryFn> =
+            vm.get_global("repl.prim.bytecode_of_expr").unwrap();
+        match bytecode_of.call_async(r#""abc""#).await {
+            Ok(IO::Value(Ok(bytecode))) => {
+                assert!(bytecode.contains("function <repl>"), "{}", bytecode);
+                assert!(bytecode.contains(r#"PushString(0)"#), "{}", bytecode);
+                assert!(bytecode.contains(r#"; "abc""#), "{}", bytecode);
+            }
+            x => assert!(false, "{:?}", x),
+        }
+    }
+
     #[tokio::test]
     async fn find_kind() {
         let _ = env_logger::try_init();
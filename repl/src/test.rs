@@ -0,0 +1,417 @@
+//! Implementation of the `gluon test` subcommand which discovers and runs `std.test` test cases
+
+use std::{
+    fs,
+    io::{self, Write},
+    panic::AssertUnwindSafe,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use futures::{channel::mpsc, future, prelude::*};
+use structopt::StructOpt;
+
+use gluon::{
+    base::{
+        filename_to_module, resolve,
+        symbol::Symbol,
+        types::{self, ArcType, NullInterner, Type, TypeExt},
+    },
+    query::Compilation,
+    vm::api::{de::De, generic::A, Getable, Hole, OpaqueValue, OwnedFunction, VmType, IO},
+    RootedThread, Thread, ThreadExt,
+};
+
+use crate::{gluon_files, Error};
+
+#[derive(StructOpt)]
+#[structopt(about = "Runs the tests defined with `std.test`")]
+pub struct TestOpt {
+    #[structopt(
+        name = "PATH",
+        parse(from_os_str),
+        help = "Files or directories to search for tests (default: the current directory)"
+    )]
+    input: Vec<PathBuf>,
+
+    #[structopt(
+        long = "filter",
+        short = "f",
+        help = "Only runs the tests whose name contains the filter. May be given multiple times"
+    )]
+    filter: Vec<String>,
+
+    #[structopt(long = "jobs", short = "j", help = "How many threads to run tests on")]
+    jobs: Option<usize>,
+
+    #[structopt(
+        long = "junit",
+        parse(from_os_str),
+        help = "Writes a JUnit XML report of the test run to the given file"
+    )]
+    junit: Option<PathBuf>,
+}
+
+macro_rules! define_test_type {
+    ($name:ident $($args: ident)*) => {
+        impl VmType for $name {
+            type Type = $name;
+            fn make_type(vm: &Thread) -> ArcType {
+                let typ = concat!("std.test.", stringify!($name));
+                Type::app(
+                    vm.get_env().find_type_info(typ).unwrap().into_type(),
+                    vec![$($args::make_type(vm),)* Type::unit()].into_iter().collect(),
+                )
+            }
+        }
+    };
+}
+
+struct TestEffIO;
+
+define_test_type! { TestEffIO A }
+
+type TestEff = OpaqueValue<RootedThread, TestEffIO>;
+type TestFn = OwnedFunction<fn(()) -> TestEff>;
+
+#[derive(Deserialize)]
+enum TestCase {
+    Test(String, TestFn),
+    Group(String, Vec<TestCase>),
+}
+
+define_test_type! { TestCase Hole }
+
+impl TestCase {
+    /// Flattens the test tree into each individual test, giving each its own thread to run on
+    fn collect(self, prefix: &str, tests: &mut Vec<Test>) -> gluon::Result<()> {
+        match self {
+            TestCase::Test(name, test) => {
+                let thread = test.vm().new_thread()?;
+                tests.push(Test {
+                    name: format!("{}/{}", prefix, name),
+                    run: Ok(TestFn::from_value(&thread, test.get_variant())),
+                });
+            }
+            TestCase::Group(name, cases) => {
+                let prefix = format!("{}/{}", prefix, name);
+                for case in cases {
+                    case.collect(&prefix, tests)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Test {
+    name: String,
+    /// The test function or the reason the test could not be loaded
+    run: Result<TestFn, String>,
+}
+
+struct TestResult {
+    name: String,
+    duration: Duration,
+    outcome: Result<(), String>,
+}
+
+impl TestResult {
+    fn module(&self) -> &str {
+        self.name.split('/').next().unwrap_or("")
+    }
+}
+
+async fn run_test(test: TestFn) -> gluon::Result<()> {
+    let mut test = test;
+    let test = test.call_async(()).await?;
+    let mut run_io: OwnedFunction<fn(TestEff) -> IO<()>> =
+        test.vm().get_global("std.test.run_io")?;
+    run_io.call_async(test).await?;
+    Ok(())
+}
+
+fn is_test_file(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .map_or(false, |stem| stem.ends_with("_test"))
+}
+
+/// Returns the fields of `typ` (or `None` for `typ` itself) which are `std.test.TestCase` values
+fn test_case_fields(vm: &Thread, test_case: &Symbol, typ: &ArcType) -> Vec<Option<String>> {
+    let is_test_case = |typ: &ArcType| {
+        types::remove_forall(typ).name().map_or(false, |name| {
+            name.definition_name() == test_case.definition_name()
+        })
+    };
+    if is_test_case(typ) {
+        return vec![None];
+    }
+    let typ = resolve::remove_aliases(
+        &vm.get_env(),
+        &mut NullInterner,
+        types::remove_forall(typ).clone(),
+    );
+    typ.row_iter()
+        .filter(|field| is_test_case(&field.typ))
+        .map(|field| Some(field.name.declared_name().to_string()))
+        .collect()
+}
+
+/// Typechecks `file` and, if it exports any test cases, loads them
+async fn discover(vm: &Thread, test_case: &Symbol, file: &Path) -> Vec<Test> {
+    let filename = file.display().to_string();
+    let module = filename_to_module(&filename);
+    let load_error = |message: String| {
+        vec![Test {
+            name: module.clone(),
+            run: Err(message),
+        }]
+    };
+
+    let typ = {
+        let mut db = vm.get_database();
+        match db.typechecked_module(module.clone(), None).await {
+            Ok(value) => value.typ,
+            Err((_, err)) if is_test_file(file) => {
+                return load_error(err.emit_string().unwrap_or_else(|_| err.to_string()))
+            }
+            Err(_) => return Vec::new(),
+        }
+    };
+
+    let fields = test_case_fields(vm, test_case, &typ);
+    if fields.is_empty() {
+        return if is_test_file(file) {
+            load_error(format!(
+                "`{}` does not export any `TestCase` values",
+                filename
+            ))
+        } else {
+            Vec::new()
+        };
+    }
+
+    let mut tests = Vec::new();
+    let result = async {
+        vm.load_file_async(&filename).await?;
+        for field in fields {
+            let global = match field {
+                Some(ref field) => format!("{}.{}", module, field),
+                None => module.clone(),
+            };
+            let De(test_case) = vm.get_global::<De<TestCase>>(&global)?;
+            let prefix = match field {
+                Some(ref field) => format!("{}/{}", module, field),
+                None => module.clone(),
+            };
+            test_case.collect(&prefix, &mut tests)?;
+        }
+        Ok::<_, gluon::Error>(())
+    }
+    .await;
+    match result {
+        Ok(()) => tests,
+        Err(err) => load_error(err.emit_string().unwrap_or_else(|_| err.to_string())),
+    }
+}
+
+/// Runs every test on a separate, multi threaded runtime. Results are sent back as soon as each
+/// test finishes.
+fn spawn_tests(
+    tests: Vec<Test>,
+    jobs: Option<usize>,
+) -> io::Result<mpsc::UnboundedReceiver<TestResult>> {
+    let mut builder = tokio::runtime::Builder::new();
+    builder.threaded_scheduler().enable_all();
+    if let Some(jobs) = jobs {
+        builder.core_threads(jobs);
+    }
+    let mut runtime = builder.build()?;
+
+    let (sender, receiver) = mpsc::unbounded();
+    std::thread::spawn(move || {
+        runtime.block_on(async move {
+            let handles = tests.into_iter().map(|test| {
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    let start = Instant::now();
+                    let outcome = match test.run {
+                        Ok(run) => AssertUnwindSafe(run_test(run))
+                            .catch_unwind()
+                            .await
+                            .unwrap_or_else(|_| Err("The test panicked".to_string().into()))
+                            .map_err(|err| err.to_string()),
+                        Err(err) => Err(err),
+                    };
+                    let _ = sender.unbounded_send(TestResult {
+                        name: test.name,
+                        duration: start.elapsed(),
+                        outcome,
+                    });
+                })
+            });
+            future::join_all(handles).await
+        })
+    });
+    Ok(receiver)
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn write_junit(out: &mut impl Write, results: &[TestResult], duration: Duration) -> io::Result<()> {
+    let failures = results.iter().filter(|r| r.outcome.is_err()).count();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<testsuites name="gluon" tests="{}" failures="{}" time="{:.3}">"#,
+        results.len(),
+        failures,
+        duration.as_secs_f64()
+    )?;
+
+    let mut start = 0;
+    while start < results.len() {
+        let module = results[start].module();
+        let end = start
+            + results[start..]
+                .iter()
+                .take_while(|result| result.module() == module)
+                .count();
+        let suite = &results[start..end];
+        writeln!(
+            out,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" time="{:.3}">"#,
+            xml_escape(module),
+            suite.len(),
+            suite.iter().filter(|r| r.outcome.is_err()).count(),
+            suite
+                .iter()
+                .map(|r| r.duration)
+                .sum::<Duration>()
+                .as_secs_f64()
+        )?;
+        for result in suite {
+            write!(
+                out,
+                r#"    <testcase name="{}" classname="{}" time="{:.3}""#,
+                xml_escape(&result.name),
+                xml_escape(module),
+                result.duration.as_secs_f64()
+            )?;
+            match result.outcome {
+                Ok(()) => writeln!(out, "/>")?,
+                Err(ref message) => {
+                    writeln!(out, ">")?;
+                    writeln!(
+                        out,
+                        r#"      <failure message="{}">{}</failure>"#,
+                        xml_escape(message.lines().next().unwrap_or("")),
+                        xml_escape(message)
+                    )?;
+                    writeln!(out, "    </testcase>")?;
+                }
+            }
+        }
+        writeln!(out, "  </testsuite>")?;
+        start = end;
+    }
+    writeln!(out, "</testsuites>")
+}
+
+pub async fn run(vm: &Thread, opt: &TestOpt) -> Result<(), Error> {
+    let start = Instant::now();
+
+    vm.load_file_async("std/test.glu").await?;
+    let test_case = vm
+        .find_type_info("std.test.TestCase")
+        .map_err(gluon::Error::from)?
+        .name
+        .clone();
+
+    let input = if opt.input.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        opt.input.clone()
+    };
+
+    let mut tests = Vec::new();
+    for file in gluon_files(&input) {
+        tests.extend(discover(vm, &test_case, &file).await);
+    }
+    tests.retain(|test| {
+        opt.filter.is_empty()
+            || opt
+                .filter
+                .iter()
+                .any(|filter| test.name.contains(&filter[..]))
+    });
+
+    println!("running {} tests", tests.len());
+    let mut receiver = spawn_tests(tests, opt.jobs).map_err(gluon::Error::from)?;
+    let mut results = Vec::new();
+    while let Some(result) = receiver.next().await {
+        println!(
+            "test {} ... {} ({:.3}s)",
+            result.name,
+            if result.outcome.is_ok() {
+                "ok"
+            } else {
+                "FAILED"
+            },
+            result.duration.as_secs_f64()
+        );
+        results.push(result);
+    }
+    results.sort_by(|l, r| l.name.cmp(&r.name));
+    let duration = start.elapsed();
+
+    let failed: Vec<_> = results
+        .iter()
+        .filter_map(|result| result.outcome.as_ref().err().map(|err| (&result.name, err)))
+        .collect();
+    if !failed.is_empty() {
+        println!("\nfailures:");
+        for (name, err) in &failed {
+            println!("\n---- {} ----\n{}", name, err);
+        }
+    }
+    println!(
+        "\ntest result: {}. {} passed; {} failed; finished in {:.2}s",
+        if failed.is_empty() { "ok" } else { "FAILED" },
+        results.len() - failed.len(),
+        failed.len(),
+        duration.as_secs_f64()
+    );
+
+    if let Some(ref path) = opt.junit {
+        let mut out = io::BufWriter::new(fs::File::create(path).map_err(gluon::Error::from)?);
+        write_junit(&mut out, &results, duration)
+            .and_then(|()| out.flush())
+            .map_err(gluon::Error::from)?;
+    }
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(failure::err_msg(format!(
+            "{} of {} tests failed",
+            failed.len(),
+            results.len()
+        ))
+        .into())
+    }
+}
@@ -61,3 +61,258 @@ fn issue_365_run_io_from_command_line() {
     }
     assert_eq!(String::from_utf8_lossy(&output.stdout), "123\n");
 }
+
+#[test]
+fn explain_error_code() {
+    let path = env::args().next().unwrap();
+    let gluon_path = Path::new(&path[..])
+        .parent()
+        .and_then(|p| p.parent())
+        .expect("folder")
+        .join("gluon");
+
+    let output = Command::new(&*gluon_path)
+        .args(&["explain", "e0101"])
+        .output()
+        .unwrap_or_else(|err| panic!("{}\nWhen opening `{}`", err, gluon_path.display()));
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("E0101: Undefined variable\n\n"),
+        "{}",
+        stdout
+    );
+
+    let output = Command::new(&*gluon_path)
+        .args(&["explain", "E9999"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "Unknown error code `E9999`\n"
+    );
+}
+
+#[test]
+fn json_error_format() {
+    let path = env::args().next().unwrap();
+    let gluon_path = Path::new(&path[..])
+        .parent()
+        .and_then(|p| p.parent())
+        .expect("folder")
+        .join("gluon");
+
+    let dir = env::temp_dir();
+    std::fs::write(
+        dir.join("gluon_json_error_format.glu"),
+        "let x : String = 1\nx\n",
+    )
+    .unwrap();
+
+    let output = Command::new(&*gluon_path)
+        .arg("--error-format=json")
+        .arg("gluon_json_error_format.glu")
+        .current_dir(&dir)
+        .output()
+        .unwrap_or_else(|err| panic!("{}\nWhen opening `{}`", err, gluon_path.display()));
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<_> = stderr.lines().collect();
+    assert_eq!(lines.len(), 1, "{}", stderr);
+    let diagnostic: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+    assert_eq!(diagnostic["code"], "E0106");
+    assert_eq!(diagnostic["severity"], "error");
+    assert_eq!(diagnostic["primary"]["start"]["line"], 1);
+    assert_eq!(diagnostic["primary"]["start"]["column"], 18);
+}
+
+#[test]
+fn test_subcommand() {
+    let path = env::args().next().unwrap();
+    let gluon_path = Path::new(&path[..])
+        .parent()
+        .and_then(|p| p.parent())
+        .expect("folder")
+        .join("gluon");
+
+    let dir = env::temp_dir().join("gluon_test_subcommand");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("arith_test.glu"),
+        r#"
+let { TestEff, assert_eq, test, group, ? } = import! std.test
+let { (<|) } = import! std.function
+let { ? } = import! std.int
+
+group "arith" [
+    test "add" <| \_ -> assert_eq (1 + 1) 2,
+    test "sub" <| \_ -> assert_eq (1 - 1) 1,
+]
+"#,
+    )
+    .unwrap();
+
+    let output = Command::new(&*gluon_path)
+        .args(&["test", "--junit", "report.xml"])
+        .current_dir(&dir)
+        .output()
+        .unwrap_or_else(|err| panic!("{}\nWhen opening `{}`", err, gluon_path.display()));
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("test arith_test/arith/add ... ok"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("test arith_test/arith/sub ... FAILED"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("1 passed; 1 failed"), "{}", stdout);
+
+    let report = std::fs::read_to_string(dir.join("report.xml")).unwrap();
+    assert!(
+        report.contains(r#"<testsuite name="arith_test" tests="2" failures="1""#),
+        "{}",
+        report
+    );
+
+    let output = Command::new(&*gluon_path)
+        .args(&["test", "--filter", "add"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("1 passed; 0 failed"), "{}", stdout);
+}
+
+#[test]
+fn check_subcommand() {
+    let path = env::args().next().unwrap();
+    let gluon_path = Path::new(&path[..])
+        .parent()
+        .and_then(|p| p.parent())
+        .expect("folder")
+        .join("gluon");
+
+    let dir = env::temp_dir().join("gluon_check_subcommand");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("bad.glu"), "let x : String = 1\nx\n").unwrap();
+    std::fs::write(
+        dir.join("main.glu"),
+        "let io = import! std.io\nio.println \"executed\"\n",
+    )
+    .unwrap();
+
+    let output = Command::new(&*gluon_path)
+        .args(&["--color", "never", "check"])
+        .current_dir(&dir)
+        .output()
+        .unwrap_or_else(|err| panic!("{}\nWhen opening `{}`", err, gluon_path.display()));
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("error[E0106]"), "{}", stderr);
+    assert!(stderr.contains("<bad>:1:18"), "{}", stderr);
+    assert!(
+        stderr.contains("1 of 2 modules failed to typecheck"),
+        "{}",
+        stderr
+    );
+
+    let output = Command::new(&*gluon_path)
+        .args(&["check", "main.glu"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+}
+
+#[test]
+fn dump_subcommand() {
+    let path = env::args().next().unwrap();
+    let gluon_path = Path::new(&path[..])
+        .parent()
+        .and_then(|p| p.parent())
+        .expect("folder")
+        .join("gluon");
+
+    let dir = env::temp_dir().join("gluon_dump_subcommand");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("dump.glu"), "let x = \"abc\"\nx\n").unwrap();
+
+    let output = Command::new(&*gluon_path)
+        .args(&["dump", "--bytecode", "dump.glu"])
+        .current_dir(&dir)
+        .output()
+        .unwrap_or_else(|err| panic!("{}\nWhen opening `{}`", err, gluon_path.display()));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(stdout.contains("function dump "), "{}", stdout);
+    assert!(stdout.contains("; \"abc\""), "{}", stdout);
+
+    let output = Command::new(&*gluon_path)
+        .args(&["dump", "--core", "dump.glu"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(stdout.contains("\"abc\""), "{}", stdout);
+}
+
+#[test]
+fn warnings_of_imported_modules() {
+    let path = env::args().next().unwrap();
+    let gluon_path = Path::new(&path[..])
+        .parent()
+        .and_then(|p| p.parent())
+        .expect("folder")
+        .join("gluon");
+
+    let dir = env::temp_dir().join("gluon_warnings_of_imported_modules");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("unused.glu"), "let unused = 1\n{ x = 2 }\n").unwrap();
+    std::fs::write(
+        dir.join("main.glu"),
+        "let { x } = import! unused\nlet y = x\nx\n",
+    )
+    .unwrap();
+
+    let output = Command::new(&*gluon_path)
+        .arg("--error-format=json")
+        .arg("main.glu")
+        .current_dir(&dir)
+        .output()
+        .unwrap_or_else(|err| panic!("{}\nWhen opening `{}`", err, gluon_path.display()));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+
+    let mut messages: Vec<_> = stderr
+        .lines()
+        .map(|line| {
+            let diagnostic: serde_json::Value = serde_json::from_str(line).unwrap();
+            diagnostic["message"].as_str().unwrap().to_string()
+        })
+        .collect();
+    messages.sort();
+    assert_eq!(
+        messages,
+        ["Unused variable `unused`", "Unused variable `y`"],
+        "{}",
+        stderr
+    );
+}
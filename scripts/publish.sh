@@ -15,6 +15,9 @@ declare -a PROJECTS=(
     gluon_c-api
     gluon_doc
     gluon_repl
+    gluon_framing
+    gluon_debugger
+    gluon_language_server
 )
 
 for PROJECT in "${PROJECTS[@]}"
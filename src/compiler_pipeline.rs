@@ -23,11 +23,14 @@ use crate::{
         error::{Errors, InFile},
         fnv::FnvMap,
         metadata::Metadata,
-        resolve,
+        pos, resolve,
         symbol::{Name, NameBuf, Symbol, SymbolModule},
         types::{ArcType, NullInterner, Type, TypeCache},
     },
-    check::{metadata, rename},
+    check::{
+        lint::{self, Warnings},
+        metadata, rename,
+    },
     query::{env, Compilation, CompilerDatabase},
     vm::{
         compiler::CompiledModule,
@@ -512,6 +515,8 @@ pub struct TypecheckValue<E> {
     pub typ: ArcType,
     pub metadata_map: FnvMap<Symbol, Arc<Metadata>>,
     pub metadata: Arc<Metadata>,
+    /// Warnings which were reported while typechecking
+    pub warnings: Warnings,
 }
 
 impl<E> TypecheckValue<E> {
@@ -521,12 +526,14 @@ impl<E> TypecheckValue<E> {
             typ,
             metadata_map,
             metadata,
+            warnings,
         } = self;
         TypecheckValue {
             expr: f(expr),
             typ,
             metadata_map,
             metadata,
+            warnings,
         }
     }
 }
@@ -603,8 +610,8 @@ fn typecheck_expr(
     file: &str,
     expected_type: Option<&ArcType>,
     metadata_map: &mut FnvMap<Symbol, Arc<Metadata>>,
-) -> Result<ArcType> {
-    use crate::check::typecheck::Typecheck;
+) -> Result<(ArcType, Warnings)> {
+    use crate::check::typecheck::{SpannedTypeError, TypeError, Typecheck};
     let env = env(compiler.database);
     let (arena, expr) = expr.arena_expr();
     let mut tc = Typecheck::new(
@@ -616,8 +623,40 @@ fn typecheck_expr(
         arena.borrow(),
     );
 
-    tc.typecheck_expr_expected(expr, expected_type)
-        .map_err(|err| InFile::new(compiler.database.state().code_map.clone(), err).into())
+    let typ = tc.typecheck_expr_expected(expr, expected_type);
+    let exhaustiveness = match typ {
+        Ok(_) => crate::check::exhaustiveness::check_expr(
+            &env,
+            &mut &*thread.global_env().type_cache(),
+            expr,
+        ),
+        Err(_) => Ok(()),
+    };
+
+    let code_map = compiler.database.state().code_map.clone();
+    let typ = typ.map_err(|err| InFile::new(code_map.clone(), err))?;
+
+    let mut warnings = match compiler.get_filemap(file) {
+        Some(filemap) => lint::lint_expr(filemap.span(), expr),
+        None => Warnings::new(),
+    };
+
+    if let Err(errors) = exhaustiveness {
+        if compiler.compiler_settings().exhaustiveness_errors {
+            let errors: Errors<SpannedTypeError<Symbol>> = errors
+                .into_iter()
+                .map(|err| pos::spanned(err.span, TypeError::from(err.value).into()))
+                .collect();
+            return Err(InFile::new(code_map, errors).into());
+        }
+        warnings.extend(
+            errors
+                .into_iter()
+                .map(|err| pos::spanned(err.span, lint::Warning::Exhaustiveness(err.value))),
+        );
+    }
+
+    Ok((typ, warnings))
 }
 
 #[async_trait::async_trait]
@@ -643,7 +682,7 @@ where
             metadata,
         } = self;
 
-        let typ = match typecheck_expr(
+        let (typ, warnings) = match typecheck_expr(
             expr.borrow_mut(),
             compiler,
             thread,
@@ -651,7 +690,7 @@ where
             expected_type,
             &mut metadata_map,
         ) {
-            Ok(typ) => typ,
+            Ok(value) => value,
             Err(err) => {
                 return Err((
                     Some(TypecheckValue {
@@ -663,6 +702,7 @@ where
                         expr,
                         metadata_map,
                         metadata,
+                        warnings: Warnings::new(),
                     }),
                     err,
                 ))
@@ -682,6 +722,7 @@ where
             typ,
             metadata_map,
             metadata,
+            warnings,
         })
     }
 }
@@ -694,6 +735,8 @@ pub struct CompileValue<E> {
     pub typ: ArcType,
     pub metadata: Arc<Metadata>,
     pub module: CompiledModule,
+    /// Warnings which were reported while compiling
+    pub warnings: Warnings,
 }
 
 impl<E> CompileValue<E> {
@@ -704,6 +747,7 @@ impl<E> CompileValue<E> {
             typ,
             metadata,
             module,
+            warnings,
         } = self;
         CompileValue {
             expr: f(expr),
@@ -711,6 +755,7 @@ impl<E> CompileValue<E> {
             typ,
             metadata,
             module,
+            warnings,
         }
     }
 }
@@ -784,6 +829,7 @@ where
                      metadata,
                      module,
                      core_expr,
+                     warnings,
                      ..
                  }| CompileValue {
                     expr: self.expr,
@@ -791,6 +837,7 @@ where
                     typ,
                     metadata,
                     module,
+                    warnings,
                 },
             )
     }
@@ -863,6 +910,7 @@ where
                 &source,
                 filename.to_string(),
                 settings.emit_debug_info,
+                settings.coverage,
             );
             compiler.compile_expr(core_expr.value.expr())?
         };
@@ -873,6 +921,7 @@ where
             typ: self.typ.clone(),
             metadata: self.metadata.clone(),
             module,
+            warnings: self.warnings.clone(),
         })
     }
 }
@@ -993,6 +1042,7 @@ where
             typ,
             mut module,
             metadata,
+            warnings: _,
         } = self;
         let run_io = compiler.database.compiler_settings().run_io;
         let module_id = Symbol::from(format!("@{}", name));
@@ -1166,6 +1216,7 @@ where
         typ,
         metadata,
         module,
+        warnings: _,
     } = self_
         .compile(compiler, thread, file, expr_str, arg)
         .await
@@ -3,6 +3,7 @@
 use std::{
     any::{Any, TypeId},
     borrow::Cow,
+    collections::BTreeSet,
     fs::File,
     io::Read,
     mem,
@@ -23,7 +24,7 @@ use {
 };
 
 use crate::base::{
-    ast::{self, expr_to_path, Expr, Literal, SpannedExpr},
+    ast::{self, expr_to_path, walk_expr, Expr, Literal, SpannedExpr, Visitor},
     filename_to_module, pos,
     symbol::Symbol,
     types::ArcType,
@@ -43,6 +44,9 @@ use crate::{
     IoError, ModuleCompiler, ThreadExt,
 };
 
+#[cfg(feature = "serialization")]
+pub(crate) mod cache;
+
 quick_error! {
     /// Error type for the import macro
     #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -67,9 +71,20 @@ quick_error! {
     }
 }
 
+impl Error {
+    /// The error code which identifies this kind of error (see `gluon explain`)
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::CyclicDependency(..) => "E0201",
+            Error::String(_) => "E0202",
+            Error::IO(_) => "E0203",
+        }
+    }
+}
+
 impl base::error::AsDiagnostic for Error {
     fn as_diagnostic(&self) -> codespan_reporting::Diagnostic {
-        codespan_reporting::Diagnostic::new_error(self.to_string())
+        codespan_reporting::Diagnostic::new_error(self.to_string()).with_code(self.code())
     }
 }
 
@@ -175,6 +190,7 @@ pub(crate) trait ImportApi: Send + Sync {
         module: &str,
         filename: &str,
     ) -> Result<Cow<'static, str>, Error>;
+    fn cache_dir(&self) -> Option<PathBuf>;
     async fn load_module(
         &self,
         compiler: &mut ModuleCompiler<'_>,
@@ -202,6 +218,9 @@ where
     ) -> Result<Cow<'static, str>, Error> {
         Self::get_module_source(self, use_standard_lib, module, filename)
     }
+    fn cache_dir(&self) -> Option<PathBuf> {
+        Self::cache_dir(self)
+    }
     async fn load_module(
         &self,
         compiler: &mut ModuleCompiler<'_>,
@@ -234,6 +253,7 @@ pub struct Import<I = DefaultImporter> {
     pub paths: RwLock<Vec<PathBuf>>,
     pub importer: I,
 
+    cache_dir: RwLock<Option<PathBuf>>,
     compiler: Mutex<CompilerDatabase>,
 }
 
@@ -272,6 +292,7 @@ impl<I> Import<I> {
     pub fn new(importer: I) -> Import<I> {
         Import {
             paths: RwLock::new(vec![PathBuf::from(".")]),
+            cache_dir: RwLock::new(None),
             compiler: CompilerDatabase::new_base(None).into(),
             importer: importer,
         }
@@ -286,6 +307,20 @@ impl<I> Import<I> {
         *self.paths.write().unwrap() = paths;
     }
 
+    /// Sets the directory where compiled modules are cached (`None` disables the cache).
+    ///
+    /// A cached module is keyed on a hash of its source, the compiler settings and the keys of
+    /// the modules it imports so it is only loaded while neither it nor any of its
+    /// dependencies have changed. Requires the `serialization` feature, without it no modules
+    /// are cached.
+    pub fn set_cache_dir(&self, dir: Option<PathBuf>) {
+        *self.cache_dir.write().unwrap() = dir;
+    }
+
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        self.cache_dir.read().unwrap().clone()
+    }
+
     pub fn modules(&self, compiler: &mut ModuleCompiler<'_>) -> Vec<Cow<'static, str>> {
         STD_LIBS
             .iter()
@@ -459,6 +494,28 @@ fn add_extern_module_(thread: &Thread, name: &str, loader: ExternLoader) {
         .set_extern_loader(name.into(), PtrEq(Arc::new(loader)));
 }
 
+/// Returns the modules which the typechecked `expr` imports
+pub fn imported_modules(expr: &SpannedExpr<Symbol>) -> BTreeSet<String> {
+    struct Imports(BTreeSet<String>);
+
+    impl<'a, 'ast> Visitor<'a, 'ast> for Imports {
+        type Ident = Symbol;
+
+        fn visit_expr(&mut self, e: &'a SpannedExpr<'ast, Symbol>) {
+            match &e.value {
+                Expr::Ident(id) if id.name.is_global() => {
+                    self.0.insert(id.name.definition_name().to_string());
+                }
+                _ => walk_expr(self, e),
+            }
+        }
+    }
+
+    let mut visitor = Imports(BTreeSet::new());
+    visitor.visit_expr(expr);
+    visitor.0
+}
+
 macro_rules! add_extern_module_if {
     (
         #[cfg($($features: tt)*)],
@@ -573,10 +630,7 @@ where
             let (tx, rx) = tokio::sync::oneshot::channel();
             spawn
                 .spawn(Box::pin(async move {
-                    let result = db
-                        .import(modulename)
-                        .await
-                        .map_err(|err| MacroError::message(err.to_string()));
+                    let result = db.import(modulename).await.map_err(MacroError::new);
                     drop(db); // Drop the database before sending the result, otherwise the forker may drop before the forked database
                     let _ = tx.send(result);
                 }))
@@ -596,7 +650,7 @@ where
                     async move {
                         db.import(modulename)
                             .await
-                            .map_err(|err| MacroError::message(err.to_string()))
+                            .map_err(MacroError::new)
                             .map(move |id| pos::spanned(span, Expr::Ident(id)))
                     }
                     .boxed()
@@ -0,0 +1,432 @@
+//! On-disk cache of compiled modules.
+//!
+//! Each module is stored as `<cache dir>/<module>.cache`. The first line of the file is a JSON
+//! header containing the hash of the module's source (which also covers the gluon version and
+//! the compiler settings) and the keys of the modules it imported, the rest of the file is the
+//! serialized `Module`. The key of a module is a hash of its source hash and the keys of its
+//! dependencies so a change to a module invalidates every module which transitively depends on
+//! it.
+//!
+//! Types in a deserialized module refer to fresh copies of the aliases defined in other
+//! modules. Since aliases are compared by their (pointer compared) `Symbol` these are replaced
+//! by the aliases of the already loaded modules before the module is used. The same goes for the
+//! `definition` symbols of the metadata which implicit resolution uses to tell whether two
+//! bindings are the same value. These are stored in the header as keys which identify the
+//! definition across all modules loaded by the vm.
+use std::{
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use salsa::Database;
+
+use crate::base::{
+    fnv::{FnvHasher, FnvMap},
+    metadata::Metadata,
+    symbol::{Name, Symbol},
+    types::{
+        self, Alias, AliasData, AliasRef, ArcType, Field, KindedIdent, NullInterner, Type,
+        TypeContext, TypeEnv,
+    },
+};
+
+use crate::vm::{
+    api::OpaqueValue, compiler::CompiledModule, gc::GcPtr, internal::ClosureData,
+    thread::RootedThread,
+};
+
+use crate::{
+    compiler_pipeline::{Module, TypecheckValue},
+    import::imported_modules,
+    query::{Compilation, CompilationBase, CompilerDatabase, ExternLoaderQuery},
+};
+
+type Dependencies = Vec<(String, u64)>;
+
+type Header = (u64, Dependencies, Vec<String>);
+
+fn cache_dir(db: &dyn Compilation) -> Option<PathBuf> {
+    crate::get_import(db.thread()).cache_dir()
+}
+
+fn cache_path(dir: &Path, module: &str) -> PathBuf {
+    let file_name: String = module
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '_' | '-' => c,
+            _ => '_',
+        })
+        .collect();
+    dir.join(format!("{}.cache", file_name))
+}
+
+fn source_hash(db: &mut dyn Compilation, module: &str, source: &str) -> u64 {
+    let mut hasher = FnvHasher::default();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    db.compiler_settings().hash(&mut hasher);
+    module.hash(&mut hasher);
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn module_key(source_hash: u64, dependencies: &Dependencies) -> u64 {
+    let mut hasher = FnvHasher::default();
+    source_hash.hash(&mut hasher);
+    dependencies.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Returns the key of the already loaded module `name`. Modules defined in Rust are not cached
+/// so their key is the hash of their type.
+fn dependency_key(db: &mut CompilerDatabase, name: &str) -> Option<u64> {
+    if let Some(key) = db.state().module_keys.get(name) {
+        return Some(*key);
+    }
+    let is_extern = db
+        .query(ExternLoaderQuery)
+        .peek(&name.to_string())
+        .is_some()
+        || db.state().extern_globals.contains(name);
+    if !is_extern {
+        return None;
+    }
+    let global = db
+        .get_extern_global(name)
+        .or_else(|| db.peek_global(name))?;
+    let mut hasher = FnvHasher::default();
+    global.typ.to_string().hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// Loads `module` from the cache if it has been stored there and neither it nor any of its
+/// dependencies have changed since.
+pub(crate) async fn load(
+    db: &mut dyn Compilation,
+    module: &str,
+) -> Option<(
+    ArcType,
+    Arc<Metadata>,
+    OpaqueValue<RootedThread, GcPtr<ClosureData>>,
+)> {
+    let dir = cache_dir(db)?;
+    let source = db.module_text(module.into()).ok()?;
+    let source_hash = source_hash(db, module, &source);
+
+    let contents = fs::read_to_string(cache_path(&dir, module)).ok()?;
+    let mut split = contents.splitn(2, '\n');
+    let header = split.next()?;
+    let body = split.next()?;
+
+    let (cached_hash, dependencies, definitions): Header = serde_json::from_str(header).ok()?;
+    if cached_hash != source_hash {
+        info!("Cached module `{}` is out of date", module);
+        return None;
+    }
+    for (dependency, key) in &dependencies {
+        db.import(dependency.clone()).await.ok()?;
+        if dependency_key(db.compiler(), dependency) != Some(*key) {
+            info!(
+                "Cached module `{}` is out of date as `{}` has changed",
+                module, dependency
+            );
+            return None;
+        }
+    }
+
+    let thread = db.thread().root_thread();
+    let Module {
+        typ,
+        mut metadata,
+        module: compiled_module,
+    } = {
+        use crate::vm::serialization::DeSeed;
+
+        let mut deserializer = serde_json::Deserializer::from_str(body);
+        match DeSeed::new(&thread, &mut thread.current_context()).deserialize(&mut deserializer) {
+            Ok(module) => module,
+            Err(err) => {
+                warn!("Unable to load cached module `{}`: {}", module, err);
+                return None;
+            }
+        }
+    };
+
+    let typ = Relinker::new(db.compiler(), module).relink(&typ);
+    {
+        let state = &mut *db.compiler().state();
+        let mut definitions = definitions.into_iter();
+        replace_definitions(Arc::make_mut(&mut metadata), &mut |definition| {
+            let key = match definitions.next() {
+                Some(key) => key,
+                None => return,
+            };
+            let symbol = state
+                .cached_definitions
+                .entry(key.clone())
+                .or_insert_with(|| definition.clone())
+                .clone();
+            state.definition_keys.insert(symbol.clone(), key);
+            *definition = symbol;
+        });
+    }
+
+    let closure = thread
+        .global_env()
+        .new_global_thunk(&thread, compiled_module)
+        .ok()?;
+
+    db.compiler()
+        .state()
+        .module_keys
+        .insert(module.into(), module_key(source_hash, &dependencies));
+    info!("Loaded `{}` from the module cache", module);
+    Some((typ, metadata, closure))
+}
+
+/// Stores the compiled `module` in the cache, if a cache directory is set.
+///
+/// Takes and returns the compiled module since `Module` needs to own it to be serialized.
+pub(crate) fn store<E>(
+    db: &mut dyn Compilation,
+    module: &str,
+    typecheck_value: &TypecheckValue<E>,
+    compiled_module: CompiledModule,
+) -> CompiledModule
+where
+    E: std::ops::Deref<Target = crate::base::ast::OwnedExpr<Symbol>>,
+{
+    let dir = match cache_dir(db) {
+        Some(dir) => dir,
+        None => return compiled_module,
+    };
+    let source = match db.module_text(module.into()) {
+        Ok(source) => source,
+        Err(_) => return compiled_module,
+    };
+    let source_hash = source_hash(db, module, &source);
+
+    let mut dependencies = Dependencies::new();
+    for dependency in imported_modules(typecheck_value.expr.expr()) {
+        match dependency_key(db.compiler(), &dependency) {
+            Some(key) => dependencies.push((dependency, key)),
+            // The dependency were loaded without computing a key so we can't tell if it changes
+            None => return compiled_module,
+        }
+    }
+    db.compiler()
+        .state()
+        .module_keys
+        .insert(module.into(), module_key(source_hash, &dependencies));
+
+    let mut definitions = Vec::new();
+    let mut new_definitions = 0;
+    {
+        let state = &mut *db.compiler().state();
+        for_each_definition(&typecheck_value.metadata, &mut |definition| {
+            let key = match state.definition_keys.get(definition) {
+                Some(key) => key.clone(),
+                None => {
+                    let key = format!("{}#{}", module, new_definitions);
+                    new_definitions += 1;
+                    state
+                        .cached_definitions
+                        .insert(key.clone(), definition.clone());
+                    state
+                        .definition_keys
+                        .insert(definition.clone(), key.clone());
+                    key
+                }
+            };
+            definitions.push(key);
+        });
+    }
+
+    let module_value = Module {
+        typ: typecheck_value.typ.clone(),
+        metadata: typecheck_value.metadata.clone(),
+        module: compiled_module,
+    };
+    let header = (source_hash, dependencies, definitions);
+    if let Err(err) = write_cache_file(&dir, module, &header, &module_value) {
+        warn!("Unable to store `{}` in the module cache: {}", module, err);
+    }
+    module_value.module
+}
+
+fn write_cache_file(
+    dir: &Path,
+    module: &str,
+    header: &Header,
+    module_value: &Module,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use crate::serde::ser::SerializeState;
+    use crate::vm::serialization::SeSeed;
+
+    let mut buffer = serde_json::to_vec(header)?;
+    buffer.push(b'\n');
+    module_value.serialize_state(
+        &mut serde_json::Serializer::new(&mut buffer),
+        &SeSeed::new(),
+    )?;
+
+    fs::create_dir_all(dir)?;
+    // Write to a temporary file first so that a concurrent load never sees a partial file
+    let path = cache_path(dir, module);
+    let temp_path = path.with_extension(format!("cache.{}", std::process::id()));
+    fs::write(&temp_path, &buffer)?;
+    fs::rename(&temp_path, &path)?;
+    Ok(())
+}
+
+/// Calls `f` on every `definition` in `metadata`, in the same order as `replace_definitions`
+fn for_each_definition(metadata: &Metadata, f: &mut impl FnMut(&Symbol)) {
+    if let Some(definition) = &metadata.definition {
+        f(definition);
+    }
+    for field in metadata.module.values() {
+        for_each_definition(field, f);
+    }
+}
+
+fn replace_definitions(metadata: &mut Metadata, f: &mut impl FnMut(&mut Symbol)) {
+    if let Some(definition) = &mut metadata.definition {
+        f(definition);
+    }
+    for field in metadata.module.values_mut() {
+        replace_definitions(Arc::make_mut(field), f);
+    }
+}
+
+/// Replaces the aliases which a deserialized type refers to with the aliases of the loaded
+/// modules which define them
+struct Relinker<'a> {
+    db: &'a mut CompilerDatabase,
+    module: &'a str,
+    foreign_aliases: FnvMap<String, Option<Alias<Symbol, ArcType>>>,
+    local_groups: FnvMap<*const AliasData<Symbol, ArcType>, Option<Vec<Alias<Symbol, ArcType>>>>,
+}
+
+impl<'a> Relinker<'a> {
+    fn new(db: &'a mut CompilerDatabase, module: &'a str) -> Self {
+        Relinker {
+            db,
+            module,
+            foreign_aliases: FnvMap::default(),
+            local_groups: FnvMap::default(),
+        }
+    }
+
+    fn relink(&mut self, typ: &ArcType) -> ArcType {
+        self.relink_opt(typ).unwrap_or_else(|| typ.clone())
+    }
+
+    fn relink_opt(&mut self, typ: &ArcType) -> Option<ArcType> {
+        types::visit_type_opt(typ, &mut |typ: &ArcType| self.relink_type(typ))
+    }
+
+    fn relink_type(&mut self, typ: &ArcType) -> Option<ArcType> {
+        match **typ {
+            Type::Alias(ref alias) => self.relink_alias(alias).map(Alias::into_type),
+            Type::Ident(ref id) => self.foreign_alias(&id.name).map(|alias| {
+                Type::ident(KindedIdent {
+                    name: alias.name.clone(),
+                    typ: id.typ.clone(),
+                })
+            }),
+            // The walk does not visit the types of type fields so they need to be relinked here
+            Type::ExtendTypeRow {
+                ref types,
+                ref rest,
+            } => {
+                let mut changed = false;
+                let types = types
+                    .iter()
+                    .map(|field| match self.relink_alias(&field.typ) {
+                        Some(alias) => {
+                            changed = true;
+                            Field::new(field.name.clone(), alias)
+                        }
+                        None => field.clone(),
+                    })
+                    .collect();
+                if changed {
+                    Some(Type::extend_type_row(types, rest.clone()))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the alias that `name` refers to if it is defined in Rust or in another module
+    fn foreign_alias(&mut self, name: &Symbol) -> Option<Alias<Symbol, ArcType>> {
+        let name_str = name.definition_name();
+        if let Some(alias) = self.foreign_aliases.get(name_str) {
+            return alias.clone();
+        }
+        let type_info = self
+            .db
+            .thread()
+            .global_env()
+            .get_globals()
+            .type_infos
+            .find_type_info(name);
+        let alias = type_info
+            .or_else(|| {
+                if Name::new(name_str).module().as_str() == self.module {
+                    None
+                } else {
+                    self.db.find_type_info(name_str).ok()
+                }
+            })
+            .filter(|alias| alias.name.definition_name() == name_str);
+        self.foreign_aliases.insert(name_str.into(), alias.clone());
+        alias
+    }
+
+    fn relink_alias(
+        &mut self,
+        alias: &AliasRef<Symbol, ArcType>,
+    ) -> Option<Alias<Symbol, ArcType>> {
+        if let Some(alias) = self.foreign_alias(&alias.name) {
+            return Some(alias);
+        }
+
+        // Aliases defined in this module are kept but the types they refer to may need to be
+        // relinked
+        let key = alias.group.as_ptr();
+        let group = match self.local_groups.get(&key) {
+            Some(group) => group.clone(),
+            None => {
+                // Insert `None` first so that recursive references to the group are left as is
+                self.local_groups.insert(key, None);
+                let mut changed = false;
+                let group: Vec<_> = alias
+                    .group
+                    .iter()
+                    .map(|data| {
+                        let mut data = data.clone();
+                        if let Some(typ) = self.relink_opt(data.unresolved_type()) {
+                            changed = true;
+                            *data.unresolved_type_mut() = typ;
+                        }
+                        data
+                    })
+                    .collect();
+                let group = if changed {
+                    // Created through `alias_group` so that implicit aliases are marked as such
+                    let mut interner = NullInterner;
+                    Some(interner.alias_group(group))
+                } else {
+                    None
+                };
+                self.local_groups.insert(key, group.clone());
+                group
+            }
+        };
+        group.map(|group| group[alias.index()].clone())
+    }
+}
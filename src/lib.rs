@@ -47,6 +47,7 @@ pub mod compiler_pipeline;
 #[macro_use]
 pub mod import;
 pub mod lift_io;
+pub mod profile;
 #[doc(hidden)]
 pub mod query;
 pub mod std_lib;
@@ -65,7 +66,7 @@ use std::{
 
 use crate::base::{
     ast::{self, OwnedExpr, SpannedExpr},
-    error::{Errors, InFile},
+    error::{AsDiagnostic, Errors, InFile, Severity, StructuredDiagnostic},
     filename_to_module,
     metadata::Metadata,
     pos::{BytePos, Span, Spanned},
@@ -191,9 +192,12 @@ impl std::hash::Hash for IoError {
     }
 }
 
-impl base::error::AsDiagnostic for Error {
+impl AsDiagnostic for Error {
     fn as_diagnostic(&self) -> codespan_reporting::Diagnostic {
-        codespan_reporting::Diagnostic::new_error(self.to_string())
+        match self {
+            Error::Other(err) => err.as_diagnostic(),
+            _ => codespan_reporting::Diagnostic::new_error(self.to_string()),
+        }
     }
 }
 
@@ -271,6 +275,26 @@ impl Error {
             }
         }
     }
+
+    /// Returns the error as a list of diagnostics with their spans resolved to files, lines and
+    /// columns
+    pub fn diagnostics(&self) -> Vec<StructuredDiagnostic> {
+        match *self {
+            Error::Parse(ref err) => err.diagnostics(),
+            Error::Typecheck(ref err) => err.diagnostics(),
+            Error::IO(ref err) => vec![StructuredDiagnostic::new(Severity::Error, err.to_string())],
+            Error::VM(ref err) => vec![StructuredDiagnostic::new(Severity::Error, err.to_string())],
+            Error::Macro(ref err) => err.diagnostics(),
+            Error::Other(ref err) => match err.downcast_ref::<Error>() {
+                Some(err) => err.diagnostics(),
+                None => vec![StructuredDiagnostic::from_diagnostic(
+                    None,
+                    &err.as_diagnostic(),
+                )],
+            },
+            Error::Multiple(ref errors) => errors.iter().flat_map(Error::diagnostics).collect(),
+        }
+    }
 }
 
 /// Type alias for results returned by gluon
@@ -284,6 +308,8 @@ pub struct Settings {
     pub use_standard_lib: bool,
     pub optimize: bool,
     pub run_io: bool,
+    pub exhaustiveness_errors: bool,
+    pub coverage: bool,
 }
 
 impl Default for Settings {
@@ -295,6 +321,8 @@ impl Default for Settings {
             use_standard_lib: true,
             optimize: true,
             run_io: false,
+            exhaustiveness_errors: false,
+            coverage: false,
         }
     }
 }
@@ -393,6 +421,22 @@ impl import::DatabaseMut {
         /// (default: false)
         run_io set_run_io: bool
     }
+
+    runtime_option! {
+        /// Sets whether non-exhaustive `match` expressions and unreachable alternatives are reported
+        /// as errors instead of as warnings.
+        /// (default: false)
+        exhaustiveness_errors set_exhaustiveness_errors: bool
+    }
+
+    runtime_option! {
+        /// Sets whether the bytecode is compiled for coverage. Pattern bindings, the code after a
+        /// `match` and function returns are then attributed to the line of the `match` or function
+        /// instead of to the last line which emitted an instruction. This changes which lines
+        /// `LINE` hooks and debuggers stop at.
+        /// (default: false)
+        coverage set_coverage: bool
+    }
 }
 
 /// Extension trait which provides methods to load and execute gluon code
@@ -506,6 +550,7 @@ pub trait ThreadExt: Send + Sync {
             typ: vm.global_env().type_cache().hole(),
             metadata: Default::default(),
             metadata_map: Default::default(),
+            warnings: Default::default(),
         }
         .compile(
             &mut ModuleCompiler::new(&mut vm.get_database()),
@@ -518,6 +563,38 @@ pub trait ThreadExt: Send + Sync {
         .map(|result| result.module)
     }
 
+    /// Compiles `expr_str` without running it. The returned value holds both the optimized core
+    /// expression (`core_expr`) and the bytecode it was compiled to (`module`) which makes it
+    /// possible to inspect what the compiler produces.
+    ///
+    /// ```
+    /// # use gluon::{new_vm, ThreadExt};
+    /// let vm = new_vm();
+    /// let value = vm.compile_str("example", "1 #Int+ 2").unwrap();
+    /// println!("{}", value.core_expr.value.expr().pretty_string(100));
+    /// println!("{}", value.module.disassemble());
+    /// ```
+    fn compile_str(&self, file: &str, expr_str: &str) -> Result<CompileValue<OwnedExpr<Symbol>>> {
+        futures::executor::block_on(self.compile_str_async(file, expr_str))
+    }
+
+    async fn compile_str_async(
+        &self,
+        file: &str,
+        expr_str: &str,
+    ) -> Result<CompileValue<OwnedExpr<Symbol>>> {
+        let vm = self.thread();
+        expr_str
+            .compile(
+                &mut ModuleCompiler::new(&mut vm.get_database()),
+                vm,
+                file,
+                expr_str,
+                None,
+            )
+            .await
+    }
+
     /// Compiles the source code `expr_str` into bytecode serialized using `serializer`
     #[cfg(feature = "serialization")]
     async fn compile_to_bytecode<S>(
@@ -719,6 +796,24 @@ pub trait ThreadExt: Send + Sync {
         ))
     }
 
+    /// Starts recording a profile of the functions that this thread executes. The profile is
+    /// returned from `Profiler::finish`.
+    ///
+    /// Any hook previously set with `Context::set_hook` is replaced while profiling and restored
+    /// once the profiler is finished or dropped.
+    fn start_profiling(&self) -> profile::Profiler {
+        profile::Profiler::start(self.thread())
+    }
+
+    /// Starts recording which lines and branches this thread executes. The coverage is
+    /// returned from `CoverageRecorder::finish`.
+    ///
+    /// Any hook previously set with `Context::set_hook` is replaced while recording and restored
+    /// once the recorder is finished or dropped.
+    fn start_coverage(&self) -> vm::coverage::CoverageRecorder {
+        vm::coverage::CoverageRecorder::start(self.thread())
+    }
+
     fn format_expr(&self, formatter: &mut Formatter, file: &str, input: &str) -> Result<String> {
         futures::executor::block_on(self.format_expr_async(formatter, file, input))
     }
@@ -851,6 +946,7 @@ let { error } = import! std.prim
 
 let __error = error
 let __string_eq: String -> String -> Bool = (==)
+let __string_append: String -> String -> String = (++)
 
 in ()
 "#;
@@ -858,6 +954,7 @@ in ()
 #[derive(Default)]
 pub struct VmBuilder {
     import_paths: Option<Vec<PathBuf>>,
+    module_cache_dir: Option<PathBuf>,
 }
 
 impl VmBuilder {
@@ -870,6 +967,12 @@ impl VmBuilder {
         import_paths set_import_paths: Option<Vec<PathBuf>>
     }
 
+    option! {
+        /// Directory where compiled modules are cached, see `Import::set_cache_dir`
+        /// (default: None)
+        module_cache_dir set_module_cache_dir: Option<PathBuf>
+    }
+
     pub fn build(self) -> RootedThread {
         futures::executor::block_on(self.build_inner(None))
     }
@@ -911,6 +1014,7 @@ impl VmBuilder {
                 if let Ok(gluon_path) = env::var("GLUON_PATH") {
                     import.add_path(gluon_path);
                 }
+                import.set_cache_dir(self.module_cache_dir);
                 macros.insert(String::from("import"), import);
             }
 
@@ -961,6 +1065,9 @@ impl VmBuilder {
             ("std.array.prim", crate::vm::primitives::load_array),
             ("std.lazy.prim", crate::vm::lazy::load),
             ("std.reference.prim", crate::vm::reference::load),
+            ("std.weak.prim", crate::vm::weak::load),
+            ("std.hash.prim", crate::vm::hash_map::load_hash),
+            ("std.hash_map.prim", crate::vm::hash_map::load),
             ("std.channel.prim", crate::vm::channel::load_channel),
             ("std.debug.prim", crate::vm::debug::load),
             ("std.process.prim", crate::std_lib::process::load),
@@ -0,0 +1,310 @@
+//! Profiling of gluon functions.
+//!
+//! A `Profiler` installs a hook on a thread which is called every time a function is entered or
+//! exited. From these events it records, for each function, the number of calls, the time spent
+//! in the function (both including and excluding the functions it calls) and the number of bytes
+//! allocated while it executed.
+//!
+//! The profiler is instrumenting, not sampling: every call and return is recorded, so all calls
+//! are counted exactly but each of them is slowed down by the hook. Functions with very short
+//! bodies will therefore appear more expensive relative to the rest of the program than they are
+//! when run without the profiler.
+//!
+//! Any hook which were installed on the thread is replaced while profiling and restored once the
+//! profiler is finished or dropped.
+//!
+//! ```rust,no_run
+//! # use gluon::{new_vm, ThreadExt};
+//! let vm = new_vm();
+//! let profiler = vm.start_profiling();
+//! vm.run_expr::<i32>("example", "let f x = x #Int+ 1 in f 1").unwrap();
+//! let profile = profiler.finish();
+//!
+//! profile.write_report(&mut std::io::stderr(), 10).unwrap();
+//! ```
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::vm::thread::{DebugInfo, HookFlags, HookFn, RootedThread, Thread, ThreadInternal};
+
+/// Statistics about a single function
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FunctionProfile {
+    /// The name of the function, prefixed by the module it were defined in
+    pub name: String,
+    pub calls: u64,
+    /// Time spent in the function, including the time spent in the functions it called
+    pub inclusive_time: Duration,
+    /// Time spent in the function, excluding the time spent in the functions it called
+    pub exclusive_time: Duration,
+    /// Bytes allocated while the function executed, including the functions it called.
+    /// Memory freed by a collection while the function runs is subtracted from this number.
+    pub allocated_bytes: usize,
+}
+
+/// The result of a profiling run
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    functions: Vec<FunctionProfile>,
+    stacks: Vec<(Vec<usize>, Duration)>,
+}
+
+impl Profile {
+    /// Returns the statistics of each called function, sorted by exclusive time (highest first)
+    pub fn functions(&self) -> &[FunctionProfile] {
+        &self.functions
+    }
+
+    /// Writes the profile as folded stacks, with the exclusive time in nanoseconds as the value of
+    /// each stack. The output can be passed directly to `flamegraph.pl` or `inferno-flamegraph`.
+    pub fn write_folded<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        for (stack, time) in &self.stacks {
+            for (i, &function) in stack.iter().enumerate() {
+                if i != 0 {
+                    write!(writer, ";")?;
+                }
+                write!(writer, "{}", self.functions[function].name)?;
+            }
+            writeln!(writer, " {}", time.as_nanos())?;
+        }
+        Ok(())
+    }
+
+    /// Writes a table of the `n` functions with the highest exclusive time
+    pub fn write_report<W>(&self, writer: &mut W, n: usize) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        writeln!(
+            writer,
+            "{:>10} {:>14} {:>14} {:>14}  {}",
+            "calls", "inclusive (ms)", "exclusive (ms)", "allocated (B)", "function"
+        )?;
+        for function in self.functions.iter().take(n) {
+            writeln!(
+                writer,
+                "{:>10} {:>14.3} {:>14.3} {:>14}  {}",
+                function.calls,
+                function.inclusive_time.as_secs_f64() * 1000.,
+                function.exclusive_time.as_secs_f64() * 1000.,
+                function.allocated_bytes,
+                function.name
+            )?;
+        }
+        Ok(())
+    }
+}
+
+struct Entry {
+    function: usize,
+    /// The depth of the stack when the function was entered
+    depth: usize,
+    start: Instant,
+    allocated_memory: usize,
+    children_time: Duration,
+}
+
+#[derive(Default)]
+struct Recorder {
+    functions: Vec<FunctionProfile>,
+    function_indexes: HashMap<String, usize>,
+    // Mirrors the vm's stack, only containing the frames which are executing functions
+    stack: Vec<Entry>,
+    stacks: HashMap<Vec<usize>, Duration>,
+}
+
+impl Recorder {
+    fn function_index(&mut self, name: String) -> usize {
+        if let Some(&index) = self.function_indexes.get(&name) {
+            return index;
+        }
+        let index = self.functions.len();
+        self.functions.push(FunctionProfile {
+            name: name.clone(),
+            ..FunctionProfile::default()
+        });
+        self.function_indexes.insert(name, index);
+        index
+    }
+
+    fn on_call(&mut self, info: &DebugInfo, now: Instant) {
+        let depth = info.stack_info_len();
+        // Tail calls replace the frame of the caller so entering a function at the same depth as a
+        // recorded function means that the recorded function has exited
+        self.exit_to(depth, info.allocated_memory(), now);
+
+        let stack_info = match info.stack_info(0) {
+            Some(stack_info) => stack_info,
+            None => return,
+        };
+        let name = match stack_info.function_name() {
+            Some(name) => name,
+            None => return,
+        };
+        // The function for the top level of a module is named after the module itself
+        let name = match stack_info.source_name() {
+            "<unknown>" | "" => name.to_string(),
+            source_name if source_name == name => name.to_string(),
+            source_name => format!("{}.{}", source_name, name),
+        };
+        let function = self.function_index(name);
+        self.functions[function].calls += 1;
+        self.stack.push(Entry {
+            function,
+            depth,
+            start: now,
+            allocated_memory: info.allocated_memory(),
+            children_time: Duration::default(),
+        });
+    }
+
+    fn on_return(&mut self, info: &DebugInfo, now: Instant) {
+        self.exit_to(info.stack_info_len(), info.allocated_memory(), now);
+    }
+
+    /// Exits all functions entered at `depth` or above
+    fn exit_to(&mut self, depth: usize, allocated_memory: usize, now: Instant) {
+        while self
+            .stack
+            .last()
+            .map_or(false, |entry| entry.depth >= depth)
+        {
+            let entry = self.stack.pop().unwrap();
+            let time = now.duration_since(entry.start);
+            let exclusive_time = time.checked_sub(entry.children_time).unwrap_or_default();
+
+            let path = self
+                .stack
+                .iter()
+                .map(|entry| entry.function)
+                .chain(Some(entry.function))
+                .collect();
+            *self.stacks.entry(path).or_default() += exclusive_time;
+
+            // Only count the time of the outermost call of recursive functions to avoid counting
+            // the same time more than once
+            let recursive = self
+                .stack
+                .iter()
+                .any(|parent| parent.function == entry.function);
+            let function = &mut self.functions[entry.function];
+            if !recursive {
+                function.inclusive_time += time;
+                function.allocated_bytes += allocated_memory.saturating_sub(entry.allocated_memory);
+            }
+            function.exclusive_time += exclusive_time;
+
+            if let Some(parent) = self.stack.last_mut() {
+                parent.children_time += time;
+            }
+        }
+    }
+
+    fn finish(mut self, allocated_memory: usize) -> Profile {
+        self.exit_to(0, allocated_memory, Instant::now());
+
+        let mut order: Vec<usize> = (0..self.functions.len()).collect();
+        order.sort_by(|&l, &r| {
+            self.functions[r]
+                .exclusive_time
+                .cmp(&self.functions[l].exclusive_time)
+                .then_with(|| self.functions[l].name.cmp(&self.functions[r].name))
+        });
+        let mut new_index = vec![0; order.len()];
+        for (i, &function) in order.iter().enumerate() {
+            new_index[function] = i;
+        }
+
+        let mut stacks: Vec<_> = self
+            .stacks
+            .into_iter()
+            .map(|(stack, time)| {
+                let stack: Vec<_> = stack.into_iter().map(|f| new_index[f]).collect();
+                (stack, time)
+            })
+            .collect();
+        let functions = &self.functions;
+        stacks.sort_by(|(l, _), (r, _)| {
+            let name = |f: &usize| &functions[order[*f]].name;
+            l.iter().map(name).cmp(r.iter().map(name))
+        });
+
+        let mut functions = self.functions;
+        let functions = order
+            .iter()
+            .map(|&function| std::mem::take(&mut functions[function]))
+            .collect();
+        Profile { functions, stacks }
+    }
+}
+
+/// Records a profile of all functions executed by a thread. Created by
+/// `ThreadExt::start_profiling`.
+///
+/// Only the thread which the profiler were started on is profiled, functions executed by other
+/// threads are not recorded.
+pub struct Profiler {
+    thread: RootedThread,
+    recorder: Arc<Mutex<Recorder>>,
+    /// The hook and hook mask which were installed before the profiler started, `None` once they
+    /// have been restored
+    previous_hook: Option<(Option<HookFn>, HookFlags)>,
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        self.restore_hook();
+    }
+}
+
+impl Profiler {
+    pub(crate) fn start(thread: &Thread) -> Profiler {
+        let recorder = Arc::new(Mutex::new(Recorder::default()));
+        let previous_hook = {
+            let recorder = recorder.clone();
+            let mut context = thread.context();
+            let previous_hook_mask = context.hook_mask();
+            let previous_hook = context.set_hook(Some(Box::new(move |_, info| {
+                let now = Instant::now();
+                let mut recorder = recorder.lock().unwrap();
+                if info.state().contains(HookFlags::CALL_FLAG) {
+                    recorder.on_call(&info, now);
+                } else {
+                    recorder.on_return(&info, now);
+                }
+                Ok(()).into()
+            })));
+            context.set_hook_mask(HookFlags::CALL_FLAG | HookFlags::RETURN_FLAG);
+            (previous_hook, previous_hook_mask)
+        };
+        Profiler {
+            thread: thread.root_thread(),
+            recorder,
+            previous_hook: Some(previous_hook),
+        }
+    }
+
+    /// Removes the profiling hook from the thread, restoring the hook which were installed before
+    /// the profiler started, and returns the recorded profile
+    pub fn finish(mut self) -> Profile {
+        self.restore_hook();
+        let allocated_memory = self.thread.allocated_memory();
+        let recorder = std::mem::take(&mut *self.recorder.lock().unwrap());
+        recorder.finish(allocated_memory)
+    }
+
+    fn restore_hook(&mut self) {
+        if let Some((hook, hook_mask)) = self.previous_hook.take() {
+            let mut context = self.thread.context();
+            context.set_hook(hook);
+            context.set_hook_mask(hook_mask);
+        }
+    }
+}
@@ -79,7 +79,13 @@ pub(crate) struct State {
     pub(crate) code_map: codespan::CodeMap,
     pub(crate) inline_modules: FnvMap<String, Arc<Cow<'static, str>>>,
     pub(crate) index_map: FnvMap<String, BytePos>,
-    extern_globals: FnvSet<String>,
+    pub(crate) extern_globals: FnvSet<String>,
+    /// The cache keys of the modules which have been loaded or stored in the module cache
+    pub(crate) module_keys: FnvMap<String, u64>,
+    /// The symbols which the `definition` of cached metadata refer to, keyed by the name they are
+    /// stored under in the module cache
+    pub(crate) cached_definitions: FnvMap<String, Symbol>,
+    pub(crate) definition_keys: FnvMap<Symbol, String>,
 }
 
 impl State {
@@ -279,6 +285,31 @@ impl CompilerDatabase {
         self.state().get_or_insert_filemap(file, source)
     }
 
+    /// Marks the source of `module` as changed, causing it to be read again the next time it is
+    /// requested. Queries which depend on the module are recomputed once they are requested.
+    pub fn invalidate_module(&mut self, module: &str) {
+        self.query_mut(ModuleTextQuery).invalidate(&module.to_string());
+    }
+
+    /// Returns every module which has been typechecked along with its typechecked expression.
+    /// Modules which failed to typecheck are included as long as they could be parsed.
+    pub fn typechecked_modules(&self) -> Vec<(String, TypecheckValue<Arc<OwnedExpr<Symbol>>>)> {
+        use salsa::debug::DebugQueryTable;
+
+        self.query(TypecheckedModuleQuery)
+            .entries::<Vec<_>>()
+            .into_iter()
+            .filter(|entry| entry.key.1.is_none())
+            .filter_map(|entry| {
+                let value = match entry.value? {
+                    Ok(value) => value,
+                    Err((value, _)) => value?,
+                };
+                Some((entry.key.0, value))
+            })
+            .collect()
+    }
+
     #[doc(hidden)]
     pub fn add_filemap<S>(&self, file: &str, source: S) -> Arc<codespan::FileMap>
     where
@@ -535,7 +566,7 @@ async fn compiled_module(
     module: String,
     expected_type: Option<ArcType>,
 ) -> StdResult<OpaqueValue<RootedThread, GcPtr<ClosureData>>, Error> {
-    let core_expr = db.core_expr(module.clone(), expected_type).await?;
+    let core_expr = db.core_expr(module.clone(), expected_type.clone()).await?;
     let settings = db.compiler_settings();
 
     let mut compiler = ModuleCompiler::new(db.compiler());
@@ -551,19 +582,32 @@ async fn compiled_module(
     );
 
     let thread = db.thread().root_thread();
-    let env = env(db.compiler());
-    let mut compiler = vm::compiler::Compiler::new(
-        &env,
-        thread.global_env(),
-        symbols,
-        &source,
-        module.clone(),
-        settings.emit_debug_info,
-    );
-
-    let mut compiled_module = compiler.compile_expr(core_expr.value.expr())?;
+    let mut compiled_module = {
+        let env = env(db.compiler());
+        let mut compiler = vm::compiler::Compiler::new(
+            &env,
+            thread.global_env(),
+            symbols,
+            &source,
+            module.clone(),
+            settings.emit_debug_info,
+            settings.coverage,
+        );
+        compiler.compile_expr(core_expr.value.expr())?
+    };
     let module_id = Symbol::from(format!("@{}", name));
     compiled_module.function.id = module_id.clone();
+
+    #[cfg(feature = "serialization")]
+    let compiled_module = if expected_type.is_none() {
+        match db.typechecked_module(module.clone(), None).await {
+            Ok(value) => crate::import::cache::store(db, &module, &value, compiled_module),
+            Err(_) => compiled_module,
+        }
+    } else {
+        compiled_module
+    };
+
     let closure = thread
         .global_env()
         .new_global_thunk(&thread, compiled_module)?;
@@ -608,11 +652,22 @@ async fn global_inner(db: &mut dyn Compilation, name: String) -> Result<Unrooted
         });
     }
 
-    let TypecheckValue { metadata, typ, .. } = db
-        .typechecked_module(name.clone(), None)
-        .await
-        .map_err(|(_, err)| err)?;
-    let closure = db.compiled_module(name.clone(), None).await?;
+    #[cfg(feature = "serialization")]
+    let cached = crate::import::cache::load(db, &name).await;
+    #[cfg(not(feature = "serialization"))]
+    let cached = None;
+
+    let (typ, metadata, closure) = match cached {
+        Some(cached) => cached,
+        None => {
+            let TypecheckValue { metadata, typ, .. } = db
+                .typechecked_module(name.clone(), None)
+                .await
+                .map_err(|(_, err)| err)?;
+            let closure = db.compiled_module(name.clone(), None).await?;
+            (typ, metadata, closure)
+        }
+    };
 
     let module_id = closure.function.name.clone();
 
@@ -786,10 +841,12 @@ where
 {
     fn get_metadata(&self, id: &SymbolRef) -> Option<Arc<Metadata>> {
         if id.is_global() {
-            self.0
-                .borrow_mut()
-                .peek_typechecked_module(id.definition_name())
+            let env = self.0.borrow_mut();
+            let name = id.definition_name();
+            env.peek_typechecked_module(name)
                 .map(|v| v.metadata.clone())
+                // Modules loaded from the module cache are never typechecked
+                .or_else(|| env.peek_global(name).map(|global| global.metadata))
         } else {
             None
         }
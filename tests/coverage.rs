@@ -0,0 +1,124 @@
+mod support;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use futures::task::Poll;
+
+use gluon::{
+    base::pos::Line,
+    vm::thread::{HookFlags, ThreadInternal},
+    ThreadExt,
+};
+
+use crate::support::make_vm;
+
+#[test]
+fn line_and_branch_coverage() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    vm.get_database_mut()
+        .implicit_prelude(false)
+        .optimize(false)
+        .set_coverage(true);
+
+    let expr = r#"
+let f x =
+    if x #Int< 0 then
+        1
+    else
+        2
+let g x =
+    x
+f 1
+"#;
+    let recorder = vm.start_coverage();
+    let (value, _) = vm
+        .run_expr::<i32>("test", expr)
+        .unwrap_or_else(|err| panic!("{}", err));
+    let coverage = recorder.finish();
+    assert_eq!(value, 2);
+
+    let module = coverage.module("test").expect("test module");
+    let executed = |line: u32| module.lines.get(&Line::from(line)).map(|&hits| hits != 0);
+    assert_eq!(executed(2), Some(true), "{:#?}", module);
+    // The `then` branch is never executed
+    assert_eq!(executed(3), Some(false), "{:#?}", module);
+    assert_eq!(executed(4), None, "{:#?}", module);
+    assert_eq!(executed(5), Some(true), "{:#?}", module);
+    // `g` is never called
+    assert_eq!(executed(7), Some(false), "{:#?}", module);
+    assert_eq!(module.lines_hit(), module.lines_found() - 2);
+
+    // `if` is a match on `True` and `False`. The test for `True` can fail while the test for
+    // `False` can't as it is the last alternative
+    let branches: Vec<_> = module
+        .branches
+        .iter()
+        .map(|branch| (branch.line, branch.taken, branch.not_taken))
+        .collect();
+    assert_eq!(
+        branches,
+        [(Line::from(2), 0, Some(1)), (Line::from(2), 1, None)]
+    );
+
+    let mut lcov = Vec::new();
+    coverage
+        .write_lcov(&mut lcov, |module| format!("{}.glu", module))
+        .unwrap();
+    let lcov = String::from_utf8(lcov).unwrap();
+    assert!(lcov.contains("SF:test.glu\n"), "{}", lcov);
+    assert!(
+        lcov.contains("BRDA:3,0,0,0\nBRDA:3,0,1,1\nBRDA:3,1,0,1\n"),
+        "{}",
+        lcov
+    );
+    assert!(lcov.contains("DA:4,0\n"), "{}", lcov);
+    assert!(lcov.contains("BRF:3\nBRH:2\n"), "{}", lcov);
+}
+
+#[test]
+fn finish_and_drop_restore_previous_hook() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    vm.get_database_mut()
+        .implicit_prelude(false)
+        .optimize(false)
+        .set_coverage(true);
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    {
+        let calls = calls.clone();
+        let mut context = vm.context();
+        context.set_hook(Some(Box::new(move |_, _| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Poll::Ready(Ok(()))
+        })));
+        context.set_hook_mask(HookFlags::CALL_FLAG);
+    }
+
+    let expr = r#"
+        let f x = x
+        f 1
+    "#;
+    let recorder = vm.start_coverage();
+    vm.run_expr::<i32>("test", expr)
+        .unwrap_or_else(|err| panic!("{}", err));
+    recorder.finish();
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+    assert_eq!(vm.context().hook_mask(), HookFlags::CALL_FLAG);
+
+    vm.run_expr::<i32>("test2", expr)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    drop(vm.start_coverage());
+    assert_eq!(vm.context().hook_mask(), HookFlags::CALL_FLAG);
+    vm.run_expr::<i32>("test3", expr)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+}
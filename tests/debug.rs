@@ -67,8 +67,56 @@ fn function_hook() {
     );
 }
 
-fn run_line_hook_test(source: &str) -> Vec<Line> {
+#[test]
+fn return_hook() {
+    let _ = env_logger::try_init();
+
     let thread = new_vm();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    {
+        let events = events.clone();
+        let mut context = thread.context();
+        context.set_hook(Some(Box::new(move |_, debug_context| {
+            let name = debug_context
+                .stack_info(0)
+                .unwrap()
+                .function_name()
+                .expect("function_name")
+                .to_string();
+            let event = if debug_context.state().contains(HookFlags::CALL_FLAG) {
+                "call"
+            } else {
+                "return"
+            };
+            events.lock().unwrap().push(format!("{} {}", event, name));
+            Poll::Ready(Ok(()))
+        })));
+        context.set_hook_mask(HookFlags::CALL_FLAG | HookFlags::RETURN_FLAG);
+    }
+
+    thread.get_database_mut().implicit_prelude(false);
+
+    thread.run_expr::<i32>("test", SIMPLE_EXPR).unwrap();
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        // `g` and `f` are both called in tail position
+        vec![
+            "call test",
+            "return test",
+            "call g",
+            "return g",
+            "call f",
+            "return f",
+        ]
+    );
+}
+
+fn run_line_hook_test(source: &str) -> Vec<Line> {
+    run_line_hook_test_with(new_vm(), source)
+}
+
+fn run_line_hook_test_with(thread: RootedThread, source: &str) -> Vec<Line> {
     {
         let mut context = thread.context();
         context.set_hook(Some(Box::new(move |_, _| Poll::Pending)));
@@ -132,6 +180,49 @@ let g y = f
     );
 }
 
+const MATCH_EXPR: &'static str = r#"
+type Test = | A Int | B
+let f x =
+    match x with
+    | A y ->
+        y
+    | B ->
+        0
+f (A 1) #Int+ f B
+    "#;
+
+#[test]
+fn line_hook_match() {
+    let _ = env_logger::try_init();
+
+    let lines = run_line_hook_test(MATCH_EXPR);
+    assert_eq!(
+        lines,
+        vec![2, 8, 3, 5, 7, 3, 5, 7]
+            .into_iter()
+            .map(Line::from)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn line_hook_match_coverage() {
+    let _ = env_logger::try_init();
+
+    let thread = new_vm();
+    thread.get_database_mut().set_coverage(true);
+
+    // Binding the pattern and returning from `f` belong to the `match`
+    let lines = run_line_hook_test_with(thread, MATCH_EXPR);
+    assert_eq!(
+        lines,
+        vec![2, 8, 3, 5, 3, 3, 7, 3]
+            .into_iter()
+            .map(Line::from)
+            .collect::<Vec<_>>()
+    );
+}
+
 #[test]
 fn line_hook_after_call() {
     let _ = env_logger::try_init();
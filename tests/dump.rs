@@ -0,0 +1,57 @@
+mod support;
+
+use gluon::ThreadExt;
+
+use crate::support::make_vm;
+
+#[test]
+fn dump_core_and_bytecode() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    vm.get_database_mut()
+        .implicit_prelude(false)
+        .set_optimize(false);
+
+    let expr = r#"
+        let greeting = "hello"
+        let add x y = x #Int+ y
+        { greeting, add }
+    "#;
+    let value = vm
+        .compile_str("test", expr)
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    let core = value.core_expr.value.expr().pretty_string(100);
+    assert!(core.contains("let greeting = \"hello\""), "{}", core);
+    assert!(core.contains("#Int+"), "{}", core);
+
+    let bytecode = value.module.disassemble().to_string();
+    let functions: Vec<_> = bytecode
+        .lines()
+        .filter(|line| line.starts_with("function "))
+        .collect();
+    assert_eq!(functions.len(), 2, "{}", bytecode);
+    assert!(functions[0].starts_with("function test "), "{}", bytecode);
+    assert!(functions[1].starts_with("function add "), "{}", bytecode);
+
+    // Constants are listed in the function and the instructions using them are annotated
+    assert!(bytecode.contains("    0: \"hello\""), "{}", bytecode);
+    assert!(bytecode.contains("{ greeting, add }"), "{}", bytecode);
+    assert!(
+        bytecode
+            .lines()
+            .any(|line| line.contains("NewClosure") && line.ends_with("; add")),
+        "{}",
+        bytecode
+    );
+    // The first instruction of each line is prefixed with its line number
+    assert!(
+        bytecode
+            .lines()
+            .any(|line| line.trim_start().starts_with("2 ") && line.contains("PushString(0)")),
+        "{}",
+        bytecode
+    );
+    assert!(bytecode.contains("AddInt"), "{}", bytecode);
+}
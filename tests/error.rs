@@ -57,6 +57,29 @@ fn panics_contain_stacktrace() {
     }
 }
 
+#[test]
+fn map_from_sorted_list_rejects_unsorted_keys() {
+    let _ = ::env_logger::try_init();
+
+    let vm = support::make_vm();
+    for keys in &[[2, 1], [1, 1]] {
+        let text = format!(
+            r#"
+            let map = import! std.map
+            let list = import! std.list
+            let entries = list.of [{{ key = {}, value = () }}, {{ key = {}, value = () }}]
+            map.size (map.from_sorted_list entries)
+            "#,
+            keys[0], keys[1]
+        );
+        match vm.run_expr::<i32>("test", &text) {
+            Err(Error::VM(VMError::Panic(ref message, _)))
+                if message.contains("from_sorted_list") => {}
+            result => panic!("Expected a panic for keys {:?}: {:?}", keys, result),
+        }
+    }
+}
+
 #[test]
 fn undefined_infix() {
     let _ = ::env_logger::try_init();
@@ -92,3 +115,140 @@ fn undefined_infix() {
         _ => panic!(),
     }
 }
+
+#[test]
+fn unused_bindings_are_reported_as_warnings() {
+    use gluon::{base::error::InFile, query::CompilationBase};
+
+    let _ = ::env_logger::try_init();
+
+    let vm = support::make_vm();
+    let text = r#"
+let { id } = import! std.function
+let io = import! std.io
+#[allow(unused)]
+let unused = 1
+let x = 2
+let x = 3
+x
+"#;
+    vm.load_script("test", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    let db = vm.get_database();
+    let warnings = db.peek_typechecked_module("test").unwrap().warnings;
+    let warnings = InFile::new(db.code_map(), warnings).emit_string().unwrap();
+    assert_eq!(warnings.matches("warning[").count(), 4, "{}", warnings);
+    assert!(warnings.contains("Unused import `id`"), "{}", warnings);
+    assert!(warnings.contains("Unused import `io`"), "{}", warnings);
+    assert!(warnings.contains("Unused variable `x`"), "{}", warnings);
+    assert!(
+        warnings.contains("`x` shadows an earlier binding with the same name"),
+        "{}",
+        warnings
+    );
+}
+
+#[test]
+fn emitted_error_codes_are_explained() {
+    use gluon::base::error_codes;
+
+    let _ = ::env_logger::try_init();
+
+    let vm = support::make_vm();
+    let scripts = [
+        "let x = 1 +",
+        "let x : Int = \"\" in y",
+        "import! undefined",
+        "{ x = 1, x = 2 }",
+        "#[implicit]\ntype Eq a = { }\nlet f x : [Eq a] -> a -> a = x\nf 1",
+    ];
+    for (i, script) in scripts.iter().enumerate() {
+        let err = vm
+            .load_script(&format!("test{}", i), script)
+            .expect_err(script)
+            .emit_string()
+            .unwrap();
+        let codes: Vec<_> = err
+            .split("error[")
+            .skip(1)
+            .map(|s| &s[..s.find(']').unwrap()])
+            .collect();
+        assert!(!codes.is_empty(), "{}", err);
+        for code in codes {
+            assert!(
+                error_codes::find(code).is_some(),
+                "`{}` is not explained\n{}",
+                code,
+                err
+            );
+        }
+    }
+}
+
+#[test]
+fn structured_diagnostics() {
+    use gluon::base::error::{Severity, SourceLocation};
+
+    let _ = ::env_logger::try_init();
+
+    let vm = support::make_vm();
+    let text = r#"
+let x = 1
+let y : String = x
+y
+"#;
+    let diagnostics = vm
+        .load_script("structured_test", text)
+        .unwrap_err()
+        .diagnostics();
+    assert_eq!(diagnostics.len(), 1, "{:#?}", diagnostics);
+
+    let diagnostic = &diagnostics[0];
+    assert_eq!(diagnostic.code.as_ref().map(|s| &s[..]), Some("E0106"));
+    assert_eq!(diagnostic.severity, Severity::Error);
+    assert!(
+        diagnostic
+            .message
+            .starts_with("Expected the following types"),
+        "{}",
+        diagnostic.message
+    );
+    let primary = diagnostic.primary.as_ref().expect("primary span");
+    assert_eq!(primary.file, "<structured_test>");
+    assert_eq!(
+        primary.start,
+        SourceLocation {
+            line: 3,
+            column: 18
+        }
+    );
+    assert_eq!(
+        primary.end,
+        SourceLocation {
+            line: 3,
+            column: 19
+        }
+    );
+}
+
+#[test]
+fn structured_diagnostics_without_spans() {
+    use gluon::base::error::Severity;
+
+    let _ = ::env_logger::try_init();
+
+    let vm = support::make_vm();
+    let diagnostics = vm
+        .run_expr::<i32>("structured_test", "error \"abc\"")
+        .unwrap_err()
+        .diagnostics();
+    assert_eq!(diagnostics.len(), 1, "{:#?}", diagnostics);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert!(
+        diagnostics[0].message.starts_with("abc"),
+        "{}",
+        diagnostics[0].message
+    );
+    assert_eq!(diagnostics[0].primary, None);
+}
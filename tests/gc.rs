@@ -0,0 +1,319 @@
+#[macro_use]
+extern crate gluon_vm;
+#[macro_use]
+extern crate gluon_codegen;
+
+mod support;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use gluon::{
+    import::add_extern_module,
+    vm::{
+        api::{FunctionRef, OpaqueValue, Userdata, VmType, IO},
+        channel::{Receiver, Sender},
+        gc::Finalizer,
+        weak::Weak,
+        ExternModule,
+    },
+    RootedThread, Thread,
+};
+
+use crate::support::{load_script, make_vm, run_expr_};
+
+const SUM_LIST: &str = r#"
+type List = | Nil | Cons { value : Int, next : List }
+
+rec
+let build n acc =
+    if n == 0 then acc else build (n - 1) (Cons { value = n, next = acc })
+let sum l acc =
+    match l with
+    | Nil -> acc
+    | Cons x -> sum x.next (acc + x.value)
+in
+sum (build 2000 Nil) 0
+"#;
+
+fn run_sum_list(vm: &Thread) {
+    let result: i32 = run_expr_(vm, SUM_LIST, true);
+    assert_eq!(result, 2001000);
+}
+
+#[test]
+fn minor_collections_keep_live_values() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    vm.set_nursery_limit(Some(1024));
+    run_sum_list(&vm);
+
+    let stats = vm.pause_stats();
+    assert!(stats.minor_collections > 0, "{:?}", stats);
+    assert_eq!(
+        stats.total_pause(),
+        stats.minor_pause + stats.major_pause,
+        "{:?}",
+        stats
+    );
+}
+
+#[test]
+fn disabled_nursery_only_runs_major_collections() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    vm.set_nursery_limit(None);
+    run_sum_list(&vm);
+    vm.collect();
+
+    let stats = vm.pause_stats();
+    assert_eq!(stats.minor_collections, 0, "{:?}", stats);
+    assert!(stats.major_collections > 0, "{:?}", stats);
+}
+
+#[test]
+fn gc_stats_group_live_bytes_by_kind() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    load_script(
+        &vm,
+        "test",
+        r#"
+let { ref } = import! std.reference
+let f x = x
+{ record = { x = "hello" }, array = [1, 2], reference = ref 1, f }
+"#,
+    )
+    .unwrap_or_else(|err| panic!("{}", err));
+
+    let stats = vm.gc_stats();
+    assert_eq!(stats.live.total(), stats.allocated_memory, "{:?}", stats);
+    assert!(stats.live.records > 0, "{:?}", stats);
+    assert!(stats.live.arrays > 0, "{:?}", stats);
+    assert!(stats.live.closures > 0, "{:?}", stats);
+    assert!(
+        stats
+            .live
+            .userdata
+            .keys()
+            .any(|name| name.contains("Reference")),
+        "{:?}",
+        stats
+    );
+
+    let collections = stats.collections();
+    vm.collect();
+    let stats = vm.gc_stats();
+    assert_eq!(stats.collections(), collections + 1);
+    assert_eq!(stats.live.total(), stats.allocated_memory, "{:?}", stats);
+}
+
+#[test]
+fn heap_snapshot_records_edges() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    load_script(&vm, "test", r#"{ x = { y = 1 } }"#).unwrap_or_else(|err| panic!("{}", err));
+
+    let snapshot = vm.heap_snapshot();
+    assert!(!snapshot.roots.is_empty());
+    let record = snapshot
+        .nodes
+        .iter()
+        .find(|node| node.kind == "record" && node.name == "{ x }")
+        .expect("Record node");
+    assert!(
+        record.edges.iter().any(|edge| snapshot
+            .nodes
+            .iter()
+            .any(|node| node.id == *edge && node.name == "{ y }")),
+        "{:?}",
+        record
+    );
+
+    let json = snapshot.to_json();
+    assert!(json.starts_with(r#"{"roots":["#), "{}", json);
+    assert!(
+        json.contains(r#""kind":"record","name":"{ x }""#),
+        "{}",
+        json
+    );
+}
+
+#[test]
+fn gc_stats_from_gluon() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    let expr = r#"
+let debug = import! std.debug
+let { wrap } = import! std.applicative
+let string = import! std.string
+let { ? } = import! std.io
+do stats = debug.gc_stats ()
+do snapshot = debug.heap_snapshot ()
+wrap (stats.allocated_memory > 0 && stats.closures > 0 && string.len snapshot > 0)
+"#;
+    let result: IO<bool> = run_expr_(&vm, expr, true);
+    assert_eq!(result, IO::Value(true));
+}
+
+#[test]
+fn weak_references_are_cleared_by_collections() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    load_script(
+        &vm,
+        "upgrade_len",
+        r#"
+let { Option } = import! std.option
+let array = import! std.array
+let { Weak, upgrade } = import! std.weak
+let upgrade_len w : Weak (Array Int) -> Int =
+    match upgrade w with
+    | Some xs -> array.len xs
+    | None -> 0
+upgrade_len
+"#,
+    )
+    .unwrap_or_else(|err| panic!("{}", err));
+    let mut upgrade_len: FunctionRef<fn(OpaqueValue<RootedThread, Weak<Vec<i32>>>) -> i32> = vm
+        .get_global("upgrade_len")
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    let expr = r#"
+let { downgrade } = import! std.weak
+let live = [1]
+(live, downgrade live, downgrade [1, 2])
+"#;
+    let (_live, weak_live, weak_dead): (
+        OpaqueValue<RootedThread, Vec<i32>>,
+        OpaqueValue<RootedThread, Weak<Vec<i32>>>,
+        OpaqueValue<RootedThread, Weak<Vec<i32>>>,
+    ) = run_expr_(&vm, expr, true);
+    assert_eq!(upgrade_len.call(weak_dead.clone()).unwrap(), 2);
+
+    vm.collect();
+    assert_eq!(upgrade_len.call(weak_live).unwrap(), 1);
+    assert_eq!(upgrade_len.call(weak_dead).unwrap(), 0);
+}
+
+#[test]
+fn dead_weak_references_can_be_sent_through_channels() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    load_script(
+        &vm,
+        "weak_channel",
+        r#"
+let { Option } = import! std.option
+let { Result } = import! std.result
+let array = import! std.array
+let { channel, send, recv } = import! std.channel
+let { Weak, downgrade, upgrade } = import! std.weak
+
+let new_channel _ : () -> _ =
+    let { sender, receiver } = channel (downgrade [0])
+    (sender, receiver)
+
+let send_weak sender w =
+    match send sender w with
+    | Ok _ -> 1
+    | Err _ -> 0
+
+let recv_len receiver =
+    match recv receiver with
+    | Ok w ->
+        match upgrade w with
+        | Some xs -> array.len xs
+        | None -> 0
+    | Err _ -> -1
+
+{ new_channel, send_weak, recv_len }
+"#,
+    )
+    .unwrap_or_else(|err| panic!("{}", err));
+
+    type WeakArray = OpaqueValue<RootedThread, Weak<Vec<i32>>>;
+    type SenderValue = OpaqueValue<RootedThread, Sender<Weak<Vec<i32>>>>;
+    type ReceiverValue = OpaqueValue<RootedThread, Receiver<Weak<Vec<i32>>>>;
+
+    let mut new_channel: FunctionRef<fn(()) -> (SenderValue, ReceiverValue)> = vm
+        .get_global("weak_channel.new_channel")
+        .unwrap_or_else(|err| panic!("{}", err));
+    let (sender, receiver) = new_channel.call(()).unwrap();
+
+    // The weak reference and its target are allocated in the child's heap, so sending it to the
+    // channel's thread deep clones it after the target has been freed
+    let child = vm.new_thread().unwrap();
+    let expr = r#"
+let { downgrade } = import! std.weak
+downgrade [1, 2]
+"#;
+    let weak_dead: WeakArray = run_expr_(&child, expr, true);
+    child.collect();
+
+    let mut send_weak: FunctionRef<fn(SenderValue, WeakArray) -> i32> = child
+        .get_global("weak_channel.send_weak")
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(send_weak.call(sender, weak_dead).unwrap(), 1);
+
+    let mut recv_len: FunctionRef<fn(ReceiverValue) -> i32> = vm
+        .get_global("weak_channel.recv_len")
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(recv_len.call(receiver).unwrap(), 0);
+}
+
+static FINALIZED: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Trace)]
+#[gluon_trace(skip)]
+struct Handle;
+
+impl Userdata for Handle {
+    fn finalizer(&mut self) -> Option<Finalizer> {
+        Some(Box::new(|| {
+            FINALIZED.fetch_add(1, Ordering::SeqCst);
+        }))
+    }
+}
+
+impl VmType for Handle {
+    type Type = Handle;
+}
+
+#[test]
+fn userdata_finalizers_run_after_collections() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    vm.register_type::<Handle>("Handle", &[])
+        .unwrap_or_else(|_| panic!("Could not add type"));
+    add_extern_module(&vm, "handle", |thread| {
+        ExternModule::new(
+            thread,
+            record! {
+                open => primitive!(1, |()| Handle)
+            },
+        )
+    });
+
+    let _kept: OpaqueValue<RootedThread, Handle> =
+        run_expr_(&vm, "let { open } = import! handle in open ()", false);
+    let () = run_expr_(
+        &vm,
+        "let { open } = import! handle in let _ = open () in ()",
+        false,
+    );
+    assert_eq!(FINALIZED.load(Ordering::SeqCst), 0);
+
+    vm.collect();
+    assert_eq!(FINALIZED.load(Ordering::SeqCst), 1);
+
+    vm.collect();
+    assert_eq!(FINALIZED.load(Ordering::SeqCst), 1);
+}
@@ -1,9 +1,11 @@
 mod support;
 
+use futures::{task::Poll, Future};
+
 use gluon::{
     vm::{
         api::{Hole, OpaqueValue},
-        thread::ThreadInternal,
+        thread::{OutOfFuel, ThreadInternal},
         Error as VMError,
     },
     Error, Thread, ThreadExt,
@@ -47,3 +49,85 @@ fn stack_overflow() {
         Ok(_) => panic!("Expected an error"),
     }
 }
+
+#[test]
+fn out_of_fuel() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    vm.context().set_fuel(Some(1000));
+    vm.get_database_mut().implicit_prelude(false);
+
+    let expr = r#"
+        let loop x = loop (x #Int+ 1)
+        loop 0
+    "#;
+    let result = vm.run_expr::<i32>("example", expr);
+
+    match result {
+        Err(Error::VM(VMError::OutOfFuel)) => (),
+        Err(err) => panic!("Unexpected error `{:?}`", err),
+        Ok(_) => panic!("Expected an error"),
+    }
+}
+
+#[test]
+fn extern_functions_consume_fuel() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    vm.get_database_mut().implicit_prelude(false);
+    vm.run_expr::<OpaqueValue<&Thread, Hole>>("load", "import! std.array")
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    // Only a handful of instructions are executed but each `append` copies more and more elements
+    vm.context().set_fuel(Some(1000));
+    let expr = r#"
+        let array = import! std.array
+        let double a = array.append a a
+        let x = double (double (double (double (double [1]))))
+        double (double (double (double (double x))))
+    "#;
+    let result = vm.run_expr::<OpaqueValue<&Thread, Hole>>("example", expr);
+
+    match result {
+        Err(Error::VM(VMError::OutOfFuel)) => (),
+        Err(err) => panic!("Unexpected error `{:?}`", err),
+        Ok(_) => panic!("Expected an error"),
+    }
+}
+
+#[test]
+fn yield_when_out_of_fuel() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    {
+        let mut context = vm.context();
+        context.set_fuel(Some(10));
+        context.set_out_of_fuel(OutOfFuel::Yield);
+    }
+    vm.get_database_mut().implicit_prelude(false);
+
+    let expr = r#"
+        let loop x = if x #Int== 100 then x else loop (x #Int+ 1)
+        loop 0
+    "#;
+    let execute = vm.run_expr_async::<i32>("example", expr);
+    futures::pin_mut!(execute);
+
+    let mut refills = 0;
+    let result = futures::executor::block_on(futures::future::poll_fn(|cx| loop {
+        match execute.as_mut().poll(cx) {
+            Poll::Ready(result) => return Poll::Ready(result),
+            Poll::Pending => {
+                assert_eq!(vm.context().fuel(), Some(0));
+                refills += 1;
+                vm.context().add_fuel(10);
+            }
+        }
+    }));
+
+    assert_eq!(result.map(|(value, _)| value), Ok(100));
+    assert!(refills > 10, "Expected execution to yield repeatedly");
+}
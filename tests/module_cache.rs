@@ -0,0 +1,177 @@
+#![cfg(feature = "serialization")]
+
+use std::{fs, path::Path};
+
+use gluon::{vm::thread::RootedThread, ThreadExt, VmBuilder};
+
+const LIB: &str = r#"
+let { Option } = import! std.option
+
+#[infix(left, 6)]
+let (+++) x y : Int -> Int -> Int = x #Int+ y
+
+type Shape =
+    | Circle Int
+    | Square Int
+
+let area shape : Shape -> Int =
+    match shape with
+    | Circle r -> 3 * r * r
+    | Square s -> s * s
+
+let find_area x : Int -> Option Shape =
+    if x #Int< 0 then None else Some (Square x)
+
+{ Shape, Option, area, find_area, (+++) }
+"#;
+
+const CLIENT: &str = r#"
+let { Shape, Option, area, find_area, (+++) } = import! lib
+let shape : Shape = Circle 2
+let x : Option Int =
+    match find_area 3 with
+    | Some s -> Some (area s)
+    | None -> None
+match x with
+| Some a -> a +++ area shape +++ 1
+| None -> 0
+"#;
+
+fn make_vm(dir: &Path) -> RootedThread {
+    VmBuilder::new()
+        .import_paths(Some(vec![dir.join("src")]))
+        .module_cache_dir(Some(dir.join("cache")))
+        .build()
+}
+
+fn typechecked(vm: &RootedThread, module: &str) -> bool {
+    vm.get_database()
+        .typechecked_modules()
+        .iter()
+        .any(|(name, _)| name == module)
+}
+
+#[test]
+fn load_modules_from_cache() {
+    let _ = ::env_logger::try_init();
+
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/lib.glu"), LIB).unwrap();
+
+    let vm = make_vm(dir.path());
+    let (value, _) = vm
+        .run_expr::<i32>("client", CLIENT)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(value, 9 + 12 + 1);
+    assert!(typechecked(&vm, "lib"));
+    assert!(dir.path().join("cache/lib.cache").exists());
+    assert!(dir.path().join("cache/std.option.cache").exists());
+
+    // A new vm loads the modules from the cache and can still typecheck code using them
+    let vm = make_vm(dir.path());
+    let (value, _) = vm
+        .run_expr::<i32>("client", CLIENT)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(value, 9 + 12 + 1);
+    assert!(!typechecked(&vm, "lib"));
+    assert!(!typechecked(&vm, "std.option"));
+}
+
+#[test]
+fn implicits_of_cached_modules() {
+    let _ = ::env_logger::try_init();
+
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+
+    // `eq` is reachable both directly and through `ord.eq` in `std.int` which must still be
+    // seen as the same value when loaded from the cache
+    let expr = r#"
+let { Eq, (==) } = import! std.cmp
+let eq_twice x y : [Eq a] -> a -> a -> Bool = x == y && y == x
+eq_twice 1 1 && eq_twice "a" "a"
+"#;
+    for _ in 0..2 {
+        let vm = make_vm(dir.path());
+        let (value, _) = vm
+            .run_expr::<bool>("client", expr)
+            .unwrap_or_else(|err| panic!("{}", err));
+        assert!(value);
+    }
+    assert!(dir.path().join("cache/std.cmp.cache").exists());
+}
+
+#[test]
+fn implicit_types_defined_in_cached_modules() {
+    let _ = ::env_logger::try_init();
+
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    // `Default` does not refer to any other module so its alias is not recreated when `lib` is
+    // loaded from the cache
+    fs::write(
+        dir.path().join("src/lib.glu"),
+        r#"
+#[implicit]
+type Default a = { default : a }
+let default ?d : [Default a] -> a = d.default
+let int_default : Default Int = { default = 3 }
+{ Default, default, int_default }
+"#,
+    )
+    .unwrap();
+
+    let expr = r#"
+let { default, int_default } = import! lib
+let x : Int = default
+x
+"#;
+    for cached in &[false, true] {
+        let vm = make_vm(dir.path());
+        let (value, _) = vm
+            .run_expr::<i32>("client", expr)
+            .unwrap_or_else(|err| panic!("{}", err));
+        assert_eq!(value, 3);
+        assert_eq!(typechecked(&vm, "lib"), !cached);
+    }
+}
+
+#[test]
+fn changed_modules_are_recompiled() {
+    let _ = ::env_logger::try_init();
+
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/dep.glu"), "{ x = 1 }").unwrap();
+    fs::write(
+        dir.path().join("src/lib.glu"),
+        "let { x } = import! dep\n{ y = x #Int+ 1 }",
+    )
+    .unwrap();
+
+    let expr = "let { y } = import! lib\ny";
+    let vm = make_vm(dir.path());
+    let (value, _) = vm
+        .run_expr::<i32>("client", expr)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(value, 2);
+
+    let vm = make_vm(dir.path());
+    let (value, _) = vm
+        .run_expr::<i32>("client", expr)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(value, 2);
+    assert!(!typechecked(&vm, "lib"));
+
+    // Changing a dependency invalidates the modules which import it
+    fs::write(dir.path().join("src/dep.glu"), "{ x = 10 }").unwrap();
+    let vm = make_vm(dir.path());
+    let (value, _) = vm
+        .run_expr::<i32>("client", expr)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(value, 11);
+    assert!(typechecked(&vm, "dep"));
+    assert!(typechecked(&vm, "lib"));
+    assert!(!typechecked(&vm, "std.types"));
+}
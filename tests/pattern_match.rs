@@ -28,6 +28,102 @@ match A with
     assert!(result.is_err());
 }
 
+#[test]
+fn non_exhaustive_pattern_error() {
+    let _ = ::env_logger::try_init();
+    let text = r"
+type AB = | A | B in
+match A with
+| B -> True
+";
+    let vm = make_vm();
+    vm.get_database_mut().exhaustiveness_errors(true);
+    let result = vm.run_expr::<bool>("non_exhaustive_pattern_error", text);
+    match result {
+        Err(err) => {
+            let err = err.to_string();
+            assert!(err.contains("`A`"), "{}", err);
+        }
+        Ok(_) => panic!("Expected an error"),
+    }
+}
+
+#[test]
+fn unreachable_alternative_error() {
+    let _ = ::env_logger::try_init();
+    let text = r"
+type AB = | A | B in
+match A with
+| A -> 1
+| _ -> 2
+| B -> 3
+";
+    let vm = make_vm();
+    vm.get_database_mut().exhaustiveness_errors(true);
+    let result = vm.run_expr::<i32>("unreachable_alternative_error", text);
+    match result {
+        Err(err) => {
+            let err = err.to_string();
+            assert!(err.contains("Unreachable alternative"), "{}", err);
+        }
+        Ok(_) => panic!("Expected an error"),
+    }
+}
+
+#[test]
+fn exhaustive_pattern_with_exhaustiveness_errors() {
+    let _ = ::env_logger::try_init();
+    let text = r#"
+let { Option } = import! std.option
+type AB = | A | B in
+match (Some A, { x = 1, y = B }) with
+| (Some A, { y = A }) -> 1
+| (Some B, _) -> 2
+| (None, { x = 1 }) -> 3
+| (_, { y }) -> 4
+"#;
+    let vm = make_vm();
+    vm.get_database_mut()
+        .implicit_prelude(false)
+        .exhaustiveness_errors(true);
+    let (result, _) = vm
+        .run_expr::<i32>("exhaustive_pattern_with_exhaustiveness_errors", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(result, 4);
+}
+
+#[test]
+fn exhaustiveness_problems_are_reported_as_warnings() {
+    use gluon::{base::error::InFile, query::CompilationBase};
+
+    let _ = ::env_logger::try_init();
+    let text = r"
+type AB = | A | B
+let f x =
+    match x with
+    | A -> 1
+    | _ -> 2
+    | B -> 3
+let g x =
+    match x with
+    | A -> 1
+{ f, g }
+";
+    let vm = make_vm();
+    vm.load_script("exhaustiveness_warnings", text)
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    let db = vm.get_database();
+    let warnings = db
+        .peek_typechecked_module("exhaustiveness_warnings")
+        .unwrap()
+        .warnings;
+    let warnings = InFile::new(db.code_map(), warnings).emit_string().unwrap();
+    assert_eq!(warnings.matches("warning[").count(), 2, "{}", warnings);
+    assert!(warnings.contains("Unreachable alternative"), "{}", warnings);
+    assert!(warnings.contains("`B`"), "{}", warnings);
+}
+
 test_expr! { match_record_pattern,
 r#"
 let string_prim = import! std.string.prim
@@ -199,3 +295,78 @@ getName ("abc", 123)
 "#,
 "abc".to_string()
 }
+
+test_expr! { or_pattern,
+r#"
+type ABC = | A Int | B Int | C
+let f x =
+    match x with
+    | A y | B y -> y
+    | C -> 0
+f (A 1) #Int+ f (B 10) #Int+ f C
+"#,
+11
+}
+
+test_expr! { nested_or_pattern,
+r#"
+type AB = | A | B
+type Option a = | None | Some a
+let f x =
+    match x with
+    | (Some (A | B), 1 | 2) -> 1
+    | (Some _, _) -> 2
+    | (None, _) -> 3
+f (Some A, 2) #Int+ f (Some B, 3) #Int+ f (None, 1)
+"#,
+6
+}
+
+test_expr! { or_pattern_after_merged_alternative,
+r#"
+type AB = | A Int | B Int
+let f x =
+    match x with
+    | A y if 10 #Int< y -> y
+    | A z | B z -> z #Int+ 1
+f (A 20) #Int+ f (A 1) #Int+ f (B 2)
+"#,
+25
+}
+
+test_expr! { guard_falls_through_to_next_alternative,
+r#"
+type Option a = | None | Some a
+let f x =
+    match x with
+    | Some y if 0 #Int< y -> y
+    | Some _ -> 100
+    | None -> 1000
+f (Some 1) #Int+ f (Some 0) #Int+ f None
+"#,
+1101
+}
+
+test_expr! { guard_falls_through_to_wildcard,
+r#"
+let f x =
+    match (x, x #Int+ 1) with
+    | (1, y) if y #Int== 3 -> 1
+    | (a, b) if a #Int< b -> 10
+    | _ -> 100
+f 1
+"#,
+10
+}
+
+test_expr! { guard_on_or_pattern,
+r#"
+type AB = | A Int | B Int
+let f x =
+    match x with
+    | A y | B y if 0 #Int< y -> y
+    | _ -> 100
+f (A 1) #Int+ f (B 0)
+"#,
+101
+}
@@ -0,0 +1,109 @@
+mod support;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use futures::task::Poll;
+
+use gluon::{
+    vm::thread::{HookFlags, ThreadInternal},
+    ThreadExt,
+};
+
+use crate::support::make_vm;
+
+#[test]
+fn profile_calls() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    vm.get_database_mut()
+        .implicit_prelude(false)
+        .set_optimize(false);
+
+    let expr = r#"
+        let add x y = x #Int+ y
+        let sum n acc = if n #Int== 0 then acc else sum (n #Int- 1) (add acc n)
+        sum 10 0
+    "#;
+    let profiler = vm.start_profiling();
+    let (value, _) = vm
+        .run_expr::<i32>("test", expr)
+        .unwrap_or_else(|err| panic!("{}", err));
+    let profile = profiler.finish();
+    assert_eq!(value, 55);
+
+    let calls = |name: &str| {
+        profile
+            .functions()
+            .iter()
+            .find(|function| function.name == name)
+            .unwrap_or_else(|| panic!("Missing `{}` in {:#?}", name, profile.functions()))
+            .calls
+    };
+    assert_eq!(calls("test.add"), 10);
+    assert_eq!(calls("test.sum"), 11);
+
+    for function in profile.functions() {
+        assert!(function.exclusive_time <= function.inclusive_time);
+    }
+
+    let mut folded = Vec::new();
+    profile.write_folded(&mut folded).unwrap();
+    let folded = String::from_utf8(folded).unwrap();
+    let stacks: Vec<_> = folded
+        .lines()
+        .map(|line| line.rsplitn(2, ' ').nth(1).unwrap())
+        .collect();
+    // `sum` is tail called so it replaces the stack frame of its caller
+    assert_eq!(stacks, ["test", "test.sum", "test.sum;test.add"]);
+
+    let mut report = Vec::new();
+    profile.write_report(&mut report, 2).unwrap();
+    // A header followed by two functions
+    assert_eq!(String::from_utf8(report).unwrap().lines().count(), 3);
+}
+
+#[test]
+fn finish_and_drop_restore_previous_hook() {
+    let _ = ::env_logger::try_init();
+
+    let vm = make_vm();
+    vm.get_database_mut()
+        .implicit_prelude(false)
+        .set_optimize(false);
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    {
+        let calls = calls.clone();
+        let mut context = vm.context();
+        context.set_hook(Some(Box::new(move |_, _| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Poll::Ready(Ok(()))
+        })));
+        context.set_hook_mask(HookFlags::CALL_FLAG);
+    }
+
+    let expr = r#"
+        let f x = x
+        f 1
+    "#;
+    let profiler = vm.start_profiling();
+    vm.run_expr::<i32>("test", expr)
+        .unwrap_or_else(|err| panic!("{}", err));
+    profiler.finish();
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+    vm.run_expr::<i32>("test2", expr)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    assert_eq!(vm.context().hook_mask(), HookFlags::CALL_FLAG);
+
+    drop(vm.start_profiling());
+    assert_eq!(vm.context().hook_mask(), HookFlags::CALL_FLAG);
+    vm.run_expr::<i32>("test3", expr)
+        .unwrap_or_else(|err| panic!("{}", err));
+    assert_eq!(calls.load(Ordering::SeqCst), 4);
+}
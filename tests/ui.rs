@@ -6,7 +6,7 @@ fn macro_error_with_line_column_info() {
     let result = thread.run_expr::<()>("test", "import! undefined");
     assert_eq!(
         result.unwrap_err().emit_string().unwrap(),
-        r#"error: Could not find module 'undefined'. Searched `.`.
+        r#"error[E0202]: Could not find module 'undefined'. Searched `.`.
 - <test>:1:1
   |
 1 | import! undefined
@@ -229,6 +229,51 @@ r"
 true
 }
 
+test_expr! { prelude interpolated_string,
+r#"
+let name = "gluon"
+let count = 3
+"user ${name} has ${show count} items"
+"#,
+String::from("user gluon has 3 items")
+}
+
+test_expr! { prelude interpolated_string_shows_values,
+r#"
+let xs = [1, 2]
+"${xs} ${1.5} \${x} ${ if 1 < 2 then "yes" else "no" }"
+"#,
+String::from("[1, 2] 1.5 ${x} yes")
+}
+
+test_expr! { prelude nested_interpolated_string,
+r#"
+let inner x = "(${x})"
+"${inner "${inner "1"}"}"
+"#,
+String::from("((1))")
+}
+
+test_expr! { prelude interpolated_string_ignores_local_append,
+r#"
+#[infix(left, 4)]
+let (<>) x y : Int -> Int -> Int = x
+let name = "gluon"
+"hello ${name}!"
+"#,
+String::from("hello gluon!")
+}
+
+test_expr! { prelude interpolated_string_infers_holes_from_later_uses,
+r#"
+let f x = "${x} ${x + 1}"
+let g x = "${x}" ++ x
+let h x : [Show a] -> a -> String = "<${x}>"
+"${f 1} ${g "a"} ${h "b"}"
+"#,
+String::from("1 2 aa <\"b\">")
+}
+
 test_expr! { implicit_call_without_type_in_scope,
 r"
 let int @ { ? } = import! std.int
@@ -84,7 +84,7 @@ where
         } else {
             self.name
         };
-        let id = Symbol::from(name.replace("::", "."));
+        let id = Symbol::from(name.replace(" ", "").replace("::", "."));
         context.context().push_new_alloc(Move(ExternFunction {
             id: id,
             args: F::arguments(),
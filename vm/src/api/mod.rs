@@ -1335,15 +1335,16 @@ where
 {
     match value.as_ref() {
         ValueRef::Data(data) => {
+            // `Bin size key value left right`
             if data.tag() == 1 {
-                let key = K2::from_value(vm, data.get_variant(0).expect("key"));
-                let value = V2::from_value(vm, data.get_variant(1).expect("value"));
+                let key = K2::from_value(vm, data.get_variant(1).expect("key"));
+                let value = V2::from_value(vm, data.get_variant(2).expect("value"));
                 map.extend(Some((key, value)));
 
-                let left = data.get_variant(2).expect("left");
+                let left = data.get_variant(3).expect("left");
                 from_gluon_map(map, vm, left);
 
-                let right = data.get_variant(3).expect("right");
+                let right = data.get_variant(4).expect("right");
                 from_gluon_map(map, vm, right);
             }
         }
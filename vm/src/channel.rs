@@ -3,6 +3,7 @@ use crate::real_std::{
     collections::VecDeque,
     fmt,
     marker::PhantomData,
+    mem,
     slice,
     sync::{Arc, Mutex},
     time::Duration,
@@ -11,7 +12,7 @@ use crate::real_std::{
 use futures::{
     future::{self, Either},
     prelude::*,
-    task::Poll,
+    task::{self, Poll, Waker},
     try_join,
 };
 
@@ -28,18 +29,101 @@ use crate::{
     },
     gc::{self, CloneUnrooted, GcPtr, Trace},
     stack::{ClosureState, ExternState, State},
-    thread::{ActiveThread, ThreadInternal},
+    thread::{ActiveThread, RootedValue, ThreadInternal},
     types::VmInt,
     value::{Callable, Userdata, Value, ValueRepr},
     vm::{RootedThread, Thread},
     Error, ExternModule, Result as VmResult, Variants,
 };
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::timer::Delay;
+
+/// The state shared between the `Sender` and `Receiver` of a channel
+struct Queue {
+    values: VecDeque<Value>,
+    /// The maximum number of values which can be in the queue, `None` for unbounded channels
+    capacity: Option<usize>,
+    closed: bool,
+    /// Tasks waiting for a value to be sent
+    receivers: Vec<Waker>,
+    /// Tasks waiting for room in the queue
+    senders: Vec<Waker>,
+}
+
+unsafe impl Trace for Queue {
+    impl_trace_fields! { self, gc; values }
+}
+
+impl Queue {
+    fn new(capacity: Option<usize>) -> SharedQueue {
+        Arc::new(Mutex::new(Queue {
+            values: VecDeque::new(),
+            capacity,
+            closed: false,
+            receivers: Vec::new(),
+            senders: Vec::new(),
+        }))
+    }
+
+    fn is_full(&self) -> bool {
+        self.capacity
+            .map_or(false, |capacity| self.values.len() >= capacity)
+    }
+
+    fn push(&mut self, value: &Value) {
+        // SAFETY Rooted when stored in `values`
+        unsafe {
+            self.values.push_back(value.clone_unrooted());
+        }
+        wake_all(&mut self.receivers);
+    }
+
+    fn pop(&mut self) -> Option<Value> {
+        let value = self.values.pop_front()?;
+        wake_all(&mut self.senders);
+        Some(value)
+    }
+
+    /// Pops a value, registering the current task to be woken once a value is sent if the queue
+    /// is empty. Returns `Ready(None)` once the channel is closed and every value has been received.
+    fn poll_pop(&mut self, cx: &mut task::Context<'_>) -> Poll<Option<Value>> {
+        match self.pop() {
+            Some(value) => Poll::Ready(Some(value)),
+            None if self.closed => Poll::Ready(None),
+            None => {
+                register(&mut self.receivers, cx);
+                Poll::Pending
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        wake_all(&mut self.receivers);
+        wake_all(&mut self.senders);
+    }
+}
+
+type SharedQueue = Arc<Mutex<Queue>>;
+
+fn register(wakers: &mut Vec<Waker>, cx: &mut task::Context<'_>) {
+    if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+        wakers.push(cx.waker().clone());
+    }
+}
+
+fn wake_all(wakers: &mut Vec<Waker>) {
+    for waker in wakers.drain(..) {
+        waker.wake();
+    }
+}
+
 pub struct Sender<T> {
     // No need to traverse this thread reference as any thread having a reference to this `Sender`
     // would also directly own a reference to the `Thread`
     thread: GcPtr<Thread>,
-    queue: Arc<Mutex<VecDeque<Value>>>,
+    queue: SharedQueue,
     _element_type: PhantomData<T>,
 }
 
@@ -50,7 +134,7 @@ where
     T: fmt::Debug,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", *self.queue.lock().unwrap())
+        write!(f, "{:?}", self.queue.lock().unwrap().values)
     }
 }
 
@@ -62,11 +146,11 @@ unsafe impl<T> Trace for Sender<T> {
 }
 
 impl<T> Sender<T> {
-    fn send(&self, value: &Value) {
-        // SAFETY Rooted when stored in `queue`
-        unsafe {
-            self.queue.lock().unwrap().push_back(value.clone_unrooted());
-        }
+    /// Clones `value` into the thread which owns the channel so that it can be stored in the queue
+    fn clone_value(&self, value: &Value) -> VmResult<RootedValue<RootedThread>> {
+        self.thread
+            .deep_clone_value(&self.thread, value)
+            .map(RootedValue::into_owned)
     }
 }
 
@@ -75,7 +159,7 @@ unsafe impl<T> Trace for Receiver<T> {
 }
 
 pub struct Receiver<T> {
-    queue: Arc<Mutex<VecDeque<Value>>>,
+    queue: SharedQueue,
     _element_type: PhantomData<T>,
 }
 
@@ -86,13 +170,13 @@ where
     T: fmt::Debug,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", *self.queue.lock().unwrap())
+        write!(f, "{:?}", self.queue.lock().unwrap().values)
     }
 }
 
 impl<T> Receiver<T> {
     fn try_recv(&self) -> Result<Value, ()> {
-        self.queue.lock().unwrap().pop_front().ok_or(())
+        self.queue.lock().unwrap().pop().ok_or(())
     }
 }
 
@@ -143,16 +227,16 @@ where
     }
 }
 
-field_decl! { sender, receiver }
+field_decl! { sender, receiver, index, value }
 
 pub type ChannelRecord<S, R> = record_type!(sender => S, receiver => R);
 
-/// FIXME The dummy `a` argument should not be needed to ensure that the channel can only be used
-/// with a single type
-fn channel(WithVM { vm, .. }: WithVM<Generic<A>>) -> ChannelRecord<Sender<A>, Receiver<A>> {
+pub type SelectRecord<T> = record_type!(index => VmInt, value => T);
+
+fn new_channel(vm: &Thread, capacity: Option<usize>) -> ChannelRecord<Sender<A>, Receiver<A>> {
     let sender = Sender {
         thread: unsafe { GcPtr::from_raw(vm) },
-        queue: Arc::new(Mutex::new(VecDeque::new())),
+        queue: Queue::new(capacity),
         _element_type: PhantomData,
     };
     let receiver = Receiver {
@@ -162,16 +246,136 @@ fn channel(WithVM { vm, .. }: WithVM<Generic<A>>) -> ChannelRecord<Sender<A>, Re
     record_no_decl!(sender => sender, receiver => receiver)
 }
 
+/// FIXME The dummy `a` argument should not be needed to ensure that the channel can only be used
+/// with a single type
+fn channel(WithVM { vm, .. }: WithVM<Generic<A>>) -> ChannelRecord<Sender<A>, Receiver<A>> {
+    new_channel(vm, None)
+}
+
+/// Creates a channel which holds at most `capacity` values. A capacity below 1 is treated as 1.
+fn bounded_channel(
+    capacity: VmInt,
+    WithVM { vm, .. }: WithVM<Generic<A>>,
+) -> ChannelRecord<Sender<A>, Receiver<A>> {
+    new_channel(vm, Some(capacity.max(1) as usize))
+}
+
 fn recv(receiver: &Receiver<A>) -> Result<Unrooted<A>, ()> {
     receiver.try_recv().map_err(|_| ()).map(Unrooted::from)
 }
 
 fn send(sender: &Sender<A>, value: Generic<A>) -> Result<(), ()> {
-    let value = sender
-        .thread
-        .deep_clone_value(&sender.thread, value.get_value())
-        .map_err(|_| ())?;
-    Ok(sender.send(value.get_value()))
+    let value = sender.clone_value(value.get_value()).map_err(|_| ())?;
+    let mut queue = sender.queue.lock().unwrap();
+    if queue.closed || queue.is_full() {
+        return Err(());
+    }
+    queue.push(value.get_value());
+    Ok(())
+}
+
+/// Sends `value`, waiting until there is room in the channel. Returns `Err` if the channel is
+/// closed.
+fn send_async(sender: &Sender<A>, value: Generic<A>) -> impl Future<Output = IO<Result<(), ()>>> {
+    let value = sender.clone_value(value.get_value());
+    let queue = sender.queue.clone();
+    future::poll_fn(move |cx| {
+        let value = match &value {
+            Ok(value) => value,
+            Err(err) => return Poll::Ready(IO::Exception(err.to_string())),
+        };
+        let mut queue = queue.lock().unwrap();
+        if queue.closed {
+            Poll::Ready(IO::Value(Err(())))
+        } else if queue.is_full() {
+            register(&mut queue.senders, cx);
+            Poll::Pending
+        } else {
+            queue.push(value.get_value());
+            Poll::Ready(IO::Value(Ok(())))
+        }
+    })
+}
+
+fn recv_future(queue: SharedQueue) -> impl Future<Output = IO<Result<Unrooted<A>, ()>>> {
+    future::poll_fn(move |cx| {
+        queue
+            .lock()
+            .unwrap()
+            .poll_pop(cx)
+            .map(|value| IO::Value(value.map(Unrooted::from).ok_or(())))
+    })
+}
+
+/// Receives a value, waiting until one is sent. Returns `Err` once the channel is closed and all
+/// values have been received.
+fn recv_async(receiver: &Receiver<A>) -> impl Future<Output = IO<Result<Unrooted<A>, ()>>> {
+    recv_future(receiver.queue.clone())
+}
+
+/// Like `recv_async` but also returns `Err` if no value were received within `ms` milliseconds
+#[cfg(not(target_arch = "wasm32"))]
+fn recv_timeout(
+    receiver: &Receiver<A>,
+    ms: VmInt,
+) -> impl Future<Output = IO<Result<Unrooted<A>, ()>>> {
+    // The delay is dropped, and thereby removed from the timer, as soon as a value is received
+    future::select(
+        recv_future(receiver.queue.clone()),
+        Delay::new(Duration::from_millis(ms.max(0) as u64)),
+    )
+    .map(|either| match either {
+        Either::Left((result, _)) => result,
+        Either::Right(((), _)) => IO::Value(Err(())),
+    })
+}
+
+#[cfg(target_arch = "wasm32")]
+fn recv_timeout(
+    _receiver: &Receiver<A>,
+    _ms: VmInt,
+) -> impl Future<Output = IO<Result<Unrooted<A>, ()>>> {
+    future::ready(IO::Exception(
+        "recv_timeout requires threads which are not available on wasm".to_string(),
+    ))
+}
+
+/// Receives a value from the first of `receivers` which has one, returning its index along with
+/// the value. Returns `Err` once every channel is closed and all values have been received.
+fn select(
+    receivers: Vec<&Receiver<A>>,
+) -> impl Future<Output = IO<Result<SelectRecord<Unrooted<A>>, ()>>> {
+    let queues: Vec<_> = receivers
+        .into_iter()
+        .map(|receiver| receiver.queue.clone())
+        .collect();
+    future::poll_fn(move |cx| {
+        let mut closed = 0;
+        for (i, queue) in queues.iter().enumerate() {
+            match queue.lock().unwrap().poll_pop(cx) {
+                Poll::Ready(Some(value)) => {
+                    let value = Unrooted::from(value);
+                    return Poll::Ready(IO::Value(Ok(
+                        record_no_decl!(index => i as VmInt, value => value),
+                    )));
+                }
+                Poll::Ready(None) => closed += 1,
+                Poll::Pending => (),
+            }
+        }
+        if closed == queues.len() {
+            Poll::Ready(IO::Value(Err(())))
+        } else {
+            Poll::Pending
+        }
+    })
+}
+
+/// Closes the channel. Values which have already been sent can still be received but any
+/// further sends fail.
+fn close(sender: &Sender<A>) -> IO<()> {
+    sender.queue.lock().unwrap().close();
+    IO::Value(())
 }
 
 async fn resume(child: RootedThread) -> RuntimeResult<Result<(), String>, String> {
@@ -362,6 +566,310 @@ fn join(
     })
 }
 
+type Child<T> = future::BoxFuture<'static, VmResult<T>>;
+
+/// Starts each of `actions` in a new child thread of `vm`. The returned futures must be polled for
+/// the actions to make progress.
+fn start_children(
+    vm: &Thread,
+    actions: &[OpaqueRef<IO<A>>],
+) -> VmResult<(Vec<RootedThread>, Vec<Child<Generic<A>>>)> {
+    actions
+        .iter()
+        .map(|action| {
+            let thread = vm.new_thread()?;
+            let mut action: OwnedFunction<fn(()) -> Generic<A>> =
+                Getable::from_value(&thread, action.get_variant());
+            let child: Child<Generic<A>> = async move { action.call_async(()).await }.boxed();
+            Ok((thread, child))
+        })
+        .collect::<VmResult<Vec<_>>>()
+        .map(|children| children.into_iter().unzip())
+}
+
+/// Cancels `threads` through their interrupt flag so that any of them which are resumed again
+/// stop as soon as they run
+fn cancel_threads<'a>(threads: impl IntoIterator<Item = &'a RootedThread>) {
+    for thread in threads {
+        thread.interrupt();
+    }
+}
+
+/// Runs every action in its own thread, returning all the results once every action has
+/// finished. If any action fails the others are cancelled and the error is propagated.
+fn join_all(
+    WithVM { vm, value: actions }: WithVM<Vec<OpaqueRef<IO<A>>>>,
+) -> impl Future<Output = RuntimeResult<IO<Vec<Generic<A>>>, Error>> {
+    let (threads, children) = match start_children(vm, &actions) {
+        Ok(x) => x,
+        Err(err) => return Either::Right(future::ready(RuntimeResult::Panic(err))),
+    };
+    Either::Left(async move {
+        let result = future::try_join_all(children).await;
+        if result.is_err() {
+            cancel_threads(&threads);
+        }
+        result.map(IO::Value).into()
+    })
+}
+
+/// Runs every action in its own thread, returning the result of the first action to finish and
+/// cancelling the rest
+fn race(
+    WithVM { vm, value: actions }: WithVM<Vec<OpaqueRef<IO<A>>>>,
+) -> impl Future<Output = RuntimeResult<IO<Generic<A>>, Error>> {
+    if actions.is_empty() {
+        return Either::Right(future::ready(RuntimeResult::Panic(
+            "`race` requires at least one action".to_string().into(),
+        )));
+    }
+    let (threads, children) = match start_children(vm, &actions) {
+        Ok(x) => x,
+        Err(err) => return Either::Right(future::ready(RuntimeResult::Panic(err))),
+    };
+    Either::Left(async move {
+        let (result, index, _) = future::select_all(children).await;
+        cancel_threads(
+            threads
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| i != index)
+                .map(|(_, thread)| thread),
+        );
+        result.map(IO::Value).into()
+    })
+}
+
+/// A scope which the threads of `Task`s are spawned in. Any task still running when the scope
+/// exits is cancelled, as are all of them if one of them fails.
+#[derive(Clone, Userdata, VmType)]
+#[gluon(vm_type = "std.thread.TaskScope")]
+#[gluon(gluon_vm)]
+pub struct TaskScope(Arc<Mutex<ScopeState>>);
+
+#[derive(Default)]
+struct ScopeState {
+    children: Vec<(Arc<Mutex<TaskState>>, Child<()>)>,
+    /// The task driving the scope, woken when a new child is spawned
+    waker: Option<Waker>,
+    exited: bool,
+}
+
+impl fmt::Debug for TaskScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TaskScope")
+    }
+}
+
+unsafe impl Trace for TaskScope {
+    impl_trace! { self, _gc, { } }
+}
+
+impl TaskScope {
+    /// Polls the children of the scope, returning `Ready` with the error of the first child that
+    /// fails
+    fn poll_children(&self, cx: &mut task::Context<'_>) -> Poll<Error> {
+        // Take the children out of the lock so that they can spawn tasks in this scope
+        let mut children = {
+            let mut state = self.0.lock().unwrap();
+            state.waker = Some(cx.waker().clone());
+            mem::take(&mut state.children)
+        };
+
+        let mut result = Poll::Pending;
+        let mut i = 0;
+        while i < children.len() {
+            match children[i].1.as_mut().poll(cx) {
+                Poll::Ready(Ok(())) => {
+                    children.remove(i);
+                }
+                Poll::Ready(Err(err)) => {
+                    if result.is_pending() {
+                        result = Poll::Ready(err);
+                    }
+                    children.remove(i);
+                }
+                Poll::Pending => i += 1,
+            }
+        }
+
+        let mut state = self.0.lock().unwrap();
+        let spawned = mem::replace(&mut state.children, children);
+        if !spawned.is_empty() {
+            state.children.extend(spawned);
+            cx.waker().wake_by_ref();
+        }
+        result
+    }
+
+    /// Cancels every task which is still running and prevents any more from being spawned
+    fn exit(&self) {
+        let children = {
+            let mut state = self.0.lock().unwrap();
+            state.exited = true;
+            mem::take(&mut state.children)
+        };
+        for (task, _) in children {
+            task.lock().unwrap().cancel();
+        }
+    }
+}
+
+/// A handle to an action running in its own thread, spawned with `spawn_task`
+#[derive(Userdata, VmType)]
+#[gluon(vm_type = "std.thread.Task")]
+#[gluon(gluon_vm)]
+pub struct Task<T>(Arc<Mutex<TaskState>>, PhantomData<T>);
+
+struct TaskState {
+    thread: RootedThread,
+    /// The result of the task, `Err` if it failed or were cancelled
+    result: Option<Result<Generic<A>, String>>,
+    /// Tasks waiting for the result
+    waiters: Vec<Waker>,
+}
+
+impl TaskState {
+    fn finish(&mut self, result: Result<Generic<A>, String>) {
+        if self.result.is_none() {
+            self.result = Some(result);
+        }
+        wake_all(&mut self.waiters);
+    }
+
+    fn cancel(&mut self) {
+        self.thread.interrupt();
+        self.finish(Err("Task was cancelled".into()));
+    }
+}
+
+impl<T> fmt::Debug for Task<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Task")
+    }
+}
+
+unsafe impl<T> Trace for Task<T> {
+    impl_trace! { self, _gc, { } }
+}
+
+/// Runs `body` with a new `TaskScope`. Once `body` finishes any tasks it spawned which are still
+/// running are cancelled. If a task fails before that, `body` and the other tasks are cancelled and
+/// the error is propagated.
+fn scope(
+    WithVM { vm, value: body }: WithVM<OpaqueRef<fn(TaskScope) -> IO<A>>>,
+) -> impl Future<Output = RuntimeResult<IO<Generic<A>>, Error>> {
+    // The body runs in its own thread so that it can be abandoned if a task fails
+    let body_thread = match vm.new_thread() {
+        Ok(thread) => thread,
+        Err(err) => return Either::Right(future::ready(RuntimeResult::Panic(err))),
+    };
+    let mut body: OwnedFunction<fn(TaskScope) -> IO<Generic<A>>> =
+        Getable::from_value(&body_thread, body.get_variant());
+    let task_scope = TaskScope(Default::default());
+
+    Either::Left(async move {
+        let run_body = {
+            let task_scope = task_scope.clone();
+            async move {
+                match body.call_async(task_scope).await? {
+                    IO::Value(value) => Ok(value),
+                    IO::Exception(err) => Err(Error::Message(err)),
+                }
+            }
+        };
+        let children = future::poll_fn(|cx| task_scope.poll_children(cx));
+
+        let result = future::select(run_body.boxed(), children.boxed()).await;
+        task_scope.exit();
+        match result {
+            Either::Left((result, _)) => result.map(IO::Value).into(),
+            Either::Right((err, _)) => {
+                body_thread.interrupt();
+                RuntimeResult::Panic(err)
+            }
+        }
+    })
+}
+
+/// Spawns `action` in a new thread which runs as long as `task_scope` has not exited
+fn spawn_task(
+    WithVM {
+        vm,
+        value: task_scope,
+    }: WithVM<&TaskScope>,
+    action: OpaqueRef<IO<A>>,
+) -> IO<Task<A>> {
+    if task_scope.0.lock().unwrap().exited {
+        return IO::Exception("Attempted to spawn a task in a scope which has exited".into());
+    }
+
+    let thread = match vm.new_thread() {
+        Ok(thread) => thread,
+        Err(err) => return IO::Exception(err.to_string()),
+    };
+    let mut action: OwnedFunction<fn(()) -> Generic<A>> =
+        Getable::from_value(&thread, action.get_variant());
+    let task = Arc::new(Mutex::new(TaskState {
+        thread,
+        result: None,
+        waiters: Vec::new(),
+    }));
+
+    let child = {
+        let task = task.clone();
+        async move {
+            let result = action.call_async(()).await;
+            let mut task = task.lock().unwrap();
+            // A cancelled task fails with `Interrupted` which should not fail the scope
+            let cancelled = task.result.is_some();
+            match result {
+                Ok(value) => {
+                    task.finish(Ok(value));
+                    Ok(())
+                }
+                Err(err) => {
+                    task.finish(Err(err.to_string()));
+                    if cancelled {
+                        Ok(())
+                    } else {
+                        Err(err)
+                    }
+                }
+            }
+        }
+    };
+
+    let mut state = task_scope.0.lock().unwrap();
+    state.children.push((task.clone(), child.boxed()));
+    if let Some(waker) = state.waker.take() {
+        waker.wake();
+    }
+    IO::Value(Task(task, PhantomData))
+}
+
+/// Waits for `task` to finish, propagating the error if it failed or were cancelled
+fn await_task(task: &Task<A>) -> impl Future<Output = RuntimeResult<IO<Generic<A>>, String>> {
+    let task = task.0.clone();
+    future::poll_fn(move |cx| {
+        let mut task = task.lock().unwrap();
+        match &task.result {
+            Some(Ok(value)) => Poll::Ready(RuntimeResult::Return(IO::Value(value.clone()))),
+            Some(Err(err)) => Poll::Ready(RuntimeResult::Panic(err.clone())),
+            None => {
+                register(&mut task.waiters, cx);
+                Poll::Pending
+            }
+        }
+    })
+}
+
+/// Cancels `task`. Awaiting a cancelled task fails.
+fn cancel(task: &Task<A>) -> IO<()> {
+    task.0.lock().unwrap().cancel();
+    IO::Value(())
+}
+
 fn new_thread(WithVM { vm, .. }: WithVM<()>) -> IO<RootedThread> {
     match vm.new_thread() {
         Ok(thread) => IO::Value(thread),
@@ -396,16 +904,27 @@ pub fn load_channel<'vm>(vm: &'vm Thread) -> VmResult<ExternModule> {
             type Sender a => Sender<A>,
             type Receiver a => Sender<A>,
             channel => primitive!(1, std::channel::channel),
+            bounded_channel => primitive!(2, std::channel::bounded_channel),
             recv => primitive!(1, std::channel::recv),
             send => primitive!(2, std::channel::send),
+            recv_async => primitive!(1, async fn std::channel::recv_async),
+            send_async => primitive!(2, async fn std::channel::send_async),
+            recv_timeout => primitive!(2, async fn std::channel::recv_timeout),
+            select => primitive!(1, async fn std::channel::select),
+            close => primitive!(1, std::channel::close),
         },
     )
 }
 
 pub fn load_thread<'vm>(vm: &'vm Thread) -> VmResult<ExternModule> {
+    let _ = vm.register_type::<TaskScope>("std.thread.TaskScope", &[]);
+    let _ = vm.register_type::<Task<A>>("std.thread.Task", &["a"]);
+
     ExternModule::new(
         vm,
         record! {
+            type TaskScope => TaskScope,
+            type Task a => Task<A>,
             resume => primitive!(1, async fn std::thread::prim::resume),
             (yield_ "yield") => primitive!(1, "std.thread.prim.yield", async fn std::thread::prim::yield_),
             spawn => primitive!(1, std::thread::prim::spawn),
@@ -414,6 +933,12 @@ pub fn load_thread<'vm>(vm: &'vm Thread) -> VmResult<ExternModule> {
             interrupt => primitive!(1, std::thread::prim::interrupt),
             sleep => primitive!(1, std::thread::prim::sleep),
             join => primitive!(2, async fn std::thread::prim::join),
+            join_all => primitive!(1, async fn std::thread::prim::join_all),
+            race => primitive!(1, async fn std::thread::prim::race),
+            scope => primitive!(1, async fn std::thread::prim::scope),
+            spawn_task => primitive!(2, std::thread::prim::spawn_task),
+            await_task => primitive!(1, async fn std::thread::prim::await_task),
+            cancel => primitive!(1, std::thread::prim::cancel),
         },
     )
 }
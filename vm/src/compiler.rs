@@ -238,10 +238,14 @@ impl FunctionEnvs {
         let instructions = self.function.instructions.len();
 
         if compiler.emit_debug_info {
-            self.function
-                .debug_info
-                .source_map
-                .close(instructions, current_line);
+            let line = self.current_line;
+            let source_map = &mut self.function.debug_info.source_map;
+            if compiler.coverage {
+                // Without this `Return` would belong to the last line which emitted an
+                // instruction, which may be the last alternative of a `match`
+                source_map.emit(instructions - 1, line);
+            }
+            source_map.close(instructions, current_line);
 
             let upvars_are_globals = self.envs.len() == 1;
             if !upvars_are_globals {
@@ -452,6 +456,7 @@ pub struct Compiler<'a> {
     source: &'a ::codespan::FileMap,
     source_name: String,
     emit_debug_info: bool,
+    coverage: bool,
     empty_symbol: Symbol,
     hole: ArcType,
 }
@@ -488,6 +493,7 @@ impl<'a> Compiler<'a> {
         source: &'a ::codespan::FileMap,
         source_name: String,
         emit_debug_info: bool,
+        coverage: bool,
     ) -> Compiler<'a> {
         Compiler {
             globals: globals,
@@ -498,6 +504,7 @@ impl<'a> Compiler<'a> {
             source: source,
             source_name: source_name,
             emit_debug_info: emit_debug_info,
+            coverage: coverage,
             hole: Type::hole(),
         }
     }
@@ -785,6 +792,7 @@ impl<'a> Compiler<'a> {
                 function.emit_call(args.len() as VmIndex, tail_position);
             }
             Expr::Match(ref expr, ref alts) => {
+                let match_line = function.current_line;
                 self.compile(expr, function, false)?;
                 // Indexes for each alternative for a successful match to the alternatives code
                 let mut start_jumps = Vec::new();
@@ -872,6 +880,11 @@ impl<'a> Compiler<'a> {
                 // after the alternative
                 let mut end_jumps = Vec::new();
                 for (alt, &start_index) in alts.iter().zip(start_jumps.iter()) {
+                    if self.coverage {
+                        // Code which binds the pattern belongs to the match, not to the end of
+                        // the previous alternative
+                        function.current_line = match_line;
+                    }
                     function.stack.enter_scope();
                     match alt.pattern {
                         Pattern::Constructor(_, ref args) => {
@@ -908,6 +921,10 @@ impl<'a> Compiler<'a> {
                     function.function.instructions[index] =
                         Jump(function.function.instructions.len() as VmIndex);
                 }
+                if self.coverage {
+                    // Likewise, the code after the match belongs to the match itself
+                    function.current_line = match_line;
+                }
             }
             Expr::Data(ref id, exprs, _) => {
                 for expr in exprs {
@@ -1190,6 +1207,7 @@ mod tests {
             &source,
             "test".into(),
             false,
+            false,
         );
         let module = compiler.compile_expr(&global).unwrap();
 
@@ -28,7 +28,6 @@ pub mod costs;
 pub mod dead_code;
 pub mod interpreter;
 pub mod optimize;
-#[cfg(feature = "test")]
 mod pretty;
 pub mod purity;
 
@@ -132,17 +131,24 @@ impl fmt::Display for Pattern {
     }
 }
 
-#[cfg(feature = "test")]
-impl<'a> fmt::Display for Expr<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl<'a> Expr<'a> {
+    /// Pretty prints the expression, breaking lines which are longer than `width`
+    pub fn pretty_string(&self, width: usize) -> String {
         use crate::core::pretty::Prec;
         let arena = ::pretty::Arena::new();
         let mut s = Vec::new();
         self.pretty(&arena, Prec::Top)
             .1
-            .render(100, &mut s)
+            .render(width, &mut s)
             .unwrap();
-        write!(f, "{}", ::std::str::from_utf8(&s).expect("utf-8"))
+        String::from_utf8(s).expect("utf-8")
+    }
+}
+
+#[cfg(feature = "test")]
+impl<'a> fmt::Display for Expr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.pretty_string(100))
     }
 }
 
@@ -976,12 +982,56 @@ impl<'a, 'e> Translator<'a, 'e> {
 
             ast::Expr::Literal(ref literal) => Expr::Const(Literal::from_ast(literal), expr.span),
 
+            ast::Expr::Interpolation(ref interpolation) => {
+                let append_id = interpolation
+                    .append_id
+                    .as_ref()
+                    .unwrap_or_else(|| ice!("append_id must be set when translating to core"));
+                let literal = |lit: &Spanned<String, BytePos>| {
+                    Expr::Const(Literal::String(Box::from(&lit.value[..])), lit.span)
+                };
+
+                let mut parts = Vec::new();
+                for (lit, hole) in interpolation.literals.iter().zip(&*interpolation.exprs) {
+                    if !lit.value.is_empty() {
+                        parts.push(literal(lit));
+                    }
+                    let hole_expr = self.translate(&hole.expr);
+                    parts.push(match hole.show_id {
+                        Some(ref show_id) => Expr::Call(
+                            self.translate_alloc(show_id),
+                            arena.alloc_fixed(Some(hole_expr)),
+                        ),
+                        None => hole_expr,
+                    });
+                }
+                if let Some(lit) = interpolation.literals.last() {
+                    if !lit.value.is_empty() {
+                        parts.push(literal(lit));
+                    }
+                }
+
+                // Build `lit0 <> (hole0 <> (lit1 <> ...))` so the parts are evaluated from left
+                // to right
+                let mut parts = parts.into_iter().rev();
+                let last = parts
+                    .next()
+                    .unwrap_or_else(|| ice!("Interpolated string without any holes"));
+                parts.fold(last, |acc, part| {
+                    Expr::Call(
+                        self.translate_alloc(append_id),
+                        arena.alloc_fixed(iterator!(part, acc)),
+                    )
+                })
+            }
+
             ast::Expr::Match(ref expr, ref alts) => {
                 let expr = self.translate_alloc(&**expr);
                 let alts: Vec<_> = alts
                     .iter()
                     .map(|alt| Equation {
                         patterns: vec![&alt.pattern],
+                        guard: alt.guard.as_ref().map(|guard| self.translate_alloc(guard)),
                         result: self.translate_alloc(&alt.expr),
                     })
                     .collect();
@@ -1152,6 +1202,7 @@ impl<'a, 'e> Translator<'a, 'e> {
                             id_expr,
                             &[Equation {
                                 patterns: vec![&pat],
+                                guard: None,
                                 result: core_body,
                             }],
                         );
@@ -1186,7 +1237,9 @@ impl<'a, 'e> Translator<'a, 'e> {
                 Expr::Cast(arena.alloc(self.translate_(expr)), typ.clone())
             }
 
-            ast::Expr::Error(_) => self.error_expr("Evaluated an invalid exprssion"),
+            ast::Expr::Hole(_) | ast::Expr::Error(_) => {
+                self.error_expr("Evaluated an invalid exprssion")
+            }
         }
     }
 
@@ -1261,6 +1314,7 @@ impl<'a, 'e> Translator<'a, 'e> {
                             bind_expr,
                             &[Equation {
                                 patterns: vec![&bind.name],
+                                guard: None,
                                 result: tail,
                             }],
                         );
@@ -1467,6 +1521,8 @@ pub struct PatternTranslator<'a, 'e: 'a>(&'a Translator<'a, 'e>);
 #[derive(Clone, PartialEq, Debug)]
 struct Equation<'a, 'p, 'ast> {
     patterns: Vec<&'p SpannedPattern<'ast, Symbol>>,
+    /// Expression which must evaluate to `True` for `result` to be selected
+    guard: Option<&'a Expr<'a>>,
     result: &'a Expr<'a>,
 }
 
@@ -1474,8 +1530,11 @@ impl<'a, 'p, 'ast> fmt::Display for Equation<'a, 'p, 'ast> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "[({:?},{})]",
+            "[({:?},{}{})]",
             self.patterns.iter().format(", "),
+            self.guard
+                .map(|guard| format!("if {} -> ", guard))
+                .unwrap_or_default(),
             self.result
         )
     }
@@ -1617,15 +1676,15 @@ impl<'a, 'e> PatternTranslator<'a, 'e> {
             // (since those need to be solved first) and then the remaining_patterns
             let new_equations = equations
                 .iter()
-                .map(|equation| (&equation.patterns[1..], equation.result))
                 .zip(&temp)
-                .map(|((remaining_equations, result), first)| Equation {
+                .map(|(equation, first)| Equation {
                     patterns: first
                         .iter()
                         .map(|pattern| &**pattern)
-                        .chain(remaining_equations.iter().cloned())
+                        .chain(equation.patterns[1..].iter().cloned())
                         .collect(),
-                    result,
+                    guard: equation.guard,
+                    result: equation.result,
                 })
                 .collect::<Vec<_>>();
 
@@ -1673,6 +1732,7 @@ impl<'a, 'e> PatternTranslator<'a, 'e> {
                 | ast::Pattern::Record { .. }
                 | ast::Pattern::Ident(_)
                 | ast::Pattern::Literal(_)
+                | ast::Pattern::Or(_)
                 | ast::Pattern::Error => unreachable!(),
             }
         }
@@ -1721,6 +1781,7 @@ impl<'a, 'e> PatternTranslator<'a, 'e> {
                                 .iter()
                                 .chain(equation.patterns.iter().cloned().skip(1))
                                 .collect(),
+                            guard: equation.guard,
                             result: equation.result,
                         }
                     })
@@ -1760,6 +1821,7 @@ impl<'a, 'e> PatternTranslator<'a, 'e> {
                 .iter()
                 .map(|equation| Equation {
                     patterns: equation.patterns[1..].to_owned(),
+                    guard: equation.guard,
                     result: equation.result,
                 })
                 .collect::<Vec<_>>(),
@@ -1810,6 +1872,7 @@ impl<'a, 'e> PatternTranslator<'a, 'e> {
                 | ast::Pattern::Tuple { .. }
                 | ast::Pattern::Record { .. }
                 | ast::Pattern::Ident(_)
+                | ast::Pattern::Or(_)
                 | ast::Pattern::Error => unreachable!(),
             }
         }
@@ -1829,6 +1892,7 @@ impl<'a, 'e> PatternTranslator<'a, 'e> {
                     .iter()
                     .map(|equation| Equation {
                         patterns: equation.patterns.iter().cloned().skip(1).collect(),
+                        guard: equation.guard,
                         result: equation.result,
                     })
                     .collect::<Vec<_>>();
@@ -1916,10 +1980,23 @@ impl<'a, 'e> PatternTranslator<'a, 'e> {
                 ast::Pattern::Record { .. } | ast::Pattern::Tuple { .. } => CType::Record,
                 ast::Pattern::Constructor(_, _) => CType::Constructor,
                 ast::Pattern::Literal(_) => CType::Literal,
+                ast::Pattern::Or(_) => unreachable!(),
                 ast::Pattern::Error => ice!("ICE: Error pattern survived typechecking"),
             }
         }
 
+        if !variables.is_empty() {
+            let or_equation = equations.iter().position(|equation| {
+                match unwrap_as(&equation.patterns[0].value) {
+                    ast::Pattern::Or(_) => true,
+                    _ => false,
+                }
+            });
+            if let Some(index) = or_equation {
+                return self.translate_or(default, variables, equations, index);
+            }
+        }
+
         let mut binder = Binder::default();
 
         // The equations must be processed by group
@@ -1932,43 +2009,16 @@ impl<'a, 'e> PatternTranslator<'a, 'e> {
             .group_by(|equation| varcon(&equation.patterns.first().expect("Pattern").value));
 
         let expr = match variables.first() {
+            // All patterns have matched so the first equation is selected, unless its guard fails
+            // in which case we fall through to the next equation
             None => equations
-                .first()
-                .map(|equation| equation.result)
-                .unwrap_or(default),
+                .iter()
+                .rev()
+                .fold(default, |expr, equation| match equation.guard {
+                    Some(guard) => self.guarded(guard, equation.result, expr),
+                    None => equation.result,
+                }),
             Some(_) => {
-                fn bind_variables<'b>(
-                    env: &dyn PrimitiveEnv<Type = ArcType>,
-                    pat: &ast::SpannedPattern<'_, Symbol>,
-                    variable: CExpr<'b>,
-                    binder: &mut Binder<'b>,
-                ) {
-                    match pat.value {
-                        ast::Pattern::As(ref id, ref pat) => {
-                            binder.bind_id(
-                                TypedIdent {
-                                    name: id.value.clone(),
-                                    typ: pat.env_type_of(&env),
-                                },
-                                variable,
-                            );
-                            bind_variables(env, pat, variable, binder);
-                        }
-                        ast::Pattern::Record {
-                            implicit_import: Some(ref implicit_import),
-                            ..
-                        } => {
-                            binder.bind_id(
-                                TypedIdent {
-                                    name: implicit_import.value.clone(),
-                                    typ: pat.env_type_of(&env),
-                                },
-                                variable,
-                            );
-                        }
-                        _ => (),
-                    }
-                }
                 // Extract the identifier from each `id@PATTERN` and bind it with `let` before this match
                 {
                     for equation in equations {
@@ -2002,6 +2052,82 @@ impl<'a, 'e> PatternTranslator<'a, 'e> {
         binder.into_expr_ref(allocator, expr)
     }
 
+    // | PATTERN if GUARD -> RESULT
+    // // ==>
+    // if GUARD then RESULT else DEFAULT
+    fn guarded(
+        &self,
+        guard: &'a Expr<'a>,
+        result: &'a Expr<'a>,
+        default: &'a Expr<'a>,
+    ) -> &'a Expr<'a> {
+        let alts = self.0.allocator.alternative_arena.alloc_fixed(iterator!(
+            Alternative {
+                pattern: Pattern::Constructor(self.0.bool_constructor(true), vec![]),
+                expr: result,
+            },
+            Alternative {
+                pattern: Pattern::Constructor(self.0.bool_constructor(false), vec![]),
+                expr: default,
+            },
+        ));
+        self.0.allocator.arena.alloc(Expr::Match(guard, alts))
+    }
+
+    // The equation at `index` starts with an or-pattern. Each alternative of the or-pattern binds
+    // the same variables so they are translated separately (instead of being merged with other
+    // equations) and tried in order, falling through to the equations after the or-pattern.
+    //
+    // | A x | B x -> RESULT
+    // | REST
+    // // ==>
+    // | A x -> RESULT
+    // | B x -> RESULT
+    // | REST
+    fn translate_or<'p>(
+        &mut self,
+        default: &'a Expr<'a>,
+        variables: &[&'a Expr<'a>],
+        equations: &[Equation<'a, 'p, '_>],
+        index: usize,
+    ) -> &'a Expr<'a> {
+        let (before, rest) = equations.split_at(index);
+        let (equation, after) = rest.split_first().unwrap();
+
+        let mut expr = if after.is_empty() {
+            default
+        } else {
+            self.translate(default, variables, after)
+        };
+
+        let first = equation.patterns[0];
+        let alternatives = match unwrap_as(&first.value) {
+            ast::Pattern::Or(alternatives) => alternatives,
+            _ => unreachable!(),
+        };
+        for alternative in alternatives.iter().rev() {
+            let alternative_equation = Equation {
+                patterns: Some(alternative)
+                    .into_iter()
+                    .chain(equation.patterns[1..].iter().cloned())
+                    .collect(),
+                guard: equation.guard,
+                result: equation.result,
+            };
+            expr = self.translate(expr, variables, &[alternative_equation]);
+        }
+
+        let mut binder = Binder::default();
+        bind_variables(self.0.env, first, variables[0], &mut binder);
+        let expr = binder.into_expr_ref(&self.0.allocator, expr);
+
+        if before.is_empty() {
+            expr
+        } else {
+            self.translate(expr, variables, before)
+        }
+    }
+
     fn extract_ident(&self, index: usize, pattern: &ast::Pattern<Symbol>) -> TypedIdent<Symbol> {
         get_ident(pattern).unwrap_or_else(|| TypedIdent {
             name: Symbol::from(format!("pattern_{}", index)),
@@ -2068,7 +2194,7 @@ impl<'a, 'e> PatternTranslator<'a, 'e> {
                         }
                     }
                 }
-                ast::Pattern::As(..) => unreachable!(),
+                ast::Pattern::As(..) | ast::Pattern::Or(..) => unreachable!(),
                 ast::Pattern::Ident(ref id) => {
                     if core_pattern.is_none() {
                         core_pattern = Some(Pattern::Ident(id.clone()));
@@ -2150,6 +2276,39 @@ impl<'a, 'e> PatternTranslator<'a, 'e> {
     }
 }
 
+fn bind_variables<'b>(
+    env: &dyn PrimitiveEnv<Type = ArcType>,
+    pat: &ast::SpannedPattern<'_, Symbol>,
+    variable: CExpr<'b>,
+    binder: &mut Binder<'b>,
+) {
+    match pat.value {
+        ast::Pattern::As(ref id, ref pat) => {
+            binder.bind_id(
+                TypedIdent {
+                    name: id.value.clone(),
+                    typ: pat.env_type_of(&env),
+                },
+                variable,
+            );
+            bind_variables(env, pat, variable, binder);
+        }
+        ast::Pattern::Record {
+            implicit_import: Some(ref implicit_import),
+            ..
+        } => {
+            binder.bind_id(
+                TypedIdent {
+                    name: implicit_import.value.clone(),
+                    typ: pat.env_type_of(&env),
+                },
+                variable,
+            );
+        }
+        _ => (),
+    }
+}
+
 fn get_ident(pattern: &ast::Pattern<Symbol>) -> Option<TypedIdent<Symbol>> {
     match *pattern {
         ast::Pattern::Ident(ref id) => Some(id.clone()),
@@ -0,0 +1,467 @@
+//! Line and branch coverage of gluon code.
+//!
+//! A `CoverageRecorder` installs a hook on a thread which records every line that is executed as
+//! well as the outcome of every conditional jump (the branches of `if` expressions and the
+//! alternatives of `match` expressions). Lines and branches which are never executed are found
+//! from the bytecode of every module that is run while recording.
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Write as _,
+    fs,
+    io::{self, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use crate::base::pos::Line;
+
+use crate::{
+    gc::GcPtr,
+    stack::State,
+    thread::{DebugInfo, HookFlags, HookFn, RootedThread, Thread, ThreadInternal},
+    types::Instruction,
+    value::BytecodeFunction,
+};
+
+/// A conditional jump in the bytecode
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BranchCoverage {
+    pub line: Line,
+    /// Number of times the condition (or the pattern of a `match` alternative) were true
+    pub taken: u64,
+    /// Number of times the condition (or the pattern of a `match` alternative) were false.
+    /// `None` if the condition can't be false, which is the case for the last alternative of a
+    /// `match`.
+    pub not_taken: Option<u64>,
+}
+
+/// The coverage of a single module
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ModuleCoverage {
+    /// The number of times each line containing code were executed
+    pub lines: BTreeMap<Line, u64>,
+    /// Every conditional jump of the module, ordered by the line they appear on
+    pub branches: Vec<BranchCoverage>,
+}
+
+impl ModuleCoverage {
+    pub fn lines_found(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn lines_hit(&self) -> usize {
+        self.lines.values().filter(|&&hits| hits != 0).count()
+    }
+
+    /// Returns the number of possible branch outcomes
+    pub fn branches_found(&self) -> usize {
+        self.branches
+            .iter()
+            .map(|branch| 1 + branch.not_taken.is_some() as usize)
+            .sum()
+    }
+
+    pub fn branches_hit(&self) -> usize {
+        self.branches
+            .iter()
+            .map(|branch| {
+                (branch.taken != 0) as usize + branch.not_taken.map_or(0, |n| (n != 0) as usize)
+            })
+            .sum()
+    }
+}
+
+/// The coverage recorded by a `CoverageRecorder`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Coverage {
+    modules: BTreeMap<String, ModuleCoverage>,
+}
+
+impl Coverage {
+    pub fn module(&self, name: &str) -> Option<&ModuleCoverage> {
+        self.modules.get(name)
+    }
+
+    /// Iterates over all modules in alphabetical order
+    pub fn modules(&self) -> impl Iterator<Item = (&str, &ModuleCoverage)> {
+        self.modules
+            .iter()
+            .map(|(name, module)| (&name[..], module))
+    }
+
+    /// Removes all modules for which `f` returns `false`
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str) -> bool,
+    {
+        self.modules = std::mem::replace(&mut self.modules, BTreeMap::new())
+            .into_iter()
+            .filter(|(name, _)| f(name))
+            .collect();
+    }
+
+    /// Returns the percentage of lines which were executed, over all modules
+    pub fn line_rate(&self) -> f64 {
+        let (hit, found) = self.modules.values().fold((0, 0), |(hit, found), module| {
+            (hit + module.lines_hit(), found + module.lines_found())
+        });
+        percentage(hit, found)
+    }
+
+    /// Returns the percentage of branch outcomes which occurred, over all modules
+    pub fn branch_rate(&self) -> f64 {
+        let (hit, found) = self.modules.values().fold((0, 0), |(hit, found), module| {
+            (hit + module.branches_hit(), found + module.branches_found())
+        });
+        percentage(hit, found)
+    }
+
+    /// Writes the coverage in the lcov tracefile format. `path` maps module names to the path
+    /// written as the source file (`SF`) of each module.
+    pub fn write_lcov<W, F>(&self, writer: &mut W, mut path: F) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+        F: FnMut(&str) -> String,
+    {
+        for (name, module) in &self.modules {
+            writeln!(writer, "TN:")?;
+            writeln!(writer, "SF:{}", path(name))?;
+            for (block, branch) in module.branches.iter().enumerate() {
+                let line = branch.line.number();
+                writeln!(writer, "BRDA:{},{},0,{}", line, block, branch.taken)?;
+                if let Some(not_taken) = branch.not_taken {
+                    writeln!(writer, "BRDA:{},{},1,{}", line, block, not_taken)?;
+                }
+            }
+            writeln!(writer, "BRF:{}", module.branches_found())?;
+            writeln!(writer, "BRH:{}", module.branches_hit())?;
+            for (line, hits) in &module.lines {
+                writeln!(writer, "DA:{},{}", line.number(), hits)?;
+            }
+            writeln!(writer, "LF:{}", module.lines_found())?;
+            writeln!(writer, "LH:{}", module.lines_hit())?;
+            writeln!(writer, "end_of_record")?;
+        }
+        Ok(())
+    }
+
+    /// Writes an HTML report to `dir`, consisting of an `index.html` summary and one page for each
+    /// module with its source annotated with the coverage. `source` returns the source code of a
+    /// module, modules without source code are only included in the summary.
+    pub fn write_html<F>(&self, dir: &Path, mut source: F) -> io::Result<()>
+    where
+        F: FnMut(&str) -> Option<String>,
+    {
+        fs::create_dir_all(dir)?;
+
+        let mut index = String::new();
+        html_header(&mut index, "Coverage report");
+        let _ = writeln!(
+            index,
+            "<p>Lines: {:.1}% Branches: {:.1}%</p>",
+            self.line_rate(),
+            self.branch_rate()
+        );
+        index.push_str("<table>\n<tr><th>Module</th><th>Lines</th><th>Branches</th></tr>\n");
+        for (name, module) in &self.modules {
+            let module_source = source(name);
+            let module_name = if module_source.is_some() {
+                format!("<a href=\"{0}.html\">{0}</a>", escape_html(name))
+            } else {
+                escape_html(name)
+            };
+            let _ = writeln!(
+                index,
+                "<tr><td>{}</td><td>{:.1}% ({}/{})</td><td>{:.1}% ({}/{})</td></tr>",
+                module_name,
+                percentage(module.lines_hit(), module.lines_found()),
+                module.lines_hit(),
+                module.lines_found(),
+                percentage(module.branches_hit(), module.branches_found()),
+                module.branches_hit(),
+                module.branches_found(),
+            );
+
+            if let Some(module_source) = module_source {
+                let page = module_html(name, module, &module_source);
+                fs::write(dir.join(format!("{}.html", name)), page)?;
+            }
+        }
+        index.push_str("</table>\n</body>\n</html>\n");
+        fs::write(dir.join("index.html"), index)
+    }
+}
+
+fn percentage(hit: usize, found: usize) -> f64 {
+    if found == 0 {
+        100.
+    } else {
+        100. * hit as f64 / found as f64
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn html_header(out: &mut String, title: &str) {
+    let _ = write!(
+        out,
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{}</title>
+<style>
+body {{ font-family: sans-serif; }}
+td, th {{ padding: 0 1em; text-align: left; }}
+pre {{ margin: 0; }}
+.hit {{ background-color: #cfc; }}
+.miss {{ background-color: #fcc; }}
+.partial {{ background-color: #ffc; }}
+.count, .branches {{ color: #666; text-align: right; }}
+</style>
+</head>
+<body>
+<h1>{}</h1>
+"#,
+        escape_html(title),
+        escape_html(title)
+    );
+}
+
+fn module_html(name: &str, module: &ModuleCoverage, source: &str) -> String {
+    let mut out = String::new();
+    html_header(&mut out, name);
+    let _ = writeln!(
+        out,
+        "<p><a href=\"index.html\">index</a> Lines: {:.1}% Branches: {:.1}%</p>",
+        percentage(module.lines_hit(), module.lines_found()),
+        percentage(module.branches_hit(), module.branches_found())
+    );
+    out.push_str("<table>\n");
+    for (i, text) in source.lines().enumerate() {
+        let line = Line::from(i as u32);
+        let branches: Vec<_> = module
+            .branches
+            .iter()
+            .filter(|branch| branch.line == line)
+            .collect();
+        let partial = branches
+            .iter()
+            .any(|branch| branch.taken == 0 || branch.not_taken == Some(0));
+        let (class, count) = match module.lines.get(&line) {
+            Some(0) => ("miss", "0".to_string()),
+            Some(_) if partial => ("partial", module.lines[&line].to_string()),
+            Some(hits) => ("hit", hits.to_string()),
+            None => ("", String::new()),
+        };
+        let branches = branches
+            .iter()
+            .map(|branch| match branch.not_taken {
+                Some(not_taken) => format!("[{}/{}]", branch.taken, not_taken),
+                None => format!("[{}]", branch.taken),
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let _ = writeln!(
+            out,
+            "<tr class=\"{}\"><td class=\"count\">{}</td><td class=\"count\">{}</td>\
+             <td class=\"branches\">{}</td><td><pre>{}</pre></td></tr>",
+            class,
+            i + 1,
+            count,
+            branches,
+            escape_html(text)
+        );
+    }
+    out.push_str("</table>\n</body>\n</html>\n");
+    out
+}
+
+#[derive(Default)]
+struct ModuleRecord {
+    lines: BTreeMap<Line, u64>,
+    // Keyed on the function name and the index of the `CJump` instruction
+    branches: BTreeMap<(Line, String, usize), BranchCoverage>,
+}
+
+#[derive(Default)]
+struct Recorder {
+    modules: BTreeMap<String, ModuleRecord>,
+    // Addresses of the functions which have been registered
+    registered_functions: HashSet<usize>,
+}
+
+impl Recorder {
+    /// Adds every line and branch of `function` and the functions defined in it to the coverage
+    fn register(&mut self, function: &GcPtr<BytecodeFunction>) {
+        if !self
+            .registered_functions
+            .insert(&**function as *const BytecodeFunction as usize)
+        {
+            return;
+        }
+        let module = self
+            .modules
+            .entry(function.debug_info.source_name.clone())
+            .or_default();
+        let source_map = &function.debug_info.source_map;
+        for (_, line) in source_map.instruction_lines(function.instructions.len()) {
+            module.lines.entry(line).or_insert(0);
+        }
+        let jump_targets: HashSet<_> = function
+            .instructions
+            .iter()
+            .filter_map(|instruction| match *instruction {
+                Instruction::Jump(target) | Instruction::CJump(target) => Some(target as usize),
+                _ => None,
+            })
+            .collect();
+        for (index, instruction) in function.instructions.iter().enumerate() {
+            if let Instruction::CJump(_) = instruction {
+                if let Some(line) = source_map.line(index) {
+                    // The last test of a `match` falls through into the code of an alternative,
+                    // which only happens if none of the patterns matched
+                    let can_fall_through = !jump_targets.contains(&(index + 1));
+                    module
+                        .branches
+                        .entry((line, function.name.declared_name().to_string(), index))
+                        .or_insert_with(|| BranchCoverage {
+                            line,
+                            taken: 0,
+                            not_taken: if can_fall_through { Some(0) } else { None },
+                        });
+                }
+            }
+        }
+        for inner in &function.inner_functions {
+            self.register(inner);
+        }
+    }
+
+    fn record(&mut self, info: &DebugInfo) {
+        let frame = match info.stack.get_frames().last() {
+            Some(frame) => frame,
+            None => return,
+        };
+        let (function, index) = match &frame.state {
+            State::Closure(state) => (&state.closure.function, state.instruction_index),
+            _ => return,
+        };
+        self.register(function);
+
+        let line = match function.debug_info.source_map.line(index) {
+            Some(line) => line,
+            None => return,
+        };
+        let module = self
+            .modules
+            .entry(function.debug_info.source_name.clone())
+            .or_default();
+        match info.branch_taken() {
+            Some(taken) => {
+                let key = (line, function.name.declared_name().to_string(), index);
+                let branch = module
+                    .branches
+                    .entry(key)
+                    .or_insert_with(|| BranchCoverage {
+                        line,
+                        ..BranchCoverage::default()
+                    });
+                if taken {
+                    branch.taken += 1;
+                } else {
+                    *branch.not_taken.get_or_insert(0) += 1;
+                }
+            }
+            None => *module.lines.entry(line).or_insert(0) += 1,
+        }
+    }
+
+    fn finish(self) -> Coverage {
+        Coverage {
+            modules: self
+                .modules
+                .into_iter()
+                .map(|(name, module)| {
+                    (
+                        name,
+                        ModuleCoverage {
+                            lines: module.lines,
+                            branches: module.branches.into_iter().map(|(_, b)| b).collect(),
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Records the coverage of all code executed by a thread.
+///
+/// Only the thread which the recorder were started on is recorded, code executed by other threads
+/// is not recorded.
+pub struct CoverageRecorder {
+    thread: RootedThread,
+    recorder: Arc<Mutex<Recorder>>,
+    /// The hook and hook mask which were installed before the recorder started, `None` once they
+    /// have been restored
+    previous_hook: Option<(Option<HookFn>, HookFlags)>,
+}
+
+impl Drop for CoverageRecorder {
+    fn drop(&mut self) {
+        self.restore_hook();
+    }
+}
+
+impl CoverageRecorder {
+    /// Starts recording the coverage of `thread`. Any hook previously set with `Context::set_hook`
+    /// is replaced while recording and restored once the recorder is finished or dropped.
+    pub fn start(thread: &Thread) -> CoverageRecorder {
+        let recorder = Arc::new(Mutex::new(Recorder::default()));
+        let previous_hook = {
+            let recorder = recorder.clone();
+            let mut context = thread.context();
+            let previous_hook_mask = context.hook_mask();
+            let previous_hook = context.set_hook(Some(Box::new(move |_, info| {
+                recorder.lock().unwrap().record(&info);
+                Ok(()).into()
+            })));
+            context.set_hook_mask(HookFlags::LINE_FLAG | HookFlags::BRANCH_FLAG);
+            (previous_hook, previous_hook_mask)
+        };
+        CoverageRecorder {
+            thread: thread.root_thread(),
+            recorder,
+            previous_hook: Some(previous_hook),
+        }
+    }
+
+    /// Restores the hook which were installed before the recorder started and returns the
+    /// recorded coverage
+    pub fn finish(mut self) -> Coverage {
+        self.restore_hook();
+        let recorder = std::mem::take(&mut *self.recorder.lock().unwrap());
+        recorder.finish()
+    }
+
+    fn restore_hook(&mut self) {
+        if let Some((hook, hook_mask)) = self.previous_hook.take() {
+            let mut context = self.thread.context();
+            context.set_hook(hook);
+            context.set_hook_mask(hook_mask);
+        }
+    }
+}
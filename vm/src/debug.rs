@@ -1,6 +1,7 @@
 use crate::{
-    api::{generic::A, Generic, OpaqueRef},
+    api::{generic::A, Generic, OpaqueRef, WithVM, IO},
     thread::Thread,
+    types::VmInt,
     value::ValueRepr,
     ExternModule, Result,
 };
@@ -20,6 +21,78 @@ fn tag(a: OpaqueRef<A>) -> Option<String> {
     }
 }
 
+field_decl! {
+    collections,
+    minor_collections,
+    major_collections,
+    total_pause,
+    max_pause,
+    bytes_freed,
+    allocated_memory,
+    closures,
+    records,
+    arrays,
+    strings,
+    functions,
+    threads,
+    userdata,
+    other,
+    name,
+    bytes
+}
+
+type UserdataBytes = record_type!(name => String, bytes => VmInt);
+
+type GcStatsRecord = record_type!(
+    collections => VmInt,
+    minor_collections => VmInt,
+    major_collections => VmInt,
+    total_pause => f64,
+    max_pause => f64,
+    bytes_freed => VmInt,
+    allocated_memory => VmInt,
+    closures => VmInt,
+    records => VmInt,
+    arrays => VmInt,
+    strings => VmInt,
+    functions => VmInt,
+    threads => VmInt,
+    userdata => Vec<UserdataBytes>,
+    other => VmInt
+);
+
+fn gc_stats(WithVM { vm, .. }: WithVM<()>) -> IO<GcStatsRecord> {
+    let stats = vm.gc_stats();
+    let live = &stats.live;
+    IO::Value(record_no_decl!(
+        collections => stats.collections() as VmInt,
+        minor_collections => stats.pauses.minor_collections as VmInt,
+        major_collections => stats.pauses.major_collections as VmInt,
+        total_pause => stats.total_pause().as_secs_f64(),
+        max_pause => stats.pauses.max_pause.as_secs_f64(),
+        bytes_freed => stats.bytes_freed as VmInt,
+        allocated_memory => stats.allocated_memory as VmInt,
+        closures => live.closures as VmInt,
+        records => live.records as VmInt,
+        arrays => live.arrays as VmInt,
+        strings => live.strings as VmInt,
+        functions => live.functions as VmInt,
+        threads => live.threads as VmInt,
+        userdata => live
+            .userdata
+            .iter()
+            .map(|(name, bytes)| {
+                record_no_decl!(name => name.to_string(), bytes => *bytes as VmInt)
+            })
+            .collect(),
+        other => live.other as VmInt
+    ))
+}
+
+fn heap_snapshot(WithVM { vm, .. }: WithVM<()>) -> IO<String> {
+    IO::Value(vm.heap_snapshot().to_json())
+}
+
 mod std {
     pub use crate::debug;
 }
@@ -30,7 +103,9 @@ pub fn load(vm: &Thread) -> Result<ExternModule> {
         record! {
             trace => primitive!(1, std::debug::trace),
             show => primitive!(1, std::debug::show),
-            tag => primitive!(1, std::debug::tag)
+            tag => primitive!(1, std::debug::tag),
+            gc_stats => primitive!(1, std::debug::gc_stats),
+            heap_snapshot => primitive!(1, std::debug::heap_snapshot)
         },
     )
 }
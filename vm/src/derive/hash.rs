@@ -0,0 +1,192 @@
+use crate::base::{
+    ast::{
+        self, Alternative, Argument, Expr, ExprField, Literal, Pattern, TypeBinding, TypedIdent,
+        ValueBinding,
+    },
+    pos,
+    symbol::{Symbol, Symbols},
+    types::{ctor_args, remove_forall, row_iter, Type, TypeContext},
+};
+
+use crate::macros::Error;
+
+use crate::derive::*;
+
+pub fn generate<'ast>(
+    mut arena: ast::ArenaRef<'_, 'ast, Symbol>,
+    symbols: &mut Symbols,
+    bind: &TypeBinding<'ast, Symbol>,
+) -> Result<ValueBinding<'ast, Symbol>, Error> {
+    let span = bind.name.span;
+
+    let x = Symbol::from("x");
+    let hash_fn = TypedIdent::new(symbols.simple_symbol("hash_"));
+
+    // `std.hash` is bound to a name which is unlikely to shadow any user defined bindings
+    let hash_module = symbols.simple_symbol("derive_hash_module");
+    let hash_module_app = |symbols: &mut Symbols, name: &str, args| {
+        let func = pos::spanned(
+            span,
+            Expr::Projection(
+                arena.alloc(ident(span, hash_module.clone())),
+                symbols.simple_symbol(name),
+                Type::hole(),
+            ),
+        );
+        pos::spanned(
+            span,
+            Expr::App {
+                func: arena.alloc(func),
+                implicit_args: &mut [],
+                args: arena.alloc_extend(args),
+            },
+        )
+    };
+
+    // Hashes each field in turn and combines it into the hash of the preceding fields, starting from
+    // `seed`
+    let combine_fields =
+        |symbols: &mut Symbols, seed: i64, fields: &[(bool, TypedIdent<Symbol>)]| {
+            let seed = pos::spanned(span, Expr::Literal(Literal::Int(seed)));
+            fields.iter().fold(seed, |acc, &(self_type, ref field)| {
+                let field_expr = ident(span, field.name.clone());
+                let hash = if self_type {
+                    arena.app(span, hash_fn.name.clone(), vec![field_expr])
+                } else {
+                    hash_module_app(symbols, "hash", vec![field_expr])
+                };
+                hash_module_app(
+                    symbols,
+                    "combine",
+                    vec![arena.paren(span, acc), arena.paren(span, hash)],
+                )
+            })
+        };
+
+    let hash_expr = match **remove_forall(bind.alias.value.unresolved_type()) {
+        Type::Variant(ref variants) => {
+            let alts: Vec<_> = row_iter(variants)
+                .enumerate()
+                .map(|(tag, variant)| {
+                    let pattern_args: Vec<_> = ctor_args(&variant.typ)
+                        .enumerate()
+                        .map(|(i, field)| {
+                            (
+                                is_self_type(&bind.alias.value.name, field),
+                                TypedIdent::new(Symbol::from(format!("arg_{}", i))),
+                            )
+                        })
+                        .collect();
+
+                    let expr = combine_fields(symbols, tag as i64, &pattern_args);
+
+                    Alternative {
+                        pattern: pos::spanned(
+                            span,
+                            Pattern::Constructor(
+                                TypedIdent::new(variant.name.clone()),
+                                arena.alloc_extend(
+                                    pattern_args
+                                        .into_iter()
+                                        .map(|(_, arg)| pos::spanned(span, Pattern::Ident(arg))),
+                                ),
+                            ),
+                        ),
+                        guard: None,
+                        expr,
+                    }
+                })
+                .collect();
+            Expr::Match(
+                arena.alloc(ident(span, x.clone())),
+                arena.alloc_extend(alts),
+            )
+        }
+        Type::Record(ref row) => {
+            let field_symbols: Vec<_> = row_iter(row)
+                .map(|field| {
+                    (
+                        is_self_type(&bind.alias.value.name, &field.typ),
+                        TypedIdent::new(Symbol::from(format!("{}", field.name.declared_name()))),
+                    )
+                })
+                .collect();
+
+            let expr = combine_fields(symbols, 0, &field_symbols);
+
+            Expr::Match(
+                arena.alloc(ident(span, x.clone())),
+                arena.alloc_extend(Some(Alternative {
+                    pattern: arena.generate_record_pattern(
+                        span,
+                        row,
+                        field_symbols.into_iter().map(|(_, field)| field),
+                    ),
+                    guard: None,
+                    expr,
+                })),
+            )
+        }
+        _ => return Err(Error::message("Unable to derive Hash for this type")),
+    };
+
+    let mut self_type = {
+        let mut arena = arena;
+        move || bind.alias.value.self_type(&mut arena)
+    };
+
+    let mut hash_import = arena.generate_import_(span, symbols, &[], &[], true, "std.hash");
+    hash_import.name = pos::spanned(
+        span,
+        Pattern::As(
+            pos::spanned(span, hash_module.clone()),
+            arena.alloc(hash_import.name),
+        ),
+    );
+
+    let hash_record_expr = Expr::rec_let_bindings(
+        arena,
+        Some(ValueBinding {
+            name: pos::spanned(span, Pattern::Ident(hash_fn.clone())),
+            args: arena.alloc_extend(Some(Argument::explicit(pos::spanned(
+                span,
+                TypedIdent::new(x.clone()),
+            )))),
+            expr: pos::spanned(span, hash_expr),
+            metadata: Default::default(),
+            typ: Some(arena.clone().function(vec![self_type()], arena.int())),
+            resolved_type: Type::hole(),
+        }),
+        pos::spanned(
+            span,
+            Expr::Record {
+                typ: Type::hole(),
+                types: &mut [],
+                exprs: arena.alloc_extend(Some(ExprField {
+                    metadata: Default::default(),
+                    name: pos::spanned(span, symbols.simple_symbol("hash")),
+                    value: Some(ident(span, hash_fn.name.clone())),
+                })),
+                base: None,
+            },
+        ),
+    );
+
+    Ok(ValueBinding {
+        name: pos::spanned(
+            span,
+            Pattern::Ident(TypedIdent::new(symbols.simple_symbol(format!(
+                "hash_{}",
+                bind.alias.value.name.declared_name()
+            )))),
+        ),
+        args: &mut [],
+        expr: pos::spanned(
+            span,
+            Expr::let_binding(arena, hash_import, pos::spanned(span, hash_record_expr)),
+        ),
+        metadata: Default::default(),
+        typ: Some(binding_type(arena, symbols, "Hash", self_type(), bind)),
+        resolved_type: Type::hole(),
+    })
+}
@@ -13,6 +13,7 @@ use crate::macros::Error;
 
 mod deserialize;
 mod eq;
+mod hash;
 mod serialize;
 mod show;
 
@@ -29,6 +30,7 @@ pub fn generate<'ast>(
             .map(|arg| {
                 Ok(match arg {
                     "Eq" => eq::generate(arena, symbols, bind),
+                    "Hash" => hash::generate(arena, symbols, bind),
                     "Show" => show::generate(arena, symbols, bind),
                     "Deserialize" => deserialize::generate(arena, symbols, bind),
                     "Serialize" => serialize::generate(arena, symbols, bind),
@@ -80,6 +80,7 @@ pub fn generate<'ast>(
                     arena.alloc(ident(span, x.clone())),
                     arena.alloc_extend(Some(Alternative {
                         pattern: arena.generate_record_pattern(span, row, field_symbols),
+                        guard: None,
                         expr,
                     })),
                 ),
@@ -137,6 +138,7 @@ pub fn generate<'ast>(
                     };
                     Ok(Alternative {
                         pattern: ctor_pattern(pattern_args),
+                        guard: None,
                         expr,
                     })
                 })
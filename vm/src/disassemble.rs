@@ -0,0 +1,166 @@
+//! Human readable listings of the bytecode produced by the compiler.
+//!
+//! Each function is printed with its string constants, record layouts and upvariables followed by
+//! its instructions. Instructions which refer to one of those tables are annotated with the value
+//! they refer to and the first instruction of each line is prefixed by that line's number (taken
+//! from the function's `SourceMap`). Inner functions are printed after the function which
+//! contains them.
+use std::fmt;
+
+use crate::base::symbol::Symbol;
+
+use crate::{
+    compiler::{CompiledFunction, CompiledModule},
+    types::{
+        Instruction::{self, *},
+        VmIndex,
+    },
+};
+
+/// Displays a `CompiledFunction` or `CompiledModule` as a listing of its bytecode
+pub struct Disassembly<'a> {
+    module_globals: &'a [Symbol],
+    function: &'a CompiledFunction,
+}
+
+impl CompiledModule {
+    /// Returns a value which displays the bytecode of the module and all of its functions
+    pub fn disassemble(&self) -> Disassembly<'_> {
+        Disassembly {
+            module_globals: &self.module_globals,
+            function: &self.function,
+        }
+    }
+}
+
+impl CompiledFunction {
+    /// Returns a value which displays the bytecode of the function and all of its inner functions
+    pub fn disassemble(&self) -> Disassembly<'_> {
+        Disassembly {
+            module_globals: &[],
+            function: self,
+        }
+    }
+}
+
+impl fmt::Display for Disassembly<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.module_globals.is_empty() {
+            writeln!(f, "globals:")?;
+            for (i, global) in self.module_globals.iter().enumerate() {
+                writeln!(f, "    {}: {}", i, global)?;
+            }
+            writeln!(f)?;
+        }
+        write_function(f, self.function, self.module_globals)
+    }
+}
+
+/// Returns what the operand of `instruction` refers to in `function`, if it refers to anything.
+/// The upvariables of a module's top level function are its `module_globals`.
+fn annotation(
+    function: &CompiledFunction,
+    module_globals: &[Symbol],
+    instruction: Instruction,
+) -> Option<String> {
+    let string = |index: VmIndex| {
+        function
+            .strings
+            .get(index as usize)
+            .map(|s| format!("{:?}", &s[..]))
+    };
+    match instruction {
+        PushString(index)
+        | GetField(index)
+        | TestPolyTag(index)
+        | ConstructPolyVariant { tag: index, .. } => string(index),
+        PushUpVar(index) => function
+            .debug_info
+            .upvars
+            .get(index as usize)
+            .map(|upvar| upvar.name.clone())
+            .or_else(|| module_globals.get(index as usize).map(|g| g.to_string())),
+        MakeClosure { function_index, .. } | NewClosure { function_index, .. } => function
+            .inner_functions
+            .get(function_index as usize)
+            .map(|inner| inner.id.to_string()),
+        NewRecord { record, .. } | ConstructRecord { record, .. } => function
+            .records
+            .get(record as usize)
+            .map(|fields| record_fields(fields)),
+        _ => None,
+    }
+}
+
+fn record_fields(fields: &[Symbol]) -> String {
+    let mut s = String::from("{");
+    for (i, field) in fields.iter().enumerate() {
+        s.push_str(if i == 0 { " " } else { ", " });
+        s.push_str(field.declared_name());
+    }
+    s.push_str(" }");
+    s
+}
+
+fn write_function(
+    f: &mut fmt::Formatter,
+    function: &CompiledFunction,
+    module_globals: &[Symbol],
+) -> fmt::Result {
+    writeln!(
+        f,
+        "function {} (args: {}, max stack: {}, source: {})",
+        function.id, function.args, function.max_stack_size, function.debug_info.source_name
+    )?;
+
+    if !function.strings.is_empty() {
+        writeln!(f, "  strings:")?;
+        for (i, s) in function.strings.iter().enumerate() {
+            writeln!(f, "    {}: {:?}", i, &s[..])?;
+        }
+    }
+    if !function.records.is_empty() {
+        writeln!(f, "  records:")?;
+        for (i, fields) in function.records.iter().enumerate() {
+            writeln!(f, "    {}: {}", i, record_fields(fields))?;
+        }
+    }
+    if !function.debug_info.upvars.is_empty() {
+        writeln!(f, "  upvars:")?;
+        for (i, upvar) in function.debug_info.upvars.iter().enumerate() {
+            writeln!(f, "    {}: {} : {}", i, upvar.name, upvar.typ)?;
+        }
+    }
+
+    writeln!(f, "  code:")?;
+    let mut lines = function
+        .debug_info
+        .source_map
+        .instruction_lines(function.instructions.len())
+        .peekable();
+    for (i, &instruction) in function.instructions.iter().enumerate() {
+        let mut line = None;
+        while let Some(&(index, l)) = lines.peek() {
+            if index > i {
+                break;
+            }
+            line = Some(l);
+            lines.next();
+        }
+        match line {
+            Some(line) => write!(f, "  {:>5} ", line.number().to_string())?,
+            None => write!(f, "        ")?,
+        }
+        let instruction_str = format!("{:?}", instruction);
+        match annotation(function, module_globals, instruction) {
+            Some(annotation) => writeln!(f, "{:>4}  {:<40} ; {}", i, instruction_str, annotation)?,
+            None => writeln!(f, "{:>4}  {}", i, instruction_str)?,
+        }
+    }
+
+    for inner in &function.inner_functions {
+        writeln!(f)?;
+        write_function(f, inner, &[])?;
+    }
+    Ok(())
+}
@@ -11,11 +11,17 @@ use std::{
     ptr::{self, NonNull},
     rc::Rc,
     result::Result as StdResult,
-    sync::{self, Arc},
+    sync::{
+        self,
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+        Arc,
+    },
+    time::Duration,
 };
 
 use crate::{
-    base::fnv::FnvMap, forget_lifetime, interner::InternedStr, types::VmIndex, Error, Result,
+    base::fnv::FnvMap, forget_lifetime, interner::InternedStr, types::VmIndex, value::Userdata,
+    Error, Result,
 };
 
 pub mod mutex;
@@ -205,7 +211,81 @@ impl Generation {
     }
 }
 
-/// A mark and sweep garbage collector.
+/// The number of bytes which may be allocated in the nursery before a minor collection is run,
+/// unless changed with `Gc::set_nursery_limit`
+pub const DEFAULT_NURSERY_LIMIT: usize = 256 * 1024;
+
+/// Pause times of the collections run by a `Gc`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PauseStats {
+    /// The number of minor collections, which only collect the nursery
+    pub minor_collections: u64,
+    /// The number of major collections, which collect the entire heap
+    pub major_collections: u64,
+    /// The total time spent in minor collections
+    pub minor_pause: Duration,
+    /// The total time spent in major collections
+    pub major_pause: Duration,
+    /// The longest time spent in a single collection
+    pub max_pause: Duration,
+    /// The time spent in the most recent collection
+    pub last_pause: Duration,
+}
+
+impl PauseStats {
+    /// The total time spent in collections
+    pub fn total_pause(&self) -> Duration {
+        self.minor_pause + self.major_pause
+    }
+
+    fn record(&mut self, minor: bool, pause: Duration) {
+        if minor {
+            self.minor_collections += 1;
+            self.minor_pause += pause;
+        } else {
+            self.major_collections += 1;
+            self.major_pause += pause;
+        }
+        self.max_pause = self.max_pause.max(pause);
+        self.last_pause = pause;
+    }
+}
+
+/// Measures the time a collection takes. `Instant` is not available on wasm so pauses are always
+/// reported as zero there.
+struct PauseTimer {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+}
+
+impl PauseTimer {
+    fn start() -> Self {
+        PauseTimer {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.start.elapsed()
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            Duration::default()
+        }
+    }
+}
+
+/// A generational mark and sweep garbage collector.
+///
+/// Objects are allocated in a nursery which is collected on its own (a minor collection) whenever
+/// `nursery_limit` bytes have been allocated in it. Objects which survive a collection are
+/// promoted to the old generation which is only collected by major collections. For a minor
+/// collection to find every young object, old objects which a young object is written into must
+/// either be reported through `write_barrier` or be allocated with a `DataDef` which does not
+/// claim to be immutable (`DataDef::is_immutable`), in which case they are always traced.
 #[derive(Debug)]
 #[cfg_attr(feature = "serde_derive", derive(DeserializeState, SerializeState))]
 #[cfg_attr(
@@ -220,11 +300,47 @@ impl Generation {
     serde(serialize_state = "crate::serialization::SeSeed")
 )]
 pub struct Gc {
-    /// Linked list of all objects allocted by this garbage collector.
+    /// Linked list of all objects allocted by this garbage collector which have not yet survived
+    /// a collection (the nursery).
     #[cfg_attr(feature = "serde_derive", serde(skip))]
     values: Option<AllocPtr>,
+    /// Linked list of all objects which have survived a collection
+    #[cfg_attr(feature = "serde_derive", serde(skip))]
+    old_values: Option<AllocPtr>,
     /// How many bytes which is currently allocated
     allocated_memory: usize,
+    /// How many bytes which is currently allocated in the nursery
+    #[cfg_attr(feature = "serde_derive", serde(skip))]
+    young_memory: usize,
+    /// How many bytes may be allocated in the nursery before a minor collection is run. `None` if
+    /// every collection should be a major collection.
+    nursery_limit: Option<usize>,
+    /// Old, immutable objects which have had young objects written into them since the last
+    /// collection
+    #[cfg_attr(feature = "serde_derive", serde(skip))]
+    remembered: Vec<HeaderPtr>,
+    /// Old objects which may be mutated without going through `write_barrier` and must therefore
+    /// be traced by every minor collection
+    #[cfg_attr(feature = "serde_derive", serde(skip))]
+    mutable_old: Vec<HeaderPtr>,
+    /// Set while a minor collection is marking, making `mark` skip old objects
+    #[cfg_attr(feature = "serde_derive", serde(skip))]
+    minor: bool,
+    #[cfg_attr(feature = "serde_derive", serde(skip))]
+    pause_stats: PauseStats,
+    /// How many bytes which have been freed by collections
+    #[cfg_attr(feature = "serde_derive", serde(skip))]
+    bytes_freed: u64,
+    /// Set on the `Gc` passed to `trace` in `Gc::trace_edges` to record the traced pointers
+    #[cfg_attr(feature = "serde_derive", serde(skip))]
+    edges: Option<Vec<usize>>,
+    /// The slots of the weak references to objects, keyed by the address of the object. Shared
+    /// with every child garbage collector since a weak reference may point into a parent.
+    #[cfg_attr(feature = "serde_derive", serde(skip))]
+    weak_slots: Arc<sync::Mutex<FnvMap<usize, sync::Weak<WeakSlot>>>>,
+    /// Finalizers of freed userdata which have yet to be run by the owning thread
+    #[cfg_attr(feature = "serde_derive", serde(skip))]
+    finalizers: Finalizers,
     /// How many bytes this garbage collector can allocate before a collection is run
     collect_limit: usize,
     /// The maximum number of bytes this garbage collector may contain
@@ -259,8 +375,14 @@ pub struct Gc {
 
 impl Drop for Gc {
     fn drop(&mut self) {
-        if let Some(values) = self.values.take() {
+        for finalizer in self.take_finalizers() {
+            finalizer();
+        }
+        let values = self.values.take();
+        let old_values = self.old_values.take();
+        if values.is_some() || old_values.is_some() {
             mem::forget(values);
+            mem::forget(old_values);
             if std::thread::panicking() {
                 eprintln!("Gc values were not dropped explicitly. Leaking the allocatons!");
             } else {
@@ -306,6 +428,13 @@ pub unsafe trait DataDef {
     fn tag(&self) -> Option<&InternedStr> {
         None
     }
+
+    /// Returns `true` if the allocated value is never modified after `initialize` returns, except
+    /// through a write which is followed by `Gc::write_barrier`. Values which are not immutable
+    /// are traced by every minor collection once they are old.
+    fn is_immutable(&self) -> bool {
+        false
+    }
 }
 
 /// `DataDef` that moves its value directly into the pointer
@@ -330,6 +459,9 @@ where
 #[derive(Debug)]
 struct TypeInfo {
     drop: unsafe fn(*mut ()),
+    trace: unsafe fn(*const (), &mut Gc),
+    finalizer: unsafe fn(*mut ()) -> Option<Finalizer>,
+    type_id: TypeId,
     generation: Generation,
     tag: Option<InternedStr>,
     fields: FnvMap<InternedStr, VmIndex>,
@@ -340,6 +472,14 @@ struct TypeInfo {
 struct GcHeader {
     next: Option<AllocPtr>,
     marked: Cell<bool>,
+    /// Set once the value has survived a collection
+    old: Cell<bool>,
+    /// Set while the value is in `Gc::remembered`
+    remembered: Cell<bool>,
+    /// Set if a `WeakSlot` may refer to the value. Atomic as the value may be weakly referenced
+    /// from a child thread.
+    weak: AtomicBool,
+    immutable: bool,
     value_size: usize,
     type_info: *const TypeInfo,
 }
@@ -351,8 +491,8 @@ struct AllocPtr {
 unsafe impl Send for AllocPtr {}
 
 impl AllocPtr {
-    fn new<T>(type_info: *const TypeInfo, value_size: usize) -> AllocPtr {
-        fn new(type_info: *const TypeInfo, value_size: usize) -> AllocPtr {
+    fn new<T>(type_info: *const TypeInfo, value_size: usize, immutable: bool) -> AllocPtr {
+        fn new(type_info: *const TypeInfo, value_size: usize, immutable: bool) -> AllocPtr {
             unsafe {
                 let alloc_size = GcHeader::value_offset() + value_size;
                 let ptr = allocate(alloc_size) as *mut GcHeader;
@@ -363,13 +503,17 @@ impl AllocPtr {
                         type_info: type_info,
                         value_size: value_size,
                         marked: Cell::new(false),
+                        old: Cell::new(false),
+                        remembered: Cell::new(false),
+                        weak: AtomicBool::new(false),
+                        immutable,
                     },
                 );
                 AllocPtr { ptr }
             }
         }
         debug_assert!(mem::align_of::<T>() <= mem::align_of::<f64>());
-        new(type_info, value_size)
+        new(type_info, value_size, immutable)
     }
 
     fn size(&self) -> usize {
@@ -430,6 +574,125 @@ impl GcHeader {
     fn generation(&self) -> Generation {
         unsafe { (*self.type_info).generation }
     }
+
+    fn trace_value(&mut self, gc: &mut Gc) {
+        unsafe { ((*self.type_info).trace)(self.value(), gc) }
+    }
+
+    fn value_ptr(&self) -> *const () {
+        unsafe { (self as *const GcHeader as *const u8).add(GcHeader::value_offset()) as *const () }
+    }
+}
+
+/// A function which is run after the userdata which returned it from `Userdata::finalizer` has
+/// been freed
+pub type Finalizer = Box<dyn FnOnce() + Send>;
+
+#[derive(Default)]
+struct Finalizers(Vec<Finalizer>);
+
+impl fmt::Debug for Finalizers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Finalizers({})", self.0.len())
+    }
+}
+
+/// Shared between the weak references to an object and cleared when the object is freed.
+///
+/// An object can only be freed by a collection which has locked the thread that owns it along
+/// with all of its child threads, so a slot can not be cleared while any of those threads are
+/// borrowed.
+#[derive(Debug)]
+pub struct WeakSlot {
+    alive: AtomicBool,
+}
+
+impl WeakSlot {
+    /// Creates a slot which is not registered with any garbage collector. Used for values which
+    /// are not garbage collected (`alive == true`) or as an already cleared slot.
+    pub fn new(alive: bool) -> Self {
+        WeakSlot {
+            alive: AtomicBool::new(alive),
+        }
+    }
+
+    /// Returns `false` once the object has been freed
+    pub fn is_alive(&self) -> bool {
+        self.alive.load(AtomicOrdering::Acquire)
+    }
+}
+
+/// An object allocated by a `Gc`, as returned from `Gc::objects`
+#[derive(Clone, Copy)]
+pub struct Object<'a> {
+    header: &'a GcHeader,
+}
+
+impl<'a> Object<'a> {
+    /// The address of the value, which is the same as the address of any `GcPtr` to it
+    pub fn address(&self) -> usize {
+        self.header.value_ptr() as usize
+    }
+
+    /// The number of bytes allocated for the object, including its header
+    pub fn size(&self) -> usize {
+        GcHeader::value_offset() + self.header.value_size
+    }
+
+    /// Returns `true` if the object has survived a collection
+    pub fn is_old(&self) -> bool {
+        self.header.old.get()
+    }
+
+    pub fn type_id(&self) -> TypeId {
+        self.type_info().type_id
+    }
+
+    /// The tag of the object if it is a variant
+    pub fn poly_tag(&self) -> Option<&'a InternedStr> {
+        self.type_info().tag.as_ref()
+    }
+
+    /// The field names of the object if it is a record
+    pub fn field_names(&self) -> &'a [InternedStr] {
+        &self.type_info().fields_key
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&'a T> {
+        if self.type_id() == TypeId::of::<T>() {
+            Some(unsafe { &*(self.header.value_ptr() as *const T) })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the addresses of the objects which this object refers to
+    pub fn children(&self) -> Vec<usize> {
+        let value = self.header.value_ptr();
+        let trace = self.type_info().trace;
+        Gc::trace_edges(|gc| unsafe { trace(value, gc) })
+    }
+
+    fn type_info(&self) -> &'a TypeInfo {
+        unsafe { &*self.header.type_info }
+    }
+}
+
+/// A pointer to the header of an object in one of the lists of a `Gc`
+#[derive(Debug)]
+struct HeaderPtr(NonNull<GcHeader>);
+
+unsafe impl Send for HeaderPtr {}
+
+impl HeaderPtr {
+    fn new(header: &GcHeader) -> Self {
+        HeaderPtr(NonNull::from(header))
+    }
+
+    /// Unsafe as the object must not have been freed
+    unsafe fn header(&mut self) -> &mut GcHeader {
+        self.0.as_mut()
+    }
 }
 
 pub struct OwnedPtr<T: ?Sized>(NonNull<T>);
@@ -1052,7 +1315,18 @@ impl Gc {
     pub fn new(generation: Generation, memory_limit: usize) -> Gc {
         Gc {
             values: None,
+            old_values: None,
             allocated_memory: 0,
+            young_memory: 0,
+            nursery_limit: Some(DEFAULT_NURSERY_LIMIT),
+            remembered: Vec::new(),
+            mutable_old: Vec::new(),
+            minor: false,
+            pause_stats: PauseStats::default(),
+            bytes_freed: 0,
+            edges: None,
+            weak_slots: Default::default(),
+            finalizers: Finalizers::default(),
             collect_limit: 100,
             memory_limit: memory_limit,
             type_infos: FnvMap::default(),
@@ -1066,6 +1340,52 @@ impl Gc {
         self.allocated_memory
     }
 
+    /// How many bytes are allocated in the nursery
+    pub fn young_memory(&self) -> usize {
+        self.young_memory
+    }
+
+    pub fn nursery_limit(&self) -> Option<usize> {
+        self.nursery_limit
+    }
+
+    /// Sets how many bytes may be allocated in the nursery before a minor collection is run.
+    /// `None` disables minor collections, making every collection a major collection.
+    pub fn set_nursery_limit(&mut self, nursery_limit: Option<usize>) {
+        self.nursery_limit = nursery_limit;
+    }
+
+    pub fn pause_stats(&self) -> &PauseStats {
+        &self.pause_stats
+    }
+
+    /// How many bytes which have been freed by collections
+    pub fn bytes_freed(&self) -> u64 {
+        self.bytes_freed
+    }
+
+    /// Iterates over every object allocated by this garbage collector, young objects first
+    pub fn objects(&self) -> impl Iterator<Item = Object<'_>> {
+        fn iter_list(list: &Option<AllocPtr>) -> impl Iterator<Item = Object<'_>> {
+            let mut current = list.as_ref();
+            std::iter::from_fn(move || {
+                let header: &GcHeader = current?;
+                current = header.next.as_ref();
+                Some(Object { header })
+            })
+        }
+        iter_list(&self.values).chain(iter_list(&self.old_values))
+    }
+
+    /// Runs `trace` with a `Gc` which records the address of every pointer passed to `mark`
+    /// instead of marking it, returning the recorded addresses.
+    pub fn trace_edges(trace: impl FnOnce(&mut Gc)) -> Vec<usize> {
+        let mut gc = Gc::new(Generation::default(), usize::MAX);
+        gc.edges = Some(Vec::new());
+        trace(&mut gc);
+        gc.edges.take().unwrap_or_default()
+    }
+
     pub fn set_memory_limit(&mut self, memory_limit: usize) {
         self.memory_limit = memory_limit;
     }
@@ -1075,7 +1395,10 @@ impl Gc {
     }
 
     pub fn new_child_gc(&self) -> Gc {
-        Gc::new(self.generation.next(), self.memory_limit)
+        let mut gc = Gc::new(self.generation.next(), self.memory_limit);
+        gc.nursery_limit = self.nursery_limit;
+        gc.weak_slots = self.weak_slots.clone();
+        gc
     }
 
     /// Allocates a new object. If the garbage collector has hit the collection limit a collection
@@ -1151,6 +1474,8 @@ impl Gc {
         fields: Option<&[InternedStr]>,
         type_id: TypeId,
         drop: unsafe fn(*mut ()),
+        trace: unsafe fn(*const (), &mut Gc),
+        finalizer: unsafe fn(*mut ()) -> Option<Finalizer>,
     ) -> *const TypeInfo {
         match fields {
             Some(fields) => match self
@@ -1171,6 +1496,9 @@ impl Gc {
                         .entry(owned_fields.clone())
                         .or_insert(Box::new(TypeInfo {
                             drop,
+                            trace,
+                            finalizer,
+                            type_id,
                             generation: self.generation,
                             tag: unsafe { tag.map(|tag| tag.clone_unrooted()) },
                             fields: unsafe {
@@ -1189,6 +1517,9 @@ impl Gc {
                     Entry::Occupied(entry) => &**entry.get(),
                     Entry::Vacant(entry) => &**entry.insert(Box::new(TypeInfo {
                         drop,
+                        trace,
+                        finalizer,
+                        type_id,
                         generation: self.generation,
                         tag: Some(unsafe { tag.clone_unrooted() }),
                         fields: FnvMap::default(),
@@ -1199,6 +1530,9 @@ impl Gc {
                     Entry::Occupied(entry) => &**entry.get(),
                     Entry::Vacant(entry) => &**entry.insert(Box::new(TypeInfo {
                         drop,
+                        trace,
+                        finalizer,
+                        type_id,
                         generation: self.generation,
                         tag: None,
                         fields: FnvMap::default(),
@@ -1218,16 +1552,29 @@ impl Gc {
             ptr::drop_in_place(t as *mut T);
         }
 
+        unsafe fn trace<T: Trace>(t: *const (), gc: &mut Gc) {
+            (*(t as *const T)).trace(gc);
+        }
+
+        unsafe fn finalizer<T: Any>(t: *mut ()) -> Option<Finalizer> {
+            (&mut *(t as *mut T) as &mut dyn Any)
+                .downcast_mut::<Box<dyn Userdata>>()?
+                .finalizer()
+        }
+
         let type_info = self.get_type_info(
             def.tag(),
             def.fields(),
             TypeId::of::<D::Value>(),
             drop::<D::Value>,
+            trace::<D::Value>,
+            finalizer::<D::Value>,
         );
 
-        let mut ptr = AllocPtr::new::<D::Value>(type_info, size);
+        let mut ptr = AllocPtr::new::<D::Value>(type_info, size, def.is_immutable());
         ptr.next = self.values.take();
         self.allocated_memory += ptr.size();
+        self.young_memory += ptr.size();
         unsafe {
             let p: *mut D::Value = D::Value::make_ptr(&def, ptr.value());
             let ret: *const D::Value = &*def.initialize(WriteOnly::new(p));
@@ -1241,6 +1588,8 @@ impl Gc {
         }
     }
 
+    /// Runs a major collection if the collection limit has been reached or a minor collection if
+    /// the nursery is full. Returns `true` if a collection was run.
     pub unsafe fn check_collect<R>(&mut self, roots: R) -> bool
     where
         R: Trace + CollectScope,
@@ -1248,6 +1597,12 @@ impl Gc {
         if self.allocated_memory >= self.collect_limit {
             self.collect(roots);
             true
+        } else if self
+            .nursery_limit
+            .map_or(false, |limit| self.young_memory >= limit)
+        {
+            self.collect_minor(roots);
+            true
         } else {
             false
         }
@@ -1260,20 +1615,91 @@ impl Gc {
         R: Trace + CollectScope,
     {
         info!("Start collect {:?}", self.generation);
+        let timer = PauseTimer::start();
         roots.scope(self, |self_| {
             roots.trace(self_);
             self_.sweep();
             self_.collect_limit = 2 * self_.allocated_memory;
-        })
+        });
+        self.pause_stats.record(false, timer.elapsed());
+    }
+
+    /// Does a mark and sweep collection of the nursery by walking from `roots` and the old objects
+    /// which may refer to young objects. Any young object which survives is promoted to the old
+    /// generation.
+    ///
+    /// Unsafe for the same reasons as `collect`. In addition every write of a young object into
+    /// an old, immutable object must have been reported through `write_barrier`.
+    pub unsafe fn collect_minor<R>(&mut self, roots: R)
+    where
+        R: Trace + CollectScope,
+    {
+        info!("Start minor collect {:?}", self.generation);
+        let timer = PauseTimer::start();
+        // Must be set before `scope` as it marks the roots of child threads
+        self.minor = true;
+        roots.scope(self, |self_| {
+            roots.trace(self_);
+            self_.trace_old_roots();
+            self_.minor = false;
+            self_.sweep_young();
+        });
+        self.minor = false;
+        self.pause_stats.record(true, timer.elapsed());
+    }
+
+    /// Traces the old objects which can refer to young objects and which were not already traced
+    /// from the roots
+    fn trace_old_roots(&mut self) {
+        let mut remembered = mem::take(&mut self.remembered);
+        let mut mutable_old = mem::take(&mut self.mutable_old);
+        for header in remembered.iter_mut().chain(&mut mutable_old) {
+            let header = unsafe { header.header() };
+            if !header.marked.get() {
+                header.marked.set(true);
+                header.trace_value(self);
+            }
+        }
+        self.remembered = remembered;
+        self.mutable_old = mutable_old;
+    }
+
+    /// Records that a young object may have been written into `value`, causing the next minor
+    /// collection to trace it.
+    ///
+    /// Only necessary for values allocated with a `DataDef` whose `is_immutable` returns `true`.
+    pub fn write_barrier<T: ?Sized>(&mut self, value: &GcPtr<T>) {
+        let header = value.header();
+        if header.old.get()
+            && header.immutable
+            && !header.remembered.get()
+            && header.generation().0 == self.generation.0
+        {
+            header.remembered.set(true);
+            self.remembered.push(HeaderPtr::new(header));
+        }
     }
 
     /// Marks the GcPtr
     /// Returns true if the pointer was already marked
     pub fn mark<T: ?Sized>(&mut self, value: &GcPtr<T>) -> bool {
+        if let Some(edges) = &mut self.edges {
+            edges.push(value.0.as_ptr() as *const u8 as usize);
+            return true;
+        }
         let header = value.header();
         // We only need to mark and trace values from this garbage collectors generation
         if header.generation().is_parent_of(self.generation()) || header.marked.get() {
             true
+        } else if self.minor
+            && header.old.get()
+            && header.immutable
+            && !header.remembered.get()
+            && header.generation().0 == self.generation.0
+        {
+            // Old values are not collected by minor collections and can only refer to young
+            // values if they were mutated, in which case they are traced like young values
+            true
         } else {
             header.marked.set(true);
             false
@@ -1284,64 +1710,147 @@ impl Gc {
     ///
     /// Unsafe as it is up to the caller to make sure that all reachable pointers have been marked
     pub unsafe fn sweep(&mut self) {
-        fn moving<T>(t: T) -> T {
-            t
+        self.forget_remembered();
+        self.mutable_old.clear();
+
+        let old_values = self.old_values.take();
+        let (old_count, old_free_count) = self.sweep_list(old_values);
+        let values = self.values.take();
+        let (count, free_count) = self.sweep_list(values);
+        info!(
+            "GC: Freed {} / Traversed {}",
+            old_free_count + free_count,
+            old_count + count
+        );
+    }
+
+    /// Clears out any unmarked pointers in the nursery and promotes the rest.
+    ///
+    /// Unsafe as it is up to the caller to make sure that all reachable young pointers have been
+    /// marked
+    unsafe fn sweep_young(&mut self) {
+        // The old objects which may refer to young objects are the only old objects which get
+        // marked
+        for header in self.remembered.iter_mut().chain(&mut self.mutable_old) {
+            header.header().marked.set(false);
         }
+        // Every surviving young object is promoted so no old object can refer to a young object
+        // after this
+        self.forget_remembered();
+
+        let values = self.values.take();
+        let (count, free_count) = self.sweep_list(values);
+        info!("GC: Freed {} / Traversed {} young", free_count, count);
+    }
 
+    fn forget_remembered(&mut self) {
+        for mut header in self.remembered.drain(..) {
+            unsafe { header.header().remembered.set(false) }
+        }
+    }
+
+    /// Frees the unmarked objects in `list` and moves the marked objects to the old generation.
+    /// Returns the number of traversed and freed objects.
+    unsafe fn sweep_list(&mut self, mut list: Option<AllocPtr>) -> (usize, usize) {
         let mut count = 0;
         let mut free_count = 0;
-
-        let mut first = self.values.take();
-        {
-            // Pointer to the current pointer (if it exists)
-            let mut maybe_header = &mut first;
-            loop {
-                let mut free = false;
-                let mut replaced_next = None;
-                match *maybe_header {
-                    Some(ref mut header) => {
-                        // If the current pointer is not marked we take the rest of the list and
-                        // move it to `replaced_next`
-                        if !header.marked.get() {
-                            replaced_next = header.next.take();
-                            free = true;
-                        } else {
-                            header.marked.set(false);
-                        }
-                    }
-                    // Reached the end of the list
-                    None => break,
-                }
-                count += 1;
-                if free {
-                    free_count += 1;
-                    // Free the current pointer
-                    self.free(maybe_header.take());
-                    *maybe_header = replaced_next;
-                } else {
-                    // Just move to the next pointer
-                    maybe_header = &mut moving(maybe_header).as_mut().unwrap().next;
-                }
+        while let Some(mut header) = list {
+            list = header.next.take();
+            count += 1;
+            if header.marked.get() {
+                header.marked.set(false);
+                self.promote(header);
+            } else {
+                free_count += 1;
+                self.free(Some(header));
             }
         }
-        info!("GC: Freed {} / Traversed {}", free_count, count);
-        self.values = first;
+        (count, free_count)
+    }
+
+    fn promote(&mut self, mut header: AllocPtr) {
+        if !header.old.get() {
+            header.old.set(true);
+            self.young_memory -= header.size();
+        }
+        if !header.immutable {
+            self.mutable_old.push(HeaderPtr::new(&header));
+        }
+        header.next = self.old_values.take();
+        self.old_values = Some(header);
     }
 
     // Drop all values.
     //
     // SAFETY: No `GcPtr` allocated from this Gc must be reachable after calling this
     pub unsafe fn clear(&mut self) {
+        self.remembered.clear();
+        self.mutable_old.clear();
+        let weak_headers: Vec<_> = self
+            .objects()
+            .filter(|object| object.header.weak.load(AtomicOrdering::Acquire))
+            .map(|object| object.header as *const GcHeader)
+            .collect();
+        for header in weak_headers {
+            self.clear_weak_slot(&*header);
+        }
         self.values = None;
+        self.old_values = None;
+        self.young_memory = 0;
     }
 
-    fn free(&mut self, header: Option<AllocPtr>) {
-        if let Some(ref ptr) = header {
+    fn free(&mut self, mut header: Option<AllocPtr>) {
+        if let Some(ref mut ptr) = header {
             self.allocated_memory -= ptr.size();
+            self.bytes_freed += ptr.size() as u64;
+            if !ptr.old.get() {
+                self.young_memory -= ptr.size();
+            }
+            if ptr.weak.load(AtomicOrdering::Acquire) {
+                self.clear_weak_slot(ptr);
+            }
+            let finalizer = unsafe { ((*ptr.type_info).finalizer)(ptr.value()) };
+            self.finalizers.0.extend(finalizer);
         }
         debug!("FREE: {:?}", header);
         drop(header);
     }
+
+    /// Returns the slot which is cleared once `value` is freed. `value` must have been allocated
+    /// by this garbage collector or one of its parents.
+    pub fn weak_slot<T: ?Sized>(&mut self, value: &GcPtr<T>) -> Arc<WeakSlot> {
+        let header = value.header();
+        let mut weak_slots = self.weak_slots.lock().unwrap();
+        let slot = weak_slots
+            .entry(header as *const GcHeader as usize)
+            .or_default();
+        match slot.upgrade() {
+            Some(existing) => existing,
+            None => {
+                let new_slot = Arc::new(WeakSlot::new(true));
+                *slot = Arc::downgrade(&new_slot);
+                header.weak.store(true, AtomicOrdering::Release);
+                new_slot
+            }
+        }
+    }
+
+    fn clear_weak_slot(&mut self, header: &GcHeader) {
+        let slot = self
+            .weak_slots
+            .lock()
+            .unwrap()
+            .remove(&(header as *const GcHeader as usize));
+        if let Some(slot) = slot.and_then(|slot| slot.upgrade()) {
+            slot.alive.store(false, AtomicOrdering::Release);
+        }
+    }
+
+    /// Takes the finalizers of the userdata which have been freed since the last call. They
+    /// should be run once the garbage collector is no longer borrowed.
+    pub fn take_finalizers(&mut self) -> Vec<Finalizer> {
+        mem::take(&mut self.finalizers.0)
+    }
 }
 
 #[cfg(test)]
@@ -1374,7 +1883,11 @@ mod tests {
     }
 
     fn object_count(gc: &Gc) -> usize {
-        let mut header: &GcHeader = match gc.values {
+        list_count(&gc.values) + list_count(&gc.old_values)
+    }
+
+    fn list_count(list: &Option<AllocPtr>) -> usize {
+        let mut header: &GcHeader = match *list {
             Some(ref x) => &**x,
             None => return 0,
         };
@@ -1421,6 +1934,20 @@ mod tests {
         }
     }
 
+    struct ImmutableDef<'a>(Def<'a>);
+    unsafe impl<'a> DataDef for ImmutableDef<'a> {
+        type Value = Vec<Value>;
+        fn size(&self) -> usize {
+            self.0.size()
+        }
+        fn initialize(self, result: WriteOnly<Vec<Value>>) -> &mut Vec<Value> {
+            self.0.initialize(result)
+        }
+        fn is_immutable(&self) -> bool {
+            true
+        }
+    }
+
     #[derive(PartialEq, Debug, Trace)]
     #[gluon(gluon_vm)]
     enum Value {
@@ -1525,4 +2052,106 @@ mod tests {
 
         unsafe { gc.clear() }
     }
+
+    #[test]
+    fn minor_collect_promotes_survivors() {
+        let mut gc: Gc = Gc::new(Generation::default(), usize::MAX);
+        let mut stack: Vec<Value> = Vec::new();
+        stack.push(new_data(gc.alloc(Def { elems: &[Int(1)] }).unwrap()));
+        gc.alloc(Def { elems: &[Int(2)] }).unwrap();
+        unsafe {
+            gc.collect_minor(&mut *stack);
+        }
+        assert_eq!(list_count(&gc.values), 0);
+        assert_eq!(list_count(&gc.old_values), 1);
+        assert_eq!(gc.young_memory(), 0);
+
+        // Old values are only freed by major collections
+        stack.pop();
+        gc.alloc(Def { elems: &[Int(3)] }).unwrap();
+        unsafe {
+            gc.collect_minor(&mut *stack);
+        }
+        assert_eq!(object_count(&gc), 1);
+        unsafe {
+            gc.collect(&mut *stack);
+        }
+        assert_eq!(object_count(&gc), 0);
+        assert_eq!(gc.pause_stats().minor_collections, 2);
+        assert_eq!(gc.pause_stats().major_collections, 1);
+
+        unsafe { gc.clear() }
+    }
+
+    fn minor_collect_keeps_values_written_to_old(immutable: bool) {
+        let mut gc: Gc = Gc::new(Generation::default(), usize::MAX);
+        let mut stack: Vec<Value> = Vec::new();
+        let def = Def { elems: &[Int(1)] };
+        let old = if immutable {
+            gc.alloc(ImmutableDef(def)).unwrap()
+        } else {
+            gc.alloc(def).unwrap()
+        };
+        stack.push(new_data(old));
+        unsafe {
+            gc.collect_minor(&mut *stack);
+        }
+
+        let young = new_data(gc.alloc(Def { elems: &[Int(2)] }).unwrap());
+        match stack[0] {
+            Data(ref mut data) => unsafe {
+                data.fields.as_mut()[0] = young;
+                if immutable {
+                    gc.write_barrier(&data.fields);
+                }
+            },
+            _ => ice!(),
+        }
+        unsafe {
+            gc.collect_minor(&mut *stack);
+        }
+        assert_eq!(object_count(&gc), 2);
+        match stack[0] {
+            Data(ref data) => match data.fields[0] {
+                Data(ref data) => assert_eq!(data.fields[0], Int(2)),
+                _ => ice!(),
+            },
+            _ => ice!(),
+        }
+
+        unsafe { gc.clear() }
+    }
+
+    #[test]
+    fn minor_collect_traces_mutable_old_values() {
+        minor_collect_keeps_values_written_to_old(false);
+    }
+
+    #[test]
+    fn minor_collect_traces_remembered_values() {
+        minor_collect_keeps_values_written_to_old(true);
+    }
+
+    #[test]
+    fn weak_slot_is_cleared_when_the_value_is_freed() {
+        let mut gc: Gc = Gc::new(Generation::default(), usize::MAX);
+        let mut stack: Vec<Value> = Vec::new();
+        let live = unsafe { gc.alloc(Def { elems: &[Int(1)] }).unwrap().unrooted() };
+        let live_slot = gc.weak_slot(&live);
+        stack.push(Data(Data_ {
+            fields: unsafe { live.clone_unrooted() },
+        }));
+        let dead = unsafe { gc.alloc(Def { elems: &[Int(2)] }).unwrap().unrooted() };
+        let dead_slot = gc.weak_slot(&dead);
+        assert!(Arc::ptr_eq(&dead_slot, &gc.weak_slot(&dead)));
+
+        unsafe {
+            gc.collect(&mut *stack);
+        }
+        assert!(live_slot.is_alive());
+        assert!(!dead_slot.is_alive());
+
+        unsafe { gc.clear() }
+        assert!(!live_slot.is_alive());
+    }
 }
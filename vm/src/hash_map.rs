@@ -0,0 +1,317 @@
+//! Persistent hash array mapped tries used to implement `std.hash_map`.
+//!
+//! The map only stores the hashes computed by gluon code. Any keys which share a hash are kept in
+//! the same bucket and comparing the keys in a bucket is left to the `Eq` instance on the gluon
+//! side.
+use crate::real_std::{
+    collections::hash_map::DefaultHasher,
+    fmt,
+    hash::{BuildHasherDefault, Hash, Hasher},
+    marker::PhantomData,
+};
+
+use rpds::HashTrieMapSync;
+
+use crate::{
+    api::{
+        generic::{K, V},
+        Collect, Generic, RuntimeResult, Unrooted, Userdata,
+    },
+    gc::{CloneUnrooted, GcRef, Move, Trace},
+    types::VmInt,
+    value::{Cloner, Value},
+    vm::Thread,
+    ExternModule, Result,
+};
+
+struct Entry {
+    key: Value,
+    value: Value,
+}
+
+unsafe impl Trace for Entry {
+    impl_trace_fields! { self, gc; key, value }
+}
+
+impl fmt::Debug for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}: {:?}", self.key, self.value)
+    }
+}
+
+impl Entry {
+    // SAFETY The caller must ensure that the entry stays reachable from a rooted value
+    unsafe fn clone_unrooted(&self) -> Entry {
+        Entry {
+            key: self.key.clone_unrooted(),
+            value: self.value.clone_unrooted(),
+        }
+    }
+}
+
+type Buckets = HashTrieMapSync<VmInt, Vec<Entry>, BuildHasherDefault<DefaultHasher>>;
+
+#[derive(VmType)]
+#[gluon(gluon_vm)]
+#[gluon(vm_type = "std.hash_map.HashMap")]
+pub struct HashMap<K, V> {
+    buckets: Buckets,
+    len: usize,
+    _marker: PhantomData<(K, V)>,
+}
+
+impl<K, V> Userdata for HashMap<K, V>
+where
+    K: Send + Sync + 'static,
+    V: Send + Sync + 'static,
+{
+    fn deep_clone<'gc>(
+        &self,
+        deep_cloner: &'gc mut Cloner,
+    ) -> Result<GcRef<'gc, Box<dyn Userdata>>> {
+        let mut buckets = Buckets::default();
+        for (&hash, bucket) in self.buckets.iter() {
+            let mut cloned_bucket = Vec::with_capacity(bucket.len());
+            for entry in bucket {
+                // SAFETY During the `alloc` call the unrooted values are scanned through the `DataDef`
+                unsafe {
+                    let key = deep_cloner.deep_clone(&entry.key)?.unrooted();
+                    let value = deep_cloner.deep_clone(&entry.value)?.unrooted();
+                    cloned_bucket.push(Entry { key, value });
+                }
+            }
+            buckets.insert_mut(hash, cloned_bucket);
+        }
+        let data: Box<dyn Userdata> = Box::new(HashMap::<K, V> {
+            buckets,
+            len: self.len,
+            _marker: PhantomData,
+        });
+        deep_cloner.gc().alloc(Move(data))
+    }
+}
+
+impl<K, V> fmt::Debug for HashMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                self.buckets
+                    .values()
+                    .flat_map(|bucket| bucket.iter().map(|entry| (&entry.key, &entry.value))),
+            )
+            .finish()
+    }
+}
+
+unsafe impl<K, V> Trace for HashMap<K, V> {
+    // The buckets are immutable and the map itself is only reachable through a `GcPtr` so rooting
+    // is a no-op, same as for the values in a `GcPtr`
+    fn trace(&self, gc: &mut crate::gc::Gc) {
+        for bucket in self.buckets.values() {
+            for entry in bucket {
+                entry.trace(gc);
+            }
+        }
+    }
+}
+
+impl<K, V> HashMap<K, V> {
+    fn new() -> Self {
+        HashMap {
+            buckets: Buckets::default(),
+            len: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    fn bucket(&self, hash: VmInt) -> &[Entry] {
+        self.buckets.get(&hash).map_or(&[], |bucket| &bucket[..])
+    }
+
+    fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.buckets.values().flat_map(|bucket| bucket.iter())
+    }
+
+    fn with_buckets<K2, V2>(&self, buckets: Buckets, len: usize) -> HashMap<K2, V2> {
+        HashMap {
+            buckets,
+            len,
+            _marker: PhantomData,
+        }
+    }
+}
+
+fn empty(_: ()) -> HashMap<K, V> {
+    HashMap::new()
+}
+
+fn len(map: &HashMap<K, V>) -> VmInt {
+    map.len as VmInt
+}
+
+fn keys_at(hash: VmInt, map: &HashMap<K, V>) -> Collect<Vec<Unrooted<K>>> {
+    // SAFETY The returned, unrooted values gets pushed immediately to the stack
+    unsafe {
+        Collect::new(
+            map.bucket(hash)
+                .iter()
+                .map(|entry| Unrooted::from(entry.key.clone_unrooted()))
+                .collect(),
+        )
+    }
+}
+
+fn value_at(hash: VmInt, index: VmInt, map: &HashMap<K, V>) -> RuntimeResult<Unrooted<V>, String> {
+    match map.bucket(hash).get(index as usize) {
+        // SAFETY The returned, unrooted value gets pushed immediately to the stack
+        Some(entry) => unsafe {
+            RuntimeResult::Return(Unrooted::from(entry.value.clone_unrooted()))
+        },
+        None => RuntimeResult::Panic(format!("Index {} is out of bounds", index)),
+    }
+}
+
+fn insert_at(
+    hash: VmInt,
+    index: VmInt,
+    key: Generic<K>,
+    value: Generic<V>,
+    map: &HashMap<K, V>,
+) -> RuntimeResult<HashMap<K, V>, String> {
+    let bucket = map.bucket(hash);
+    // SAFETY The entries are rooted by the returned map once it is pushed to the stack
+    let mut new_bucket: Vec<_> =
+        unsafe { bucket.iter().map(|entry| entry.clone_unrooted()).collect() };
+    let new_entry = unsafe {
+        Entry {
+            key: key.get_value().clone_unrooted(),
+            value: value.get_value().clone_unrooted(),
+        }
+    };
+    let len = if index < 0 {
+        new_bucket.push(new_entry);
+        map.len + 1
+    } else {
+        match new_bucket.get_mut(index as usize) {
+            Some(entry) => *entry = new_entry,
+            None => return RuntimeResult::Panic(format!("Index {} is out of bounds", index)),
+        }
+        map.len
+    };
+    RuntimeResult::Return(map.with_buckets(map.buckets.insert(hash, new_bucket), len))
+}
+
+fn remove_at(hash: VmInt, index: VmInt, map: &HashMap<K, V>) -> HashMap<K, V> {
+    let bucket = map.bucket(hash);
+    if index < 0 || index as usize >= bucket.len() {
+        return map.with_buckets(map.buckets.clone(), map.len);
+    }
+    let buckets = if bucket.len() == 1 {
+        map.buckets.remove(&hash)
+    } else {
+        // SAFETY The entries are rooted by the returned map once it is pushed to the stack
+        let new_bucket = unsafe {
+            bucket
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| i != index as usize)
+                .map(|(_, entry)| entry.clone_unrooted())
+                .collect()
+        };
+        map.buckets.insert(hash, new_bucket)
+    };
+    map.with_buckets(buckets, map.len - 1)
+}
+
+fn keys(map: &HashMap<K, V>) -> Collect<Vec<Unrooted<K>>> {
+    // SAFETY The returned, unrooted values gets pushed immediately to the stack
+    unsafe {
+        Collect::new(
+            map.entries()
+                .map(|entry| Unrooted::from(entry.key.clone_unrooted()))
+                .collect(),
+        )
+    }
+}
+
+fn values(map: &HashMap<K, V>) -> Collect<Vec<Unrooted<V>>> {
+    // SAFETY The returned, unrooted values gets pushed immediately to the stack
+    unsafe {
+        Collect::new(
+            map.entries()
+                .map(|entry| Unrooted::from(entry.value.clone_unrooted()))
+                .collect(),
+        )
+    }
+}
+
+/// Returns the hash of each entry, in the same order as `keys` and `values`
+fn hashes(map: &HashMap<K, V>) -> Vec<VmInt> {
+    map.buckets
+        .iter()
+        .flat_map(|(&hash, bucket)| bucket.iter().map(move |_| hash))
+        .collect()
+}
+
+fn hash_string(s: &str) -> VmInt {
+    let mut hasher = DefaultHasher::new();
+    s.hash(&mut hasher);
+    hasher.finish() as VmInt
+}
+
+fn hash_float(f: f64) -> VmInt {
+    // `0.0 == -0.0` so both must hash to the same value
+    let f = if f == 0.0 { 0.0 } else { f };
+    f.to_bits() as VmInt
+}
+
+fn hash_byte(b: u8) -> VmInt {
+    b as VmInt
+}
+
+fn combine(seed: VmInt, hash: VmInt) -> VmInt {
+    seed ^ hash
+        .wrapping_add(0x9e37_79b9)
+        .wrapping_add(seed << 6)
+        .wrapping_add(seed >> 2)
+}
+
+mod std {
+    pub mod hash {
+        pub use crate::hash_map as prim;
+    }
+    pub mod hash_map {
+        pub use crate::hash_map as prim;
+    }
+}
+
+pub fn load_hash(vm: &Thread) -> Result<ExternModule> {
+    ExternModule::new(
+        vm,
+        record! {
+            hash_string => primitive!(1, std::hash::prim::hash_string),
+            hash_float => primitive!(1, std::hash::prim::hash_float),
+            hash_byte => primitive!(1, std::hash::prim::hash_byte),
+            combine => primitive!(2, std::hash::prim::combine),
+        },
+    )
+}
+
+pub fn load(vm: &Thread) -> Result<ExternModule> {
+    let _ = vm.register_type::<HashMap<K, V>>("std.hash_map.HashMap", &["k", "v"]);
+    ExternModule::new(
+        vm,
+        record! {
+            type HashMap k v => HashMap<K, V>,
+            empty => primitive!(1, std::hash_map::prim::empty),
+            len => primitive!(1, std::hash_map::prim::len),
+            keys_at => primitive!(2, std::hash_map::prim::keys_at),
+            value_at => primitive!(3, std::hash_map::prim::value_at),
+            insert_at => primitive!(5, std::hash_map::prim::insert_at),
+            remove_at => primitive!(3, std::hash_map::prim::remove_at),
+            keys => primitive!(1, std::hash_map::prim::keys),
+            values => primitive!(1, std::hash_map::prim::values),
+            hashes => primitive!(1, std::hash_map::prim::hashes),
+        },
+    )
+}
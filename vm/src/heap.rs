@@ -0,0 +1,309 @@
+//! Statistics about the garbage collected heap of a thread and snapshots of its object graph.
+//!
+//! `Thread::gc_stats` summarizes the collections which have run on a thread's heap and how many
+//! bytes each kind of value occupies in it. `Thread::heap_snapshot` records every object in the
+//! heap along with the objects it refers to, which can be written out as JSON with
+//! `HeapSnapshot::write_json` and inspected to find out what keeps values alive.
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    time::Duration,
+};
+
+use crate::{
+    gc::{Gc, Object, PauseStats},
+    thread::Thread,
+    value::{
+        BytecodeFunction, ClosureData, DataStruct, ExternFunction, PartialApplicationData,
+        Userdata, ValueArray, ValueStr,
+    },
+};
+
+/// Statistics about the garbage collector of a thread
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GcStats {
+    /// The number of collections and the time spent in them
+    pub pauses: PauseStats,
+    /// How many bytes which have been freed by collections
+    pub bytes_freed: u64,
+    /// How many bytes which are currently allocated
+    pub allocated_memory: usize,
+    /// How many bytes each kind of value currently occupies. Values which are no longer reachable
+    /// are included until they are collected so run `Thread::collect` first to only count the
+    /// values which are live.
+    pub live: LiveBytes,
+}
+
+impl GcStats {
+    /// The number of collections, minor and major
+    pub fn collections(&self) -> u64 {
+        self.pauses.minor_collections + self.pauses.major_collections
+    }
+
+    /// The total time spent in collections
+    pub fn total_pause(&self) -> Duration {
+        self.pauses.total_pause()
+    }
+}
+
+/// The number of bytes allocated for each kind of value
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LiveBytes {
+    pub closures: usize,
+    /// Records and variants
+    pub records: usize,
+    pub arrays: usize,
+    pub strings: usize,
+    /// Bytecode and extern functions along with partially applied functions
+    pub functions: usize,
+    pub threads: usize,
+    /// Userdata, grouped by `Userdata::type_name`
+    pub userdata: BTreeMap<&'static str, usize>,
+    /// Any other values allocated by the vm or by extern functions
+    pub other: usize,
+}
+
+impl LiveBytes {
+    /// The total number of bytes of all kinds
+    pub fn total(&self) -> usize {
+        self.closures
+            + self.records
+            + self.arrays
+            + self.strings
+            + self.functions
+            + self.threads
+            + self.userdata.values().sum::<usize>()
+            + self.other
+    }
+
+    fn add(&mut self, kind: Kind, size: usize) {
+        let bytes = match kind {
+            Kind::Closure => &mut self.closures,
+            Kind::Record | Kind::Variant => &mut self.records,
+            Kind::Array => &mut self.arrays,
+            Kind::String => &mut self.strings,
+            Kind::Function | Kind::PartialApplication => &mut self.functions,
+            Kind::Thread => &mut self.threads,
+            Kind::Userdata(name) => self.userdata.entry(name).or_default(),
+            Kind::Other => &mut self.other,
+        };
+        *bytes += size;
+    }
+}
+
+/// A snapshot of every object in the heap of a thread
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HeapSnapshot {
+    /// The objects which are directly reachable from the thread (its stack, rooted values and,
+    /// for the root thread, the global values)
+    pub roots: Vec<usize>,
+    pub nodes: Vec<HeapNode>,
+}
+
+/// An object in a `HeapSnapshot`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HeapNode {
+    /// The address of the object
+    pub id: usize,
+    /// One of `closure`, `record`, `variant`, `array`, `string`, `function`,
+    /// `partial_application`, `thread`, `userdata` or `other`
+    pub kind: &'static str,
+    /// The name of the function for closures and functions, the field names of records, the tag
+    /// of variants and the type name of userdata. Empty for other objects.
+    pub name: String,
+    /// The number of bytes allocated for the object
+    pub size: usize,
+    /// Whether the object has survived a collection
+    pub old: bool,
+    /// The objects which this object refers to. Objects owned by a parent thread are referred to
+    /// but do not appear in `nodes`.
+    pub edges: Vec<usize>,
+}
+
+impl HeapSnapshot {
+    /// Writes the snapshot as a JSON object with a `roots` array of ids and a `nodes` array of
+    /// objects with the fields of `HeapNode`
+    pub fn write_json(&self, mut writer: impl Write) -> io::Result<()> {
+        write!(writer, "{{\"roots\":")?;
+        write_ids(&mut writer, &self.roots)?;
+        write!(writer, ",\"nodes\":[")?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            if i != 0 {
+                write!(writer, ",")?;
+            }
+            write!(
+                writer,
+                "{{\"id\":{},\"kind\":\"{}\",\"name\":",
+                node.id, node.kind
+            )?;
+            write_json_string(&mut writer, &node.name)?;
+            write!(
+                writer,
+                ",\"size\":{},\"old\":{},\"edges\":",
+                node.size, node.old
+            )?;
+            write_ids(&mut writer, &node.edges)?;
+            write!(writer, "}}")?;
+        }
+        write!(writer, "]}}")
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = Vec::new();
+        self.write_json(&mut json)
+            .expect("Writing to a Vec does not fail");
+        String::from_utf8(json).expect("The snapshot is valid UTF-8")
+    }
+}
+
+fn write_ids(writer: &mut impl Write, ids: &[usize]) -> io::Result<()> {
+    write!(writer, "[")?;
+    for (i, id) in ids.iter().enumerate() {
+        if i != 0 {
+            write!(writer, ",")?;
+        }
+        write!(writer, "{}", id)?;
+    }
+    write!(writer, "]")
+}
+
+fn write_json_string(writer: &mut impl Write, s: &str) -> io::Result<()> {
+    write!(writer, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(writer, "\\\"")?,
+            '\\' => write!(writer, "\\\\")?,
+            '\n' => write!(writer, "\\n")?,
+            '\r' => write!(writer, "\\r")?,
+            '\t' => write!(writer, "\\t")?,
+            c if (c as u32) < 0x20 => write!(writer, "\\u{:04x}", c as u32)?,
+            c => write!(writer, "{}", c)?,
+        }
+    }
+    write!(writer, "\"")
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Closure,
+    Record,
+    Variant,
+    Array,
+    String,
+    Function,
+    PartialApplication,
+    Thread,
+    Userdata(&'static str),
+    Other,
+}
+
+impl Kind {
+    fn of(object: &Object) -> Kind {
+        if object.downcast_ref::<ClosureData>().is_some() {
+            Kind::Closure
+        } else if object.downcast_ref::<DataStruct>().is_some() {
+            if object.field_names().is_empty() {
+                Kind::Variant
+            } else {
+                Kind::Record
+            }
+        } else if object.downcast_ref::<ValueArray>().is_some() {
+            Kind::Array
+        } else if object.downcast_ref::<ValueStr>().is_some() {
+            Kind::String
+        } else if object.downcast_ref::<BytecodeFunction>().is_some()
+            || object.downcast_ref::<ExternFunction>().is_some()
+        {
+            Kind::Function
+        } else if object.downcast_ref::<PartialApplicationData>().is_some() {
+            Kind::PartialApplication
+        } else if object.downcast_ref::<Thread>().is_some() {
+            Kind::Thread
+        } else if let Some(userdata) = object.downcast_ref::<Box<dyn Userdata>>() {
+            Kind::Userdata(userdata.type_name())
+        } else {
+            Kind::Other
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Kind::Closure => "closure",
+            Kind::Record => "record",
+            Kind::Variant => "variant",
+            Kind::Array => "array",
+            Kind::String => "string",
+            Kind::Function => "function",
+            Kind::PartialApplication => "partial_application",
+            Kind::Thread => "thread",
+            Kind::Userdata(_) => "userdata",
+            Kind::Other => "other",
+        }
+    }
+
+    fn name(self, object: &Object) -> String {
+        match self {
+            Kind::Closure => object
+                .downcast_ref::<ClosureData>()
+                .map(|closure| closure.function.name.to_string()),
+            Kind::Record => Some(format!(
+                "{{ {} }}",
+                object
+                    .field_names()
+                    .iter()
+                    .map(|field| &field[..])
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            Kind::Variant => object.poly_tag().map(|tag| tag.to_string()),
+            Kind::Function => object
+                .downcast_ref::<BytecodeFunction>()
+                .map(|function| function.name.to_string())
+                .or_else(|| {
+                    object
+                        .downcast_ref::<ExternFunction>()
+                        .map(|function| function.id.to_string())
+                }),
+            Kind::Userdata(name) => Some(name.to_string()),
+            _ => None,
+        }
+        .unwrap_or_default()
+    }
+}
+
+pub(crate) fn gc_stats(gc: &Gc) -> GcStats {
+    let mut live = LiveBytes::default();
+    for object in gc.objects() {
+        live.add(Kind::of(&object), object.size());
+    }
+    GcStats {
+        pauses: gc.pause_stats().clone(),
+        bytes_freed: gc.bytes_freed(),
+        allocated_memory: gc.allocated_memory(),
+        live,
+    }
+}
+
+pub(crate) fn heap_snapshot(gc: &Gc, roots: Vec<usize>) -> HeapSnapshot {
+    let nodes = gc
+        .objects()
+        .map(|object| {
+            let edges = match object.downcast_ref::<Thread>() {
+                // The context of the thread may be locked (it is if it is the thread which the
+                // snapshot is taken of) so its stack is left out
+                Some(thread) => Gc::trace_edges(|gc| thread.trace_fields_except_stack(gc)),
+                None => object.children(),
+            };
+            let kind = Kind::of(&object);
+            HeapNode {
+                id: object.address(),
+                kind: kind.as_str(),
+                name: kind.name(&object),
+                size: object.size(),
+                old: object.is_old(),
+                edges,
+            }
+        })
+        .collect();
+    HeapSnapshot { roots, nodes }
+}
@@ -7,6 +7,8 @@ extern crate collect_mac;
 #[doc(hidden)]
 pub extern crate frunk_core;
 #[macro_use]
+extern crate lazy_static;
+#[macro_use]
 extern crate log;
 #[macro_use]
 extern crate quick_error;
@@ -52,8 +54,12 @@ pub mod api;
 pub mod channel;
 pub mod compiler;
 pub mod core;
+pub mod coverage;
 pub mod debug;
+pub mod disassemble;
 pub mod dynamic;
+pub mod hash_map;
+pub mod heap;
 pub mod lazy;
 pub mod macros;
 pub mod primitives;
@@ -61,12 +67,15 @@ pub mod reference;
 pub mod stack;
 pub mod thread;
 pub mod types;
+pub mod weak;
 pub mod vm;
 
 mod array;
 mod derive;
 mod interner;
 mod source_map;
+#[cfg(not(target_arch = "wasm32"))]
+mod timer;
 mod value;
 
 use std::{self as real_std, fmt, marker::PhantomData};
@@ -186,6 +195,9 @@ quick_error! {
         Interrupted {
             display("Thread was interrupted")
         }
+        OutOfFuel {
+            display("Thread ran out of fuel")
+        }
         Panic(err: String, stacktrace: Option<Stacktrace>) {
             display("{}", Panic { err, stacktrace })
         }
@@ -215,6 +215,13 @@ impl Error {
     {
         self.0.downcast().map_err(Self)
     }
+
+    pub fn downcast_ref<T>(&self) -> Option<&T>
+    where
+        T: MacroError,
+    {
+        self.0.downcast_ref()
+    }
 }
 
 /// A trait which abstracts over macros.
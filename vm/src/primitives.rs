@@ -93,9 +93,15 @@ pub mod array {
                     result
                 }
             }
+
+            fn is_immutable(&self) -> bool {
+                true
+            }
         }
 
         let mut context = array.vm().context();
+        // Copying the elements is not represented by any instructions so charge for it explicitly
+        context.consume_fuel((end - start) as u64);
         let result = context.alloc(Slice {
             start,
             end,
@@ -146,9 +152,14 @@ pub mod array {
                     result
                 }
             }
+
+            fn is_immutable(&self) -> bool {
+                true
+            }
         }
         let vm = lhs.vm();
         let mut context = vm.context();
+        context.consume_fuel((lhs.len() + rhs.len()) as u64);
         let value = {
             let result = context.alloc(Append {
                 lhs: &lhs.get_array(),
@@ -195,6 +206,10 @@ mod string {
                     result
                 }
             }
+
+            fn is_immutable(&self) -> bool {
+                true
+            }
         }
 
         let vm = lhs.vm;
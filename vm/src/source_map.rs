@@ -34,6 +34,18 @@ impl SourceMap {
         }
     }
 
+    /// Returns the index of the first instruction of each line that has instructions, in a
+    /// function with `instructions_len` instructions
+    pub fn instruction_lines(
+        &self,
+        instructions_len: usize,
+    ) -> impl Iterator<Item = (usize, Line)> + '_ {
+        self.map
+            .iter()
+            .cloned()
+            .filter(move |&(index, _)| index < instructions_len)
+    }
+
     /// Returns the line where the instruction at `instruction_index` were defined
     pub fn line(&self, instruction_index: usize) -> Option<Line> {
         // The line for `instruction_index` is at the last index still larger than
@@ -36,10 +36,11 @@ use crate::base::{
 use crate::{
     api::{Getable, Pushable, ValueRef, VmType},
     compiler::UpvarInfo,
-    gc::{self, CloneUnrooted, DataDef, Gc, GcPtr, GcRef, Generation, Move},
+    gc::{self, CloneUnrooted, DataDef, Gc, GcPtr, GcRef, Generation, Move, PauseStats},
+    heap::{GcStats, HeapSnapshot},
     interner::InternedStr,
     macros::MacroEnv,
-    source_map::LocalIter,
+    source_map::{Local, LocalIter},
     stack::{
         ClosureState, ExternCallState, ExternState, Frame, Lock, Stack, StackFrame, StackState,
         State,
@@ -913,6 +914,28 @@ impl Thread {
     pub fn collect(&self) {
         let mut context = self.owned_context();
         self.collect_with_context(&mut context);
+        drop(context);
+        self.run_finalizers();
+    }
+
+    /// Runs a garbage collection of only the values allocated since the last collection.
+    pub fn collect_minor(&self) {
+        let mut context = self.owned_context();
+        self.with_roots(&mut context, |gc, roots| unsafe {
+            gc.collect_minor(roots);
+        });
+        drop(context);
+        self.run_finalizers();
+    }
+
+    /// Runs the finalizers (see `Userdata::finalizer`) of the userdata which collections have
+    /// freed from this thread's heap. Collections which run while the thread is executing leave
+    /// their finalizers to be run before the next extern function is called.
+    pub fn run_finalizers(&self) {
+        let finalizers = self.owned_context().gc.take_finalizers();
+        for finalizer in finalizers {
+            finalizer();
+        }
     }
 
     fn collect_with_context(&self, context: &mut OwnedContext) {
@@ -944,6 +967,40 @@ impl Thread {
         self.owned_context().gc.set_memory_limit(memory_limit)
     }
 
+    /// Sets how many bytes may be allocated before a minor collection is run. `None` makes every
+    /// collection collect the entire heap. Threads spawned after this inherit the limit.
+    pub fn set_nursery_limit(&self, nursery_limit: Option<usize>) {
+        self.owned_context().gc.set_nursery_limit(nursery_limit)
+    }
+
+    /// Returns the pause times of the collections which have run on this thread's heap
+    pub fn pause_stats(&self) -> PauseStats {
+        self.owned_context().gc.pause_stats().clone()
+    }
+
+    /// Returns statistics about the collections which have run on this thread's heap and the
+    /// values it currently contains
+    pub fn gc_stats(&self) -> GcStats {
+        crate::heap::gc_stats(&self.owned_context().gc)
+    }
+
+    /// Takes a snapshot of every object in this thread's heap and what they refer to
+    pub fn heap_snapshot(&self) -> HeapSnapshot {
+        let mut context = self.owned_context();
+        self.with_roots(&mut context, |gc, roots| {
+            let roots = Gc::trace_edges(|gc| roots.trace(gc));
+            crate::heap::heap_snapshot(gc, roots)
+        })
+    }
+
+    /// Consumes `amount` units of this thread's fuel (see `Context::set_fuel`).
+    ///
+    /// Intended to be called from extern functions (which run without holding the context lock)
+    /// that do work proportional to their input.
+    pub fn consume_fuel(&self, amount: u64) {
+        self.owned_context().consume_fuel(amount)
+    }
+
     pub fn interrupt(&self) {
         self.interrupt.store(true, atomic::Ordering::Relaxed)
     }
@@ -968,7 +1025,7 @@ impl Thread {
         self.context()
     }
 
-    fn trace_fields_except_stack(&self, gc: &mut Gc) {
+    pub(crate) fn trace_fields_except_stack(&self, gc: &mut Gc) {
         if gc.generation().is_root() {
             self.global_state.trace(gc);
         }
@@ -1305,8 +1362,10 @@ impl ThreadInternal for Thread {
 pub type HookFn = Box<dyn FnMut(&Thread, DebugInfo) -> Poll<Result<()>> + Send + Sync>;
 
 pub struct DebugInfo<'a> {
-    stack: &'a Stack,
+    pub(crate) stack: &'a Stack,
     state: HookFlags,
+    allocated_memory: usize,
+    branch_taken: Option<bool>,
 }
 
 impl fmt::Debug for DebugInfo<'_> {
@@ -1346,6 +1405,18 @@ impl<'a> DebugInfo<'a> {
     pub fn stack_info_len(&self) -> usize {
         self.stack.get_frames().len()
     }
+
+    /// Returns the number of bytes allocated by the garbage collector of the thread
+    pub fn allocated_memory(&self) -> usize {
+        self.allocated_memory
+    }
+
+    /// Returns `Some(true)` if the conditional jump that caused the hook to be called (see
+    /// `HookFlags::BRANCH_FLAG`) were taken, `Some(false)` if it were not taken and `None` if the
+    /// hook were not called because of a branch
+    pub fn branch_taken(&self) -> Option<bool> {
+        self.branch_taken
+    }
 }
 
 pub struct StackInfo<'a> {
@@ -1440,6 +1511,22 @@ impl<'a> StackInfo<'a> {
             _ => &[],
         }
     }
+
+    /// Returns the value of `local` (as returned from `locals`) in this frame
+    pub fn local_value(&self, local: &Local) -> Option<Variants<'a>> {
+        let stack = self.info.stack;
+        stack.get_variant(stack.get_frames()[self.index].offset + local.index)
+    }
+
+    /// Returns the value of the upvar at `index` (in the same order as `upvars`)
+    pub fn upvar_value(&self, index: usize) -> Option<Variants<'a>> {
+        match self.info.stack.get_frames()[self.index].state {
+            State::Closure(ClosureState { ref closure, .. }) => {
+                closure.upvars.get(index).map(Variants::new)
+            }
+            _ => None,
+        }
+    }
 }
 
 bitflags::bitflags! {
@@ -1449,6 +1536,12 @@ bitflags::bitflags! {
         const LINE_FLAG = 0b01;
         /// Call the hook when a function is called
         const CALL_FLAG = 0b10;
+        /// Call the hook when a function returns (or tail calls another function). Execution can
+        /// not be suspended at this point so returning `Poll::Pending` from the hook is ignored
+        const RETURN_FLAG = 0b100;
+        /// Call the hook after a conditional jump (from an `if` or a `match`) has been evaluated.
+        /// `DebugInfo::branch_taken` returns which way the branch went
+        const BRANCH_FLAG = 0b1000;
     }
 }
 
@@ -1460,6 +1553,29 @@ struct Hook {
     previous_instruction_index: usize,
 }
 
+/// Determines what a thread does when it has used up all of its fuel
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OutOfFuel {
+    /// Stop execution with `Error::OutOfFuel`
+    Error,
+    /// Return `Poll::Pending` from the executing future, without waking the task. The host may
+    /// refill the fuel with `Context::add_fuel` and poll the future again to resume execution.
+    Yield,
+}
+
+impl Default for OutOfFuel {
+    fn default() -> Self {
+        OutOfFuel::Error
+    }
+}
+
+#[derive(Default)]
+struct Fuel {
+    // `None` if the thread may execute an unlimited amount of instructions
+    remaining: Option<u64>,
+    out_of_fuel: OutOfFuel,
+}
+
 type PollFnInner<'a> = Box<
     dyn for<'vm> FnMut(
             &mut task::Context<'_>,
@@ -1493,6 +1609,8 @@ pub struct Context {
     pub(crate) gc: Gc,
     #[cfg_attr(feature = "serde_derive", serde(skip))]
     hook: Hook,
+    #[cfg_attr(feature = "serde_derive", serde(skip))]
+    fuel: Fuel,
     max_stack_size: VmIndex,
 
     /// Stack of polling functions used for extern functions returning futures
@@ -1510,6 +1628,7 @@ impl Context {
                 flags: HookFlags::empty(),
                 previous_instruction_index: usize::max_value(),
             },
+            fuel: Fuel::default(),
             max_stack_size: VmIndex::max_value(),
             poll_fns: Vec::new(),
         }
@@ -1585,10 +1704,47 @@ impl Context {
         self.hook.flags = flags;
     }
 
+    pub fn hook_mask(&self) -> HookFlags {
+        self.hook.flags
+    }
+
     pub fn set_max_stack_size(&mut self, limit: VmIndex) {
         self.max_stack_size = limit;
     }
 
+    /// Sets the amount of fuel available to this thread. Every executed instruction consumes one
+    /// unit of fuel and once it runs out the thread stops according to `set_out_of_fuel`.
+    /// `None` (the default) lets the thread run without limit.
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.fuel.remaining = fuel;
+    }
+
+    /// Returns the amount of fuel that remains, or `None` if the thread is not limited
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel.remaining
+    }
+
+    /// Adds `fuel` to the remaining fuel. Does nothing if the thread is not limited.
+    pub fn add_fuel(&mut self, fuel: u64) {
+        if let Some(remaining) = &mut self.fuel.remaining {
+            *remaining = remaining.saturating_add(fuel);
+        }
+    }
+
+    /// Consumes `amount` units of fuel. Lets extern functions charge for work that is not
+    /// represented by instructions. Execution stops before the next instruction if there is not
+    /// enough fuel left.
+    pub fn consume_fuel(&mut self, amount: u64) {
+        if let Some(remaining) = &mut self.fuel.remaining {
+            *remaining = remaining.saturating_sub(amount);
+        }
+    }
+
+    /// Sets what happens when the thread runs out of fuel
+    pub fn set_out_of_fuel(&mut self, out_of_fuel: OutOfFuel) {
+        self.fuel.out_of_fuel = out_of_fuel;
+    }
+
     pub fn stacktrace(&self, frame_level: usize) -> crate::stack::Stacktrace {
         self.stack.stacktrace(frame_level)
     }
@@ -1652,6 +1808,8 @@ impl<'b> OwnedContext<'b> {
         DebugInfo {
             stack: &self.stack,
             state: HookFlags::empty(),
+            allocated_memory: self.gc.allocated_memory(),
+            branch_taken: None,
         }
     }
 
@@ -1756,6 +1914,8 @@ impl<'b> OwnedContext<'b> {
                             let info = DebugInfo {
                                 stack: &context.stack.stack(),
                                 state: HookFlags::CALL_FLAG,
+                                allocated_memory: context.gc.allocated_memory(),
+                                branch_taken: None,
                             };
                             ready!(hook(thread, info))?
                         }
@@ -1862,7 +2022,11 @@ impl<'b> OwnedContext<'b> {
                 // Make sure that the stack is not borrowed during the external function call
                 // Necessary since we do not know what will happen during the function call
                 let thread = self.thread;
+                let finalizers = self.gc.take_finalizers();
                 drop(self);
+                for finalizer in finalizers {
+                    finalizer();
+                }
                 status = (function.function)(thread);
 
                 if status == Status::Yield {
@@ -1945,6 +2109,15 @@ impl<'b> OwnedContext<'b> {
 
             stack.clear();
         }
+        {
+            let Context {
+                ref mut hook,
+                ref stack,
+                ref gc,
+                ..
+            } = *self.context;
+            run_return_hook(self.thread, hook, stack, gc)?;
+        }
         self = self.exit_scope().map_err(|_| {
             Error::Message(format!(
                 "Popped the last frame or a locked frame in execute_function: {}",
@@ -1984,6 +2157,7 @@ impl<'b> OwnedContext<'b> {
             gc: &mut context.gc,
             stack: StackFrame::current(&mut context.stack),
             hook: &mut context.hook,
+            fuel: &mut context.fuel,
             max_stack_size: context.max_stack_size,
             poll_fns: &context.poll_fns,
         }
@@ -2019,6 +2193,7 @@ pub struct ExecuteContext<'b, 'gc, S: StackState = ClosureState> {
     pub stack: StackFrame<'b, S>,
     pub gc: &'gc mut Gc,
     hook: &'b mut Hook,
+    fuel: &'b mut Fuel,
     max_stack_size: VmIndex,
     poll_fns: &'b [PollFn],
 }
@@ -2111,6 +2286,10 @@ impl<'b, 'gc> ExecuteContext<'b, 'gc> {
 
             debug_instruction(&self.stack, instruction_index, instr);
 
+            if self.fuel.remaining.is_some() {
+                ready!(self.consume_fuel(instruction_index))?;
+            }
+
             if self.hook.flags.contains(HookFlags::LINE_FLAG) {
                 ready!(self.run_hook(&function, instruction_index))?;
             }
@@ -2166,6 +2345,7 @@ impl<'b, 'gc> ExecuteContext<'b, 'gc> {
                         self.stack.frame().state,
                         function.name
                     );
+                    run_return_hook(self.thread, self.hook, &self.stack.stack(), self.gc)?;
                     let mut context = self.exit_scope().unwrap_or_else(|x| x);
                     debug!(
                         "Clearing {} {} {:?}",
@@ -2288,6 +2468,9 @@ impl<'b, 'gc> ExecuteContext<'b, 'gc> {
                                 {
                                     *var = value.clone_unrooted();
                                 }
+                                // A collection may have promoted the record while its fields
+                                // were evaluated
+                                self.gc.write_barrier(&data);
                                 self.stack.pop_many(data.fields.len() as VmIndex);
                             }
                         }
@@ -2386,13 +2569,24 @@ impl<'b, 'gc> ExecuteContext<'b, 'gc> {
                     program_counter.jump(i as usize);
                     continue;
                 }
-                CJump(i) => match self.stack.pop().get_repr() {
-                    ValueRepr::Tag(0) => (),
-                    _ => {
+                CJump(i) => {
+                    let taken = match self.stack.pop().get_repr() {
+                        ValueRepr::Tag(0) => false,
+                        _ => true,
+                    };
+                    if self.hook.flags.contains(HookFlags::BRANCH_FLAG) {
+                        let next = if taken {
+                            i as usize
+                        } else {
+                            instruction_index + 1
+                        };
+                        ready!(self.run_branch_hook(instruction_index, next, taken))?;
+                    }
+                    if taken {
                         program_counter.jump(i as usize);
                         continue;
                     }
-                },
+                }
                 Pop(n) => self.stack.pop_many(n),
                 Slide(n) => {
                     trace!("{:?}", &self.stack[..]);
@@ -2446,6 +2640,7 @@ impl<'b, 'gc> ExecuteContext<'b, 'gc> {
                                 {
                                     *var = value.clone_unrooted();
                                 }
+                                self.gc.write_barrier(&closure);
                             }
                             let pop = closure.upvars.len() as VmIndex + 1;
                             self.stack.pop_many(pop); //Remove the closure
@@ -2484,6 +2679,8 @@ impl<'b, 'gc> ExecuteContext<'b, 'gc> {
                 }
             }
         }
+        run_return_hook(self.thread, self.hook, &self.stack.stack(), self.gc)?;
+
         let len = self.stack.len();
         let frame_has_excess = self.stack.frame().excess;
 
@@ -2525,6 +2722,44 @@ impl<'b, 'gc> ExecuteContext<'b, 'gc> {
         }
     }
 
+    fn consume_fuel(&mut self, index: usize) -> Poll<Result<()>> {
+        if let Some(remaining) = &mut self.fuel.remaining {
+            if *remaining == 0 {
+                return match self.fuel.out_of_fuel {
+                    OutOfFuel::Error => Err(Error::OutOfFuel).into(),
+                    OutOfFuel::Yield => {
+                        // Resume at this instruction once the thread is polled again
+                        self.stack.frame_mut().state.instruction_index = index;
+                        Poll::Pending
+                    }
+                };
+            }
+            *remaining -= 1;
+        }
+        Ok(()).into()
+    }
+
+    fn run_branch_hook(&mut self, index: usize, next: usize, taken: bool) -> Poll<Result<()>> {
+        if let Some(ref mut hook) = self.hook.function {
+            self.stack.frame_mut().state.instruction_index = index;
+            let info = DebugInfo {
+                stack: &self.stack.stack(),
+                state: HookFlags::BRANCH_FLAG,
+                allocated_memory: self.gc.allocated_memory(),
+                branch_taken: Some(taken),
+            };
+            match hook(self.thread, info) {
+                Poll::Ready(result) => result?,
+                Poll::Pending => {
+                    // Resume after the jump when the thread is polled again
+                    self.stack.frame_mut().state.instruction_index = next;
+                    return Poll::Pending;
+                }
+            }
+        }
+        Ok(()).into()
+    }
+
     fn run_hook(&mut self, function: &BytecodeFunction, index: usize) -> Poll<Result<()>> {
         if let Some(ref mut hook) = self.hook.function {
             let current_line = function.debug_info.source_map.line(index);
@@ -2538,6 +2773,8 @@ impl<'b, 'gc> ExecuteContext<'b, 'gc> {
                 let info = DebugInfo {
                     stack: &self.stack.stack(),
                     state: HookFlags::LINE_FLAG,
+                    allocated_memory: self.gc.allocated_memory(),
+                    branch_taken: None,
                 };
                 ready!(hook(self.thread, info))?
             }
@@ -2546,6 +2783,23 @@ impl<'b, 'gc> ExecuteContext<'b, 'gc> {
     }
 }
 
+fn run_return_hook(thread: &Thread, hook: &mut Hook, stack: &Stack, gc: &Gc) -> Result<()> {
+    if hook.flags.contains(HookFlags::RETURN_FLAG) {
+        if let Some(ref mut hook) = hook.function {
+            let info = DebugInfo {
+                stack,
+                state: HookFlags::RETURN_FLAG,
+                allocated_memory: gc.allocated_memory(),
+                branch_taken: None,
+            };
+            if let Poll::Ready(Err(err)) = hook(thread, info) {
+                return Err(err);
+            }
+        }
+    }
+    Ok(())
+}
+
 impl<'b, 'gc> ExecuteContext<'b, 'gc, State> {
     fn from_state<T>(self) -> ExecuteContext<'b, 'gc, T>
     where
@@ -2556,6 +2810,7 @@ impl<'b, 'gc> ExecuteContext<'b, 'gc, State> {
             stack: self.stack.from_state(),
             gc: self.gc,
             hook: self.hook,
+            fuel: self.fuel,
             max_stack_size: self.max_stack_size,
             poll_fns: self.poll_fns,
         }
@@ -2572,6 +2827,7 @@ where
             stack: self.stack.to_state(),
             gc: self.gc,
             hook: self.hook,
+            fuel: self.fuel,
             max_stack_size: self.max_stack_size,
             poll_fns: self.poll_fns,
         }
@@ -2588,6 +2844,7 @@ where
             stack,
             gc: self.gc,
             hook: self.hook,
+            fuel: self.fuel,
             max_stack_size: self.max_stack_size,
             poll_fns: self.poll_fns,
         }
@@ -2611,6 +2868,7 @@ where
                     stack,
                     gc: self.gc,
                     hook: self.hook,
+                    fuel: self.fuel,
                     max_stack_size: self.max_stack_size,
                     poll_fns: self.poll_fns,
                 })
@@ -2620,6 +2878,7 @@ where
                 stack: StackFrame::current(stack),
                 gc: self.gc,
                 hook: self.hook,
+                fuel: self.fuel,
                 max_stack_size: self.max_stack_size,
                 poll_fns: self.poll_fns,
             }),
@@ -2904,6 +3163,7 @@ impl<'vm> ActiveThread<'vm> {
             gc: &mut context.gc,
             stack: StackFrame::current(&mut context.stack),
             hook: &mut context.hook,
+            fuel: &mut context.fuel,
             max_stack_size: context.max_stack_size,
             poll_fns: &context.poll_fns,
         }
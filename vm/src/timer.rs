@@ -0,0 +1,151 @@
+//! A timer which completes `Delay` futures.
+//!
+//! The vm is not tied to a specific executor so it can not use the executor's timer. Instead a
+//! single thread, started the first time a `Delay` is created, waits for the earliest deadline of
+//! all pending delays and wakes their tasks as they expire.
+use crate::real_std::{
+    collections::BTreeMap,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use futures::{
+    prelude::*,
+    task::{self, Poll, Waker},
+};
+
+#[derive(Default)]
+struct State {
+    next_id: u64,
+    /// The task waiting on each pending delay, keyed and ordered by its deadline. Delays are
+    /// removed once they expire or are dropped.
+    delays: BTreeMap<(Instant, u64), Option<Waker>>,
+}
+
+struct Timer {
+    state: Mutex<State>,
+    /// Notified whenever a delay with an earlier deadline than the current earliest is added
+    changed: Condvar,
+}
+
+lazy_static! {
+    static ref TIMER: Arc<Timer> = {
+        let timer = Arc::new(Timer {
+            state: Mutex::new(State::default()),
+            changed: Condvar::new(),
+        });
+        let thread_timer = timer.clone();
+        thread::Builder::new()
+            .name("gluon-timer".into())
+            .spawn(move || thread_timer.run())
+            .expect("Unable to start the timer thread");
+        timer
+    };
+}
+
+impl Timer {
+    fn run(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            let expired: Vec<_> = state
+                .delays
+                .range(..=(now, u64::max_value()))
+                .map(|(key, _)| *key)
+                .collect();
+            for key in expired {
+                if let Some(Some(waker)) = state.delays.remove(&key) {
+                    waker.wake();
+                }
+            }
+
+            state = match state.delays.keys().next() {
+                Some(&(deadline, _)) => self.changed.wait_timeout(state, deadline - now).unwrap().0,
+                None => self.changed.wait(state).unwrap(),
+            };
+        }
+    }
+}
+
+/// A future which completes once its deadline has passed. Dropping it before then removes it from
+/// the timer.
+pub(crate) struct Delay {
+    /// `None` if the deadline is too far into the future to be represented, in which case the
+    /// delay never completes
+    key: Option<(Instant, u64)>,
+}
+
+impl Delay {
+    pub(crate) fn new(duration: Duration) -> Delay {
+        let deadline = match Instant::now().checked_add(duration) {
+            Some(deadline) => deadline,
+            None => return Delay { key: None },
+        };
+        let mut state = TIMER.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+
+        let earliest = state
+            .delays
+            .keys()
+            .next()
+            .map_or(true, |&(earliest, _)| deadline < earliest);
+        let key = (deadline, id);
+        state.delays.insert(key, None);
+        if earliest {
+            TIMER.changed.notify_one();
+        }
+        Delay { key: Some(key) }
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<()> {
+        let key = match self.key {
+            Some(key) => key,
+            None => return Poll::Pending,
+        };
+        let mut state = TIMER.state.lock().unwrap();
+        match state.delays.get_mut(&key) {
+            Some(waker) if Instant::now() < key.0 => {
+                *waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            _ => Poll::Ready(()),
+        }
+    }
+}
+
+impl Drop for Delay {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            TIMER.state.lock().unwrap().delays.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_complete() {
+        futures::executor::block_on(future::join(
+            Delay::new(Duration::from_millis(20)),
+            Delay::new(Duration::from_millis(10)),
+        ));
+    }
+
+    #[test]
+    fn dropped_delays_are_removed_from_the_timer() {
+        let delay = Delay::new(Duration::from_secs(3600));
+        let key = delay.key.unwrap();
+        assert!(TIMER.state.lock().unwrap().delays.contains_key(&key));
+        drop(delay);
+        assert!(!TIMER.state.lock().unwrap().delays.contains_key(&key));
+    }
+}
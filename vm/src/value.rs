@@ -23,8 +23,8 @@ use crate::{
     array::Array,
     compiler::DebugInfo,
     gc::{
-        self, CloneUnrooted, CopyUnrooted, DataDef, Gc, GcPtr, GcRef, Generation, Move, Trace,
-        WriteOnly,
+        self, CloneUnrooted, CopyUnrooted, DataDef, Finalizer, Gc, GcPtr, GcRef, Generation, Move,
+        Trace, WriteOnly,
     },
     interner::InternedStr,
     thread::{Status, Thread},
@@ -43,6 +43,18 @@ pub trait Userdata: Downcast + Trace + fmt::Debug + Send + Sync {
         let _ = deep_cloner;
         Err(Error::Message("Userdata cannot be cloned".into()))
     }
+
+    /// The name of the type, used to group userdata in `heap::GcStats` and heap snapshots
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Called when the garbage collector frees this value. The returned function is run once the
+    /// collection has finished, on the thread which owns the value, and without the thread being
+    /// borrowed. Useful for closing native handles at a predictable point.
+    fn finalizer(&mut self) -> Option<Finalizer> {
+        None
+    }
 }
 
 impl PartialEq for dyn Userdata {
@@ -99,6 +111,10 @@ where
             result
         }
     }
+
+    fn is_immutable(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Trace)]
@@ -122,6 +138,10 @@ unsafe impl DataDef for ClosureInitDef {
             result
         }
     }
+
+    fn is_immutable(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -223,6 +243,10 @@ unsafe impl<'b> DataDef for Def<'b> {
             result
         }
     }
+
+    fn is_immutable(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Trace)]
@@ -252,6 +276,10 @@ unsafe impl<'b> DataDef for VariantDef<'b> {
     fn tag(&self) -> Option<&InternedStr> {
         self.poly_tag
     }
+
+    fn is_immutable(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Trace)]
@@ -275,6 +303,10 @@ unsafe impl DataDef for UninitializedVariantDef {
             result
         }
     }
+
+    fn is_immutable(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Trace)]
@@ -302,6 +334,10 @@ unsafe impl<'b> DataDef for RecordDef<'b> {
     fn fields(&self) -> Option<&[InternedStr]> {
         Some(self.fields)
     }
+
+    fn is_immutable(&self) -> bool {
+        true
+    }
 }
 
 pub(crate) struct UninitializedRecord<'b> {
@@ -327,6 +363,10 @@ unsafe impl<'b> DataDef for UninitializedRecord<'b> {
     fn fields(&self) -> Option<&[InternedStr]> {
         Some(self.fields)
     }
+
+    fn is_immutable(&self) -> bool {
+        true
+    }
 }
 
 unsafe impl<'b> Trace for UninitializedRecord<'b> {
@@ -966,6 +1006,10 @@ unsafe impl<'b> DataDef for PartialApplicationDataDef<'b> {
             result
         }
     }
+
+    fn is_immutable(&self) -> bool {
+        true
+    }
 }
 
 unsafe impl Trace for Value {
@@ -1150,6 +1194,10 @@ macro_rules! impl_repr {
                     result
                 }
             }
+
+            fn is_immutable(&self) -> bool {
+                true
+            }
         }
 
         unsafe impl DataDef for Vec<$id> {
@@ -1160,6 +1208,10 @@ macro_rules! impl_repr {
             fn initialize<'w>(self, result: WriteOnly<'w, ValueArray>) -> &'w mut ValueArray {
                 DataDef::initialize(&self[..], result)
             }
+
+            fn is_immutable(&self) -> bool {
+                true
+            }
         }
         )*
         impl Repr {
@@ -1198,6 +1250,10 @@ unsafe impl<'a> DataDef for &'a str {
             &mut *(ptr as *mut ValueArray as *mut ValueStr)
         }
     }
+
+    fn is_immutable(&self) -> bool {
+        true
+    }
 }
 
 impl Repr {
@@ -1445,6 +1501,10 @@ unsafe impl<'a> DataDef for &'a ValueArray {
             result
         }
     }
+
+    fn is_immutable(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Trace)]
@@ -1476,6 +1536,10 @@ unsafe impl<'b> DataDef for ArrayDef<'b> {
             result
         }
     }
+
+    fn is_immutable(&self) -> bool {
+        true
+    }
 }
 
 #[derive(PartialEq, Trace)]
@@ -1516,6 +1580,11 @@ impl<'t> Cloner<'t> {
         self.gc
     }
 
+    /// The generation of the values which do not need to be cloned
+    pub fn receiver_generation(&self) -> Generation {
+        self.receiver_generation
+    }
+
     /// Deep clones the entire value doing no sharing
     pub fn force_full_clone(&mut self) -> &mut Self {
         self.receiver_generation = Generation::disjoint();
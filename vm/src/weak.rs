@@ -0,0 +1,179 @@
+//! Weak references to garbage collected values.
+//!
+//! A `Weak` does not keep its value alive. Instead the garbage collector clears the `WeakSlot`
+//! which is shared by all weak references to a value when it frees the value, after which
+//! `upgrade` returns `None`.
+use crate::real_std::{any::Any, fmt, marker::PhantomData, sync::Arc};
+
+use crate::{
+    api::{generic::A, ActiveThread, Generic, Pushable, Unrooted, Userdata, VmType, WithVM},
+    base::types::ArcType,
+    gc::{CloneUnrooted, Gc, GcRef, Generation, Move, Trace, WeakSlot},
+    thread::ThreadInternal,
+    value::{Cloner, Value, ValueRepr},
+    vm::Thread,
+    ExternModule, Result,
+};
+
+#[derive(VmType)]
+#[gluon(gluon_vm)]
+#[gluon(vm_type = "std.weak.Weak")]
+pub struct Weak<T> {
+    // Not traced. Only valid to access while `slot` is alive.
+    value: Value,
+    // The generation of `value`, recorded so it can be checked without accessing `value`
+    generation: Generation,
+    slot: Arc<WeakSlot>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Userdata for Weak<T>
+where
+    T: Any + Send + Sync,
+{
+    fn deep_clone<'gc>(
+        &self,
+        deep_cloner: &'gc mut Cloner,
+    ) -> Result<GcRef<'gc, Box<dyn Userdata>>> {
+        let shared = deep_cloner
+            .receiver_generation()
+            .can_contain_values_from(self.generation);
+        // SAFETY The value is only accessed while the slot is alive and during the `alloc` call
+        // the unrooted value is not scanned as it is weak
+        unsafe {
+            let data: Box<dyn Userdata> = if !self.slot.is_alive() {
+                Box::new(Weak {
+                    value: Value::int(0),
+                    generation: Generation::default(),
+                    slot: self.slot.clone(),
+                    _marker: PhantomData::<A>,
+                })
+            } else if shared {
+                Box::new(Weak {
+                    value: self.value.clone_unrooted(),
+                    generation: self.generation,
+                    slot: self.slot.clone(),
+                    _marker: PhantomData::<A>,
+                })
+            } else {
+                // The receiving thread can not refer to the value so it gets its own clone, which
+                // is the same clone as any strong reference to the value in the cloned value.
+                let value = deep_cloner
+                    .deep_clone(&self.value)?
+                    .get_value()
+                    .clone_unrooted();
+                let slot = weak_slot(deep_cloner.gc(), &value);
+                Box::new(Weak {
+                    generation: value.generation(),
+                    value,
+                    slot,
+                    _marker: PhantomData::<A>,
+                })
+            };
+            deep_cloner.gc().alloc(Move(data))
+        }
+    }
+}
+
+impl<T> fmt::Debug for Weak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.slot.is_alive() {
+            write!(f, "Weak(<alive>)")
+        } else {
+            write!(f, "Weak(<freed>)")
+        }
+    }
+}
+
+unsafe impl<T> Trace for Weak<T> {
+    impl_trace! { self, _gc, {} }
+}
+
+/// The value returned from `upgrade`. The slot is checked as the value is pushed, at which point
+/// the thread is borrowed so no collection can free the value before it is rooted on the stack.
+struct Upgrade<T> {
+    value: Value,
+    slot: Arc<WeakSlot>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: VmType> VmType for Upgrade<T>
+where
+    T::Type: Sized,
+{
+    type Type = Option<T::Type>;
+
+    fn make_type(vm: &Thread) -> ArcType {
+        Option::<Unrooted<T>>::make_type(vm)
+    }
+}
+
+impl<'vm, T: VmType> Pushable<'vm> for Upgrade<T>
+where
+    T::Type: Sized,
+{
+    fn push(self, context: &mut ActiveThread<'vm>) -> Result<()> {
+        let value = if self.slot.is_alive() {
+            Some(Unrooted::<T>::from(self.value))
+        } else {
+            None
+        };
+        value.push(context)
+    }
+}
+
+fn weak_slot(gc: &mut Gc, value: &Value) -> Arc<WeakSlot> {
+    match value.get_repr() {
+        ValueRepr::String(p) => gc.weak_slot(p),
+        ValueRepr::Data(p) => gc.weak_slot(p),
+        ValueRepr::Array(p) => gc.weak_slot(p),
+        ValueRepr::Function(p) => gc.weak_slot(p),
+        ValueRepr::Closure(p) => gc.weak_slot(p),
+        ValueRepr::PartialApplication(p) => gc.weak_slot(p),
+        ValueRepr::Userdata(p) => gc.weak_slot(p),
+        ValueRepr::Thread(p) => gc.weak_slot(p),
+        // Values which are not garbage collected are never freed
+        ValueRepr::Byte(_) | ValueRepr::Int(_) | ValueRepr::Float(_) | ValueRepr::Tag(_) => {
+            Arc::new(WeakSlot::new(true))
+        }
+    }
+}
+
+fn downgrade(WithVM { vm, value }: WithVM<Generic<A>>) -> Weak<A> {
+    let value = value.get_value();
+    let slot = weak_slot(&mut vm.context().gc, value);
+    Weak {
+        // SAFETY The value is only accessed while the slot is alive
+        value: unsafe { value.clone_unrooted() },
+        generation: value.generation(),
+        slot,
+        _marker: PhantomData,
+    }
+}
+
+fn upgrade(weak: &Weak<A>) -> Upgrade<A> {
+    Upgrade {
+        // SAFETY Only accessed if the slot is alive when it is pushed
+        value: unsafe { weak.value.clone_unrooted() },
+        slot: weak.slot.clone(),
+        _marker: PhantomData,
+    }
+}
+
+mod std {
+    pub mod weak {
+        pub use crate::weak as prim;
+    }
+}
+
+pub fn load(vm: &Thread) -> Result<ExternModule> {
+    let _ = vm.register_type::<Weak<A>>("std.weak.Weak", &["a"]);
+    ExternModule::new(
+        vm,
+        record! {
+            type Weak a => Weak<A>,
+            downgrade => primitive!(1, "std.weak.prim.downgrade", std::weak::prim::downgrade),
+            upgrade => primitive!(1, "std.weak.prim.upgrade", std::weak::prim::upgrade),
+        },
+    )
+}